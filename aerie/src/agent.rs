@@ -23,6 +23,8 @@ use typed_builder::TypedBuilder;
 
 use crate::{
     config::ConfigExt as _,
+    keys::{ApiKeys, KeySource},
+    rig::client::ProviderValue,
     storage::CachedDirStore as _,
     toolbox::ToolStore,
     utils::{ErrorDistiller as _, ErrorList},
@@ -102,6 +104,10 @@ pub struct AgentFactory {
     #[builder(default)]
     pub toolbox: Toolbox,
 
+    /// Keys from settings, taking precedence over environment variables
+    #[builder(default)]
+    pub api_keys: ApiKeys,
+
     #[builder(default)]
     pub cache: Arc<ArcSwap<im::HashMap<AgentSpec, AgentT>>>,
 
@@ -121,13 +127,33 @@ impl AgentFactory {
 
         tracing::info!("Building agent with provider {provider} model {model}");
 
-        let completion = DynClientBuilder::new().completion(provider.leak(), &model)?;
+        // Environment keys are picked up by the client builder, along with any base URLs
+        let completion = if let Some((key, source)) = self.api_keys.resolve(&provider)
+            && source != KeySource::Environment
+        {
+            DynClientBuilder::new()
+                .build_val(&provider, ProviderValue::Simple(key))?
+                .as_completion()
+                .with_context(|| format!("Provider {provider} does not support completions"))?
+                .completion_model(&model)
+        } else {
+            DynClientBuilder::new().completion(provider.leak(), &model)?
+        };
 
         let handle = CompletionModelHandle::new(Arc::from(completion));
         Ok(AgentBuilder::new(handle).temperature(temperature))
     }
 
     pub fn spec_to_agent(&self, spec: &AgentSpec) -> anyhow::Result<AgentT> {
+        // Cache on the stop sequences actually applied, so a new default takes effect
+        let spec = &if spec.stop.is_none() {
+            let mut spec = spec.clone();
+            spec.stop(self.settings.view(|s| s.stop_sequences()));
+            spec
+        } else {
+            spec.clone()
+        };
+
         let cache = self.cache.load();
         if let Some(cached) = cache.get(spec) {
             return Ok(cached.clone());
@@ -151,6 +177,11 @@ impl AgentFactory {
             agent = agent.context(context_doc);
         }
 
+        if let Some(stop) = spec.stop.as_ref().filter(|stop| !stop.is_empty()) {
+            let (provider, _) = self.parse_model(model)?;
+            agent = agent.additional_params(stop_params(&provider, stop));
+        }
+
         let agent = if let Some(schema) = &spec.schema {
             let tool = StructuredSubmit::from(schema.as_ref());
             agent.tool(tool).build()
@@ -169,6 +200,20 @@ impl AgentFactory {
         Ok(agent)
     }
 
+    /// Saves a provider's key, dropping agents built with the old one
+    pub fn store_api_key(&self, provider: &str, key: String) -> anyhow::Result<KeySource> {
+        let source = self.api_keys.store(provider, key)?;
+        self.cache.store(Default::default());
+        Ok(source)
+    }
+
+    /// Removes a provider's saved keys, dropping agents built with them
+    pub fn clear_api_key(&self, provider: &str) -> anyhow::Result<()> {
+        self.api_keys.clear(provider)?;
+        self.cache.store(Default::default());
+        Ok(())
+    }
+
     fn parse_model(&self, provider_model: &str) -> anyhow::Result<(String, String)> {
         let (provider, model) = provider_model
             .split_once("/")
@@ -273,6 +318,44 @@ pub struct _AgentSpec_ {
     pub tools: Arc<ToolSelector>,
 
     pub schema: Arc<serde_json::Value>,
+
+    /// Markers that end generation when the model outputs them
+    pub stop: Vec<String>,
+}
+
+/// Reads stop sequences from lines of text, skipping blank lines.
+///
+/// `\n`, `\t` and `\\` are unescaped, so a sequence can span lines.
+pub fn parse_stop_sequences<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    lines
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let mut result = String::with_capacity(line.len());
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match (c, chars.clone().next()) {
+                    ('\\', Some('n')) => result.push('\n'),
+                    ('\\', Some('t')) => result.push('\t'),
+                    ('\\', Some('\\')) => result.push('\\'),
+                    _ => {
+                        result.push(c);
+                        continue;
+                    }
+                }
+                chars.next();
+            }
+            result
+        })
+        .collect()
+}
+
+/// Request parameters for stop sequences, named the way the provider expects
+fn stop_params(provider: &str, stop: &[String]) -> serde_json::Value {
+    match provider {
+        "anthropic" => serde_json::json!({"stop_sequences": stop}),
+        _ => serde_json::json!({"stop": stop}),
+    }
 }
 
 impl AgentSpec {
@@ -286,3 +369,16 @@ impl AgentSpec {
 
     // TODO: method to just get rig tools from selection
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stop_sequences() {
+        let stop = parse_stop_sequences("</answer>\n\nUser:\\n\nC:\\\\path\\t".lines());
+        assert_eq!(stop, vec!["</answer>", "User:\n", "C:\\path\t"]);
+
+        assert!(parse_stop_sequences("".lines()).is_empty());
+    }
+}
@@ -13,7 +13,7 @@ use std::{
         Arc,
         atomic::{AtomicU16, Ordering},
     },
-    time::{Duration, Instant},
+    time::Instant,
 };
 use tracing_subscriber::{
     Layer as _, filter, layer::SubscriberExt as _, util::SubscriberInitExt as _,
@@ -23,6 +23,8 @@ use crate::{
     AgentFactory, LogChannelLayer, LogEntry, Settings,
     chat::ChatSession,
     config::{Args, Command, ConfigExt, SessionCommand},
+    keys::ApiKeys,
+    logging::{DEFAULT_LOG_CAPACITY, LogHistory},
     storage::CachedDirStore as _,
     toolbox::ToolStore,
     ui::{AppState, Pane, shortcuts::SHORTCUT_QUIT, state::WorkflowState},
@@ -174,26 +176,55 @@ impl App {
         let mut stored_settings = Arc::new(settings.clone());
         let settings = Arc::new(ArcSwap::from_pointee(settings));
         let task_count = Arc::new(AtomicU16::new(0));
-        let log_history: Arc<arc_swap::ArcSwapAny<Arc<im::Vector<LogEntry>>>> =
-            Arc::new(ArcSwap::from_pointee(im::Vector::<LogEntry>::new()));
+        let log_history = LogHistory::default();
         let cache = CommonMarkCache::default();
-        let mut debounce = Instant::now() + Duration::from_secs(1);
+        // Settings as of the last frame they were seen changing
+        let mut pending_settings = stored_settings.clone();
+        let mut last_change = Instant::now();
         let next_workflow: Arc<ArcSwapOption<String>> = Default::default();
         let next_prompt: Arc<ArcSwapOption<String>> = Default::default();
         let log_history_ = log_history.clone();
 
+        let settings_ = settings.clone();
+        let log_file = args.log_file.clone();
+
         rt.handle().spawn(async move {
+            use tokio::io::AsyncWriteExt as _;
+
+            let mut log_file = match &log_file {
+                Some(path) => match tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                {
+                    Ok(file) => Some(file),
+                    Err(err) => {
+                        eprintln!("Cannot open log file {path:?}: {err:?}");
+                        None
+                    }
+                },
+                None => None,
+            };
+
             while let Ok(entry) = log_rx.recv_async().await {
-                log_history_.rcu(|logs| {
-                    let mut logs = logs.as_ref().clone();
-                    logs.push_back(entry.clone());
-
-                    if logs.len() > 1000 {
-                        logs.skip(logs.len() - 1000)
-                    } else {
-                        logs
+                if let Some(file) = &mut log_file {
+                    let line = format!(
+                        "{} {} {}: {}\n",
+                        chrono::Local::now().to_rfc3339(),
+                        entry.level(),
+                        entry.target(),
+                        entry.message()
+                    );
+
+                    if let Err(err) = file.write_all(line.as_bytes()).await {
+                        eprintln!("Cannot write to log file: {err:?}");
+                        log_file = None;
                     }
-                });
+                }
+
+                let capacity = settings_.view(|s| s.log_capacity.unwrap_or(DEFAULT_LOG_CAPACITY));
+                log_history_.push(entry, capacity);
             }
         });
 
@@ -225,7 +256,16 @@ impl App {
 
         let flow_name = settings.view(|s| s.automation.clone());
         let flow_store = (self.workstore_fn)(WorkflowStoreDir::load_all(workflow_dir, true)?);
-        let flow_state = WorkflowState::new(flow_store.clone(), flow_name);
+        let mut flow_state = WorkflowState::new(flow_store.clone(), flow_name);
+
+        // Undo steps saved on exit by a previous session
+        let history_path = data_dir.join("undo.yml");
+        flow_state.undo_limit = settings.view(|s| s.undo_limit());
+        if settings.view(|s| s.undo_persist()) > 0
+            && let Err(err) = flow_state.load_history(&history_path)
+        {
+            log::warn!("Could not restore undo history: {err:?}");
+        }
 
         let tool_store = ToolStore::new(tool_dir);
         tool_store.preload_all();
@@ -241,6 +281,7 @@ impl App {
                 .store(Some(flow_store.clone()))
                 .next_workflow(next_workflow.clone())
                 .next_prompt(next_prompt.clone())
+                .api_keys(ApiKeys::load(settings_path.with_file_name("keys.yml"))?)
                 .build(),
         );
         agent_factory.reload_tools()?;
@@ -297,12 +338,27 @@ impl App {
                 max_rect = ui.min_rect();
             });
 
-            behavior.handle_events();
+            behavior.handle_events(ctx);
 
             if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_QUIT)) {
                 ctx.send_viewport_cmd(egui::ViewportCommand::Close);
             }
 
+            if ctx.input(|i| i.viewport().close_requested()) {
+                let depth = settings_.view(|s| s.undo_persist());
+                let result = if depth > 0 {
+                    behavior.workflows.save_history(&history_path, depth)
+                } else if history_path.is_file() {
+                    std::fs::remove_file(&history_path).map_err(|e| e.into())
+                } else {
+                    Ok(())
+                };
+
+                if let Err(err) = result {
+                    log::warn!("Could not save undo history: {err:?}");
+                }
+            }
+
             let errors = behavior.errors.load();
             if !errors.is_empty() {
                 let modal = egui::Modal::new(egui::Id::new("Errors")).show(ctx, |ui| {
@@ -342,19 +398,30 @@ impl App {
 
             let dirty = settings_.view(|s| *s != *stored_settings);
 
-            if dirty && debounce < Instant::now() {
-                // This will apply/save settings every n seconds *while* or *after* they have been changed
-                let settings__ = settings_.clone();
-                let settings_path_ = settings_path_.clone();
-                debounce = Instant::now() + Duration::from_secs(5);
+            if dirty {
+                // Wait until edits have stopped for a while before applying them
+                if settings_.view(|s| *s != *pending_settings) {
+                    pending_settings = settings_.view(|s| Arc::new(s.clone()));
+                    last_change = Instant::now();
+                }
 
-                log::info!("Settings changed, reloading agent");
+                let delay = settings_.view(|s| s.save_delay());
+                let idle = last_change.elapsed();
 
-                rt_.spawn(async move {
-                    Self::save_settings(settings__, settings_path_).await;
-                });
+                if idle >= delay {
+                    let settings__ = settings_.clone();
+                    let settings_path_ = settings_path_.clone();
+
+                    log::info!("Settings changed, reloading agent");
 
-                stored_settings = settings_.view(|s| Arc::new(s.clone()));
+                    rt_.spawn(async move {
+                        Self::save_settings(settings__, settings_path_).await;
+                    });
+
+                    stored_settings = pending_settings.clone();
+                } else {
+                    ctx.request_repaint_after(delay - idle);
+                }
             }
 
             let running = task_count.load(Ordering::Relaxed) > 0;
@@ -2,11 +2,14 @@ use std::{fs::OpenOptions, path::PathBuf, sync::Arc};
 
 use aerie::{
     AgentFactory, ChatSession, Settings,
+    keys::ApiKeys,
     storage::CachedDirStore as _,
     toolbox::ToolStore,
+    transmute::Transmuter,
     utils::message_text,
     workflow::{
-        RootContext, RunContext, Workflow,
+        RootContext, RunContext, Value, Workflow,
+        nodes::Start,
         runner::WorkflowRunner,
         store::{WorkflowStore as _, WorkflowStoreDir},
         write_value,
@@ -68,6 +71,11 @@ struct Args {
     #[arg(short = 'I', long)]
     input_file: Option<PathBuf>,
 
+    /// Value for a parameter declared on the workflow's Start node, as `name=value`.
+    /// Lists and JSON parameters take JSON text.
+    #[arg(short = 'P', long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+
     /// Save outputs as individual files in a directory
     #[arg(short, long)]
     out_dir: Option<PathBuf>,
@@ -161,6 +169,13 @@ fn main() -> anyhow::Result<()> {
         .enable_all()
         .build()?;
 
+    // Keys saved from the settings tab live beside the configuration file
+    let api_keys = if args.config.is_some() {
+        ApiKeys::load(settings_path.with_file_name("keys.yml"))?
+    } else {
+        ApiKeys::default()
+    };
+
     let next_workflow: Arc<ArcSwapOption<String>> = Default::default();
     let next_prompt: Arc<ArcSwapOption<String>> = Default::default();
     let mut agent_factory = AgentFactory::builder()
@@ -170,6 +185,7 @@ fn main() -> anyhow::Result<()> {
         .store(workflow_store.clone())
         .next_workflow(next_workflow.clone())
         .next_prompt(next_prompt.clone())
+        .api_keys(api_keys)
         .build();
 
     agent_factory.reload_tools()?;
@@ -198,11 +214,23 @@ fn main() -> anyhow::Result<()> {
     }
 
     let workflow_path = args.workflow.as_path();
+
+    // Stored workflows are named by their file stem, which subgraphs refer to
+    let workflow_name = if workflow_path.is_file() {
+        workflow_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+    } else {
+        workflow_path.to_string_lossy()
+    }
+    .to_string();
+
     let mut shadow: Workflow = if workflow_path.is_file() {
         let reader = OpenOptions::new().read(true).open(workflow_path)?;
         serde_yml::from_reader(reader)?
     } else if let Some(store) = &mut workflow_store {
-        store.load(&args.workflow.display().to_string())?
+        store.load(&workflow_name)?
     } else {
         anyhow::bail!("Invalid file: {workflow_path:?}");
     };
@@ -215,6 +243,14 @@ fn main() -> anyhow::Result<()> {
             .metadata(shadow.metadata.clone())
             .history(session.history.clone())
             .seed(settings.seed.clone())
+            .concurrent(settings.concurrent_nodes)
+            .node_timeout(settings.node_timeout)
+            .transmuter(
+                Transmuter::builder()
+                    .rules(settings.conversions.clone())
+                    .build(),
+            )
+            .call_stack(im::vector![workflow_name.clone()])
             .build();
 
         let saver_task = if let Some(out_dir) = &args.out_dir {
@@ -231,10 +267,13 @@ fn main() -> anyhow::Result<()> {
                 .spawn(console_output(run_ctx.outputs.receiver()))
         };
 
+        shadow.graph.check_results()?;
+        let params = parse_params(&shadow, &args.params)?;
         let inputs = RootContext::builder()
             .history(session.history.clone())
             .workflow(shadow.clone())
             .user_prompt(prompt.clone())
+            .params(params)
             .model(settings.llm_model.clone())
             .temperature(settings.temperature)
             .build()
@@ -312,6 +351,29 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Converts `name=value` arguments according to the kinds declared on the Start node
+fn parse_params(workflow: &Workflow, args: &[String]) -> anyhow::Result<im::OrdMap<String, Value>> {
+    let Some(start) = workflow.graph.start_node() else {
+        return Ok(Default::default());
+    };
+
+    let mut params = im::OrdMap::new();
+    for arg in args {
+        let Some((name, text)) = arg.split_once('=') else {
+            anyhow::bail!("Expected a parameter of the form name=value: {arg:?}");
+        };
+
+        let Some(kind) = start.parameter_kind(name)? else {
+            tracing::warn!("Workflow has no parameter named {name:?}");
+            continue;
+        };
+
+        params.insert(name.to_string(), Start::parse_parameter(kind, text)?);
+    }
+
+    Ok(params)
+}
+
 async fn console_output(
     out_rx: flume::Receiver<(String, aerie::workflow::Value)>,
 ) -> anyhow::Result<()> {
@@ -1,6 +1,6 @@
-use crate::rig::message::Message;
+use crate::rig::message::{ImageMediaType, Message, UserContent};
 use anyhow::anyhow;
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use cached::proc_macro::cached;
 use derive_builder::Builder;
 use itertools::Itertools;
@@ -14,7 +14,11 @@ use std::{
     fs::OpenOptions,
     ops::Deref,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
 };
 use uuid::Uuid;
 
@@ -28,6 +32,72 @@ pub struct ChatSession {
 
     #[builder(default)]
     pub scratch: AtomicBuffer<Result<Message, String>>,
+
+    /// Statistics of the response currently streaming into the scratch buffer
+    #[builder(default)]
+    pub progress: Arc<ArcSwapOption<StreamProgress>>,
+}
+
+/// Live statistics of an assistant message while it streams in
+#[derive(Debug)]
+pub struct StreamProgress {
+    pub started: Instant,
+
+    /// Text chunks received. Providers usually send about one token per chunk.
+    pub chunks: AtomicUsize,
+
+    pub chars: AtomicUsize,
+}
+
+impl StreamProgress {
+    /// Publishes a new record in `slot`, which is withdrawn when the returned handle drops
+    pub fn start(slot: &Arc<ArcSwapOption<StreamProgress>>) -> StreamHandle {
+        let progress = Arc::new(Self {
+            started: Instant::now(),
+            chunks: Default::default(),
+            chars: Default::default(),
+        });
+        slot.store(Some(progress.clone()));
+
+        StreamHandle {
+            slot: slot.clone(),
+            progress,
+        }
+    }
+
+    /// Short description such as "~120 tokens · 480 chars · 6.0s"
+    pub fn summary(&self) -> String {
+        let chunks = self.chunks.load(Ordering::Relaxed);
+        let chars = self.chars.load(Ordering::Relaxed);
+        let elapsed = self.started.elapsed().as_secs_f64();
+
+        format!("~{chunks} tokens · {chars} chars · {elapsed:.1}s")
+    }
+}
+
+/// Records chunks of a streaming response until dropped
+pub struct StreamHandle {
+    slot: Arc<ArcSwapOption<StreamProgress>>,
+    progress: Arc<StreamProgress>,
+}
+
+impl StreamHandle {
+    pub fn record(&self, text: &str) {
+        self.progress.chunks.fetch_add(1, Ordering::Relaxed);
+        self.progress
+            .chars
+            .fetch_add(text.chars().count(), Ordering::Relaxed);
+    }
+}
+
+impl Drop for StreamHandle {
+    fn drop(&mut self) {
+        // Leave a newer stream alone
+        self.slot.rcu(|current| match current {
+            Some(it) if Arc::ptr_eq(it, &self.progress) => None,
+            _ => current.clone(),
+        });
+    }
 }
 
 impl ChatSession {
@@ -218,6 +288,91 @@ impl ChatSession {
     }
 }
 
+/// Largest image accepted as an attachment. Most providers reject anything bigger.
+pub const MAX_ATTACHMENT_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Providers with models that accept images in user messages
+const MULTIMODAL_PROVIDERS: &[&str] = &[
+    "anthropic",
+    "azure",
+    "gemini",
+    "mistral",
+    "ollama",
+    "openai",
+    "openrouter",
+    "xai",
+];
+
+/// Whether the provider of a `provider/model` string is known to take images.
+///
+/// Only a hint: not every model of these providers is multimodal.
+pub fn accepts_images(provider_model: &str) -> bool {
+    let provider = provider_model
+        .split_once("/")
+        .map_or(provider_model, |(p, _)| p);
+
+    MULTIMODAL_PROVIDERS.contains(&provider)
+}
+
+/// Images attached to the prompt that starts a workflow run
+#[derive(Debug, Clone, Default)]
+pub struct Attachments {
+    /// Text of the prompt the images belong to
+    pub prompt: String,
+
+    pub images: im::Vector<UserContent>,
+}
+
+impl Attachments {
+    /// Reads an image file and encodes it as base64 content
+    pub fn load_image(path: &Path) -> anyhow::Result<UserContent> {
+        use base64::prelude::*;
+
+        let media_type = match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .as_deref()
+        {
+            Some("png") => ImageMediaType::PNG,
+            Some("jpg" | "jpeg") => ImageMediaType::JPEG,
+            Some("gif") => ImageMediaType::GIF,
+            Some("webp") => ImageMediaType::WEBP,
+            _ => return Err(anyhow!("Unsupported image type: {path:?}")),
+        };
+
+        let size = std::fs::metadata(path)?.len();
+        if size > MAX_ATTACHMENT_BYTES {
+            return Err(anyhow!(
+                "{path:?} is {:.1} MB, over the {} MB limit",
+                size as f64 / (1024.0 * 1024.0),
+                MAX_ATTACHMENT_BYTES / (1024 * 1024),
+            ));
+        }
+
+        let data = BASE64_STANDARD.encode(std::fs::read(path)?);
+        Ok(UserContent::image_base64(data, Some(media_type), None))
+    }
+
+    /// Appends the images to `message` when it is the prompt they were attached to
+    pub fn apply(&self, message: Message) -> Message {
+        if self.images.is_empty() {
+            return message;
+        }
+
+        match message {
+            Message::User { mut content } if matches!(content.first(), UserContent::Text(text) if text.text == self.prompt) =>
+            {
+                for image in &self.images {
+                    content.push(image.clone());
+                }
+                Message::User { content }
+            }
+            message => message,
+        }
+    }
+}
+
 #[cached(time = 5)]
 pub fn list_sessions(dir: PathBuf) -> Vec<String> {
     tracing::info!("listing sessions for {dir:?}");
@@ -240,6 +395,10 @@ pub enum ChatContent {
     },
     Error {
         err: String,
+
+        /// Prompt of a failed turn, which can be submitted again
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        retry: Option<String>,
     },
 }
 
@@ -256,7 +415,7 @@ impl From<Result<Message, String>> for ChatContent {
     fn from(value: Result<Message, String>) -> Self {
         match value {
             Ok(msg) => ChatContent::Message(msg),
-            Err(err) => ChatContent::Error { err },
+            Err(err) => ChatContent::Error { err, retry: None },
         }
     }
 }
@@ -346,6 +505,44 @@ impl ChatHistory {
         )
     }
 
+    /// Records a failed turn along with the prompt to retry it
+    pub fn push_failed_turn(
+        &'_ self,
+        err: impl std::fmt::Display,
+        prompt: &str,
+    ) -> anyhow::Result<Cow<'_, Self>> {
+        self.push(ChatContent::Error {
+            err: err.to_string(),
+            retry: Some(prompt.to_string()),
+        })
+    }
+
+    /// Removes a failed turn from the head of the current branch, returning its prompt.
+    ///
+    /// The prompt never reached the history, so resubmitting it will not duplicate it.
+    pub fn take_retry(&'_ self, id: Uuid) -> anyhow::Result<(Cow<'_, Self>, String)> {
+        let Some(entry) = self.last().filter(|entry| entry.id == id) else {
+            return Err(anyhow!("Only the latest turn can be retried"));
+        };
+
+        let ChatContent::Error {
+            retry: Some(prompt),
+            ..
+        } = &entry.content
+        else {
+            return Err(anyhow!("Entry is not a failed turn"));
+        };
+
+        let mut result = self.clone();
+        result.store = self.store.without(&id);
+        result.branches = match entry.parent {
+            Some(parent) => self.branches.update(self.head.clone(), parent),
+            None => self.branches.without(&self.head),
+        };
+
+        Ok((Cow::Owned(result), prompt.clone()))
+    }
+
     pub fn extend(
         &'_ self,
         contents: impl std::iter::IntoIterator<Item = ChatContent>,
@@ -536,7 +733,7 @@ impl ChatHistory {
     pub fn iter_msgs(&self) -> impl Iterator<Item = Cow<'_, Message>> {
         self.iter().filter_map(|entry| match &entry.content {
             ChatContent::Message(message) => Some(Cow::Borrowed(message)),
-            ChatContent::Error { err } => {
+            ChatContent::Error { err, .. } => {
                 Some(Cow::Owned(Message::user(format!("Error:\n{err:?}"))))
             }
             _ => None,
@@ -758,6 +955,105 @@ impl ChatHistory {
 
         None
     }
+
+    /// Appends the entries of other histories that are not already on this branch.
+    ///
+    /// Ancestry shared with this history, or with an earlier one, is only kept once.
+    pub fn merge_append(&'_ self, others: &[&Self]) -> anyhow::Result<Cow<'_, Self>> {
+        let mut seen = self.path_ids().into_iter().collect::<BTreeSet<_>>();
+        let mut contents = vec![];
+        for other in others {
+            contents.extend(other.unseen_contents(&mut seen));
+        }
+
+        self.extend(contents)
+    }
+
+    /// Alternates turns of each history after the last entry shared by all of them.
+    ///
+    /// Entries carry no timestamps, so turns are taken round-robin starting with this history.
+    /// A turn runs from one user prompt to the next, which keeps tool calls with their results.
+    pub fn merge_interleave(&'_ self, others: &[&Self]) -> anyhow::Result<Cow<'_, Self>> {
+        if others.is_empty() {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let path = self.path_ids();
+        let fork = others
+            .iter()
+            .map(|other| {
+                self.with_base(None)
+                    .find_common(other.with_base(None).as_ref())
+                    .and_then(|id| path.iter().position(|it| *it == id))
+            })
+            .min()
+            .flatten();
+
+        let mut seen = BTreeSet::new();
+        let mut trunk = self.clone();
+        match fork {
+            Some(pos) => {
+                seen.extend(path[..=pos].iter().copied());
+                trunk.branches = self.branches.update(self.head.clone(), path[pos]);
+            }
+            None => {
+                trunk.branches = self.branches.without(&self.head);
+            }
+        }
+
+        let mut turns = std::iter::once(self)
+            .chain(others.iter().copied())
+            .map(|chat| split_turns(chat.unseen_contents(&mut seen)).into_iter())
+            .collect_vec();
+
+        let mut contents = vec![];
+        loop {
+            let mut exhausted = true;
+            for turn in turns.iter_mut().filter_map(|it| it.next()) {
+                contents.extend(turn);
+                exhausted = false;
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+
+        Ok(Cow::Owned(trunk.extend(contents)?.into_owned()))
+    }
+
+    /// Ids on the current branch starting from the root, regardless of base
+    fn path_ids(&self) -> Vec<Uuid> {
+        self.with_base(None).iter().map(|entry| entry.id).collect()
+    }
+
+    /// Contents on the current branch not yet in `seen`, which are then marked as seen
+    fn unseen_contents(&self, seen: &mut BTreeSet<Uuid>) -> Vec<ChatContent> {
+        self.with_base(None)
+            .iter()
+            .filter(|entry| seen.insert(entry.id))
+            .map(|entry| entry.content.clone())
+            .collect()
+    }
+}
+
+/// Splits contents before each user prompt. Tool results don't count as prompts.
+fn split_turns(contents: Vec<ChatContent>) -> Vec<Vec<ChatContent>> {
+    let mut turns: Vec<Vec<ChatContent>> = vec![];
+    for content in contents {
+        let is_prompt = matches!(
+            &content,
+            ChatContent::Message(Message::User { content })
+                if !content.iter().any(|it| matches!(it, UserContent::ToolResult(_)))
+        );
+
+        match turns.last_mut() {
+            Some(turn) if !is_prompt => turn.push(content),
+            _ => turns.push(vec![content]),
+        }
+    }
+
+    turns
 }
 
 struct ChatRevIter<'a>(&'a ChatHistory, Option<Uuid>);
@@ -777,3 +1073,97 @@ impl<'a> Iterator for ChatRevIter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_failed_turn() {
+        let history = ChatHistory::default();
+        let history = history
+            .push(ChatContent::Message(Message::user("hello")))
+            .unwrap();
+        let history = history
+            .push(ChatContent::Message(Message::assistant("hi")))
+            .unwrap();
+        let before = history.clone().into_owned();
+
+        let failed = history
+            .push_failed_turn("connection reset", "tell me a joke")
+            .unwrap();
+        let failed_id = failed.last().unwrap().id;
+
+        let (retried, prompt) = failed.take_retry(failed_id).unwrap();
+        assert_eq!(prompt, "tell me a joke");
+        assert_eq!(retried.as_ref(), &before);
+
+        // Only the latest entry of the branch can be retried
+        let moved_on = failed
+            .push(ChatContent::Message(Message::user("never mind")))
+            .unwrap();
+        assert!(moved_on.take_retry(failed_id).is_err());
+    }
+
+    #[test]
+    fn test_apply_attachments() {
+        let image = UserContent::image_base64("aGk=", Some(ImageMediaType::PNG), None);
+        let attachments = Attachments {
+            prompt: "what is this?".into(),
+            images: im::vector![image],
+        };
+
+        let Message::User { content } = attachments.apply(Message::user("what is this?")) else {
+            panic!("expected a user message");
+        };
+        assert_eq!(content.len(), 2);
+        assert!(matches!(content.iter().last(), Some(UserContent::Image(_))));
+
+        // Other prompts, e.g. from a template in the workflow, are left alone
+        let other = Message::user("summarize the chat");
+        assert_eq!(attachments.apply(other.clone()), other);
+    }
+
+    fn texts(history: &ChatHistory) -> Vec<String> {
+        history
+            .iter_msgs()
+            .map(|msg| crate::utils::message_text(&msg))
+            .collect()
+    }
+
+    fn chat(history: &ChatHistory, texts: &[&str]) -> ChatHistory {
+        let contents = texts.iter().enumerate().map(|(i, text)| {
+            ChatContent::Message(if i % 2 == 0 {
+                Message::user(*text)
+            } else {
+                Message::assistant(*text)
+            })
+        });
+
+        history.extend(contents).unwrap().into_owned()
+    }
+
+    #[test]
+    fn test_merge_histories() {
+        let shared = chat(&ChatHistory::default(), &["a", "b"]);
+        let main = chat(&shared, &["p", "q", "r", "s"]);
+        let side = chat(&shared, &["x", "y"]);
+
+        let appended = main.merge_append(&[&side]).unwrap();
+        assert_eq!(texts(&appended), ["a", "b", "p", "q", "r", "s", "x", "y"]);
+
+        let interleaved = main.merge_interleave(&[&side]).unwrap();
+        assert_eq!(
+            texts(&interleaved),
+            ["a", "b", "p", "q", "x", "y", "r", "s"]
+        );
+
+        // Nothing new when one history already contains the other
+        let merged = main.merge_append(&[&shared]).unwrap();
+        assert_eq!(texts(&merged), texts(&main));
+        assert_eq!(
+            texts(&main.merge_interleave(&[&shared]).unwrap()),
+            texts(&main)
+        );
+    }
+}
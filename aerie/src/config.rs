@@ -8,9 +8,10 @@ use std::{
         Arc, RwLock,
         atomic::{AtomicU64, Ordering},
     },
+    time::Duration,
 };
 
-use crate::rmcp::model::Tool;
+use crate::{rmcp::model::Tool, transmute::ConversionRules};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
@@ -37,6 +38,10 @@ pub struct Args {
     #[arg(long)]
     pub backup_dir: Option<PathBuf>,
 
+    /// Append captured log entries to this file
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -91,12 +96,40 @@ pub struct Settings {
 
     pub seed: Option<SeedConfig>,
 
+    /// Default stop sequences for agents, one per line as entered
+    #[serde(default, skip_serializing_if = "im::Vector::is_empty")]
+    pub stop_sequences: im::Vector<String>,
+
     #[serde(default)]
     pub show_logs: bool,
 
     #[serde(default)]
     pub autoscroll: bool,
 
+    /// Overview of the graph in the corner of the workflow editor
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub show_minimap: bool,
+
+    /// Maximum number of log entries kept in memory
+    #[serde(default)]
+    pub log_capacity: Option<usize>,
+
+    /// Seconds without further changes before settings are saved and applied
+    #[serde(default)]
+    pub save_delay: Option<f64>,
+
+    /// Seconds a workflow node may run before it fails with a timeout. Zero or unset waits forever.
+    #[serde(default)]
+    pub node_timeout: Option<u64>,
+
+    /// Maximum number of undo steps kept for each workflow
+    #[serde(default)]
+    pub undo_limit: Option<usize>,
+
+    /// Number of recent undo steps for each workflow saved across restarts
+    #[serde(default)]
+    pub undo_persist: Option<usize>,
+
     /// Whether to rerun only changed/selected nodes or all dependents
     #[serde(default)]
     pub cascade: bool,
@@ -116,19 +149,91 @@ pub struct Settings {
     #[serde(default)]
     pub last_output_dir: PathBuf,
 
+    /// Directory that file nodes read and write within. Uses the current directory when unset.
+    #[serde(default)]
+    pub work_dir: Option<PathBuf>,
+
+    /// URL of the qdrant server queried by Search Repo nodes
+    #[serde(default)]
+    pub qdrant_url: Option<String>,
+
+    /// Collection queried by Search Repo nodes that do not name their own
+    #[serde(default)]
+    pub search_collection: Option<String>,
+
+    /// Embeds search queries. Must match the model that indexed the collection.
+    #[serde(default)]
+    pub embed_model: Option<String>,
+
     // Making this configurable since not 100% confident in the streaming implementation
     // The runner will fall back to non-streaming.
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub streaming: bool,
 
+    /// Run independent branches of a workflow at the same time
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub concurrent_nodes: bool,
+
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub autosave: bool,
 
+    /// Let the chat agent call tools when no workflow is selected
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub chat_tools: bool,
+
+    /// Workflow whose tools the chat agent inherits. Uses `chat_toolset` when unset.
+    #[serde(default)]
+    pub chat_tool_source: Option<String>,
+
+    #[serde(default, skip_serializing_if = "ToolSelector::is_empty")]
+    pub chat_toolset: ToolSelector,
+
+    /// Implicit conversions applied to node inputs during a run
+    #[serde(default, skip_serializing_if = "ConversionRules::is_default")]
+    pub conversions: ConversionRules,
+
     // Don't clobber unknown settings
     #[serde(flatten)]
     pub _extra: im::OrdMap<String, serde_json::Value>,
 }
 
+/// Seconds without further changes before settings are saved, unless configured
+pub const DEFAULT_SAVE_DELAY: f64 = 2.0;
+
+/// Undo steps kept for each workflow, unless configured
+pub const DEFAULT_UNDO_LIMIT: usize = 128;
+
+impl Settings {
+    /// How long settings must stay unchanged before they are saved and applied
+    pub fn save_delay(&self) -> Duration {
+        Duration::try_from_secs_f64(self.save_delay.unwrap_or(DEFAULT_SAVE_DELAY))
+            .unwrap_or(Duration::from_secs_f64(DEFAULT_SAVE_DELAY))
+    }
+
+    /// Maximum depth of each undo and redo stack
+    pub fn undo_limit(&self) -> usize {
+        self.undo_limit.unwrap_or(DEFAULT_UNDO_LIMIT).max(1)
+    }
+
+    /// Stop sequences applied to agents that do not set their own
+    pub fn stop_sequences(&self) -> Vec<String> {
+        crate::agent::parse_stop_sequences(self.stop_sequences.iter().map(String::as_str))
+    }
+
+    /// Root of the paths given to file nodes
+    pub fn work_dir(&self) -> PathBuf {
+        match &self.work_dir {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.clone(),
+            _ => std::env::current_dir().unwrap_or_default(),
+        }
+    }
+
+    /// Undo steps to save on exit, never more than are kept in memory
+    pub fn undo_persist(&self) -> usize {
+        self.undo_persist.unwrap_or_default().min(self.undo_limit())
+    }
+}
+
 pub trait ConfigExt {
     fn view<T>(&self, cb: impl FnMut(&Settings) -> T) -> T;
 
@@ -313,6 +418,10 @@ impl ToolSelector {
         self.0.contains("*/*")
     }
 
+    pub fn union(&self, other: &ToolSelector) -> Self {
+        Self(self.0.clone().union(other.0.clone()))
+    }
+
     pub fn with_include(mut self, provider: &str, tool: &str) -> Self {
         self.0.insert(format!("{provider}/{tool}"));
         self
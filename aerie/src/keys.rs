@@ -0,0 +1,296 @@
+use std::{
+    io::Write as _,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use arc_swap::ArcSwap;
+use serde_yaml_ng as serde_yml;
+
+/// LLM providers offered in the settings tab, with whether they need an API key
+pub const KNOWN_PROVIDERS: &[(&str, bool)] = &[
+    ("anthropic", true),
+    ("cohere", true),
+    ("deepseek", true),
+    ("gemini", true),
+    ("groq", true),
+    ("mistral", true),
+    ("ollama", false),
+    ("openai", true),
+    ("openrouter", true),
+    ("perplexity", true),
+    ("together", true),
+    ("xai", true),
+];
+
+/// Service name for entries in the OS keyring
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "aerie";
+
+/// Where a provider's key was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySource {
+    Environment,
+    Keyring,
+    File,
+}
+
+/// Environment variable conventionally holding a provider's key
+pub fn env_var(provider: &str) -> String {
+    format!("{}_API_KEY", provider.to_uppercase())
+}
+
+/// API keys for LLM providers, stored apart from the settings file.
+///
+/// Keys are kept in the OS keyring when built with the `keyring` feature.
+/// Otherwise, they are written in plain text to a file only readable by the current user.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    path: Option<PathBuf>,
+    keys: Arc<ArcSwap<im::OrdMap<String, String>>>,
+
+    /// Keyring lookups by provider, including misses, to avoid hitting the OS every frame
+    keyring: Arc<ArcSwap<im::OrdMap<String, Option<String>>>>,
+
+    /// Set once the keyring backend fails, after which only the file is used
+    keyring_failed: Arc<AtomicBool>,
+}
+
+impl ApiKeys {
+    /// Reads keys from the file, if it exists
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        let keys = if path.is_file() {
+            serde_yml::from_str(&std::fs::read_to_string(&path)?)?
+        } else {
+            Default::default()
+        };
+
+        Ok(Self {
+            path: Some(path),
+            keys: Arc::new(ArcSwap::from_pointee(keys)),
+            ..Default::default()
+        })
+    }
+
+    /// Key stored in the file
+    pub fn get(&self, provider: &str) -> Option<String> {
+        self.keys.load().get(provider).cloned()
+    }
+
+    pub fn contains(&self, provider: &str) -> bool {
+        self.keys.load().contains_key(provider)
+    }
+
+    /// Finds a provider's key in the environment, then the keyring, then the file
+    pub fn resolve(&self, provider: &str) -> Option<(String, KeySource)> {
+        if let Ok(key) = std::env::var(env_var(provider)) {
+            return Some((key, KeySource::Environment));
+        }
+
+        if let Some(key) = self.keyring_get(provider) {
+            return Some((key, KeySource::Keyring));
+        }
+
+        self.get(provider).map(|key| (key, KeySource::File))
+    }
+
+    /// Whether keys can currently be saved to the OS keyring
+    pub fn keyring_available(&self) -> bool {
+        cfg!(feature = "keyring") && !self.keyring_failed.load(Ordering::Relaxed)
+    }
+
+    pub fn keyring_get(&self, provider: &str) -> Option<String> {
+        if !self.keyring_available() {
+            return None;
+        }
+
+        if let Some(cached) = self.keyring.load().get(provider) {
+            return cached.clone();
+        }
+
+        let key = match keyring_read(provider) {
+            Ok(key) => key,
+            Err(err) => {
+                self.keyring_unavailable(err);
+                return None;
+            }
+        };
+
+        self.keyring
+            .rcu(|cache| cache.update(provider.to_string(), key.clone()));
+        key
+    }
+
+    /// Saves a key to the keyring, falling back to the file if the keyring fails
+    pub fn store(&self, provider: &str, key: String) -> anyhow::Result<KeySource> {
+        if self.keyring_available() {
+            match keyring_write(provider, Some(&key)) {
+                Ok(()) => {
+                    self.keyring
+                        .rcu(|cache| cache.update(provider.to_string(), Some(key.clone())));
+                    return Ok(KeySource::Keyring);
+                }
+                Err(err) => self.keyring_unavailable(err),
+            }
+        }
+
+        self.set(provider, Some(key))?;
+        Ok(KeySource::File)
+    }
+
+    /// Moves a key from the file into the keyring
+    pub fn migrate(&self, provider: &str) -> anyhow::Result<()> {
+        let Some(key) = self.get(provider) else {
+            anyhow::bail!("No stored key for {provider}");
+        };
+
+        anyhow::ensure!(self.keyring_available(), "Keyring is not available");
+        keyring_write(provider, Some(&key))?;
+
+        self.keyring
+            .rcu(|cache| cache.update(provider.to_string(), Some(key.clone())));
+        self.set(provider, None)
+    }
+
+    /// Removes a provider's key from both the keyring and the file
+    pub fn clear(&self, provider: &str) -> anyhow::Result<()> {
+        if self.keyring_available() && self.keyring_get(provider).is_some() {
+            keyring_write(provider, None)?;
+            self.keyring
+                .rcu(|cache| cache.update(provider.to_string(), None));
+        }
+
+        if self.contains(provider) {
+            self.set(provider, None)?;
+        }
+
+        Ok(())
+    }
+
+    fn keyring_unavailable(&self, err: anyhow::Error) {
+        tracing::warn!("Keyring unavailable, using file storage for API keys: {err:?}");
+        self.keyring_failed.store(true, Ordering::Relaxed);
+    }
+
+    /// Stores or clears the key for a provider, then persists all keys
+    pub fn set(&self, provider: &str, key: Option<String>) -> anyhow::Result<()> {
+        self.keys.rcu(|keys| match &key {
+            Some(key) => keys.update(provider.to_string(), key.clone()),
+            None => keys.without(provider),
+        });
+
+        self.save()
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let text = serde_yml::to_string(self.keys.load().as_ref())?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt as _, PermissionsExt as _};
+            options.mode(0o600);
+
+            // Mode only applies to new files
+            if path.is_file() {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(path)?;
+        file.write_all(text.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_read(provider: &str) -> anyhow::Result<Option<String>> {
+    match keyring::Entry::new(KEYRING_SERVICE, provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_read(_provider: &str) -> anyhow::Result<Option<String>> {
+    Ok(None)
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_write(provider: &str, key: Option<&str>) -> anyhow::Result<()> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, provider)?;
+    match key {
+        Some(key) => entry.set_password(key)?,
+        None => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(err) => Err(err)?,
+        },
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "keyring"))]
+fn keyring_write(_provider: &str, _key: Option<&str>) -> anyhow::Result<()> {
+    anyhow::bail!("Built without keyring support")
+}
+
+/// Shows a key with all but the last few characters hidden
+pub fn mask_key(key: &str) -> String {
+    let chars = key.chars().count();
+    if chars <= 8 {
+        return "•".repeat(chars);
+    }
+
+    let tail: String = key.chars().skip(chars - 4).collect();
+    format!("{}{tail}", "•".repeat(8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_key() {
+        assert_eq!(mask_key(""), "");
+        assert_eq!(mask_key("abc"), "•••");
+        assert_eq!(mask_key("sk-1234567890abcd"), "••••••••abcd");
+    }
+
+    #[test]
+    fn test_roundtrip() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join(format!("aerie-keys-{}.yml", std::process::id()));
+
+        let keys = ApiKeys::load(&path)?;
+        keys.set("openai", Some("sk-test".into()))?;
+        keys.set("groq", Some("gsk-test".into()))?;
+        keys.set("groq", None)?;
+
+        let keys = ApiKeys::load(&path)?;
+        assert_eq!(keys.get("openai").as_deref(), Some("sk-test"));
+        assert!(!keys.contains("groq"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
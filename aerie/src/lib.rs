@@ -2,6 +2,7 @@ pub mod agent;
 pub mod app;
 pub mod chat;
 pub mod config;
+pub mod keys;
 pub mod logging;
 pub mod pipeline;
 pub mod storage;
@@ -1,9 +1,18 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use arc_swap::ArcSwap;
 use tracing::Subscriber;
 use tracing_subscriber::{Layer, layer::Context, registry::LookupSpan};
 
+/// Number of log entries kept in memory when not configured
+pub const DEFAULT_LOG_CAPACITY: usize = 5000;
+
 // TODO: preserve more data
 #[derive(Debug, Clone)]
-pub struct LogEntry(pub tracing::Level, pub String);
+pub struct LogEntry(pub tracing::Level, pub String, pub String);
 
 impl LogEntry {
     pub fn level(&self) -> tracing::Level {
@@ -13,6 +22,53 @@ impl LogEntry {
     pub fn message(&self) -> &str {
         &self.1
     }
+
+    pub fn target(&self) -> &str {
+        &self.2
+    }
+}
+
+/// Ring buffer of the most recent log entries, shared between the drain task and UI
+#[derive(Debug, Clone, Default)]
+pub struct LogHistory {
+    entries: Arc<ArcSwap<im::Vector<LogEntry>>>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl LogHistory {
+    /// Appends an entry, evicting the oldest entries beyond the capacity
+    pub fn push(&self, entry: LogEntry, capacity: usize) {
+        let mut evicted = 0;
+        self.entries.rcu(|logs| {
+            let mut logs = logs.as_ref().clone();
+            logs.push_back(entry.clone());
+
+            evicted = logs.len().saturating_sub(capacity);
+            if evicted > 0 {
+                logs.skip(evicted)
+            } else {
+                logs
+            }
+        });
+
+        if evicted > 0 {
+            self.dropped.fetch_add(evicted, Ordering::Relaxed);
+        }
+    }
+
+    pub fn entries(&self) -> Arc<im::Vector<LogEntry>> {
+        self.entries.load_full()
+    }
+
+    /// Number of entries evicted or cleared since startup
+    pub fn dropped(&self) -> usize {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn clear(&self) {
+        let old = self.entries.swap(Default::default());
+        self.dropped.fetch_add(old.len(), Ordering::Relaxed);
+    }
 }
 
 #[derive(Clone)]
@@ -45,8 +101,46 @@ where
                 .send(LogEntry(
                     *event.metadata().level(),
                     msg.trim_matches('"').to_string(),
+                    event.metadata().target().to_string(),
                 ))
                 .unwrap();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(i: usize) -> LogEntry {
+        LogEntry(tracing::Level::INFO, i.to_string(), "test".into())
+    }
+
+    #[test]
+    fn test_history_keeps_recent() {
+        let history = LogHistory::default();
+        for i in 0..10 {
+            history.push(entry(i), 4);
+        }
+
+        let messages = history
+            .entries()
+            .iter()
+            .map(|e| e.message().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["6", "7", "8", "9"]);
+        assert_eq!(history.dropped(), 6);
+    }
+
+    #[test]
+    fn test_history_clear() {
+        let history = LogHistory::default();
+        for i in 0..3 {
+            history.push(entry(i), 10);
+        }
+
+        history.clear();
+        assert!(history.entries().is_empty());
+        assert_eq!(history.dropped(), 3);
+    }
+}
@@ -536,6 +536,25 @@ impl Toolbox {
         result
     }
 
+    /// Definitions of the selected tools, ordered by provider then tool name.
+    ///
+    /// Rig's toolset is hash-based, so its own listing varies between runs.
+    pub async fn get_tool_definitions(
+        &self,
+        toolset: &ToolSelector,
+    ) -> Result<Vec<rig::completion::ToolDefinition>, rig::tool::ToolSetError> {
+        let mut result = vec![];
+        let providers = self.providers.load();
+        for (name, provider) in providers.as_ref() {
+            let tools = provider.get_tools(name, |tool| toolset.apply(name, tool));
+            let mut defs = tools.get_tool_definitions().await?;
+            defs.sort_by(|a, b| a.name.cmp(&b.name));
+            result.extend(defs);
+        }
+
+        Ok(result)
+    }
+
     pub fn select_tools<M: CompletionModel>(
         &self,
         agent: AgentBuilder<M>,
@@ -684,3 +703,57 @@ impl crate::storage::CachedDirStore<ToolSpec> for ToolStore {
         self.cache.rcu(|cache| cb(cache));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::store::WorkflowStore as _;
+
+    #[tokio::test]
+    async fn test_stable_tool_order() -> anyhow::Result<()> {
+        let toolbox = Toolbox::default();
+
+        for provider in ["second", "first"] {
+            let dir =
+                std::env::temp_dir().join(format!("aerie-tools-{}-{provider}", std::process::id()));
+            std::fs::create_dir_all(&dir)?;
+
+            let mut workflows = WorkflowStoreDir::load_all(&dir, false)?;
+            for name in ["zeta", "alpha", "mu", "beta", "omega"] {
+                workflows.save(name, Default::default())?;
+            }
+
+            toolbox.with_provider(
+                provider,
+                ToolProvider::Chainer {
+                    workflows,
+                    next_workflow: Default::default(),
+                    next_prompt: Default::default(),
+                },
+            );
+        }
+
+        let toolset = ToolSelector::all();
+        let names = |defs: Vec<rig::completion::ToolDefinition>| {
+            defs.into_iter().map(|d| d.name).collect_vec()
+        };
+
+        let expected = names(toolbox.get_tool_definitions(&toolset).await?);
+        assert_eq!(expected.len(), 12);
+        assert_eq!(&expected[..6], &expected[6..]);
+        assert!(expected[..6].is_sorted());
+
+        for _ in 0..10 {
+            let names = names(toolbox.get_tool_definitions(&toolset).await?);
+            assert_eq!(names, expected);
+        }
+
+        for provider in ["second", "first"] {
+            let dir =
+                std::env::temp_dir().join(format!("aerie-tools-{}-{provider}", std::process::id()));
+            std::fs::remove_dir_all(dir)?;
+        }
+
+        Ok(())
+    }
+}
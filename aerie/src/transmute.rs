@@ -4,18 +4,235 @@ use jaq_core::{
     load::{Arena, File, Loader},
 };
 use jaq_json::Val;
+use kinded::Kinded as _;
 use minijinja::Environment;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Arc;
 use typed_builder::TypedBuilder;
 
+use crate::{
+    utils::message_text,
+    workflow::{self, DynNode, ValueKind, WorkflowError},
+};
+
+/// Implicit conversion applied to a node input when the wire kind isn't accepted by the pin.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Conversion {
+    IntegerToNumber,
+    IntegerToText,
+    NumberToText,
+    NumberToInteger,
+    TextToNumber,
+    TextToInteger,
+    TextToJson,
+    JsonToText,
+    MessageToText,
+}
+
+impl Conversion {
+    pub const ALL: &[Conversion] = &[
+        Conversion::IntegerToNumber,
+        Conversion::IntegerToText,
+        Conversion::NumberToText,
+        Conversion::NumberToInteger,
+        Conversion::TextToNumber,
+        Conversion::TextToInteger,
+        Conversion::TextToJson,
+        Conversion::JsonToText,
+        Conversion::MessageToText,
+    ];
+
+    /// Conversions that cannot fail are enabled by default.
+    ///
+    /// Integers beyond 2^53 in magnitude are rounded to the nearest representable number.
+    pub const DEFAULT: &[Conversion] = &[
+        Conversion::IntegerToNumber,
+        Conversion::IntegerToText,
+        Conversion::NumberToText,
+        Conversion::JsonToText,
+        Conversion::MessageToText,
+    ];
+
+    pub fn source(&self) -> ValueKind {
+        use Conversion::*;
+        match self {
+            IntegerToNumber | IntegerToText => ValueKind::Integer,
+            NumberToText | NumberToInteger => ValueKind::Number,
+            TextToNumber | TextToInteger | TextToJson => ValueKind::Text,
+            JsonToText => ValueKind::Json,
+            MessageToText => ValueKind::Message,
+        }
+    }
+
+    pub fn target(&self) -> ValueKind {
+        use Conversion::*;
+        match self {
+            IntegerToText | NumberToText | JsonToText | MessageToText => ValueKind::Text,
+            IntegerToNumber | TextToNumber => ValueKind::Number,
+            NumberToInteger | TextToInteger => ValueKind::Integer,
+            TextToJson => ValueKind::Json,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{:?} → {:?}", self.source(), self.target())
+    }
+
+    pub fn tooltip(&self) -> &'static str {
+        use Conversion::*;
+        match self {
+            IntegerToNumber => "Widen integers to floating point numbers",
+            IntegerToText => "Format integers as decimal text",
+            NumberToText => "Format numbers as decimal text",
+            NumberToInteger => "Use numbers without a fractional part as integers",
+            TextToNumber => "Parse text as a floating point number",
+            TextToInteger => "Parse text as an integer",
+            TextToJson => "Parse text as a JSON document",
+            JsonToText => "Use JSON strings as-is or serialize other JSON values",
+            MessageToText => "Extract the text content of a message",
+        }
+    }
+
+    pub fn apply(&self, value: &workflow::Value) -> Result<workflow::Value, WorkflowError> {
+        use workflow::Value as V;
+
+        let value = match (self, value) {
+            (Conversion::IntegerToNumber, V::Integer(num)) => V::float(*num as f64),
+            (Conversion::IntegerToText, V::Integer(num)) => V::text(num.to_string()),
+            (Conversion::NumberToText, V::Number(num)) => V::text(num.into_inner().to_string()),
+            (Conversion::NumberToInteger, V::Number(num)) => {
+                let num = num.into_inner();
+                if num.fract() != 0.0 || num < i64::MIN as f64 || num > i64::MAX as f64 {
+                    Err(WorkflowError::Conversion(format!(
+                        "{num} is not an integer"
+                    )))?
+                }
+                V::Integer(num as i64)
+            }
+            (Conversion::TextToNumber, V::Text(text)) => {
+                let num = text.trim().parse::<f64>().map_err(|err| {
+                    WorkflowError::Conversion(format!("Cannot parse {text:?} as a number: {err}"))
+                })?;
+                V::float(num)
+            }
+            (Conversion::TextToInteger, V::Text(text)) => {
+                let num = text.trim().parse::<i64>().map_err(|err| {
+                    WorkflowError::Conversion(format!("Cannot parse {text:?} as an integer: {err}"))
+                })?;
+                V::Integer(num)
+            }
+            (Conversion::TextToJson, V::Text(text)) => {
+                let value = serde_json::from_str(text).map_err(|err| {
+                    WorkflowError::Conversion(format!("Cannot parse text as JSON: {err}"))
+                })?;
+                V::Json(Arc::new(value))
+            }
+            (Conversion::JsonToText, V::Json(value)) => match value.as_ref() {
+                Value::String(text) => V::text(text),
+                other => V::text(other.to_string()),
+            },
+            (Conversion::MessageToText, V::Message(message)) => V::text(message_text(message)),
+            (conversion, value) => Err(WorkflowError::Conversion(format!(
+                "{conversion:?} does not apply to {:?}",
+                value.kind()
+            )))?,
+        };
+
+        Ok(value)
+    }
+}
+
+/// The set of implicit conversions allowed between pin kinds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConversionRules(pub im::OrdSet<Conversion>);
+
+impl Default for ConversionRules {
+    fn default() -> Self {
+        Self(Conversion::DEFAULT.iter().copied().collect())
+    }
+}
+
+impl ConversionRules {
+    pub fn none() -> Self {
+        Self(im::OrdSet::new())
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_enabled(&self, conversion: Conversion) -> bool {
+        self.0.contains(&conversion)
+    }
+
+    pub fn set_enabled(&mut self, conversion: Conversion, enabled: bool) {
+        if enabled {
+            self.0.insert(conversion);
+        } else {
+            self.0.remove(&conversion);
+        }
+    }
+
+    /// Finds an enabled conversion from a wire kind into one of the kinds accepted by a pin
+    pub fn find(&self, source: ValueKind, targets: &[ValueKind]) -> Option<Conversion> {
+        targets.iter().find_map(|target| {
+            self.0
+                .iter()
+                .find(|c| c.source() == source && c.target() == *target)
+                .copied()
+        })
+    }
+
+    pub fn accepts(&self, source: ValueKind, targets: &[ValueKind]) -> bool {
+        targets.contains(&source) || self.find(source, targets).is_some()
+    }
+}
+
 // Pointless as a struct if we're not caching filters or templates
 /// Utility for rendering templates, transforming JSON, and other data conversions
 #[derive(Default, Clone, TypedBuilder)]
-pub struct Transmuter {}
+pub struct Transmuter {
+    #[builder(default)]
+    pub rules: ConversionRules,
+}
 
 pub type FilterT = Filter<Native<Val>>;
 
 impl Transmuter {
+    pub fn with_rules(&self, rules: ConversionRules) -> Self {
+        Self {
+            rules,
+            ..self.clone()
+        }
+    }
+
+    /// Converts inputs not accepted by their pins using the enabled conversion rules.
+    /// Inputs without an applicable rule are left alone for the node to validate.
+    pub fn coerce_inputs(
+        &self,
+        node: &dyn DynNode,
+        mut inputs: Vec<Option<workflow::Value>>,
+    ) -> Result<Vec<Option<workflow::Value>>, WorkflowError> {
+        for (pin, input) in inputs.iter_mut().enumerate() {
+            let Some(value) = input else {
+                continue;
+            };
+
+            let kinds = node.in_kinds(pin);
+            if kinds.contains(&value.kind()) {
+                continue;
+            }
+
+            if let Some(conversion) = self.rules.find(value.kind(), &kinds) {
+                tracing::debug!("Applying {conversion:?} to input pin #{pin}");
+                *input = Some(conversion.apply(value)?);
+            }
+        }
+
+        Ok(inputs)
+    }
+
     // Using anyhow has a placeholder until we can figure out a better way to handle Jaq's errors
     pub fn init_filter(&self, filter: &str) -> anyhow::Result<FilterT> {
         let arena = Arena::default();
@@ -94,3 +311,146 @@ impl Transmuter {
         Ok(tmpl.render(vars)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use serde_json::json;
+    use serde_yaml_ng as serde_yml;
+
+    use super::*;
+    use crate::{config::Settings, rig::message::Message, workflow::Value as V};
+
+    /// Single input node accepting only the given kind
+    struct Sink(ValueKind);
+
+    impl DynNode for Sink {
+        fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+            Cow::Owned(vec![self.0])
+        }
+    }
+
+    fn coerce(rules: ConversionRules, value: V, target: ValueKind) -> Option<V> {
+        let transmuter = Transmuter::builder().rules(rules).build();
+        transmuter
+            .coerce_inputs(&Sink(target), vec![Some(value)])
+            .unwrap()
+            .pop()
+            .unwrap()
+    }
+
+    fn sample(conversion: Conversion) -> (V, V) {
+        use Conversion::*;
+        match conversion {
+            IntegerToNumber => (V::Integer(3), V::float(3.0)),
+            IntegerToText => (V::Integer(-42), V::text("-42")),
+            NumberToText => (V::float(1.5), V::text("1.5")),
+            NumberToInteger => (V::float(7.0), V::Integer(7)),
+            TextToNumber => (V::text(" 2.25 "), V::float(2.25)),
+            TextToInteger => (V::text("17\n"), V::Integer(17)),
+            TextToJson => (
+                V::text(r#"{"a": [1, 2]}"#),
+                V::Json(Arc::new(json!({"a": [1, 2]}))),
+            ),
+            JsonToText => (V::Json(Arc::new(json!({"a": 1}))), V::text(r#"{"a":1}"#)),
+            MessageToText => (V::Message(Message::user("hello")), V::text("hello")),
+        }
+    }
+
+    #[test]
+    fn test_default_matrix() {
+        let rules = ConversionRules::default();
+
+        for conversion in Conversion::ALL {
+            assert_eq!(
+                rules.is_enabled(*conversion),
+                Conversion::DEFAULT.contains(conversion),
+                "{conversion:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_enabled_conversions() {
+        for conversion in Conversion::ALL {
+            let mut rules = ConversionRules::none();
+            rules.set_enabled(*conversion, true);
+
+            let (input, expected) = sample(*conversion);
+            let actual = coerce(rules, input, conversion.target());
+            assert_eq!(actual, Some(expected), "{conversion:?}");
+        }
+    }
+
+    #[test]
+    fn test_disabled_conversions() {
+        for conversion in Conversion::ALL {
+            let mut rules = ConversionRules(Conversion::ALL.iter().copied().collect());
+            rules.set_enabled(*conversion, false);
+
+            // Left untouched for the node to reject during validation
+            let (input, _) = sample(*conversion);
+            let actual = coerce(rules.clone(), input.clone(), conversion.target());
+            assert_eq!(actual, Some(input), "{conversion:?}");
+            assert!(!rules.accepts(conversion.source(), &[conversion.target()]));
+        }
+    }
+
+    #[test]
+    fn test_accepted_kinds_untouched() {
+        let rules = ConversionRules(Conversion::ALL.iter().copied().collect());
+        let actual = coerce(rules, V::text("12"), ValueKind::Text);
+        assert_eq!(actual, Some(V::text("12")));
+    }
+
+    #[test]
+    fn test_failed_conversions() {
+        let transmuter = Transmuter::builder()
+            .rules(ConversionRules(Conversion::ALL.iter().copied().collect()))
+            .build();
+
+        let cases = [
+            (V::text("twelve"), ValueKind::Number),
+            (V::text("1.5"), ValueKind::Integer),
+            (V::text("{nope"), ValueKind::Json),
+            (V::float(1.5), ValueKind::Integer),
+        ];
+
+        for (input, target) in cases {
+            let result = transmuter.coerce_inputs(&Sink(target), vec![Some(input.clone())]);
+            assert!(
+                matches!(result, Err(WorkflowError::Conversion(_))),
+                "{input:?} -> {target:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_string_to_text() {
+        let rules = ConversionRules::default();
+        let actual = coerce(rules, V::Json(Arc::new(json!("plain"))), ValueKind::Text);
+        assert_eq!(actual, Some(V::text("plain")));
+    }
+
+    #[test]
+    fn test_rules_roundtrip() {
+        let mut settings = Settings::default();
+        settings
+            .conversions
+            .set_enabled(Conversion::TextToJson, true);
+        settings
+            .conversions
+            .set_enabled(Conversion::MessageToText, false);
+
+        let text = serde_yml::to_string(&settings).unwrap();
+        let restored: Settings = serde_yml::from_str(&text).unwrap();
+        assert_eq!(restored.conversions, settings.conversions);
+
+        // Defaults are omitted when saving and restored when loading
+        let text = serde_yml::to_string(&Settings::default()).unwrap();
+        assert!(!text.contains("conversions"));
+        let restored: Settings = serde_yml::from_str(&text).unwrap();
+        assert!(restored.conversions.is_default());
+    }
+}
@@ -0,0 +1,54 @@
+use egui::{Rect, Sense, Stroke, StrokeKind, Vec2, emath::TSTransform, vec2};
+
+use super::workflow::WorkflowViewer;
+
+/// Size of the overview drawn in the corner of the graph editor
+const MINIMAP_SIZE: Vec2 = vec2(200.0, 140.0);
+
+/// Nominal extent of a node, since the shadow graph only records positions
+pub const NODE_EXTENT: Vec2 = vec2(160.0, 80.0);
+
+impl WorkflowViewer {
+    /// Draws node bounds and the visible region at small scale.
+    ///
+    /// Clicking or dragging recenters the editor on the pointer.
+    pub fn show_minimap(&mut self, ui: &mut egui::Ui) {
+        let view = self.transform.inverse() * self.viewport;
+
+        let bounds = self
+            .shadow
+            .nodes
+            .values()
+            .map(|node| Rect::from_min_size(node.pos, NODE_EXTENT))
+            .fold(view, |acc, rect| acc.union(rect));
+
+        let (response, painter) = ui.allocate_painter(MINIMAP_SIZE, Sense::click_and_drag());
+        let rect = response.rect.shrink(4.0);
+
+        let scale = (rect.width() / bounds.width()).min(rect.height() / bounds.height());
+        let to_mini = TSTransform::new(
+            rect.center().to_vec2() - bounds.center().to_vec2() * scale,
+            scale,
+        );
+
+        let visuals = ui.visuals();
+        for node in self.shadow.nodes.values() {
+            let node_rect = to_mini * Rect::from_min_size(node.pos, NODE_EXTENT);
+            painter.rect_filled(node_rect, 1.0, visuals.widgets.inactive.bg_fill);
+        }
+
+        painter.rect_stroke(
+            to_mini * view,
+            2.0,
+            Stroke::new(1.5, visuals.selection.stroke.color),
+            StrokeKind::Inside,
+        );
+
+        if (response.clicked() || response.dragged())
+            && let Some(pointer) = response.interact_pointer_pos()
+        {
+            self.center_on(to_mini.inverse() * pointer);
+            ui.ctx().request_repaint();
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use eframe::egui;
 use egui::WidgetText;
 
+pub mod minimap;
 pub mod runner;
+pub mod search;
 pub mod shortcuts;
 pub mod state;
 pub mod tiles;
@@ -13,7 +15,7 @@ use uuid::Uuid;
 
 use crate::{
     utils::PriorityQueue,
-    workflow::{AnyPin, GraphId},
+    workflow::{AnyPin, GraphId, WorkNode, runner::ExecId},
 };
 
 pub enum Pane {
@@ -41,6 +43,10 @@ pub enum AppEvent {
 
     DisableNode(GraphId, NodeId),
 
+    /// Replaces a node with its state after a run, for nodes that remember values between runs.
+    /// Only reaches graphs in the current ViewStack.
+    UpdateNode(ExecId, NodeId, WorkNode),
+
     /// Removes a pin from a node of a graph. Graph must be in the current ViewStack.
     PinRemoved(GraphId, AnyPin),
 
@@ -52,11 +58,20 @@ pub enum AppEvent {
     // User requested to run the current workflow
     UserRunWorkflow,
 
+    /// Runs the current workflow even if it has unreachable nodes
+    UserRunAnyway,
+
     NodesChanged(GraphId, im::OrdSet<NodeId>),
     RerunNodes(GraphId, Vec<NodeId>),
 
+    /// Executes one node of the graph in view without running the rest of the workflow
+    RunNode(GraphId, NodeId),
+
     SetPrompt(String),
 
+    /// Puts text on the clipboard, which only the UI thread can reach
+    CopyText(String),
+
     Freeze(Option<bool>),
     Undo,
     Redo,
@@ -71,7 +86,7 @@ impl AppEvent {
         use AppEvent::*;
         match self {
             EnterSubgraph(_) | LeaveSubgraph(_) => -100,
-            UserRunWorkflow | SetPrompt(_) => -200,
+            UserRunWorkflow | UserRunAnyway | SetPrompt(_) => -200,
             _ => 0,
         }
     }
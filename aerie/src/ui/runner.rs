@@ -7,25 +7,119 @@ use std::{
 use arc_swap::ArcSwap;
 use egui::RichText;
 use egui_phosphor::regular::{PLAY, STOP};
+use egui_snarl::NodeId;
 use scopeguard::defer;
 
 use crate::{
-    config::ConfigExt as _,
+    ToolSelector,
+    chat::Attachments,
+    config::{ConfigExt as _, Ternary},
+    toolbox::ChainTool,
     utils::ErrorDistiller as _,
     workflow::{
-        RootContext, RunContext,
-        runner::{WorkflowRun, WorkflowRunner},
+        GraphId, RootContext, RunContext, WorkflowError,
+        runner::{ExecId, WorkflowRun, WorkflowRunner},
+        store::WorkflowStore as _,
     },
 };
 
 impl super::AppState {
+    /// Tools for the chat agent when running without a selected workflow
+    fn chat_tools(&self) -> Option<Arc<ToolSelector>> {
+        use crate::rig::tool::Tool as _;
+
+        let (enabled, source, toolset) = self.settings.view(|s| {
+            (
+                s.chat_tools,
+                s.chat_tool_source.clone(),
+                s.chat_toolset.clone(),
+            )
+        });
+
+        if !enabled || !self.workflows.editing.is_empty() {
+            return None;
+        }
+
+        let toolset = match source {
+            Some(name) => self.workflows.store.get(&name)?.graph.tool_selection(),
+            None => toolset,
+        };
+
+        // Chaining only makes sense from inside a workflow
+        let toolbox = &self.agent_factory.toolbox;
+        let toolset = toolbox.toggle_provider(&toolset, ChainTool::NAME, Ternary::None);
+
+        Some(Arc::new(toolset))
+    }
+
+    fn run_context(&self, exec_id: ExecId) -> RunContext {
+        RunContext::builder()
+            .runtime(self.rt.clone())
+            .exec_id(exec_id)
+            .agent_factory(self.agent_factory.clone())
+            .metadata(self.workflows.shadow.metadata.clone())
+            .events(Some(self.events.clone()))
+            .node_state(self.workflows.node_state.clone())
+            .cache(self.workflows.cache.clone())
+            .previews(self.workflows.previews.clone())
+            .transmuter(
+                self.transmuter
+                    .with_rules(self.settings.view(|s| s.conversions.clone())),
+            )
+            .interrupt(self.workflows.interrupt.clone())
+            .history(self.session.history.clone())
+            .seed(self.settings.view(|s| s.seed.clone()))
+            .default_tools(self.chat_tools())
+            .call_stack(im::vector![self.workflows.editing.clone()])
+            .errors(self.errors.clone())
+            .scratch(Some(self.session.scratch.clone()))
+            .progress(Some(self.session.progress.clone()))
+            .attachments(Arc::new(Attachments {
+                prompt: self.prompt.clone(),
+                images: self
+                    .attachments
+                    .iter()
+                    .map(|(_, image)| image.clone())
+                    .collect(),
+            }))
+            .streaming(self.settings.view(|s| s.streaming))
+            .concurrent(self.settings.view(|s| s.concurrent_nodes))
+            .node_timeout(self.settings.view(|s| s.node_timeout))
+            .build()
+    }
+
+    /// Inputs of the Start node in the root graph
+    fn root_inputs(&self) -> Vec<Option<crate::workflow::Value>> {
+        RootContext::builder()
+            .history(self.session.history.clone())
+            .workflow(self.workflows.shadow.clone())
+            .user_prompt(self.prompt.clone())
+            .model(self.settings.view(|s| s.llm_model.clone()))
+            .temperature(self.settings.view(|s| s.temperature))
+            .build()
+            .inputs()
+            .unwrap()
+    }
+
     /// Runs the workflow currently being edited and updates nodes in the viewer with results.
     pub fn exec_workflow(&mut self) {
+        if self
+            .errors
+            .distil(
+                self.workflows
+                    .shadow
+                    .graph
+                    .check_results()
+                    .map_err(|e| e.into()),
+            )
+            .is_none()
+        {
+            return;
+        }
+
         let mut target = self.workflows.view_stack.root_snarl().unwrap();
         let task_count_ = self.task_count.clone();
 
-        let prompt = self.prompt.clone();
-
         self.settings
             .update(|s| s.automation = Some(self.workflows.editing.clone()));
 
@@ -33,32 +127,9 @@ impl super::AppState {
 
         let exec_id = self.workflows.shadow.graph.uuid.into();
         let mut exec = {
-            let run_ctx = RunContext::builder()
-                .runtime(self.rt.clone())
-                .exec_id(exec_id)
-                .agent_factory(self.agent_factory.clone())
-                .metadata(self.workflows.shadow.metadata.clone())
-                .events(Some(self.events.clone()))
-                .node_state(self.workflows.node_state.clone())
-                .previews(self.workflows.previews.clone())
-                .transmuter(self.transmuter.clone())
-                .interrupt(self.workflows.interrupt.clone())
-                .history(self.session.history.clone())
-                .seed(self.settings.view(|s| s.seed.clone()))
-                .errors(self.errors.clone())
-                .scratch(Some(self.session.scratch.clone()))
-                .streaming(self.settings.view(|s| s.streaming))
-                .build();
+            let run_ctx = self.run_context(exec_id);
 
-            let inputs = RootContext::builder()
-                .history(self.session.history.clone())
-                .workflow(self.workflows.shadow.clone())
-                .user_prompt(prompt)
-                .model(self.settings.view(|s| s.llm_model.clone()))
-                .temperature(self.settings.view(|s| s.temperature))
-                .build()
-                .inputs()
-                .unwrap();
+            let inputs = self.root_inputs();
 
             self.workflows.interrupt.store(false, Ordering::Relaxed);
 
@@ -74,6 +145,7 @@ impl super::AppState {
         };
 
         let session = self.session.clone();
+        let prompt = self.prompt.clone();
         let running = self.workflows.running.clone();
         let errors = self.errors.clone();
         let interrupt = self.workflows.interrupt.clone();
@@ -96,6 +168,15 @@ impl super::AppState {
 
         thread::spawn(move || {
             let started = SystemTime::now();
+            let rx = exec.run_ctx.outputs.receiver();
+            let collect_outputs = || {
+                while let Ok((label, value)) = rx.try_recv() {
+                    tracing::debug!("Received output {label}: {value:?}");
+
+                    outputs.rcu(|it| it.update(label.clone(), value.clone()));
+                }
+            };
+
             task_count_.fetch_add(1, Ordering::Relaxed);
             running.store(true, std::sync::atomic::Ordering::Relaxed);
             defer! {
@@ -116,22 +197,24 @@ impl super::AppState {
                     }
                     Ok(true) => {}
                     Err(err) => {
+                        // Keep the turn around so it can be retried from the chat
+                        if !prompt.is_empty() && !matches!(*err, WorkflowError::Interrupted) {
+                            errors.distil(
+                                session
+                                    .transform(|history| history.push_failed_turn(&err, &prompt)),
+                            );
+                        }
                         errors.push(err.into());
                         break;
                     }
                 }
 
-                let rx = exec.run_ctx.outputs.receiver();
-                while !rx.is_empty() {
-                    let Ok((label, value)) = rx.recv() else {
-                        break;
-                    };
-                    tracing::debug!("Received output {label}: {value:?}");
-
-                    outputs.rcu(|it| it.update(label.clone(), value.clone()));
-                }
+                collect_outputs();
             }
 
+            // Results from the Finish node arrive after the last step
+            collect_outputs();
+
             duration.store(Arc::new(started.elapsed().unwrap_or_default()));
             errors.distil(session.save());
 
@@ -142,6 +225,60 @@ impl super::AppState {
             }
         });
     }
+
+    /// Runs a single node of the graph in view using the latest results of its upstream nodes.
+    ///
+    /// Other nodes keep their current state, so this can be repeated while tweaking the node.
+    pub fn exec_node(&mut self, graph_id: GraphId, node_id: NodeId) {
+        let stack = &self.workflows.view_stack;
+        let shadow = stack.leaf();
+        if shadow.uuid != graph_id || !shadow.nodes.contains_key(&node_id) {
+            tracing::warn!("Node {node_id:?} is not in the graph in view");
+            return;
+        }
+
+        let Some(exec_id) = stack.exec_id() else {
+            return;
+        };
+
+        let inputs = if stack.is_empty() {
+            self.root_inputs()
+        } else {
+            vec![]
+        };
+
+        let Some(mut target) = self.errors.distil(stack.leaf_snarl()) else {
+            return;
+        };
+
+        let mut exec = WorkflowRunner::builder()
+            .graph(shadow.repair())
+            .inputs(inputs)
+            .run_ctx(self.run_context(exec_id))
+            .state_view(self.workflows.node_state.view(exec_id))
+            .build();
+
+        exec.calculate_deps();
+
+        self.workflows.interrupt.store(false, Ordering::Relaxed);
+
+        let task_count_ = self.task_count.clone();
+        let running = self.workflows.running.clone();
+        let errors = self.errors.clone();
+
+        thread::spawn(move || {
+            task_count_.fetch_add(1, Ordering::Relaxed);
+            running.store(true, Ordering::Relaxed);
+            defer! {
+                task_count_.fetch_sub(1, Ordering::Relaxed);
+                running.store(false, Ordering::Relaxed);
+            };
+
+            if let Err(err) = exec.run_node(&mut target, node_id) {
+                errors.push(err.into());
+            }
+        });
+    }
 }
 
 pub fn play_button() -> egui::Button<'static> {
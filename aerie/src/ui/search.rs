@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use egui_snarl::NodeId;
+use itertools::Itertools as _;
+
+use crate::workflow::{GraphId, ShadowGraph, WorkNode, nodes::Subgraph};
+
+/// How long a node found by search stays highlighted
+pub const HIGHLIGHT_DURATION: Duration = Duration::from_secs(2);
+
+/// A node matching a search of the current view
+#[derive(Debug, Clone)]
+pub struct NodeHit {
+    /// Subgraph containers to enter, from the root graph down to the node's graph
+    pub path: Vec<NodeId>,
+
+    /// Titles of the subgraphs along the path
+    pub location: Vec<String>,
+
+    pub graph: GraphId,
+    pub node: NodeId,
+    pub title: String,
+    pub kind: String,
+}
+
+impl NodeHit {
+    pub fn label(&self) -> String {
+        self.location
+            .iter()
+            .chain(std::iter::once(&self.title))
+            .join(" › ")
+    }
+}
+
+/// State of the node search box in the workflow controls
+#[derive(Debug, Clone, Default)]
+pub struct NodeSearch {
+    pub query: String,
+    pub hits: Vec<NodeHit>,
+    pub selected: usize,
+
+    /// Whether to also search inside subgraphs
+    pub nested: bool,
+
+    /// Node to center once its graph is in view
+    pub focus: Option<(GraphId, NodeId)>,
+
+    /// Node to outline, and when it was found
+    pub highlight: Option<(NodeId, Instant)>,
+}
+
+impl NodeSearch {
+    /// Remembers the hit to center and highlight on the next frames
+    pub fn focus(&mut self, hit: &NodeHit) {
+        self.focus = Some((hit.graph, hit.node));
+        self.highlight = Some((hit.node, Instant::now()));
+    }
+}
+
+/// Finds nodes whose title or kind contains the query, ignoring case.
+///
+/// Descends into subgraphs when `nested` is set.
+pub fn search_nodes(graph: &ShadowGraph<WorkNode>, query: &str, nested: bool) -> Vec<NodeHit> {
+    let mut hits = vec![];
+    let query = query.trim().to_lowercase();
+    if !query.is_empty() {
+        collect_hits(graph, &query, nested, &mut vec![], &mut vec![], &mut hits);
+    }
+
+    hits
+}
+
+fn collect_hits(
+    graph: &ShadowGraph<WorkNode>,
+    query: &str,
+    nested: bool,
+    path: &mut Vec<NodeId>,
+    location: &mut Vec<String>,
+    hits: &mut Vec<NodeHit>,
+) {
+    for (node_id, meta) in graph.nodes.iter() {
+        let title = meta.value.as_ui().title();
+        let kind = meta.value.kind();
+
+        if title.to_lowercase().contains(query) || kind.to_lowercase().contains(query) {
+            hits.push(NodeHit {
+                path: path.clone(),
+                location: location.clone(),
+                graph: graph.uuid,
+                node: *node_id,
+                title: title.to_string(),
+                kind: kind.to_string(),
+            });
+        }
+
+        if nested && let Some(subgraph) = meta.value.as_node::<Subgraph>() {
+            path.push(*node_id);
+            location.push(title.to_string());
+            collect_hits(&subgraph.graph, query, nested, path, location, hits);
+            location.pop();
+            path.pop();
+        }
+    }
+}
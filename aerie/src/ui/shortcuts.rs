@@ -71,6 +71,9 @@ pub enum Shortcut {
 
     #[assoc(key=shortcut(CTRL, Key::T))]
     FormatCode,
+
+    #[assoc(key=shortcut(CTRL, Key::F))]
+    FindNode,
 }
 
 pub const SHORTCUT_QUIT: KeyboardShortcut = Shortcut::Quit.key();
@@ -110,6 +113,8 @@ pub const SHORTCUT_DISABLE_NODE: KeyboardShortcut = Shortcut::DisableNode.key();
 
 pub const SHORTCUT_REMOVE_NODE: KeyboardShortcut = Shortcut::RemoveNode.key();
 
+pub const SHORTCUT_FIND: KeyboardShortcut = Shortcut::FindNode.key();
+
 #[derive(TypedBuilder)]
 pub struct ShortcutHandler<'a> {
     pub snarl: &'a mut Snarl<WorkNode>,
@@ -328,6 +333,12 @@ pub fn show_shortcuts(ui: &mut egui::Ui, scope: ShowHelp) {
                     ui.label("Redo undone edits");
                     ui.end_row();
 
+                    if scope == ShowHelp::Workflow {
+                        render_shortcut(ui, SHORTCUT_FIND);
+                        ui.label("Search for nodes by title");
+                        ui.end_row();
+                    }
+
                     if scope == ShowHelp::Subgraph {
                         render_shortcut(ui, SHORTCUT_EXIT_SUBGRAPH);
                         ui.label("Leave the subgraph");
@@ -1,3 +1,4 @@
+use crate::rig::message::UserContent;
 use crate::rmcp::model::Tool;
 use arc_swap::ArcSwap;
 use eframe::egui;
@@ -23,26 +24,26 @@ use uuid::Uuid;
 
 use super::{Pane, workflow::ViewStack};
 use crate::{
-    AgentFactory, LogEntry, Settings, ToolSpec,
+    AgentFactory, Settings, ToolSpec,
     chat::ChatSession,
-    config::ConfigExt as _,
+    config::{ConfigExt as _, DEFAULT_UNDO_LIMIT},
+    logging::LogHistory,
     toolbox::ToolStore,
     transmute::Transmuter,
     ui::{
         AppEvent, ShowHelp,
-        tiles::messages::MessageGraph,
+        search::NodeSearch,
+        tiles::{logview::LogFilter, messages::MessageGraph},
         workflow::{ProgressEntry, WorkflowViewer},
     },
     utils::{ErrorDistiller as _, ErrorList},
     workflow::{
         EditContext, GraphId, PreviewData, ShadowGraph, WorkNode, Workflow,
-        runner::{ExecId, NodeStateMap, WorkflowRun},
+        runner::{ExecId, NodeCache, NodeStateMap, WorkflowRun},
         store::{WorkflowStore, WorkflowStoreDir},
     },
 };
 
-const SOFT_LIMIT: usize = 128;
-
 pub enum ToolEditorState {
     EditProvider {
         original: Option<(String, ToolSpec)>,
@@ -74,7 +75,10 @@ pub struct AppState {
     pub task_count: Arc<AtomicU16>,
 
     #[builder(default)]
-    pub log_history: Arc<arc_swap::ArcSwapAny<Arc<im::Vector<LogEntry>>>>,
+    pub log_history: LogHistory,
+
+    #[builder(default)]
+    pub log_filter: LogFilter,
 
     pub session: ChatSession,
 
@@ -83,6 +87,10 @@ pub struct AppState {
     #[builder(default)]
     pub prompt: String,
 
+    /// Images to send with the prompt, by file name
+    #[builder(default)]
+    pub attachments: Vec<(String, UserContent)>,
+
     #[builder(default)]
     pub run_count: usize,
 
@@ -129,6 +137,7 @@ impl AppState {
                 .flavor(stack.flavor())
                 .errors(self.errors.clone())
                 .previews(self.workflows.previews.clone())
+                .store(Some(self.workflows.store.clone()))
                 .build();
 
             let viewer = WorkflowViewer::builder()
@@ -146,6 +155,8 @@ impl AppState {
 
         let viewer = self.workflows.viewer.as_mut().unwrap();
         viewer.frozen = self.workflows.frozen;
+        viewer.highlight = self.workflows.search.highlight;
+        viewer.edit_ctx.conversions = self.settings.view(|s| s.conversions.clone());
         viewer.running = self
             .workflows
             .running
@@ -154,7 +165,7 @@ impl AppState {
         viewer
     }
 
-    pub fn handle_events(&mut self) {
+    pub fn handle_events(&mut self, ctx: &egui::Context) {
         use AppEvent::*;
 
         // Only allow one run request per frame
@@ -167,11 +178,18 @@ impl AppState {
 
             handled = handled
                 || match &event {
-                    UserRunWorkflow if !executed => {
-                        self.run_count = 0;
-                        self.workflows.node_state.clear();
-                        self.exec_workflow();
-                        executed = true;
+                    UserRunWorkflow | UserRunAnyway if !executed => {
+                        let unreachable = self.workflows.view_stack.root().unreachable_nodes();
+                        if matches!(event, UserRunWorkflow) && !unreachable.is_empty() {
+                            // Ask before running, since these nodes will never execute
+                            self.workflows.unreachable = unreachable.into_iter().collect();
+                        } else {
+                            self.workflows.unreachable.clear();
+                            self.run_count = 0;
+                            self.workflows.node_state.clear();
+                            self.exec_workflow();
+                            executed = true;
+                        }
                         true
                     }
                     NodesChanged(graph_id, nodes) => {
@@ -192,7 +210,19 @@ impl AppState {
                         executed = true;
                         true
                     }
+                    RunNode(graph_id, node) if !executed => {
+                        self.exec_node(*graph_id, *node);
+                        executed = true;
+                        true
+                    }
+                    CopyText(text) => {
+                        ctx.copy_text(text.clone());
+                        true
+                    }
                     SetPrompt(prompt) => {
+                        if prompt.is_empty() {
+                            self.attachments.clear();
+                        }
                         self.prompt = prompt.clone();
                         true
                     }
@@ -204,6 +234,8 @@ impl AppState {
             }
         }
 
+        self.workflows.undo_limit = self.settings.view(|s| s.undo_limit());
+
         let shadow = self.workflows.view_stack.root();
         self.workflows.cast_shadow(shadow);
     }
@@ -309,8 +341,19 @@ pub struct WorkflowState<W: WorkflowStore> {
     pub undo_stack: im::OrdMap<String, VecDeque<(SystemTime, Workflow)>>,
     pub redo_stack: im::OrdMap<String, VecDeque<(SystemTime, Workflow)>>,
 
+    /// Maximum depth of each undo/redo stack. Oldest entries are dropped past it.
+    pub undo_limit: usize,
+
     pub previews: PreviewData,
     pub outputs: im::Vector<WorkflowRun>,
+
+    /// Outputs of cacheable nodes reused by later runs
+    pub cache: NodeCache,
+
+    pub search: NodeSearch,
+
+    /// Nodes of the root graph found unreachable by the last run request, awaiting a decision
+    pub unreachable: Vec<NodeId>,
 }
 
 impl<W: WorkflowStore> WorkflowState<W> {
@@ -340,8 +383,12 @@ impl<W: WorkflowStore> WorkflowState<W> {
             node_state: Default::default(),
             undo_stack: Default::default(),
             redo_stack: Default::default(),
+            undo_limit: DEFAULT_UNDO_LIMIT,
             previews: Default::default(),
             outputs: Default::default(),
+            cache: Default::default(),
+            search: Default::default(),
+            unreachable: Default::default(),
         }
     }
 
@@ -367,10 +414,9 @@ impl<W: WorkflowStore> WorkflowState<W> {
         };
 
         // Stash current editee to preserve unsaved changes
-        self.undo_stack
-            .entry(self.editing.clone())
-            .or_default()
-            .push_front((self.modtime, self.shadow.clone()));
+        let undo_stack = self.undo_stack.entry(self.editing.clone()).or_default();
+        undo_stack.push_front((self.modtime, self.shadow.clone()));
+        undo_stack.truncate(self.undo_limit);
 
         self.baseline = target;
         if let Some(undos) = self.undo_stack.get_mut(workflow_name)
@@ -467,24 +513,13 @@ impl<W: WorkflowStore> WorkflowState<W> {
 
         undo_stack.push_front((self.modtime, self.shadow.clone()));
 
-        if undo_stack.len() > SOFT_LIMIT {
-            tracing::info!(
-                "Pruning undo stack for {} ({}). {:?}",
-                &self.editing,
-                undo_stack.len(),
-                undo_stack.iter().map(|it| it.0).collect_vec()
-            );
-            for i in 1..=(SOFT_LIMIT / 2) {
-                undo_stack.swap(i, i * 2);
-            }
-
-            undo_stack.truncate(SOFT_LIMIT / 2 + 1);
-            tracing::info!(
-                "Finished pruning undo stack for {} ({}). {:?}",
+        if undo_stack.len() > self.undo_limit {
+            tracing::debug!(
+                "Dropping {} oldest undo steps for {}",
+                undo_stack.len() - self.undo_limit,
                 &self.editing,
-                undo_stack.len(),
-                undo_stack.iter().map(|it| it.0).collect_vec()
             );
+            undo_stack.truncate(self.undo_limit);
         }
 
         self.redo_stack.remove(&self.editing);
@@ -553,6 +588,7 @@ impl<W: WorkflowStore> WorkflowState<W> {
 
         if let Some((mut modtime, mut shadow)) = undo_stack.pop_front() {
             redo_stack.push_front((self.modtime, self.shadow.clone()));
+            redo_stack.truncate(self.undo_limit);
 
             // Fast forward over duplicates
             while !undo_stack.is_empty() && self.shadow == shadow {
@@ -562,6 +598,7 @@ impl<W: WorkflowStore> WorkflowState<W> {
                 }
             }
 
+            let shadow = self.view_stack.stabilize(shadow);
             self.shadow = shadow.clone();
             self.modtime = modtime;
             self.switch_count += 1;
@@ -587,6 +624,8 @@ impl<W: WorkflowStore> WorkflowState<W> {
 
         if let Some((ts, shadow)) = redo_stack.pop_front() {
             undo_stack.push_front((self.modtime, self.shadow.clone()));
+            undo_stack.truncate(self.undo_limit);
+            let shadow = self.view_stack.stabilize(shadow);
             self.shadow = shadow.clone();
             self.modtime = ts;
             self.switch_count += 1;
@@ -600,6 +639,54 @@ impl<W: WorkflowStore> WorkflowState<W> {
         );
     }
 
+    /// Saves the newest `depth` undo steps of every workflow so they survive a restart
+    pub fn save_history(&self, path: &Path, depth: usize) -> anyhow::Result<()> {
+        // Other workflows have their latest edit stashed on top. It's not an undo step.
+        let history = self
+            .undo_stack
+            .iter()
+            .map(|(name, stack)| {
+                let stashed = usize::from(*name != self.editing);
+                let steps = stack.iter().skip(stashed).take(depth).collect_vec();
+                (name.clone(), steps)
+            })
+            .filter(|(_, stack)| !stack.is_empty())
+            .collect::<im::OrdMap<_, _>>();
+
+        let writer = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        serde_yml::to_writer(writer, &history)?;
+        Ok(())
+    }
+
+    /// Restores undo steps saved by a previous session, up to the current limit
+    pub fn load_history(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !path.is_file() {
+            return Ok(());
+        }
+
+        let reader = OpenOptions::new().read(true).open(path)?;
+        let history: im::OrdMap<String, VecDeque<(SystemTime, Workflow)>> =
+            serde_yml::from_reader(reader)?;
+
+        for (name, mut stack) in history {
+            stack.truncate(self.undo_limit);
+            if name == self.editing {
+                self.undo_stack.insert(name, stack);
+            } else if let Some(baseline) = self.store.get(&name) {
+                // Switching to the workflow unstashes the top, so it has to be the saved version
+                stack.push_front((SystemTime::now(), baseline));
+                self.undo_stack.insert(name, stack);
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn export(&mut self, path: &Path) -> anyhow::Result<()> {
         let writer = OpenOptions::new()
             .write(true)
@@ -736,6 +823,18 @@ impl<W: WorkflowStore> WorkflowState<W> {
                 });
                 true
             }
+            UpdateNode(exec_id, node_id, node) => {
+                if let Some(graph_id) = self.view_stack.exec_graph(*exec_id) {
+                    let _ = self.view_stack.propagate(self.view_stack.leaf(), |graph| {
+                        if graph.uuid == graph_id {
+                            graph.replace_node(*node_id, node.clone())
+                        } else {
+                            graph
+                        }
+                    });
+                }
+                true
+            }
             PinRemoved(graph_id, pin) => {
                 // At this point, the node already considers the pin gone,
                 // but we need to update the wires to reflect that.
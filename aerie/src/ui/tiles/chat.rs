@@ -1,12 +1,18 @@
-use crate::rig::message::{Message, UserContent};
+use crate::rig::message::{DocumentSourceKind, Message, UserContent};
 use eframe::egui;
 use egui_commonmark::*;
-use egui_phosphor::regular::GIT_BRANCH;
+use egui_phosphor::regular::{ARROW_CLOCKWISE, GIT_BRANCH, PAPERCLIP, WARNING, WRENCH, X};
 use itertools::Itertools;
-use std::{borrow::Cow, sync::atomic::Ordering};
+use std::{
+    borrow::Cow,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    sync::atomic::Ordering,
+    time::Duration,
+};
 
 use crate::{
     ChatContent,
+    chat::{Attachments, accepts_images},
     config::ConfigExt,
     ui::{AppEvent, agent_bubble, error_bubble, shortcuts::squelch, user_bubble},
     utils::{ErrorDistiller as _, FormatOpts},
@@ -33,12 +39,41 @@ impl super::AppState {
                             (i.modifiers.ctrl || i.modifiers.alt) && i.key_pressed(egui::Key::Enter)
                         });
 
+                        if !self.attachments.is_empty() {
+                            self.attachments_ui(ui);
+                        }
+
                         ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                             submitted |= ui.button("Chat").clicked();
 
+                            if ui
+                                .button(PAPERCLIP)
+                                .on_hover_text("Attach an image to the prompt")
+                                .clicked()
+                                && let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("Images", &["png", "jpg", "jpeg", "gif", "webp"])
+                                    .pick_file()
+                            {
+                                let name = path
+                                    .file_name()
+                                    .map(|name| name.display().to_string())
+                                    .unwrap_or_default();
+
+                                if let Some(image) = errors.distil(Attachments::load_image(&path)) {
+                                    self.attachments.push((name, image));
+                                }
+                            }
+
                             ui.add_space(16.0);
 
                             settings.update(|settings_rw| {
+                                ui.add_enabled_ui(settings_rw.automation.is_none(), |ui| {
+                                    ui.toggle_value(&mut settings_rw.chat_tools, WRENCH)
+                                        .on_hover_text(
+                                            "Let the chat agent use tools when no workflow is selected",
+                                        );
+                                });
+
                                 egui::ComboBox::from_label("Workflow")
                                     .selected_text(
                                         settings_rw.automation.as_ref().unwrap_or(&String::new()),
@@ -75,22 +110,11 @@ impl super::AppState {
                 });
 
                 if submitted {
-                    let automation = self
-                        .settings
-                        .view(|s| s.automation.clone())
-                        .unwrap_or_default();
-
-                    if automation.is_empty() || workflows.contains(&automation) {
-                        // TODO: deal with this nuking any edits in progress
-                        self.workflows.switch(&automation);
-                        self.events.insert(AppEvent::UserRunWorkflow);
-                        self.events.insert(AppEvent::SetPrompt(String::new()));
-                    } else {
-                        errors.push(anyhow::anyhow!("Workflow {automation} does not exist."));
-                    }
+                    self.submit_prompt(&workflows);
                 }
             });
 
+        let mut retry_turn = None;
         egui::CentralPanel::default().show_inside(ui, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 ui.set_width(ui.available_width());
@@ -100,7 +124,9 @@ impl super::AppState {
                         || ui.button("Scroll to bottom.").clicked());
 
                 let md_cache = &mut self.cache;
+                let idle = self.task_count.load(Ordering::Relaxed) == 0;
                 self.session.view(|history| {
+                    let last_id = history.last().map(|entry| entry.id);
                     for msg in history.iter() {
                         ui.push_id(msg.id, |ui| {
                             let aside = history.iter_aside(msg).collect_vec();
@@ -155,12 +181,29 @@ impl super::AppState {
                                         render_message(ui, md_cache, message);
                                     }
                                 }
-                                ChatContent::Error { err } => {
+                                ChatContent::Error { err, retry } => {
                                     error_bubble(ui, |ui| {
                                         ui.set_width(ui.available_width());
-                                        ui.label(
-                                            egui::RichText::new(err).color(egui::Color32::RED),
-                                        );
+                                        ui.vertical(|ui| {
+                                            ui.label(
+                                                egui::RichText::new(err).color(egui::Color32::RED),
+                                            );
+
+                                            if retry.is_some()
+                                                && Some(msg.id) == last_id
+                                                && ui
+                                                    .add_enabled(
+                                                        idle,
+                                                        egui::Button::new(format!(
+                                                            "{ARROW_CLOCKWISE} Retry"
+                                                        )),
+                                                    )
+                                                    .on_hover_text("Submit the same prompt again")
+                                                    .clicked()
+                                            {
+                                                retry_turn = Some(msg.id);
+                                            }
+                                        });
                                     });
                                 }
                             }
@@ -186,6 +229,12 @@ impl super::AppState {
                     }
                 }
 
+                if let Some(progress) = self.session.progress.load_full() {
+                    ui.label(egui::RichText::new(progress.summary()).small().weak());
+                    // Keep the elapsed time ticking between chunks
+                    ui.ctx().request_repaint_after(Duration::from_millis(100));
+                }
+
                 if self.task_count.load(Ordering::Relaxed) > 0 {
                     ui.spinner();
                 }
@@ -200,6 +249,10 @@ impl super::AppState {
             });
         });
 
+        if let Some(id) = retry_turn {
+            self.retry_turn(id, &workflows);
+        }
+
         if let Some(branch_point) = self.branch_point {
             let mut submit = false;
             let unique_name = !self.new_branch.is_empty() && {
@@ -271,6 +324,69 @@ impl super::AppState {
     }
 }
 
+impl super::AppState {
+    /// Lists images attached to the prompt, with a button to drop each
+    fn attachments_ui(&mut self, ui: &mut egui::Ui) {
+        let model = self.settings.view(|s| s.llm_model.clone());
+
+        ui.horizontal_wrapped(|ui| {
+            let mut removed = None;
+            for (idx, (name, _)) in self.attachments.iter().enumerate() {
+                ui.label(format!("{PAPERCLIP} {name}"));
+                if ui.small_button(X).on_hover_text("Remove").clicked() {
+                    removed = Some(idx);
+                }
+            }
+
+            if let Some(idx) = removed {
+                self.attachments.remove(idx);
+            }
+
+            if !accepts_images(&model) {
+                ui.colored_label(
+                    egui::Color32::ORANGE,
+                    format!("{WARNING} {model} may not accept images"),
+                );
+            }
+        });
+    }
+
+    /// Runs the selected workflow, or plain chat, on the current prompt
+    fn submit_prompt(&mut self, workflows: &[String]) {
+        let automation = self
+            .settings
+            .view(|s| s.automation.clone())
+            .unwrap_or_default();
+
+        if automation.is_empty() || workflows.contains(&automation) {
+            // TODO: deal with this nuking any edits in progress
+            self.workflows.switch(&automation);
+            self.events.insert(AppEvent::UserRunWorkflow);
+            self.events.insert(AppEvent::SetPrompt(String::new()));
+        } else {
+            self.errors
+                .push(anyhow::anyhow!("Workflow {automation} does not exist."));
+        }
+    }
+
+    /// Drops a failed turn from the history and submits its prompt again
+    fn retry_turn(&mut self, id: uuid::Uuid, workflows: &[String]) {
+        let mut prompt = None;
+        let result = self.session.transform(|history| {
+            let (history, text) = history.take_retry(id)?;
+            prompt = Some(text);
+            Ok(history)
+        });
+
+        if self.errors.distil(result).is_some()
+            && let Some(prompt) = prompt
+        {
+            self.prompt = prompt;
+            self.submit_prompt(workflows);
+        }
+    }
+}
+
 pub fn render_message(ui: &mut egui::Ui, cache: &mut CommonMarkCache, message: &Message) {
     render_message_width(ui, cache, message, None);
 }
@@ -340,6 +456,14 @@ pub fn render_message_width(
                             }
                         }
                     }
+
+                    if let Message::User { content } = message {
+                        for item in content.iter() {
+                            if let UserContent::Image(image) = item {
+                                render_image(ui, &image.data);
+                            }
+                        }
+                    }
                 });
             });
         }
@@ -450,3 +574,34 @@ pub fn render_message_width(
         }
     }
 }
+
+/// Shows a base64 encoded image from a message
+fn render_image(ui: &mut egui::Ui, data: &DocumentSourceKind) {
+    use base64::prelude::*;
+
+    let DocumentSourceKind::Base64(data) = data else {
+        return;
+    };
+
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    let uri = format!("bytes://attachment/{:x}", hasher.finish());
+
+    // Decode only once, egui keeps the bytes around afterward
+    if ui.ctx().try_load_bytes(&uri).is_err() {
+        match BASE64_STANDARD.decode(data) {
+            Ok(bytes) => ui.ctx().include_bytes(uri.clone(), bytes),
+            Err(err) => {
+                ui.label(format!("Invalid image: {err}"));
+                return;
+            }
+        }
+    }
+
+    ui.add(
+        egui::Image::new(uri)
+            .max_height(240.0)
+            .fit_to_original_size(1.0)
+            .corner_radius(8),
+    );
+}
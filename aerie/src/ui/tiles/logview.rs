@@ -1,30 +1,118 @@
 use std::sync::atomic::Ordering;
 
-use crate::config::ConfigExt as _;
+use egui::RichText;
+use egui_phosphor::regular::{CARET_LEFT, CARET_RIGHT, TRASH};
+use itertools::Itertools as _;
+
+use crate::{LogEntry, config::ConfigExt as _};
+
+const PAGE_SIZE: usize = 200;
+
+const LEVELS: [tracing::Level; 5] = [
+    tracing::Level::ERROR,
+    tracing::Level::WARN,
+    tracing::Level::INFO,
+    tracing::Level::DEBUG,
+    tracing::Level::TRACE,
+];
+
+/// Display state for the logs tile
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    /// Most verbose level shown, or all levels when unset
+    pub level: Option<tracing::Level>,
+
+    /// Only show entries whose target contains this text
+    pub target: String,
+
+    /// Pages back from the most recent entries
+    pub page: usize,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.level.is_none_or(|level| entry.level() <= level)
+            && (self.target.is_empty() || entry.target().contains(&self.target))
+    }
+}
 
 impl super::AppState {
     pub fn logview_ui(&mut self, ui: &mut egui::Ui) {
-        let scroll_bottom =
-            self.task_count.load(Ordering::Relaxed) > 0 && self.settings.view(|s| s.autoscroll);
-
-        egui::ScrollArea::both().show(ui, |ui| {
-            let language = "json";
-            let theme =
-                egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx(), ui.style());
-
-            let logs_r = self.log_history.load();
-            for entry in logs_r.iter() {
-                // ui.label(entry.message());
-                egui_extras::syntax_highlighting::code_view_ui(
-                    ui,
-                    &theme,
-                    entry.message(),
-                    language,
+        let logs_r = self.log_history.entries();
+        let filter = &mut self.log_filter;
+
+        let entries = logs_r.iter().filter(|e| filter.matches(e)).collect_vec();
+        let pages = entries.len().div_ceil(PAGE_SIZE).max(1);
+        filter.page = filter.page.min(pages - 1);
+
+        egui::TopBottomPanel::top("logview_filters").show_inside(ui, |ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("log_level")
+                    .selected_text(filter.level.map(|l| l.to_string()).unwrap_or("ALL".into()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut filter.level, None, "ALL");
+                        for level in LEVELS {
+                            ui.selectable_value(&mut filter.level, Some(level), level.to_string());
+                        }
+                    });
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut filter.target)
+                        .hint_text("target")
+                        .desired_width(160.0),
                 );
-            }
-            if scroll_bottom {
-                ui.scroll_to_cursor(Some(egui::Align::BOTTOM));
-            }
+
+                ui.add_enabled_ui(filter.page + 1 < pages, |ui| {
+                    if ui.button(CARET_LEFT).on_hover_text("older").clicked() {
+                        filter.page += 1;
+                    }
+                });
+
+                ui.label(format!("{}/{pages}", pages - filter.page));
+
+                ui.add_enabled_ui(filter.page > 0, |ui| {
+                    if ui.button(CARET_RIGHT).on_hover_text("newer").clicked() {
+                        filter.page -= 1;
+                    }
+                });
+
+                if ui.button(TRASH).on_hover_text("clear").clicked() {
+                    self.log_history.clear();
+                }
+
+                let dropped = self.log_history.dropped();
+                if dropped > 0 {
+                    ui.label(RichText::new(format!("{dropped} dropped")).weak());
+                }
+            });
+        });
+
+        let scroll_bottom = filter.page == 0
+            && self.task_count.load(Ordering::Relaxed) > 0
+            && self.settings.view(|s| s.autoscroll);
+
+        let end = entries.len().saturating_sub(filter.page * PAGE_SIZE);
+        let start = end.saturating_sub(PAGE_SIZE);
+
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            egui::ScrollArea::both().show(ui, |ui| {
+                let language = "json";
+                let theme =
+                    egui_extras::syntax_highlighting::CodeTheme::from_memory(ui.ctx(), ui.style());
+
+                for entry in &entries[start..end] {
+                    // ui.label(entry.message());
+                    egui_extras::syntax_highlighting::code_view_ui(
+                        ui,
+                        &theme,
+                        entry.message(),
+                        language,
+                    );
+                }
+                if scroll_bottom {
+                    ui.scroll_to_cursor(Some(egui::Align::BOTTOM));
+                }
+            });
         });
     }
 }
@@ -1,8 +1,19 @@
+use std::path::PathBuf;
+
 use egui::{RichText, TextEdit};
-use egui_phosphor::regular::CLOCK_COUNTER_CLOCKWISE;
+use egui_phosphor::regular::{CLOCK_COUNTER_CLOCKWISE, FLOPPY_DISK, LOCK_KEY, TRASH};
 use itertools::Itertools;
 
-use crate::{config::ConfigExt as _, workflow::store::WorkflowStore as _};
+use crate::{
+    ToolProvider,
+    config::{ConfigExt as _, DEFAULT_SAVE_DELAY, Ternary},
+    keys::{KNOWN_PROVIDERS, KeySource, env_var, mask_key},
+    logging::DEFAULT_LOG_CAPACITY,
+    transmute::Conversion,
+    ui::shortcuts::squelch,
+    utils::ErrorDistiller as _,
+    workflow::store::WorkflowStore as _,
+};
 
 impl super::AppState {
     pub fn settings_ui(&mut self, ui: &mut egui::Ui) {
@@ -74,6 +85,22 @@ impl super::AppState {
 
                         ui.end_row();
 
+                        ui.label("stop sequences").on_hover_text(
+                            "Default markers that end generation, one per line.\n\
+                            Write \\n for a line break. Agents can override these.",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut text = settings_rw.stop_sequences.iter().join("\n");
+                            let widget = egui::TextEdit::multiline(&mut text)
+                                .desired_rows(2)
+                                .hint_text("one per line");
+                            if squelch(ui.add(widget)).changed() {
+                                settings_rw.stop_sequences =
+                                    text.split('\n').map(String::from).collect();
+                            }
+                        });
+                        ui.end_row();
+
                         ui.label("autorun").on_hover_text(
                             "Number of additional turns to execute chained workflows automatically",
                         );
@@ -83,6 +110,102 @@ impl super::AppState {
                             ui.add(widget);
                         });
                         ui.end_row();
+
+                        ui.label("log capacity")
+                            .on_hover_text("Number of recent log entries to keep in memory");
+                        settings.update(|settings_rw| {
+                            let mut capacity =
+                                settings_rw.log_capacity.unwrap_or(DEFAULT_LOG_CAPACITY);
+                            let widget = egui::DragValue::new(&mut capacity)
+                                .range(100..=100_000)
+                                .speed(10)
+                                .update_while_editing(false);
+                            if ui.add(widget).changed() {
+                                settings_rw.log_capacity = Some(capacity);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("save delay").on_hover_text(
+                            "Seconds without further changes before settings are saved and applied",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut delay = settings_rw.save_delay.unwrap_or(DEFAULT_SAVE_DELAY);
+                            let widget = egui::DragValue::new(&mut delay)
+                                .range(0.5..=60.0)
+                                .speed(0.1)
+                                .suffix(" s")
+                                .update_while_editing(false);
+                            if ui.add(widget).changed() {
+                                settings_rw.save_delay = Some(delay);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("node timeout").on_hover_text(
+                            "Seconds a workflow node may run before it fails with a timeout.\n\
+                            Nodes with their own timeout use that instead. Zero waits forever.",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut timeout = settings_rw.node_timeout.unwrap_or_default();
+                            let widget = egui::DragValue::new(&mut timeout)
+                                .range(0..=86_400)
+                                .suffix(" s")
+                                .update_while_editing(false);
+                            if ui.add(widget).changed() {
+                                settings_rw.node_timeout = Some(timeout).filter(|it| *it > 0);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("undo limit").on_hover_text(
+                            "Undo steps kept for each workflow. Oldest are dropped first.",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut limit = settings_rw.undo_limit();
+                            let widget = egui::DragValue::new(&mut limit)
+                                .range(1..=4096)
+                                .update_while_editing(false);
+                            if ui.add(widget).changed() {
+                                settings_rw.undo_limit = Some(limit);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("undo persist").on_hover_text(
+                            "Undo steps for each workflow saved on exit and restored on startup.\n\
+                            Zero disables persistence.",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut depth = settings_rw.undo_persist();
+                            let widget = egui::DragValue::new(&mut depth)
+                                .range(0..=settings_rw.undo_limit())
+                                .update_while_editing(false);
+                            if ui.add(widget).changed() {
+                                settings_rw.undo_persist = Some(depth);
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("work dir").on_hover_text(
+                            "Directory that file nodes read and write within.\n\
+                            Paths cannot escape it. Uses the current directory when empty.",
+                        );
+                        settings.update(|settings_rw| {
+                            let mut text = settings_rw
+                                .work_dir
+                                .as_ref()
+                                .map(|dir| dir.display().to_string())
+                                .unwrap_or_default();
+                            let widget = egui::TextEdit::singleline(&mut text)
+                                .hint_text("current directory");
+                            if squelch(ui.add(widget)).changed() {
+                                settings_rw.work_dir = Some(text.trim())
+                                    .filter(|it| !it.is_empty())
+                                    .map(PathBuf::from);
+                            }
+                        });
+                        ui.end_row();
                     });
 
                 settings.update(|settings_rw| {
@@ -94,12 +217,164 @@ impl super::AppState {
                                 ui.toggle_value(&mut settings_rw.autosave, "autosave");
                                 ui.toggle_value(&mut settings_rw.autoscroll, "autoscroll");
                                 ui.toggle_value(&mut settings_rw.streaming, "streaming");
+                                ui.toggle_value(&mut settings_rw.concurrent_nodes, "concurrent")
+                                    .on_hover_text(
+                                        "Run every ready node at the same time, \
+                                            e.g. agents on independent branches",
+                                    );
                                 ui.toggle_value(&mut settings_rw.cascade, "cascade");
                             });
                         });
                 });
 
+                settings.update(|settings_rw| {
+                    egui::CollapsingHeader::new("Conversions")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                for conversion in Conversion::ALL {
+                                    let mut enabled =
+                                        settings_rw.conversions.is_enabled(*conversion);
+                                    if ui
+                                        .toggle_value(&mut enabled, conversion.label())
+                                        .on_hover_text(conversion.tooltip())
+                                        .changed()
+                                    {
+                                        settings_rw.conversions.set_enabled(*conversion, enabled);
+                                    }
+                                }
+                            });
+
+                            if ui
+                                .add_enabled(
+                                    !settings_rw.conversions.is_default(),
+                                    egui::Button::new("reset").small(),
+                                )
+                                .clicked()
+                            {
+                                settings_rw.conversions = Default::default();
+                            }
+                        });
+                });
+
+                settings.update(|settings_rw| {
+                    egui::CollapsingHeader::new("Repository search")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            egui::Grid::new("Search Settings")
+                                .num_columns(2)
+                                .show(ui, |ui| {
+                                    let fields = [
+                                        (
+                                            "qdrant url",
+                                            "http://localhost:6334",
+                                            &mut settings_rw.qdrant_url,
+                                        ),
+                                        (
+                                            "collection",
+                                            "myproject",
+                                            &mut settings_rw.search_collection,
+                                        ),
+                                        (
+                                            "embed model",
+                                            "Xenova/all-MiniLM-L6-v2",
+                                            &mut settings_rw.embed_model,
+                                        ),
+                                    ];
+
+                                    for (label, hint, field) in fields {
+                                        ui.label(label);
+                                        let mut text = field.clone().unwrap_or_default();
+                                        let widget =
+                                            TextEdit::singleline(&mut text).hint_text(hint);
+                                        if squelch(ui.add(widget)).changed() {
+                                            *field = Some(text.trim().to_string())
+                                                .filter(|it| !it.is_empty());
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                });
+
                 let workflows = self.workflows.names().map(|s| s.to_string()).collect_vec();
+                let providers = self.agent_factory.toolbox.providers.load();
+
+                settings.update(|settings_rw| {
+                    egui::CollapsingHeader::new("Chat tools")
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            ui.toggle_value(&mut settings_rw.chat_tools, "enabled")
+                                .on_hover_text(
+                                    "Let the chat agent use tools when no workflow is selected",
+                                );
+
+                            egui::ComboBox::from_label("Inherit from")
+                                .selected_text(
+                                    settings_rw
+                                        .chat_tool_source
+                                        .as_deref()
+                                        .unwrap_or("(settings)"),
+                                )
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut settings_rw.chat_tool_source,
+                                        None,
+                                        "(settings)",
+                                    );
+
+                                    for flow in workflows.iter().filter(|f| !f.is_empty()) {
+                                        ui.selectable_value(
+                                            &mut settings_rw.chat_tool_source,
+                                            Some(flow.clone()),
+                                            flow,
+                                        );
+                                    }
+                                });
+
+                            if settings_rw.chat_tool_source.is_none() {
+                                ui.horizontal_wrapped(|ui| {
+                                    for (name, provider) in providers.iter() {
+                                        if matches!(provider, ToolProvider::Chainer { .. }) {
+                                            continue;
+                                        }
+
+                                        let selection =
+                                            settings_rw.chat_toolset.provider_selection(name);
+                                        let (mut checked, maybe) = match selection {
+                                            Ternary::None => (false, false),
+                                            Ternary::Some(_) => (false, true),
+                                            Ternary::All => (true, false),
+                                        };
+
+                                        let widget = egui::Checkbox::new(&mut checked, name)
+                                            .indeterminate(maybe);
+                                        if ui
+                                            .add(widget)
+                                            .on_hover_text(provider.description())
+                                            .clicked()
+                                        {
+                                            settings_rw.chat_toolset =
+                                                self.agent_factory.toolbox.toggle_provider(
+                                                    &settings_rw.chat_toolset,
+                                                    name,
+                                                    if checked {
+                                                        Ternary::All
+                                                    } else {
+                                                        Ternary::None
+                                                    },
+                                                );
+                                        }
+                                    }
+                                });
+                            }
+                        });
+                });
+
+                egui::CollapsingHeader::new("API keys")
+                    .default_open(false)
+                    .show(ui, |ui| self.api_keys_ui(ui));
+
                 egui::collapsing_header::CollapsingState::load_with_default_open(
                     ui.ctx(),
                     ui.make_persistent_id("workflow_info"),
@@ -138,4 +413,92 @@ impl super::AppState {
             });
         });
     }
+
+    /// Lists known providers with controls to store or clear their keys
+    fn api_keys_ui(&self, ui: &mut egui::Ui) {
+        let api_keys = &self.agent_factory.api_keys;
+        let keyring = api_keys.keyring_available();
+
+        let note = if keyring {
+            "New keys are saved to the OS keyring"
+        } else {
+            "Stored unencrypted in keys.yml beside the settings file"
+        };
+        ui.label(RichText::new(note).weak());
+
+        egui::Grid::new("api_keys")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                for (provider, needs_key) in KNOWN_PROVIDERS {
+                    ui.label(*provider);
+
+                    if !needs_key {
+                        ui.label(RichText::new("no key needed").weak());
+                        ui.end_row();
+                        continue;
+                    }
+
+                    match api_keys.resolve(provider) {
+                        Some((key, source)) => {
+                            let label = match source {
+                                KeySource::Environment => env_var(provider),
+                                KeySource::Keyring => "keyring".into(),
+                                KeySource::File => "keys.yml".into(),
+                            };
+                            ui.monospace(mask_key(&key)).on_hover_text(label)
+                        }
+                        None => ui.label(RichText::new("not set").weak()),
+                    };
+
+                    let draft_id = ui.id().with(("api_key_draft", provider));
+                    let mut draft: String =
+                        ui.data_mut(|d| d.get_temp(draft_id)).unwrap_or_default();
+
+                    ui.horizontal(|ui| {
+                        squelch(
+                            ui.add(
+                                TextEdit::singleline(&mut draft)
+                                    .password(true)
+                                    .desired_width(160.0)
+                                    .hint_text("new key"),
+                            ),
+                        );
+
+                        if ui
+                            .add_enabled(!draft.trim().is_empty(), egui::Button::new(FLOPPY_DISK))
+                            .on_hover_text(if keyring { "save to keyring" } else { "save" })
+                            .clicked()
+                        {
+                            let key = std::mem::take(&mut draft).trim().to_string();
+                            self.errors
+                                .distil(self.agent_factory.store_api_key(provider, key));
+                        }
+
+                        let in_file = api_keys.contains(provider);
+                        if keyring
+                            && ui
+                                .add_enabled(in_file, egui::Button::new(LOCK_KEY))
+                                .on_hover_text("move key from keys.yml to keyring")
+                                .clicked()
+                        {
+                            self.errors.distil(api_keys.migrate(provider));
+                        }
+
+                        let saved = in_file || api_keys.keyring_get(provider).is_some();
+                        if ui
+                            .add_enabled(saved, egui::Button::new(TRASH))
+                            .on_hover_text("clear")
+                            .clicked()
+                        {
+                            self.errors
+                                .distil(self.agent_factory.clear_api_key(provider));
+                        }
+                    });
+
+                    ui.data_mut(|d| d.insert_temp(draft_id, draft));
+                    ui.end_row();
+                }
+            });
+    }
 }
@@ -48,7 +48,9 @@ impl super::AppState {
                 .id(viewer.view_id)
                 .style(get_subgraph_style());
 
-            let pointee = widget.show(&mut snarl, viewer, ui).contains_pointer();
+            let response = widget.show(&mut snarl, viewer, ui);
+            let pointee = response.contains_pointer();
+            viewer.viewport = response.rect;
 
             // Unfortunately, there's no event for node movement so we have to
             // iterate through the whole collection to find moved nodes.
@@ -69,6 +71,8 @@ impl super::AppState {
                 .propagate(shadow, identity)
                 .unwrap();
 
+            self.update_search_focus(ui.ctx());
+
             egui::Area::new(egui::Id::new("subgraph controls"))
                 .default_pos(egui::pos2(16.0, 32.0))
                 .default_size(egui::vec2(100.0, 100.0))
@@ -130,6 +134,10 @@ impl super::AppState {
 
             ui.separator();
 
+            self.node_search_ui(ui);
+
+            ui.separator();
+
             StripBuilder::new(ui)
                 .size(Size::exact(16.0))
                 .vertical(|mut strip| {
@@ -139,10 +147,11 @@ impl super::AppState {
                             .horizontal(|mut strip| {
                                 strip.cell(|ui| {
                                     let stack = self.workflows.get_undo_count();
+                                    let limit = self.workflows.undo_limit;
                                     ui.add_enabled_ui(!running && stack > 0, |ui| {
                                         if ui
                                             .button(ARROW_COUNTER_CLOCKWISE)
-                                            .on_hover_text(format!("{stack}"))
+                                            .on_hover_text(format!("Undo ({stack} of {limit})"))
                                             .clicked()
                                         {
                                             // TODO: stay in this view when undoing
@@ -152,10 +161,11 @@ impl super::AppState {
                                 });
                                 strip.cell(|ui| {
                                     let stack = self.workflows.get_redo_count();
+                                    let limit = self.workflows.undo_limit;
                                     ui.add_enabled_ui(!running && stack > 0, |ui| {
                                         if ui
                                             .button(ARROW_CLOCKWISE)
-                                            .on_hover_text(format!("{stack}"))
+                                            .on_hover_text(format!("Redo ({stack} of {limit})"))
                                             .clicked()
                                         {
                                             self.workflows.redo();
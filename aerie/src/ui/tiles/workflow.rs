@@ -1,10 +1,10 @@
 use std::{borrow::Cow, convert::identity, sync::atomic::Ordering, time::Duration};
 
-use egui::{Align2, Color32, ComboBox};
+use egui::{Align2, Color32, ComboBox, Key, Modifiers};
 use egui_extras::{Size, StripBuilder};
 use egui_phosphor::regular::{
-    ARROW_CLOCKWISE, ARROW_COUNTER_CLOCKWISE, DOWNLOAD_SIMPLE, INFO, MAGIC_WAND, PENCIL, TRASH,
-    UPLOAD_SIMPLE,
+    ARROW_CLOCKWISE, ARROW_COUNTER_CLOCKWISE, BROOM, DOWNLOAD_SIMPLE, INFO, MAGIC_WAND,
+    MAGNIFYING_GLASS, MAP_TRIFOLD, PENCIL, TRASH, TREE_STRUCTURE, UPLOAD_SIMPLE,
 };
 use egui_snarl::ui::SnarlWidget;
 use itertools::Itertools;
@@ -14,7 +14,10 @@ use crate::{
     ui::{
         AppEvent, ShowHelp,
         runner::{play_button, stop_button},
-        shortcuts::{SHORTCUT_HELP, SHORTCUT_RUN, ShortcutHandler, show_shortcuts, squelch},
+        search::{HIGHLIGHT_DURATION, NodeHit, search_nodes},
+        shortcuts::{
+            SHORTCUT_FIND, SHORTCUT_HELP, SHORTCUT_RUN, ShortcutHandler, show_shortcuts, squelch,
+        },
         state::MetaEdit,
         workflow::get_snarl_style,
     },
@@ -41,6 +44,7 @@ impl super::AppState {
             // Forces new widget state in children after switching or undos so that
             // Snarl will draw our persisted positions and sizes.
             let mut snarl = self.workflows.view_stack.root_snarl().unwrap();
+            let show_minimap = self.settings.view(|s| s.show_minimap);
 
             let (shadow, widget) = {
                 let meta = self.workflows.shadow.metadata.clone();
@@ -53,12 +57,28 @@ impl super::AppState {
                 let widget = SnarlWidget::new()
                     .id(viewer.view_id)
                     .style(get_snarl_style());
-                pointee = widget.show(&mut snarl, viewer, ui).contains_pointer();
+                let response = widget.show(&mut snarl, viewer, ui);
+                pointee = response.contains_pointer();
+                viewer.viewport = response.rect;
 
                 // Unfortunately, there's no event for node movement so we have to
                 // iterate through the whole collection to find moved nodes.
                 viewer.cast_positions(&snarl);
 
+                if show_minimap {
+                    egui::Area::new(egui::Id::new("workflow minimap"))
+                        .pivot(Align2::RIGHT_BOTTOM)
+                        .fixed_pos(response.rect.right_bottom() - egui::vec2(16.0, 16.0))
+                        .show(ui.ctx(), |ui| {
+                            egui::Frame::dark_canvas(&Default::default())
+                                .inner_margin(4.0)
+                                .corner_radius(8)
+                                .show(ui, |ui| {
+                                    viewer.show_minimap(ui);
+                                });
+                        });
+                }
+
                 (viewer.shadow.clone(), widget)
             };
 
@@ -154,6 +174,8 @@ impl super::AppState {
                 .propagate(shadow.clone(), identity)
                 .unwrap();
 
+            self.update_search_focus(ui.ctx());
+
             egui::Area::new(egui::Id::new("workflow controls"))
                 .default_pos(egui::pos2(16.0, 32.0))
                 .default_size(egui::vec2(100.0, 100.0))
@@ -202,6 +224,64 @@ impl super::AppState {
                 self.show_help = None;
             }
         }
+
+        if !self.workflows.unreachable.is_empty() {
+            self.unreachable_modal(ui);
+        }
+    }
+
+    /// Warns about nodes that will never run before starting the workflow
+    fn unreachable_modal(&mut self, ui: &mut egui::Ui) {
+        let root = self.workflows.view_stack.root();
+        let at_root = self.workflows.view_stack.path.is_empty();
+        let modal = egui::Modal::new(egui::Id::new("Unreachable nodes")).show(ui.ctx(), |ui| {
+            ui.heading("Unreachable nodes");
+            ui.label(
+                "These nodes are not connected to Start, Finish or an Output and will not run:",
+            );
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    for node_id in &self.workflows.unreachable {
+                        if let Some(node) = root.nodes.get(node_id) {
+                            ui.label(format!("• {}", node.value.as_ui().title()));
+                        }
+                    }
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Run anyway").clicked() {
+                    self.events.insert(AppEvent::UserRunAnyway);
+                    ui.close();
+                }
+
+                let remove = ui
+                    .add_enabled(at_root, egui::Button::new("Remove unreachable"))
+                    .on_disabled_hover_text("Return to the top-level workflow to remove nodes");
+                if remove.clicked() {
+                    let pruned = self
+                        .workflows
+                        .unreachable
+                        .iter()
+                        .fold(root.clone(), |graph, node_id| graph.without_node(node_id));
+                    self.workflows
+                        .view_stack
+                        .propagate(pruned, identity)
+                        .unwrap();
+                    ui.close();
+                }
+
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+
+        if modal.should_close() {
+            self.workflows.unreachable.clear();
+        }
     }
 
     pub fn workflow_controls(&mut self, ui: &mut egui::Ui) {
@@ -216,6 +296,9 @@ impl super::AppState {
         ui.set_max_width(150.0);
         ui.vertical_centered_justified(|ui| {
             ui.heading("Workflow");
+            self.node_search_ui(ui);
+            ui.separator();
+
             ComboBox::from_id_salt("workflow")
                 .wrap()
                 .width(ui.available_width())
@@ -359,10 +442,11 @@ impl super::AppState {
                             .horizontal(|mut strip| {
                                 strip.cell(|ui| {
                                     let stack = self.workflows.get_undo_count();
+                                    let limit = self.workflows.undo_limit;
                                     ui.add_enabled_ui(!running && stack > 0, |ui| {
                                         if ui
                                             .button(ARROW_COUNTER_CLOCKWISE)
-                                            .on_hover_text(format!("{stack}"))
+                                            .on_hover_text(format!("Undo ({stack} of {limit})"))
                                             .clicked()
                                         {
                                             self.workflows.undo();
@@ -371,10 +455,11 @@ impl super::AppState {
                                 });
                                 strip.cell(|ui| {
                                     let stack = self.workflows.get_redo_count();
+                                    let limit = self.workflows.undo_limit;
                                     ui.add_enabled_ui(!running && stack > 0, |ui| {
                                         if ui
                                             .button(ARROW_CLOCKWISE)
-                                            .on_hover_text(format!("{stack}"))
+                                            .on_hover_text(format!("Redo ({stack} of {limit})"))
                                             .clicked()
                                         {
                                             self.workflows.redo();
@@ -416,6 +501,29 @@ impl super::AppState {
                     .on_hover_text(frozen_hint);
             });
 
+            let mut minimap = settings.view(|s| s.show_minimap);
+            if ui
+                .toggle_value(&mut minimap, format!("{MAP_TRIFOLD} Minimap"))
+                .on_hover_text("Show an overview of the graph. Click it to jump around.")
+                .changed()
+            {
+                settings.update(|s| s.show_minimap = minimap);
+            }
+
+            let cached = self.workflows.cache.len();
+            ui.add_enabled_ui(!running && cached > 0, |ui| {
+                if ui
+                    .button(format!("{BROOM} Clear cache"))
+                    .on_hover_text(format!(
+                        "Forget {cached} cached results of pure nodes like Text and Parse JSON, \
+                            so the next run executes them again."
+                    ))
+                    .clicked()
+                {
+                    self.workflows.cache.clear();
+                }
+            });
+
             ui.separator();
             ui.scope(|ui| {
                 // Bigger button
@@ -433,4 +541,129 @@ impl super::AppState {
             });
         });
     }
+
+    /// Search box listing matching nodes, navigating to the one picked.
+    ///
+    /// Arrow keys move through the hits and Enter jumps to the highlighted one.
+    pub fn node_search_ui(&mut self, ui: &mut egui::Ui) {
+        let search_id = egui::Id::new("node search");
+        if ui.ctx().input_mut(|i| i.consume_shortcut(&SHORTCUT_FIND)) {
+            ui.memory_mut(|m| m.request_focus(search_id));
+        }
+
+        let root = self.workflows.view_stack.root();
+        let search = &mut self.workflows.search;
+
+        let editor = ui
+            .horizontal(|ui| {
+                ui.toggle_value(&mut search.nested, TREE_STRUCTURE)
+                    .on_hover_text("Include subgraphs");
+
+                squelch(
+                    ui.add(
+                        egui::TextEdit::singleline(&mut search.query)
+                            .id(search_id)
+                            .desired_width(f32::INFINITY)
+                            .hint_text(format!("{MAGNIFYING_GLASS} find node")),
+                    ),
+                )
+            })
+            .inner;
+
+        search.hits = search_nodes(&root, &search.query, search.nested);
+        search.selected = search.selected.min(search.hits.len().saturating_sub(1));
+
+        let mut picked = None;
+
+        if editor.has_focus() {
+            ui.input_mut(|i| {
+                if i.consume_key(Modifiers::NONE, Key::ArrowDown) {
+                    search.selected =
+                        (search.selected + 1).min(search.hits.len().saturating_sub(1));
+                }
+
+                if i.consume_key(Modifiers::NONE, Key::ArrowUp) {
+                    search.selected = search.selected.saturating_sub(1);
+                }
+            });
+        }
+
+        if editor.lost_focus() {
+            if ui.input(|i| i.key_pressed(Key::Enter)) {
+                picked = search.hits.get(search.selected).cloned();
+            } else if ui.input(|i| i.key_pressed(Key::Escape)) {
+                search.query.clear();
+                search.hits.clear();
+            }
+        }
+
+        if !search.query.trim().is_empty() {
+            if search.hits.is_empty() {
+                ui.label(egui::RichText::new("no matches").weak());
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(160.0)
+                .show(ui, |ui| {
+                    for (i, hit) in search.hits.iter().enumerate() {
+                        let label = ui
+                            .selectable_label(i == search.selected, hit.label())
+                            .on_hover_text(&hit.kind);
+
+                        if i == search.selected {
+                            label.scroll_to_me(None);
+                        }
+
+                        if label.clicked() {
+                            picked = Some(hit.clone());
+                        }
+                    }
+                });
+        }
+
+        if let Some(hit) = picked {
+            self.goto_node(&hit);
+        }
+    }
+
+    /// Navigates into the subgraph holding a search hit and queues it to be centered
+    fn goto_node(&mut self, hit: &NodeHit) {
+        let stack = &mut self.workflows.view_stack;
+        let current = stack.path.iter().rev().cloned().collect_vec();
+
+        if current != hit.path {
+            let depth = stack.path.len();
+            let moved = stack
+                .exit(depth)
+                .and_then(|_| hit.path.iter().try_for_each(|node| stack.enter(*node)));
+
+            self.errors.distil(moved);
+            self.workflows.viewer = None;
+        }
+
+        self.workflows.search.focus(hit);
+    }
+
+    /// Centers the node picked by search once its graph is on screen
+    pub fn update_search_focus(&mut self, ctx: &egui::Context) {
+        if let Some((_, found)) = self.workflows.search.highlight {
+            let elapsed = found.elapsed();
+            if elapsed < HIGHLIGHT_DURATION {
+                ctx.request_repaint_after(HIGHLIGHT_DURATION - elapsed);
+            } else {
+                self.workflows.search.highlight = None;
+            }
+        }
+
+        let Some((graph, node)) = self.workflows.search.focus else {
+            return;
+        };
+
+        let viewer = self.workflow_viewer();
+        if viewer.shadow.uuid == graph {
+            viewer.center_on_node(node);
+            self.workflows.search.focus = None;
+            ctx.request_repaint();
+        }
+    }
 }
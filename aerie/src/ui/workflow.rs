@@ -2,6 +2,7 @@ use std::{
     collections::{BTreeMap, BTreeSet},
     iter,
     sync::{Arc, atomic::AtomicUsize},
+    time::Instant,
 };
 
 use anyhow::Context as _;
@@ -24,15 +25,16 @@ use crate::{
     workflow::{
         EditContext, GraphId, MetaNode, ShadowGraph, WorkNode, Workflow,
         nodes::{
-            AgentNode, ChatContext, ChatNode, CommentNode, Demote, EnvironmentNode, Fallback,
-            Flavor, GateNode, GraphSubmenu, InvokeTool, Matcher, Number, OutputNode, Panic,
-            Preview, Select, StructuredChat, Subgraph, TemplateNode, Text, Tools,
+            AgentNode, ChatContext, ChatNode, CleanText, CommentNode, Concat, Demote, EnvVar,
+            EnvironmentNode, ExtractCode, Fallback, Flavor, GateNode, GraphSubmenu, InvokeTool,
+            Matcher, Math, Number, OutputNode, Panic, Preview, Random, RegexExtract, Select, Split,
+            StructuredChat, Subgraph, Switch, TemplateNode, Text, TokenCount, Tools,
         },
         runner::{ExecId, ExecState, NodeStateMap},
     },
 };
 
-use super::AppEvents;
+use super::{AppEvent, AppEvents, minimap::NODE_EXTENT, search::HIGHLIGHT_DURATION};
 
 #[cached]
 pub fn get_snarl_style() -> SnarlStyle {
@@ -72,6 +74,44 @@ pub fn get_subgraph_style() -> SnarlStyle {
     }
 }
 
+/// Built-in entries of the graph menu as (submenu, label, constructor).
+///
+/// Entries without a submenu are listed after those registered with [GraphSubmenu].
+const NODE_MENU: &[(Option<&str>, &str, fn() -> WorkNode)] = &[
+    (Some("Control"), "Fallback", new_node::<Fallback>),
+    (Some("Control"), "Matcher", new_node::<Matcher>),
+    (Some("Control"), "Select", new_node::<Select>),
+    (Some("Control"), "Switch", new_node::<Switch>),
+    (Some("Control"), "Gate", new_node::<GateNode>),
+    (Some("Control"), "Demote", new_node::<Demote>),
+    (Some("Control"), "Panic", new_node::<Panic>),
+    (Some("Value"), "Number", new_node::<Number>),
+    (Some("Value"), "Math", new_node::<Math>),
+    (Some("Value"), "Plain Text", new_node::<Text>),
+    (Some("Value"), "Template", new_node::<TemplateNode>),
+    (Some("Value"), "Concat", new_node::<Concat>),
+    (Some("Value"), "Split Text", new_node::<Split>),
+    (Some("Value"), "Environment", new_node::<EnvironmentNode>),
+    (Some("Value"), "Env Var", new_node::<EnvVar>),
+    (Some("Value"), "Random", new_node::<Random>),
+    (Some("Value"), "Extract Code", new_node::<ExtractCode>),
+    (Some("Value"), "Regex Extract", new_node::<RegexExtract>),
+    (Some("Value"), "Clean Text", new_node::<CleanText>),
+    (Some("LLM"), "Agent", new_node::<AgentNode>),
+    (Some("LLM"), "Context", new_node::<ChatContext>),
+    (Some("LLM"), "Chat", new_node::<ChatNode>),
+    (Some("LLM"), "Structured", new_node::<StructuredChat>),
+    (Some("LLM"), "Token Count", new_node::<TokenCount>),
+    (Some("Tools"), "Select Tools", new_node::<Tools>),
+    (Some("Tools"), "Invoke Tools", new_node::<InvokeTool>),
+    (None, "Preview", new_node::<Preview>),
+    (None, "Output", new_node::<OutputNode>),
+];
+
+fn new_node<T: Default + Into<WorkNode>>() -> WorkNode {
+    T::default().into()
+}
+
 #[derive(Clone, TypedBuilder)]
 pub struct ViewStack {
     pub root_id: egui::Id,
@@ -111,12 +151,43 @@ impl ViewStack {
     /// Replace root graph with a different version.
     ///
     /// Attempts to preserve path, but will navigate as far as possible
-    /// if subgraphs are absent.
+    /// if subgraphs are absent. Versions of the same workflow keep the
+    /// graph identities of the current stack (see [Self::stabilize]).
     pub fn switch(&mut self, name: &str, workflow: Workflow) {
+        let workflow = if name == self.root_name {
+            self.stabilize(workflow)
+        } else {
+            workflow
+        };
+
         let path = self.path.clone();
         *self = Self::new(name, workflow, path.into_iter());
     }
 
+    /// Carries the graph uuids along the current path over to another version of the workflow.
+    ///
+    /// Execution state is keyed by [ExecId], which is derived from graph uuids.
+    /// Keeping them stable lets results line up with the same nodes after an undo or redo.
+    pub fn stabilize(&self, workflow: Workflow) -> Workflow {
+        let ids = self
+            .path
+            .iter()
+            .rev()
+            .cloned()
+            .zip(self.levels.iter().rev().skip(1).map(|g| g.uuid))
+            .collect_vec();
+
+        let graph = adopt_ids(workflow.graph.with_uuid(self.root().uuid), &ids);
+        if graph.uuid == workflow.graph.uuid && graph.fast_eq(&workflow.graph) {
+            workflow
+        } else {
+            Workflow {
+                graph: Arc::new(graph),
+                ..workflow
+            }
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.path.is_empty()
     }
@@ -187,6 +258,26 @@ impl ViewStack {
         result
     }
 
+    /// The graph of the stack whose nodes run under the execution id, if any
+    pub fn exec_graph(&self, exec_id: ExecId) -> Option<GraphId> {
+        let mut current: Option<ExecId> = None;
+
+        for level in self.levels.iter().rev() {
+            let level_id = match current {
+                None => ExecId::from(level.uuid),
+                Some(parent) => parent.scope(level.uuid, 0),
+            };
+
+            if level_id == exec_id {
+                return Some(level.uuid);
+            }
+
+            current = Some(level_id);
+        }
+
+        None
+    }
+
     pub fn exit(&mut self, levels: usize) -> anyhow::Result<()> {
         for _ in 0..levels {
             if self.path.pop_front().is_none()
@@ -318,6 +409,18 @@ pub struct WorkflowViewer {
     #[builder(default)]
     pub transform: TSTransform,
 
+    /// Screen-space offset applied to the view on the next frame
+    #[builder(default)]
+    pub pan: Option<egui::Vec2>,
+
+    /// Screen rect of the graph editor in the last frame
+    #[builder(default = egui::Rect::ZERO)]
+    pub viewport: egui::Rect,
+
+    /// Node found by search, outlined until the highlight expires
+    #[builder(default)]
+    pub highlight: Option<(NodeId, Instant)>,
+
     pub edit_ctx: EditContext,
 
     pub events: Arc<AppEvents>,
@@ -350,6 +453,18 @@ impl WorkflowViewer {
         !self.frozen()
     }
 
+    /// Pans the view on the next frame so that a point of the graph sits in the middle
+    pub fn center_on(&mut self, target: egui::Pos2) {
+        self.pan = Some(self.viewport.center() - self.transform * target);
+    }
+
+    /// Centers the view on a node of the current graph
+    pub fn center_on_node(&mut self, node: NodeId) {
+        if let Some(meta) = self.shadow.nodes.get(&node) {
+            self.center_on(meta.pos + NODE_EXTENT / 2.0);
+        }
+    }
+
     pub fn cast_positions(&mut self, snarl: &Snarl<WorkNode>) {
         if self.frozen {
             return;
@@ -445,6 +560,46 @@ impl WorkflowViewer {
         }
     }
 
+    /// Clones the target nodes and wires between them, placing the copies at a small offset
+    pub fn duplicate_nodes(
+        &mut self,
+        ui: &mut Ui,
+        snarl: &mut Snarl<WorkNode>,
+        node: Option<NodeId>,
+    ) -> Vec<NodeId> {
+        let targets = self
+            .target_nodes(ui, node)
+            .into_iter()
+            .filter(|n| !snarl[*n].is_protected())
+            .collect_vec();
+
+        if targets.is_empty() {
+            return targets;
+        }
+
+        // Round trip through serde for fresh instances rather than shared internals
+        let copied = filter_graph(self.shadow.clone(), egui::Vec2::ZERO, &targets);
+        let copied = match serde_yml::to_string(&copied)
+            .and_then(|text| serde_yml::from_str::<ShadowGraph<WorkNode>>(&text))
+        {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::warn!("Could not duplicate nodes: {err:?}");
+                return vec![];
+            }
+        };
+
+        let inserted = merge_graphs(snarl, &mut self.shadow, DUPLICATE_OFFSET, copied);
+
+        SnarlWidget::new()
+            .id(self.view_id)
+            .update_selected_nodes(ui, |nodes| {
+                *nodes = inserted.clone();
+            });
+
+        inserted
+    }
+
     pub fn disable_nodes(
         &mut self,
         ui: &mut egui::Ui,
@@ -488,6 +643,15 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
         _outputs: &[egui_snarl::OutPin],
         snarl: &Snarl<WorkNode>,
     ) -> egui::Frame {
+        let default = if let Some((target, found)) = self.highlight
+            && target == node
+            && found.elapsed() < HIGHLIGHT_DURATION
+        {
+            default.stroke(egui::Stroke::new(3.0, Color32::YELLOW))
+        } else {
+            default
+        };
+
         if snarl[node].0.downcast_ref::<CommentNode>().is_some() {
             default.fill(CommentNode::bg_color())
         } else {
@@ -740,91 +904,18 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
     }
 
     fn show_graph_menu(&mut self, pos: egui::Pos2, ui: &mut Ui, snarl: &mut Snarl<WorkNode>) {
-        ui.menu_button("Control", |ui| {
-            if ui.button("Fallback").clicked() {
-                snarl.insert_node(pos, Fallback::default().into());
-                ui.close();
-            }
-
-            if ui.button("Matcher").clicked() {
-                snarl.insert_node(pos, Matcher::default().into());
-                ui.close();
-            }
-
-            if ui.button("Select").clicked() {
-                snarl.insert_node(pos, Select::default().into());
-                ui.close();
-            }
-
-            if ui.button("Gate").clicked() {
-                snarl.insert_node(pos, GateNode::default().into());
-                ui.close();
-            }
-
-            if ui.button("Demote").clicked() {
-                snarl.insert_node(pos, Demote::default().into());
-                ui.close();
-            }
-
-            if ui.button("Panic").clicked() {
-                snarl.insert_node(pos, Panic::default().into());
-                ui.close();
-            }
-        });
-
-        ui.menu_button("Value", |ui| {
-            if ui.button("Number").clicked() {
-                snarl.insert_node(pos, Number::default().into());
-                ui.close();
-            }
-
-            if ui.button("Plain Text").clicked() {
-                snarl.insert_node(pos, Text::default().into());
-                ui.close();
-            }
-
-            if ui.button("Template").clicked() {
-                snarl.insert_node(pos, TemplateNode::default().into());
-                ui.close();
-            }
-            if ui.button("Environment").clicked() {
-                snarl.insert_node(pos, EnvironmentNode::default().into());
-                ui.close();
-            }
-        });
-
-        ui.menu_button("LLM", |ui| {
-            if ui.button("Agent").clicked() {
-                snarl.insert_node(pos, AgentNode::default().into());
-                ui.close();
-            }
-
-            if ui.button("Context").clicked() {
-                snarl.insert_node(pos, ChatContext::default().into());
-                ui.close();
-            }
-
-            if ui.button("Chat").clicked() {
-                snarl.insert_node(pos, ChatNode::default().into());
-                ui.close();
-            }
-
-            if ui.button("Structured").clicked() {
-                snarl.insert_node(pos, StructuredChat::default().into());
-                ui.close();
-            }
-        });
+        let submenus = NODE_MENU.iter().filter_map(|entry| entry.0).dedup();
+        for submenu in submenus {
+            ui.menu_button(submenu, |ui| {
+                for (_, label, make) in NODE_MENU.iter().filter(|e| e.0 == Some(submenu)) {
+                    if ui.button(*label).clicked() {
+                        snarl.insert_node(pos, make());
+                        ui.close();
+                    }
+                }
+            });
+        }
 
-        ui.menu_button("Tools", |ui| {
-            if ui.button("Select Tools").clicked() {
-                snarl.insert_node(pos, Tools::default().into());
-                ui.close();
-            }
-            if ui.button("Invoke Tools").clicked() {
-                snarl.insert_node(pos, InvokeTool::default().into());
-                ui.close();
-            }
-        });
         let menus = inventory::iter::<GraphSubmenu>
             .into_iter()
             .sorted_by_key(|m| m.0)
@@ -834,14 +925,11 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
             (cb.1)(ui, snarl, pos);
         }
 
-        if ui.button("Preview").clicked() {
-            snarl.insert_node(pos, Preview::default().into());
-            ui.close();
-        }
-
-        if ui.button("Output").clicked() {
-            snarl.insert_node(pos, OutputNode::default().into());
-            ui.close();
+        for (_, label, make) in NODE_MENU.iter().filter(|e| e.0.is_none()) {
+            if ui.button(*label).clicked() {
+                snarl.insert_node(pos, make());
+                ui.close();
+            }
         }
 
         if ui.button("Comment").clicked() {
@@ -978,6 +1066,22 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
             ui.add(Hyperlink::from_label_and_url("Help", help_link).open_in_new_tab(true));
         }
 
+        let runnable = !(snarl[node].is_comment()
+            || snarl[node].is_start()
+            || snarl[node].is_finish()
+            || self.shadow.is_disabled(node));
+
+        if runnable
+            && ui
+                .add_enabled(self.can_edit(), egui::Button::new("Run this node"))
+                .on_hover_text("Execute only this node using the latest results of its inputs")
+                .clicked()
+        {
+            self.events
+                .insert(AppEvent::RunNode(self.edit_ctx.current_graph, node));
+            ui.close();
+        }
+
         if !snarl[node].is_comment() {
             if self.shadow.is_disabled(node) {
                 if ui.button("Enable").clicked() {
@@ -996,6 +1100,11 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
             }
         }
 
+        if targets.iter().any(|n| !snarl[*n].is_protected()) && ui.button("Duplicate").clicked() {
+            self.duplicate_nodes(ui, snarl, Some(node));
+            ui.close();
+        }
+
         if ui.button("Remove").clicked() {
             self.remove_nodes(ui, snarl, Some(node));
             ui.close();
@@ -1007,10 +1116,53 @@ impl SnarlViewer<WorkNode> for WorkflowViewer {
         to_global: &mut egui::emath::TSTransform,
         _snarl: &mut Snarl<WorkNode>,
     ) {
+        if let Some(pan) = self.pan.take() {
+            to_global.translation += pan;
+        }
+
         self.transform = *to_global;
     }
 }
 
+/// Reassigns the uuids of nested subgraphs following a path of (container, uuid) pairs from the root.
+#[must_use]
+fn adopt_ids(graph: ShadowGraph<WorkNode>, ids: &[(NodeId, GraphId)]) -> ShadowGraph<WorkNode> {
+    let Some(((node_id, uuid), rest)) = ids.split_first() else {
+        return graph;
+    };
+
+    let Some(meta) = graph.nodes.get(node_id) else {
+        return graph;
+    };
+
+    let Some(subgraph) = meta.value.as_node::<Subgraph>() else {
+        return graph;
+    };
+
+    let inner = adopt_ids(subgraph.graph.with_uuid(*uuid), rest);
+    if inner.uuid == subgraph.graph.uuid && inner.fast_eq(&subgraph.graph) {
+        return graph;
+    }
+
+    let node = Subgraph {
+        graph: inner,
+        ..subgraph.clone()
+    };
+
+    let meta = MetaNode {
+        value: node.into(),
+        ..meta.clone()
+    };
+
+    ShadowGraph {
+        nodes: graph.nodes.update(*node_id, meta),
+        ..graph
+    }
+}
+
+/// Displacement of duplicated nodes from their originals
+const DUPLICATE_OFFSET: egui::Vec2 = egui::vec2(32.0, 32.0);
+
 #[must_use]
 pub fn filter_graph(
     graph: ShadowGraph<WorkNode>,
@@ -1127,3 +1279,111 @@ pub fn merge_graphs(
         .filter(|id| start_id != Some(*id))
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::WorkflowError;
+
+    fn with_subgraph(workflow: &Workflow, node_id: NodeId) -> Workflow {
+        let meta = MetaNode {
+            value: Subgraph::default().into(),
+            pos: egui::pos2(0.0, 0.0),
+            open: true,
+        };
+
+        let graph = ShadowGraph {
+            nodes: workflow.graph.nodes.update(node_id, meta),
+            ..workflow.graph.as_ref().clone()
+        };
+
+        Workflow {
+            graph: Arc::new(graph),
+            ..workflow.clone()
+        }
+    }
+
+    #[test]
+    fn test_switch_keeps_root_identity() {
+        let original = Workflow::default();
+        let mut stack = ViewStack::from_root("test", original.clone());
+        let exec_id = stack.exec_id().unwrap();
+
+        let node_state = NodeStateMap::default();
+        node_state
+            .view(exec_id)
+            .insert(NodeId(0), ExecState::Done(vec![]));
+
+        let regenerated = Workflow {
+            graph: Arc::new(original.graph.with_uuid(GraphId::default())),
+            ..original.clone()
+        };
+
+        stack.switch("test", regenerated);
+
+        assert_eq!(stack.exec_id(), Some(exec_id));
+        assert_eq!(stack.root().uuid, original.graph.uuid);
+        assert!(matches!(
+            node_state.view(stack.exec_id().unwrap()).get(&NodeId(0)),
+            Some(ExecState::Done(_))
+        ));
+    }
+
+    #[test]
+    fn test_switch_keeps_subgraph_identity() {
+        let container = NodeId(42);
+        let original = with_subgraph(&Workflow::default(), container);
+        let mut stack = ViewStack::new("test", original.clone(), [container].into_iter());
+        assert_eq!(stack.path.len(), 1);
+
+        let exec_id = stack.exec_id().unwrap();
+        let node_state = NodeStateMap::default();
+        node_state.view(exec_id).insert(
+            NodeId(0),
+            ExecState::Failed(Arc::new(WorkflowError::Timeout)),
+        );
+
+        // A fresh subgraph in the same container, as if restored from an older snapshot
+        let regenerated = with_subgraph(&original, container);
+        assert_ne!(stack.leaf().uuid, {
+            let node = &regenerated.graph.nodes[&container].value;
+            node.as_node::<Subgraph>().unwrap().graph.uuid
+        });
+
+        stack.switch("test", regenerated);
+
+        assert_eq!(stack.path.len(), 1);
+        assert_eq!(stack.exec_id(), Some(exec_id));
+        assert!(matches!(
+            node_state.view(exec_id).get(&NodeId(0)),
+            Some(ExecState::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn test_node_menu_entries_unique() {
+        let labels = NODE_MENU.iter().map(|(menu, label, _)| (*menu, *label));
+        assert!(labels.all_unique());
+
+        let kinds = NODE_MENU
+            .iter()
+            .map(|(_, _, make)| make().kind().to_string());
+        assert!(kinds.all_unique());
+
+        // Submenus are contiguous so each is shown once
+        let submenus = NODE_MENU.iter().filter_map(|entry| entry.0);
+        assert!(submenus.dedup().all_unique());
+    }
+
+    #[test]
+    fn test_switch_other_workflow_takes_new_identity() {
+        let original = Workflow::default();
+        let mut stack = ViewStack::from_root("test", original.clone());
+
+        let other = Workflow::default();
+        stack.switch("other", other.clone());
+
+        assert_eq!(stack.root().uuid, other.graph.uuid);
+        assert_ne!(stack.root().uuid, original.graph.uuid);
+    }
+}
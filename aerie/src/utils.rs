@@ -370,6 +370,8 @@ pub fn extract_user_content(content: &UserContent) -> Vec<(String, FormatOpts)>
                 _ => None,
             })
             .collect_vec(),
+        // Rendered separately by the chat bubble
+        UserContent::Image(_) => vec![],
         other => vec![(format!("{other:?}"), FormatOpts::Unknown)],
     }
 }
@@ -429,6 +431,115 @@ where
     extract_json(input, false)
 }
 
+/// Parses likely-JSON text from a language model, fixing common mistakes.
+///
+/// Markdown code fences and trailing commas are always removed.
+/// Aggressive repairs also convert single-quoted strings, quote bare keys
+/// and replace Python literals like `True` and `None`.
+/// Falls back on [extract_json] when the text surrounding the JSON is chatter.
+pub fn repair_json(text: &str, aggressive: bool) -> Option<Value> {
+    let text = strip_code_fences(text.trim());
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+
+    let repaired = repair_json_syntax(text, aggressive);
+    serde_json::from_str(&repaired)
+        .ok()
+        .or_else(|| extract_json(&repaired, false))
+        .or_else(|| extract_json(&repaired, true))
+}
+
+fn strip_code_fences(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+
+    // Skip the language tag following the opening fence
+    let body = &text[start + 3..];
+    let body = body.find('\n').map(|i| &body[i + 1..]).unwrap_or(body);
+
+    match body.find("```") {
+        Some(end) => &body[..end],
+        None => body,
+    }
+}
+
+/// Consumes whitespace so the following token can be inspected
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut ws = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_whitespace()) {
+        ws.push(c);
+    }
+    ws
+}
+
+fn repair_json_syntax(text: &str, aggressive: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                out.push(c);
+                while let Some(c) = chars.next() {
+                    out.push(c);
+                    match c {
+                        '\\' => out.extend(chars.next()),
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+            }
+            '\'' if aggressive => {
+                out.push('"');
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' if chars.peek() == Some(&'\'') => out.extend(chars.next()),
+                        '\\' => {
+                            out.push(c);
+                            out.extend(chars.next());
+                        }
+                        '"' => out.push_str("\\\""),
+                        '\'' => break,
+                        _ => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            ',' => {
+                let ws = skip_whitespace(&mut chars);
+                if !matches!(chars.peek(), Some('}' | ']')) {
+                    out.push(c);
+                }
+                out.push_str(&ws);
+            }
+            c if aggressive && (c.is_alphabetic() || c == '_') => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+
+                let ws = skip_whitespace(&mut chars);
+                if chars.peek() == Some(&':') {
+                    out.push_str(&format!("\"{word}\""));
+                } else {
+                    out.push_str(match word.as_str() {
+                        "True" => "true",
+                        "False" => "false",
+                        "None" => "null",
+                        _ => &word,
+                    });
+                }
+                out.push_str(&ws);
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
@@ -462,4 +573,28 @@ mod tests {
             Some(json!({"hello": "world", "number": 1}))
         );
     }
+
+    #[test]
+    fn test_repair_json() {
+        let fenced = "Here you go:\n```json\n{\"a\": [1, 2,], \"b\": \"x, }\",}\n```\nEnjoy!";
+        assert_eq!(
+            repair_json(fenced, false),
+            Some(json!({"a": [1, 2], "b": "x, }"}))
+        );
+
+        let sloppy = "{name: 'O\\'Brien', 'quote': 'say \"hi\"', ok: True, none: None,}";
+        assert_eq!(repair_json(sloppy, false), None);
+        assert_eq!(
+            repair_json(sloppy, true),
+            Some(json!({"name": "O'Brien", "quote": "say \"hi\"", "ok": true, "none": null}))
+        );
+
+        let numbers = "[1e5, -2.5E-3, true, null,]";
+        assert_eq!(
+            repair_json(numbers, true),
+            Some(json!([1e5, -2.5e-3, true, null]))
+        );
+
+        assert_eq!(repair_json("not json at all", true), None);
+    }
 }
@@ -2,7 +2,7 @@ use crate::rig::{
     message::Message,
     tool::{ToolSetError, server::ToolServerError},
 };
-use arc_swap::ArcSwap;
+use arc_swap::{ArcSwap, ArcSwapOption};
 use decorum::{E32, E64};
 use downcast_rs::{Downcast, impl_downcast};
 use dyn_clone::DynClone;
@@ -23,9 +23,14 @@ use serde_with::skip_serializing_none;
 use serde_yaml_ng as serde_yml;
 use std::{
     borrow::Cow,
+    collections::BTreeSet,
     fmt::Debug,
     hash::Hash,
-    sync::{Arc, atomic::AtomicBool},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
 };
 use thiserror::Error;
 use typed_builder::TypedBuilder;
@@ -34,19 +39,23 @@ use uuid::Uuid;
 use crate::{
     AgentFactory, ChatHistory, ToolSelector, Toolbox,
     agent::AgentSpec,
+    chat::{Attachments, StreamHandle, StreamProgress},
     config::SeedConfig,
-    transmute::Transmuter,
+    transmute::{ConversionRules, Transmuter},
     ui::{AppEvent, AppEvents},
     utils::{AtomicBuffer, ErrorList, ImmutableMapExt as _, ImmutableSetExt as _, message_text},
     workflow::{
-        nodes::{Finish, Flavor, Start},
-        runner::{ExecId, ExecState, NodeStateMap},
+        nodes::{CommentNode, Finish, Flavor, OutputNode, PROMPT_PARAMETER, Preview, Start},
+        runner::{ExecId, ExecState, NodeCache, NodeStateMap},
+        store::WorkflowStoreDir,
     },
 };
 
 pub mod nodes;
 pub mod runner;
 pub mod store;
+#[cfg(test)]
+pub mod testing;
 
 pub use nodes::WorkNode;
 // Note: Need to use decourm wrappers for floats in the graph to allow for hashing and equivalence,
@@ -227,6 +236,14 @@ pub struct EditContext {
 
     #[builder(default)]
     pub edit_pin: Arc<ArcSwap<Option<AnyPin>>>,
+
+    /// Implicit conversions allowed when connecting wires
+    #[builder(default)]
+    pub conversions: ConversionRules,
+
+    /// Saved workflows, for nodes that refer to them by name
+    #[builder(default)]
+    pub store: Option<WorkflowStoreDir>,
 }
 
 impl EditContext {
@@ -280,9 +297,21 @@ pub struct RunContext {
     #[builder(default)]
     pub streaming: bool,
 
+    /// Execute all ready nodes at once instead of one at a time
+    #[builder(default)]
+    pub concurrent: bool,
+
+    /// Seconds any node may execute before failing, unless it sets its own timeout
+    #[builder(default)]
+    pub node_timeout: Option<u64>,
+
     #[builder(default)]
     pub node_state: NodeStateMap,
 
+    /// Outputs of cacheable nodes kept between runs
+    #[builder(default)]
+    pub cache: NodeCache,
+
     #[builder(default)]
     pub previews: PreviewData,
 
@@ -295,6 +324,10 @@ pub struct RunContext {
     #[builder(default)]
     pub interrupt: Arc<AtomicBool>,
 
+    /// Raised when the node running with this context exceeds its timeout
+    #[builder(default)]
+    pub cancel: Arc<AtomicBool>,
+
     /// Snapshot of the chat before the workflow is run
     #[builder(default)]
     pub history: Arc<ArcSwap<ChatHistory>>,
@@ -302,9 +335,29 @@ pub struct RunContext {
     #[builder(default)]
     pub seed: Option<SeedConfig>,
 
+    /// Base for random values in this run, captured from `seed` before any node can advance it
+    #[builder(default = seed.as_ref().map(|s| s.value.load(std::sync::atomic::Ordering::Relaxed)))]
+    pub rng_seed: Option<u64>,
+
+    /// Tools given to agents that have none, such as the direct chat agent
+    #[builder(default)]
+    pub default_tools: Option<Arc<ToolSelector>>,
+
+    /// Names of the saved workflows being run, outermost first
+    #[builder(default)]
+    pub call_stack: im::Vector<String>,
+
     #[builder(default)]
     pub scratch: Option<AtomicBuffer<Result<Message, String>>>,
 
+    /// Where streaming helpers publish statistics of the message being generated
+    #[builder(default)]
+    pub progress: Option<Arc<ArcSwapOption<StreamProgress>>>,
+
+    /// Images the user attached to the prompt of this run
+    #[builder(default)]
+    pub attachments: Arc<Attachments>,
+
     /// Final chat snapshot at the end of the workflow run that we want to keep
     #[builder(default)]
     pub response: Option<Arc<ChatHistory>>,
@@ -313,7 +366,55 @@ pub struct RunContext {
     pub errors: ErrorList<anyhow::Error>,
 }
 
+/// Longest stretch a blocking node goes without checking whether it should stop
+const CANCEL_CHECK: Duration = Duration::from_millis(100);
+
 impl RunContext {
+    /// Whether the run was stopped or the node running with this context timed out
+    pub fn is_cancelled(&self) -> bool {
+        self.interrupt.load(Ordering::Relaxed) || self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Drives the future until it finishes, dropping it early once the node is cancelled
+    pub async fn cancellable<T>(
+        &self,
+        task: impl Future<Output = Result<T, WorkflowError>>,
+    ) -> Result<T, WorkflowError> {
+        let watch = async {
+            while !self.is_cancelled() {
+                tokio::time::sleep(CANCEL_CHECK).await;
+            }
+        };
+
+        tokio::select! {
+            biased;
+            result = task => result,
+            _ = watch => Err(WorkflowError::Interrupted),
+        }
+    }
+
+    /// Random number generator for a node.
+    ///
+    /// With a seed, the sequence depends only on the seed, the node and its subgraph iteration,
+    /// so reruns produce the same values. Otherwise it is seeded from entropy.
+    pub fn rng(&self, node_id: NodeId) -> fastrand::Rng {
+        let Some(seed) = self.rng_seed else {
+            return fastrand::Rng::new();
+        };
+
+        let ExecId(high, low) = self.exec_id;
+        let mixed = [high, low, node_id.0 as u64]
+            .into_iter()
+            .fold(seed, |acc, it| fastrand::Rng::with_seed(acc ^ it).u64(..));
+
+        fastrand::Rng::with_seed(mixed)
+    }
+
+    /// Starts publishing statistics of a streaming response, until the handle drops
+    pub fn track_stream(&self) -> Option<StreamHandle> {
+        self.progress.as_ref().map(StreamProgress::start)
+    }
+
     #[inline]
     pub fn event(&self, event: AppEvent) {
         let Some(queue) = &self.events else {
@@ -350,6 +451,10 @@ pub struct RootContext {
     /// The user's prompt that initiated the workflow run
     #[builder(default)]
     pub user_prompt: String,
+
+    /// Values for the parameters declared on the Start node, by name
+    #[builder(default)]
+    pub params: im::OrdMap<String, Value>,
 }
 
 impl RootContext {
@@ -362,13 +467,30 @@ impl RootContext {
         };
 
         // TODO: Probably don't need most of these in the object
-        let values = vec![
+        let mut values = vec![
             Some(Value::Text(Arc::new(self.model.clone()))),
             Some(Value::Number(E64::assert(self.temperature))),
             Some(Value::Chat(self.history.load().clone())),
             Some(Value::Json(Arc::new(schema))),
             Some(Value::Text(Arc::new(self.user_prompt.clone()))),
         ];
+
+        let parameters = self.workflow.graph.start_node().into_iter();
+        for (name, kind) in parameters.flat_map(|start| start.parameters()) {
+            let value = match self.params.get(name) {
+                Some(value) if value.kind() == *kind => Some(value.clone()),
+                Some(value) => Err(WorkflowError::Conversion(format!(
+                    "Parameter {name} expects {kind:?} but got {:?}",
+                    value.kind()
+                )))?,
+                None if name == PROMPT_PARAMETER && *kind == ValueKind::Text => {
+                    Some(Value::text(self.user_prompt.clone()))
+                }
+                None => None,
+            };
+            values.push(value);
+        }
+
         Ok(values)
     }
 }
@@ -567,6 +689,17 @@ impl Default for ShadowGraph<WorkNode> {
     }
 }
 
+impl ShadowGraph<WorkNode> {
+    /// Combined selection of all enabled Tools nodes at the top level of the graph
+    pub fn tool_selection(&self) -> ToolSelector {
+        self.nodes
+            .iter()
+            .filter(|(id, _)| !self.is_disabled(**id))
+            .filter_map(|(_, meta)| meta.value.as_node::<nodes::Tools>())
+            .fold(ToolSelector::empty(), |acc, node| acc.union(&node.toolset))
+    }
+}
+
 // TODO: get a handle on generics here
 impl TryFrom<ShadowGraph<WorkNode>> for Snarl<WorkNode> {
     type Error = anyhow::Error;
@@ -602,6 +735,19 @@ where
         }
     }
 
+    /// Assigns a different identity to this graph, keeping its contents.
+    #[must_use]
+    pub fn with_uuid(&self, uuid: GraphId) -> Self {
+        if self.uuid == uuid {
+            self.clone()
+        } else {
+            Self {
+                uuid,
+                ..self.clone()
+            }
+        }
+    }
+
     /// Quickly see if the collections have the same memory address.
     /// Does not account for identical copies in different addresses.
     /// Use the standard comparator to do a deep check instead.
@@ -844,6 +990,24 @@ where
             ..self.clone()
         }
     }
+
+    /// Swaps in a new value for an existing node, keeping its position
+    pub fn replace_node(&self, id: NodeId, value: T) -> Self {
+        let Some(meta) = self.nodes.get(&id) else {
+            return self.clone();
+        };
+
+        Self {
+            nodes: self.nodes.update(
+                id,
+                MetaNode {
+                    value,
+                    ..meta.clone()
+                },
+            ),
+            ..self.clone()
+        }
+    }
 }
 
 impl ShadowGraph<WorkNode> {
@@ -909,6 +1073,82 @@ impl ShadowGraph<WorkNode> {
         }
     }
 
+    /// Fails with the names of results on the Finish node that have nothing wired to them
+    pub fn check_results(&self) -> Result<(), WorkflowError> {
+        let finish = self
+            .finish
+            .iter()
+            .chain(self.nodes.keys())
+            .find_map(|id| Some((*id, self.nodes.get(id)?.value.as_node::<Finish>()?)));
+
+        let Some((node_id, finish)) = finish else {
+            return Ok(());
+        };
+
+        let missing = finish
+            .results()
+            .filter(|(pin, _)| {
+                let in_pin = InPinId {
+                    node: node_id,
+                    input: *pin,
+                };
+                !self.wires.iter().any(|wire| wire.in_pin == in_pin)
+            })
+            .map(|(_, (name, _))| format!("Result {name:?} is not connected"))
+            .collect_vec();
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(WorkflowError::Required(missing))
+        }
+    }
+
+    /// Nodes that cannot affect a run, since no chain of wires links them to Start, Finish
+    /// or an Output node.
+    ///
+    /// Comments, previews and disabled nodes are never reported.
+    pub fn unreachable_nodes(&self) -> BTreeSet<NodeId> {
+        let mut linked: im::OrdMap<NodeId, Vec<NodeId>> = Default::default();
+        for Wire { out_pin, in_pin } in &self.wires {
+            linked.entry(out_pin.node).or_default().push(in_pin.node);
+            linked.entry(in_pin.node).or_default().push(out_pin.node);
+        }
+
+        let mut queue = self
+            .nodes
+            .iter()
+            .filter(|(id, meta)| {
+                Some(**id) == self.start
+                    || Some(**id) == self.finish
+                    || meta.value.as_node::<Start>().is_some()
+                    || meta.value.as_node::<Finish>().is_some()
+                    || meta.value.as_node::<OutputNode>().is_some()
+            })
+            .map(|(id, _)| *id)
+            .collect_vec();
+
+        let mut reached: BTreeSet<NodeId> = queue.iter().cloned().collect();
+        while let Some(id) = queue.pop() {
+            for other in linked.get(&id).into_iter().flatten() {
+                if reached.insert(*other) {
+                    queue.push(*other);
+                }
+            }
+        }
+
+        self.nodes
+            .iter()
+            .filter(|(id, meta)| {
+                !reached.contains(id)
+                    && !self.is_disabled(**id)
+                    && meta.value.as_node::<CommentNode>().is_none()
+                    && meta.value.as_node::<Preview>().is_none()
+            })
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
     pub fn start_kinds(&self) -> impl Iterator<Item = ValueKind> {
         if let Some(start) = self.start_node() {
             Either::Right((0..start.outputs()).map(|i| start.out_kind(i)))
@@ -984,6 +1224,16 @@ pub trait DynNode {
         5000
     }
 
+    /// Seconds the runner lets this node execute before failing it, overriding the default
+    fn timeout(&self) -> Option<u64> {
+        None
+    }
+
+    /// Whether outputs depend only on the node and its inputs, so the runner can reuse them
+    fn cacheable(&self) -> bool {
+        false
+    }
+
     fn uuid(&self) -> Option<Uuid> {
         None
     }
@@ -1050,8 +1300,7 @@ pub trait DynNode {
     }
 
     fn connect(&mut self, in_pin: usize, kind: ValueKind, ctx: &EditContext) -> Result<(), String> {
-        let _ = ctx;
-        if !self.in_kinds(in_pin).contains(&kind) {
+        if !ctx.conversions.accepts(kind, &self.in_kinds(in_pin)) {
             tracing::warn!(
                 "Refusing to connect {kind:?} to {in_pin:?} accepting {:?}",
                 self.in_kinds(in_pin)
@@ -1187,6 +1436,12 @@ pub enum WorkflowError {
     #[error("Error while executing subgraph")]
     Subgraph(#[source] Arc<WorkflowError>),
 
+    #[error("Recursive workflow call: {0}")]
+    Recursion(String),
+
+    #[error("HTTP request failed: {0}")]
+    Http(String),
+
     // #[error("Scripting error {0:?}")]
     // RhaiScript(#[source] Arc<rhai::EvalAltResult>),
     #[error("{0}")]
@@ -1213,6 +1468,8 @@ impl PartialEq for WorkflowError {
             (Self::Validation(l0), Self::Validation(r0)) => std::ptr::eq(l0, r0),
             (Self::Unfinished(l0), Self::Unfinished(r0)) => std::ptr::eq(l0, r0),
             (Self::Subgraph(l0), Self::Subgraph(r0)) => l0 == r0,
+            (Self::Recursion(l0), Self::Recursion(r0)) => l0 == r0,
+            (Self::Http(l0), Self::Http(r0)) => l0 == r0,
             (Self::Unknown(l0), Self::Unknown(r0)) => l0 == r0,
             _ => core::mem::discriminant(self) == core::mem::discriminant(other),
         }
@@ -1237,6 +1494,16 @@ impl From<Box<rhai::EvalAltResult>> for WorkflowError {
     }
 }
 
+impl From<reqwest::Error> for WorkflowError {
+    fn from(value: reqwest::Error) -> Self {
+        if value.is_timeout() {
+            WorkflowError::Timeout
+        } else {
+            WorkflowError::Http(value.to_string())
+        }
+    }
+}
+
 impl From<anyhow::Error> for WorkflowError {
     fn from(value: anyhow::Error) -> Self {
         WorkflowError::Unknown(format!("{value:?}"))
@@ -1301,3 +1568,52 @@ pub fn write_value(mut fh: impl std::io::Write, value: &Value) -> Result<(), any
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::nodes::Number;
+
+    #[test]
+    fn test_unreachable_nodes() {
+        let meta = |value: WorkNode| MetaNode {
+            value,
+            pos: egui::pos2(0.0, 0.0),
+            open: true,
+        };
+        let wire = |from: usize, to: usize| -> Wire {
+            (
+                OutPinId {
+                    node: NodeId(from),
+                    output: 0,
+                },
+                InPinId {
+                    node: NodeId(to),
+                    input: 0,
+                },
+            )
+                .into()
+        };
+
+        // 0 -> 1 -> 2 is the main path, 3 feeds into it, 4 -> 5 is detached
+        let graph = ShadowGraph {
+            nodes: im::ordmap! {
+                NodeId(0) => meta(Start::default().into()),
+                NodeId(1) => meta(Number::default().into()),
+                NodeId(2) => meta(Finish::default().into()),
+                NodeId(3) => meta(Number::default().into()),
+                NodeId(4) => meta(Number::default().into()),
+                NodeId(5) => meta(Preview::default().into()),
+                NodeId(6) => meta(CommentNode::default().into()),
+                NodeId(7) => meta(Number::default().into())
+            },
+            wires: im::ordset![wire(0, 1), wire(1, 2), wire(3, 1), wire(4, 5)],
+            disabled: im::ordset![NodeId(7)],
+            start: Some(NodeId(0)),
+            finish: Some(NodeId(2)),
+            ..ShadowGraph::empty()
+        };
+
+        assert_eq!(graph.unreachable_nodes(), BTreeSet::from([NodeId(4)]));
+    }
+}
@@ -8,6 +8,7 @@ use std::{borrow::Cow, sync::Arc, time::Duration};
 use super::{DynNode, EditContext, RunContext, UiNode, Value, ValueKind};
 use crate::{
     ToolProvider, ToolSelector,
+    agent::parse_stop_sequences,
     config::Ternary,
     toolbox::{ChainBreaker, ChainTool},
     ui::{resizable_frame, resizable_frame_opt, shortcuts::squelch},
@@ -220,6 +221,9 @@ pub struct AgentNode {
 
     pub temperature: Option<E64>,
 
+    /// Stop sequences overriding the default from settings, one per line
+    pub stop: Option<String>,
+
     pub size: Option<crate::utils::EVec2>,
 }
 
@@ -228,7 +232,7 @@ impl FlexNode for AgentNode {}
 
 impl DynNode for AgentNode {
     fn inputs(&self) -> usize {
-        5
+        6
     }
 
     fn outputs(&self) -> usize {
@@ -242,6 +246,7 @@ impl DynNode for AgentNode {
             2 => &[ValueKind::Number],
             3 => &[ValueKind::Tools],
             4 => &[ValueKind::Text],
+            5 => &[ValueKind::TextList],
             _ => ValueKind::all(),
         })
     }
@@ -255,7 +260,7 @@ impl DynNode for AgentNode {
 
     fn execute(
         &mut self,
-        _ctx: &RunContext,
+        ctx: &RunContext,
         _node_id: egui_snarl::NodeId,
         inputs: Vec<Option<Value>>,
     ) -> Result<Vec<Value>, WorkflowError> {
@@ -267,9 +272,11 @@ impl DynNode for AgentNode {
             _ => unreachable!(),
         };
 
+        // Precedence for model, temperature and preamble:
+        // connected pin, then the node's own field, then whatever the incoming agent has.
         let model = match &inputs[1] {
-            Some(Value::Text(name)) => Some((**name).clone()),
-            None => self.model.clone(),
+            Some(Value::Text(name)) if !name.is_empty() => Some((**name).clone()),
+            Some(Value::Text(_)) | None => self.model.clone().filter(|name| !name.is_empty()),
             _ => unreachable!(),
         };
 
@@ -297,27 +304,63 @@ impl DynNode for AgentNode {
             _ => unreachable!(),
         };
 
+        let stop = match &inputs[5] {
+            Some(Value::TextList(items)) => {
+                Some(items.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+            }
+            None => self
+                .stop
+                .as_deref()
+                .map(|text| parse_stop_sequences(text.lines())),
+            _ => unreachable!(),
+        };
+
+        if let Some(stop) = &stop
+            && stop.iter().any(String::is_empty)
+        {
+            Err(WorkflowError::Conversion(
+                "Stop sequences cannot be empty".into(),
+            ))?;
+        }
+
         let mut agent = agent.unwrap_or_default();
         let builder = Arc::make_mut(&mut agent);
 
+        let toolset = toolset.or_else(|| {
+            if builder.tools.is_none() {
+                ctx.default_tools.clone()
+            } else {
+                None
+            }
+        });
+
         if let Some(model) = model {
             builder.model(model);
         }
 
         if let Some(temp) = temperature {
-            tracing::debug!("Setting agent temperature {temp}");
             builder.temperature(temp);
         }
 
         if let Some(preamble) = &preamble {
-            tracing::debug!("Setting agent preamble {preamble}");
             builder.preamble(preamble.clone());
         }
 
+        tracing::debug!(
+            model = ?builder.model,
+            temperature = ?builder.temperature,
+            preamble = ?builder.preamble,
+            "Resolved agent settings"
+        );
+
         if let Some(tools) = toolset {
             builder.tools(tools);
         }
 
+        if let Some(stop) = stop {
+            builder.stop(stop);
+        }
+
         Ok(vec![Value::Agent(agent)])
     }
 }
@@ -444,6 +487,24 @@ impl UiNode for AgentNode {
                     ui.label("preamble");
                 }
             }
+            5 => {
+                if remote.is_none() {
+                    let help = "stop sequences\n\
+                        \n\
+                        Generation ends when the model outputs one of these, one per line.\n\
+                        Write \\n for a line break. Overrides the default from settings.";
+                    crate::ui::toggled_field(ui, "X", Some(help), &mut self.stop, |ui, value| {
+                        let widget = egui::TextEdit::multiline(value)
+                            .id_salt("stopseq")
+                            .desired_rows(2)
+                            .hint_text("stop sequences");
+
+                        squelch(ui.add(widget).on_hover_text(help));
+                    });
+                } else {
+                    ui.label("stop");
+                }
+            }
             _ => unreachable!(),
         };
 
@@ -657,7 +718,7 @@ impl DynNode for InvokeTool {
     ) -> Result<Vec<Value>, WorkflowError> {
         let _ = (node_id,);
         let rt = ctx.runtime.clone();
-        rt.block_on(self.forward(ctx, inputs))
+        rt.block_on(ctx.cancellable(self.forward(ctx, inputs)))
     }
 }
 
@@ -751,8 +812,12 @@ impl InvokeTool {
             _ => unreachable!(),
         };
 
-        let rig_tools = run_ctx.agent_factory.toolbox.get_tools(&toolset);
-        let tool_defs = rig_tools.get_tool_definitions().await.unwrap_or_default();
+        let tool_defs = run_ctx
+            .agent_factory
+            .toolbox
+            .get_tool_definitions(&toolset)
+            .await
+            .unwrap_or_default();
         tracing::debug!("Tool definitions {:?}", &tool_defs);
 
         let single_tool = if let [tool] = tool_defs.as_slice() {
@@ -780,15 +845,7 @@ impl InvokeTool {
             _ => unreachable!(),
         };
 
-        let future = rig_tools.call(tool_name, args.to_string());
-        let tool_output =
-            if let Some(seconds) = run_ctx.agent_factory.toolbox.timeout(&toolset, tool_name) {
-                tokio::time::timeout(Duration::from_secs(seconds), future)
-                    .await
-                    .map_err(|_| WorkflowError::Timeout)??
-            } else {
-                future.await?
-            };
+        let tool_output = call_tool(run_ctx, &toolset, tool_name, &args).await?;
 
         let msg = Message::tool_result(tool_name, &tool_output);
 
@@ -807,3 +864,116 @@ impl InvokeTool {
         ])
     }
 }
+
+/// Calls a tool from the toolset, honoring the provider's timeout
+pub(crate) async fn call_tool(
+    run_ctx: &RunContext,
+    toolset: &ToolSelector,
+    tool_name: &str,
+    args: &serde_json::Value,
+) -> Result<String, WorkflowError> {
+    let toolbox = &run_ctx.agent_factory.toolbox;
+    let rig_tools = toolbox.get_tools(toolset);
+    let future = rig_tools.call(tool_name, args.to_string());
+
+    Ok(if let Some(seconds) = toolbox.timeout(toolset, tool_name) {
+        tokio::time::timeout(Duration::from_secs(seconds), future)
+            .await
+            .map_err(|_| WorkflowError::Timeout)??
+    } else {
+        future.await?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{agent::AgentSpec, workflow::testing::run_ctx};
+
+    fn resolve(
+        ctx: &RunContext,
+        node: &mut AgentNode,
+        agent: Option<&AgentSpec>,
+        pins: [Option<Value>; 3],
+    ) -> Result<Arc<AgentSpec>, WorkflowError> {
+        let [model, temperature, preamble] = pins;
+        let inputs = vec![
+            agent.map(|spec| Value::Agent(Arc::new(spec.clone()))),
+            model,
+            temperature,
+            None,
+            preamble,
+            None,
+        ];
+
+        match node.execute(ctx, egui_snarl::NodeId(0), inputs)?.remove(0) {
+            Value::Agent(spec) => Ok(spec),
+            other => panic!("Expected an agent, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_agent_precedence() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx = run_ctx(&rt);
+
+        let mut incoming = AgentSpec::default();
+        incoming
+            .model("agent/model".into())
+            .temperature(E64::assert(0.1))
+            .preamble("agent preamble".into());
+
+        let mut node = AgentNode {
+            model: Some("field/model".into()),
+            temperature: Some(E64::assert(0.5)),
+            preamble: Some("field preamble".into()),
+            ..Default::default()
+        };
+        let pins = || {
+            [
+                Some(Value::text("pin/model")),
+                Some(Value::Number(E64::assert(0.9))),
+                Some(Value::text("pin preamble")),
+            ]
+        };
+
+        // Connected pins win over both the fields and the incoming agent
+        let spec = resolve(&ctx, &mut node, Some(&incoming), pins()).unwrap();
+        assert_eq!(spec.model.as_deref(), Some("pin/model"));
+        assert_eq!(spec.temperature, Some(E64::assert(0.9)));
+        assert_eq!(spec.preamble.as_deref(), Some("pin preamble"));
+
+        // Fields win over the incoming agent
+        let spec = resolve(&ctx, &mut node, Some(&incoming), Default::default()).unwrap();
+        assert_eq!(spec.model.as_deref(), Some("field/model"));
+        assert_eq!(spec.temperature, Some(E64::assert(0.5)));
+        assert_eq!(spec.preamble.as_deref(), Some("field preamble"));
+
+        // The incoming agent is kept when nothing overrides it
+        let mut bare = AgentNode::default();
+        let spec = resolve(&ctx, &mut bare, Some(&incoming), Default::default()).unwrap();
+        assert_eq!(spec.as_ref(), &incoming);
+
+        // An empty model name on the pin falls back to the field
+        let spec = resolve(
+            &ctx,
+            &mut node,
+            Some(&incoming),
+            [Some(Value::text("")), None, None],
+        )
+        .unwrap();
+        assert_eq!(spec.model.as_deref(), Some("field/model"));
+
+        // Pins apply without an incoming agent
+        let spec = resolve(&ctx, &mut bare, None, pins()).unwrap();
+        assert_eq!(spec.model.as_deref(), Some("pin/model"));
+
+        // Some model is required
+        assert!(matches!(
+            resolve(&ctx, &mut bare, None, Default::default()),
+            Err(WorkflowError::Required(_))
+        ));
+    }
+}
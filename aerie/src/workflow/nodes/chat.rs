@@ -18,7 +18,7 @@ use serde_with::skip_serializing_none;
 use crate::{
     ChatContent, ToolSelector,
     ui::{resizable_frame, shortcuts::squelch},
-    utils::{CowExt as _, extract_json, message_text},
+    utils::{CowExt as _, extract_json, message_text, repair_json},
     workflow::{FlexNode, WorkflowError},
 };
 
@@ -34,12 +34,19 @@ pub struct ChatNode {
     pub prompt: String,
 
     pub size: Option<crate::utils::EVec2>,
+
+    /// Seconds to wait for the model, including tool calls
+    pub timeout: Option<u64>,
 }
 
 #[typetag::serde]
 impl FlexNode for ChatNode {}
 
 impl DynNode for ChatNode {
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
     fn inputs(&self) -> usize {
         3
     }
@@ -74,7 +81,7 @@ impl DynNode for ChatNode {
     ) -> Result<Vec<Value>, WorkflowError> {
         let _ = (node_id,);
         let rt = ctx.runtime.clone();
-        rt.block_on(self.forward(ctx, inputs))
+        rt.block_on(ctx.cancellable(self.forward(ctx, inputs)))
     }
 }
 
@@ -97,6 +104,14 @@ impl UiNode for ChatNode {
             back to the model for follow-up."
     }
 
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        timeout_editor(ui, &mut self.timeout);
+    }
+
     fn show_output(
         &mut self,
         ui: &mut egui::Ui,
@@ -206,6 +221,7 @@ impl ChatNode {
             }
             _ => Err(WorkflowError::Required(vec!["A prompt is required".into()]))?,
         };
+        let prompt = run_ctx.attachments.apply(prompt);
 
         let last_idx = messages.len();
 
@@ -264,6 +280,9 @@ pub struct StructuredChat {
 
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub extract: bool,
+
+    /// Seconds to wait for the model, including retries
+    pub timeout: Option<u64>,
 }
 
 #[typetag::serde]
@@ -271,6 +290,10 @@ impl FlexNode for StructuredChat {}
 
 // outputs: chat, message, structured data
 impl DynNode for StructuredChat {
+    fn timeout(&self) -> Option<u64> {
+        self.timeout
+    }
+
     fn inputs(&self) -> usize {
         4
     }
@@ -308,7 +331,7 @@ impl DynNode for StructuredChat {
     ) -> Result<Vec<Value>, WorkflowError> {
         let _ = (node_id,);
         let rt = ctx.runtime.clone();
-        rt.block_on(self.forward(ctx, inputs))
+        rt.block_on(ctx.cancellable(self.forward(ctx, inputs)))
     }
 }
 
@@ -422,10 +445,35 @@ impl UiNode for StructuredChat {
                 "If the model fails to submit a proper tool call,\n\
                     Attempt to find tool arguments inside its text response.",
             );
+
+            timeout_editor(ui, &mut self.timeout);
         });
     }
 }
 
+/// Edits the seconds a chat node may run, where zero defers to the settings
+fn timeout_editor(ui: &mut egui::Ui, timeout: &mut Option<u64>) {
+    ui.horizontal(|ui| {
+        ui.label("timeout");
+
+        let mut seconds = timeout.unwrap_or_default();
+        let widget = egui::DragValue::new(&mut seconds)
+            .range(0..=3600)
+            .suffix(" s");
+
+        if ui
+            .add(widget)
+            .on_hover_text(
+                "Fail if the model has not finished after this many seconds.\n\
+                    Zero uses the node timeout from settings.",
+            )
+            .changed()
+        {
+            *timeout = Some(seconds).filter(|it| *it > 0);
+        }
+    });
+}
+
 // TODO: investigate why errors in the MCP server cause a panic here
 impl StructuredChat {
     pub async fn forward(
@@ -470,7 +518,8 @@ impl StructuredChat {
             },
             None if !self.prompt.is_empty() => Some(Message::user(self.prompt.clone())),
             _ => None,
-        };
+        }
+        .map(|prompt| run_ctx.attachments.apply(prompt));
 
         let validator = if let Some(schema) = schema.as_ref() {
             Some(
@@ -511,7 +560,7 @@ impl StructuredChat {
         // }
 
         let result: Result<_, WorkflowError> = loop {
-            if run_ctx.interrupt.load(Ordering::Relaxed) {
+            if run_ctx.is_cancelled() {
                 Err(WorkflowError::Interrupted)?;
             }
 
@@ -575,7 +624,7 @@ impl StructuredChat {
                         // Still failed? If we asked for just an object try to find one to validate
                         if schema.is_some()
                             && tool_func.is_none()
-                            && let Some(args) = extract_json::<serde_json::Value>(&text, false)
+                            && let Some(args) = repair_json(&text, false)
                         {
                             tool_func = Some(ToolFunction {
                                 name: "???".into(),
@@ -733,15 +782,19 @@ async fn one_shot_completion(
     } else {
         None
     };
+    let progress = run_ctx.track_stream();
 
     while let Some(content) = stream.next().await {
-        if run_ctx.interrupt.load(Ordering::Relaxed) {
+        if run_ctx.is_cancelled() {
             Err(WorkflowError::Interrupted)?;
         }
 
         match content {
             Ok(item) => match item {
                 StreamedAssistantContent::Text(text) => {
+                    if let Some(progress) = &progress {
+                        progress.record(&text.text);
+                    }
                     texts.push_str(&text.text);
                     let msg = Message::assistant(&texts);
                     if let Some(a) = &agent_msg {
@@ -879,17 +932,21 @@ async fn multi_turn_completion(
             .scratch
             .as_ref()
             .map(|s| s.push_back(Ok(Message::assistant(""))));
+        let progress = run_ctx.track_stream();
 
         let mut reasonings = Vec::new();
         let mut texts = String::new();
         let mut tool_calls = vec![];
 
         while let Some(content) = stream.next().await {
-            if run_ctx.interrupt.load(Ordering::Relaxed) {
+            if run_ctx.is_cancelled() {
                 Err(WorkflowError::Interrupted)?;
             }
             match content {
                 Ok(StreamedAssistantContent::Text(text)) => {
+                    if let Some(progress) = &progress {
+                        progress.record(&text.text);
+                    }
                     texts.push_str(&text.text);
                     let msg = Message::assistant(&texts);
                     if let Some(a) = &agent_msg {
@@ -0,0 +1,203 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::GraphSubmenu;
+use crate::{
+    ui::AppEvent,
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkNode,
+        WorkflowError,
+    },
+};
+
+/// Gets the text on the system clipboard.
+///
+/// Paste events only reach the UI when the user presses a shortcut,
+/// so the clipboard is read directly from the worker thread instead.
+pub fn clipboard_text() -> Result<String, WorkflowError> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(|err| match err {
+            arboard::Error::ContentNotAvailable => {
+                WorkflowError::Required(vec!["Clipboard has no text".into()])
+            }
+            err => WorkflowError::Provider(err.into()),
+        })
+}
+
+/// Outputs the text on the clipboard
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardRead {}
+
+#[typetag::serde]
+impl FlexNode for ClipboardRead {}
+
+impl DynNode for ClipboardRead {
+    fn inputs(&self) -> usize {
+        0
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        _inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        Ok(vec![
+            Value::text(clipboard_text()?),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ClipboardRead {
+    fn title(&self) -> &str {
+        "Read Clipboard"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Outputs the text on the clipboard when the workflow runs.\n\
+            Goes to the failure output if the clipboard holds no text."
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+/// Copies text to the clipboard, passing it on unchanged
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClipboardWrite {}
+
+#[typetag::serde]
+impl FlexNode for ClipboardWrite {}
+
+impl DynNode for ClipboardWrite {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "Text to copy is required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        // The clipboard belongs to the window, so the UI thread does the copying
+        ctx.event(AppEvent::CopyText(text.to_string()));
+
+        Ok(vec![Value::Text(text)])
+    }
+}
+
+impl UiNode for ClipboardWrite {
+    fn title(&self) -> &str {
+        "Write Clipboard"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Copies its input to the clipboard and passes it on.\n\
+            Only works in the graphical interface."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+fn clipboard_node_menu(
+    ui: &mut egui::Ui,
+    snarl: &mut egui_snarl::Snarl<WorkNode>,
+    pos: egui::Pos2,
+) {
+    ui.menu_button("Clipboard", |ui| {
+        if ui.button("Read Clipboard").clicked() {
+            snarl.insert_node(pos, ClipboardRead::default().into());
+            ui.close();
+        }
+
+        if ui.button("Write Clipboard").clicked() {
+            snarl.insert_node(pos, ClipboardWrite::default().into());
+            ui.close();
+        }
+    });
+}
+
+inventory::submit! {
+    GraphSubmenu("clipboard", clipboard_node_menu)
+}
@@ -0,0 +1,309 @@
+use std::{borrow::Cow, sync::Arc};
+
+use base64::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use super::GraphSubmenu;
+use crate::{
+    ui::{resizable_frame, shortcuts::squelch},
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkNode,
+        WorkflowError,
+    },
+};
+
+/// Text encodings for binary-safe payloads
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Base64,
+    Base64Url,
+    Hex,
+}
+
+impl Encoding {
+    pub const ALL: [Encoding; 3] = [Encoding::Base64, Encoding::Base64Url, Encoding::Hex];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Encoding::Base64 => "base64",
+            Encoding::Base64Url => "base64 (url-safe)",
+            Encoding::Hex => "hex",
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> String {
+        match self {
+            Encoding::Base64 => BASE64_STANDARD.encode(data),
+            Encoding::Base64Url => BASE64_URL_SAFE_NO_PAD.encode(data),
+            Encoding::Hex => hex::encode(data),
+        }
+    }
+
+    pub fn decode(&self, text: &str) -> Result<Vec<u8>, WorkflowError> {
+        let text = text.trim();
+        match self {
+            Encoding::Base64 => BASE64_STANDARD
+                .decode(text)
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid base64: {e}"))),
+            Encoding::Base64Url => BASE64_URL_SAFE_NO_PAD
+                .decode(text.trim_end_matches('='))
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid base64: {e}"))),
+            Encoding::Hex => hex::decode(text)
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid hex: {e}"))),
+        }
+    }
+}
+
+/// Whether [`EncodeText`] encodes or decodes its input
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    #[default]
+    Encode,
+    Decode,
+}
+
+impl Direction {
+    pub fn is_encode(&self) -> bool {
+        *self == Self::Encode
+    }
+}
+
+/// Converts text to or from base64 or hex
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodeText {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub text: String,
+
+    #[serde(default)]
+    pub encoding: Encoding,
+
+    #[serde(default, skip_serializing_if = "Direction::is_encode")]
+    pub direction: Direction,
+
+    pub size: Option<crate::utils::EVec2>,
+}
+
+impl EncodeText {
+    /// Encodes the UTF-8 bytes of the text, or decodes text back into UTF-8
+    pub fn convert(&self, text: &str) -> Result<String, WorkflowError> {
+        match self.direction {
+            Direction::Encode => Ok(self.encoding.encode(text.as_bytes())),
+            Direction::Decode => {
+                let bytes = self.encoding.decode(text)?;
+                String::from_utf8(bytes).map_err(|e| {
+                    WorkflowError::Conversion(format!("Decoded data is not UTF-8 text: {e}"))
+                })
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for EncodeText {}
+
+impl DynNode for EncodeText {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.clone(),
+            None => Arc::new(self.text.clone()),
+            _ => unreachable!(),
+        };
+
+        Ok(vec![
+            Value::text(self.convert(&text)?),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for EncodeText {
+    fn title(&self) -> &str {
+        match self.direction {
+            Direction::Encode => "Encode Text",
+            Direction::Decode => "Decode Text",
+        }
+    }
+
+    fn tooltip(&self) -> &str {
+        "Encodes text as base64 or hex, or decodes it back.\n\
+            Invalid input or non-UTF-8 results go to the failure output when decoding."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            let widget = egui::TextEdit::multiline(&mut self.text)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY)
+                                .hint_text("text");
+
+                            squelch(ui.add_sized(ui.available_size(), widget));
+                        });
+                    });
+                } else {
+                    ui.label("text");
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.direction, Direction::Encode, "encode");
+                ui.selectable_value(&mut self.direction, Direction::Decode, "decode");
+            });
+
+            egui::ComboBox::from_id_salt("EncodeTextEncoding")
+                .selected_text(self.encoding.label())
+                .show_ui(ui, |ui| {
+                    for encoding in Encoding::ALL {
+                        ui.selectable_value(&mut self.encoding, encoding, encoding.label());
+                    }
+                });
+        });
+    }
+}
+
+fn encoding_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos: egui::Pos2) {
+    ui.menu_button("Encoding", |ui| {
+        if ui.button("Encode Text").clicked() {
+            snarl.insert_node(pos, EncodeText::default().into());
+            ui.close();
+        }
+
+        if ui.button("Decode Text").clicked() {
+            let node = EncodeText {
+                direction: Direction::Decode,
+                ..Default::default()
+            };
+            snarl.insert_node(pos, node.into());
+            ui.close();
+        }
+    });
+}
+
+inventory::submit! {
+    GraphSubmenu("encoding", encoding_node_menu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(encoding: Encoding, direction: Direction) -> EncodeText {
+        EncodeText {
+            encoding,
+            direction,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        for encoding in Encoding::ALL {
+            let encoded = node(encoding, Direction::Encode)
+                .convert("héllo? wörld>")
+                .unwrap();
+            let decoded = node(encoding, Direction::Decode).convert(&encoded).unwrap();
+            assert_eq!(decoded, "héllo? wörld>");
+        }
+
+        assert_eq!(
+            node(Encoding::Base64, Direction::Encode)
+                .convert("hi")
+                .unwrap(),
+            "aGk="
+        );
+        assert_eq!(
+            node(Encoding::Hex, Direction::Encode)
+                .convert("hi")
+                .unwrap(),
+            "6869"
+        );
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(
+            node(Encoding::Base64, Direction::Decode)
+                .convert("not base64!")
+                .is_err()
+        );
+        assert!(
+            node(Encoding::Hex, Direction::Decode)
+                .convert("xyz")
+                .is_err()
+        );
+
+        // Valid hex, but not UTF-8
+        assert!(
+            node(Encoding::Hex, Direction::Decode)
+                .convert("ff")
+                .is_err()
+        );
+    }
+}
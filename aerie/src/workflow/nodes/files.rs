@@ -0,0 +1,487 @@
+use std::{
+    borrow::Cow,
+    io::Write as _,
+    path::{Component, Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::GraphSubmenu;
+use crate::{
+    config::ConfigExt as _,
+    ui::shortcuts::squelch,
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkNode,
+        WorkflowError, write_value,
+    },
+};
+
+/// Resolves a path given to a file node inside the working directory.
+///
+/// Relative paths are joined onto the root, while absolute paths must already be inside it.
+/// Any `..` component is rejected outright, as are symlinks leading out of the root.
+pub fn jail_path(root: &Path, path: &str) -> Result<PathBuf, WorkflowError> {
+    let escape =
+        || WorkflowError::Conversion(format!("Path is outside the working directory: {path}"));
+
+    let path = path.trim();
+    if path.is_empty() {
+        Err(WorkflowError::Required(vec!["Path is required".into()]))?;
+    }
+
+    let real_root = root.canonicalize().map_err(|err| {
+        WorkflowError::Conversion(format!("Invalid working directory {root:?}: {err}"))
+    })?;
+
+    let requested = Path::new(path);
+    let relative = if requested.is_absolute() {
+        requested
+            .strip_prefix(root)
+            .or_else(|_| requested.strip_prefix(&real_root))
+            .map_err(|_| escape())?
+    } else {
+        requested
+    };
+
+    let mut resolved = real_root.clone();
+    for part in relative.components() {
+        match part {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => continue,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => Err(escape())?,
+        }
+
+        // Symlinks inside the root could still point anywhere, even when dangling
+        let is_link = std::fs::symlink_metadata(&resolved).is_ok_and(|it| it.is_symlink());
+        if is_link {
+            let real = resolve_link(&resolved, MAX_LINKS).map_err(|err| {
+                WorkflowError::Conversion(format!("Cannot resolve {path}: {err}"))
+            })?;
+
+            if !real.starts_with(&real_root) {
+                Err(escape())?;
+            }
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Longest chain of symlinks followed before giving up, matching the usual OS limit
+const MAX_LINKS: usize = 40;
+
+/// Where a symlink leads once every link along the way is followed.
+///
+/// Unlike [Path::canonicalize], this works for links whose target does not exist yet.
+fn resolve_link(link: &Path, hops: usize) -> std::io::Result<PathBuf> {
+    if let Ok(real) = link.canonicalize() {
+        return Ok(real);
+    }
+
+    if hops == 0 {
+        return Err(std::io::Error::other("Too many levels of symbolic links"));
+    }
+
+    let parent = link.parent().unwrap_or(Path::new("/"));
+    let target = parent.join(std::fs::read_link(link)?);
+
+    let mut real = PathBuf::new();
+    for part in target.components() {
+        match part {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                real.pop();
+            }
+            other => {
+                real.push(other);
+                if std::fs::symlink_metadata(&real).is_ok_and(|it| it.is_symlink()) {
+                    real = resolve_link(&real, hops - 1)?;
+                }
+            }
+        }
+    }
+
+    Ok(real)
+}
+
+/// Reads a text file inside the working directory
+pub fn read_file(root: &Path, path: &str) -> Result<String, WorkflowError> {
+    let resolved = jail_path(root, path)?;
+
+    std::fs::read_to_string(&resolved)
+        .map_err(|err| WorkflowError::Conversion(format!("Cannot read {path}: {err}")))
+}
+
+/// Writes a value to a file inside the working directory, creating missing parent directories
+pub fn write_file(root: &Path, path: &str, value: &Value) -> Result<PathBuf, WorkflowError> {
+    let resolved = jail_path(root, path)?;
+    let failed = |err: &dyn std::fmt::Display| {
+        WorkflowError::Conversion(format!("Cannot write {path}: {err}"))
+    };
+
+    if let Some(parent) = resolved.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| failed(&e))?;
+    }
+
+    let fh = std::fs::File::create(&resolved).map_err(|e| failed(&e))?;
+    let mut writer = std::io::BufWriter::new(fh);
+    write_value(&mut writer, value).map_err(|e| failed(&e))?;
+    writer.flush().map_err(|e| failed(&e))?;
+
+    Ok(resolved)
+}
+
+fn show_path_input(ui: &mut egui::Ui, path: &mut String, remote: Option<Value>) {
+    if remote.is_none() {
+        squelch(ui.add(egui::TextEdit::singleline(path).hint_text("path")));
+    } else {
+        ui.label("path");
+    }
+}
+
+/// Outputs the contents of a text file
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReadFile {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+}
+
+#[typetag::serde]
+impl FlexNode for ReadFile {}
+
+impl DynNode for ReadFile {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let path = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => self.path.as_str(),
+            _ => unreachable!(),
+        };
+
+        let root = ctx.agent_factory.settings.view(|s| s.work_dir());
+
+        Ok(vec![
+            Value::text(read_file(&root, path)?),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ReadFile {
+    fn title(&self) -> &str {
+        "Read File"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Reads a text file relative to the working directory in settings.\n\
+            Paths outside the working directory go to the failure output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => show_path_input(ui, &mut self.path, remote),
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+/// Saves a value to a file, formatted like the outputs panel does
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WriteFile {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub path: String,
+}
+
+#[typetag::serde]
+impl FlexNode for WriteFile {}
+
+impl DynNode for WriteFile {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        match in_pin {
+            0 => Cow::Borrowed(&[ValueKind::Text]),
+            1 => Cow::Borrowed(ValueKind::all()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let path = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => self.path.as_str(),
+            _ => unreachable!(),
+        };
+
+        let Some(content) = &inputs[1] else {
+            Err(WorkflowError::Required(vec!["Content is required".into()]))?
+        };
+
+        let root = ctx.agent_factory.settings.view(|s| s.work_dir());
+        let resolved = write_file(&root, path, content)?;
+
+        Ok(vec![
+            Value::text(resolved.display().to_string()),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for WriteFile {
+    fn title(&self) -> &str {
+        "Write File"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Writes a value to a file relative to the working directory in settings.\n\
+            Replaces an existing file and creates missing directories.\n\
+            Outputs the full path of the file written."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => show_path_input(ui, &mut self.path, remote),
+            1 => {
+                ui.label("content");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("path");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+fn files_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos: egui::Pos2) {
+    ui.menu_button("Files", |ui| {
+        if ui.button("Read File").clicked() {
+            snarl.insert_node(pos, ReadFile::default().into());
+            ui.close();
+        }
+
+        if ui.button("Write File").clicked() {
+            snarl.insert_node(pos, WriteFile::default().into());
+            ui.close();
+        }
+    });
+}
+
+inventory::submit! {
+    GraphSubmenu("files", files_node_menu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn work_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aerie-files-{}-{name}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_jail_relative() {
+        let root = work_dir("relative");
+
+        assert_eq!(
+            jail_path(&root, "notes.txt").unwrap(),
+            root.join("notes.txt")
+        );
+        assert_eq!(
+            jail_path(&root, "./drafts/notes.txt").unwrap(),
+            root.join("drafts/notes.txt")
+        );
+        assert_eq!(
+            jail_path(&root, " notes.txt\n").unwrap(),
+            root.join("notes.txt")
+        );
+
+        assert!(matches!(
+            jail_path(&root, "  "),
+            Err(WorkflowError::Required(_))
+        ));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_jail_traversal() {
+        let root = work_dir("traversal");
+
+        assert!(jail_path(&root, "..").is_err());
+        assert!(jail_path(&root, "../secrets.txt").is_err());
+        assert!(jail_path(&root, "drafts/../../secrets.txt").is_err());
+
+        // Even when it would land back inside the root
+        assert!(jail_path(&root, "drafts/../notes.txt").is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_jail_absolute() {
+        let root = work_dir("absolute");
+
+        let inside = root.join("notes.txt");
+        assert_eq!(jail_path(&root, inside.to_str().unwrap()).unwrap(), inside);
+
+        assert!(jail_path(&root, "/etc/passwd").is_err());
+
+        let sibling = format!("{}-sibling/notes.txt", root.display());
+        assert!(jail_path(&root, &sibling).is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_jail_missing_root() {
+        let root = std::env::temp_dir().join(format!("aerie-files-{}-missing", std::process::id()));
+
+        assert!(jail_path(&root, "notes.txt").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_jail_symlink() {
+        let root = work_dir("symlink");
+        let outside = work_dir("symlink-outside");
+
+        std::os::unix::fs::symlink(&outside, root.join("escape")).unwrap();
+        std::os::unix::fs::symlink(root.join("drafts"), root.join("shortcut")).unwrap();
+        std::fs::create_dir_all(root.join("drafts")).unwrap();
+
+        assert!(jail_path(&root, "escape/notes.txt").is_err());
+        assert!(jail_path(&root, "escape").is_err());
+        assert!(jail_path(&root, "shortcut/notes.txt").is_ok());
+
+        // Dangling links are judged by where they would lead
+        std::os::unix::fs::symlink(outside.join("missing.txt"), root.join("dangling")).unwrap();
+        std::os::unix::fs::symlink("../missing.txt", root.join("relative")).unwrap();
+        std::os::unix::fs::symlink(root.join("dangling"), root.join("chained")).unwrap();
+        std::os::unix::fs::symlink(root.join("drafts/later.txt"), root.join("later")).unwrap();
+
+        assert!(jail_path(&root, "dangling").is_err());
+        assert!(jail_path(&root, "relative").is_err());
+        assert!(jail_path(&root, "chained").is_err());
+        assert!(write_file(&root, "dangling", &Value::text("nope")).is_err());
+        assert!(!outside.join("missing.txt").exists());
+        assert!(jail_path(&root, "later").is_ok());
+
+        std::fs::remove_dir_all(root).unwrap();
+        std::fs::remove_dir_all(outside).unwrap();
+    }
+
+    #[test]
+    fn test_write_read() {
+        let root = work_dir("write-read");
+
+        let written = write_file(&root, "drafts/notes.txt", &Value::text("hello")).unwrap();
+        assert_eq!(written, root.join("drafts/notes.txt"));
+        assert_eq!(read_file(&root, "drafts/notes.txt").unwrap(), "hello\n");
+
+        let json = Value::Json(std::sync::Arc::new(serde_json::json!({"a": 1})));
+        write_file(&root, "data.json", &json).unwrap();
+        assert_eq!(read_file(&root, "data.json").unwrap(), r#"{"a":1}"#);
+
+        assert!(write_file(&root, "../notes.txt", &Value::text("nope")).is_err());
+        assert!(read_file(&root, "missing.txt").is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
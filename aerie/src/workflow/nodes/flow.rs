@@ -0,0 +1,1103 @@
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use decorum::E64;
+use egui::TextEdit;
+use egui_snarl::OutPinId;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use super::{
+    GraphSubmenu, JsonPredicate,
+    agent::call_tool,
+    json::{json_editor, value_to_json},
+};
+use crate::{
+    ui::{AppEvent, resizable_frame, shortcuts::squelch},
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkNode,
+        WorkflowError,
+    },
+};
+
+/// Longest stretch slept without checking for interrupts
+const INTERRUPT_CHECK: Duration = Duration::from_millis(100);
+
+/// Sleeps for the duration, waking early with an error if the node is cancelled
+async fn interruptible_sleep(ctx: &RunContext, duration: Duration) -> Result<(), WorkflowError> {
+    let deadline = Instant::now() + duration;
+
+    loop {
+        if ctx.is_cancelled() {
+            return Err(WorkflowError::Interrupted);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+
+        tokio::time::sleep(remaining.min(INTERRUPT_CHECK)).await;
+    }
+}
+
+/// Repeatedly calls a tool until its output satisfies a predicate
+#[skip_serializing_none]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PollUntil {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub tool_name: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub arguments: String,
+
+    /// Delay between attempts in milliseconds
+    pub interval: u64,
+
+    pub max_attempts: u32,
+
+    #[serde(flatten)]
+    pub until: JsonPredicate,
+
+    pub size: Option<crate::utils::EVec2>,
+}
+
+impl Default for PollUntil {
+    fn default() -> Self {
+        Self {
+            tool_name: Default::default(),
+            arguments: Default::default(),
+            interval: 5000,
+            max_attempts: 10,
+            until: Default::default(),
+            size: None,
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for PollUntil {}
+
+impl DynNode for PollUntil {
+    fn inputs(&self) -> usize {
+        3
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Tools],
+            1 => &[ValueKind::Text],
+            2 => &[ValueKind::Json],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Json,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        let rt = ctx.runtime.clone();
+        rt.block_on(ctx.cancellable(self.forward(ctx, inputs)))
+    }
+}
+
+impl PollUntil {
+    pub async fn forward(
+        &mut self,
+        run_ctx: &RunContext,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let toolset = match &inputs[0] {
+            Some(Value::Tools(spec)) => spec.clone(),
+            None => Err(WorkflowError::Required(vec!["Toolset is required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let tool_name = match &inputs[1] {
+            Some(Value::Text(text)) => text.as_str(),
+            None if !self.tool_name.is_empty() => self.tool_name.as_str(),
+            None => Err(WorkflowError::Required(vec!["Tool name required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let args = match &inputs[2] {
+            Some(Value::Json(value)) => value.clone(),
+            None if !self.arguments.is_empty() => Arc::new(
+                serde_json::from_str(&self.arguments)
+                    .map_err(|e| WorkflowError::Conversion(format!("Invalid JSON: {e:?}")))?,
+            ),
+            None => Arc::new(serde_json::Value::Object(Default::default())),
+            _ => unreachable!(),
+        };
+
+        let predicate = self.until.prepare(&run_ctx.transmuter)?;
+        let interval = Duration::from_millis(self.interval);
+
+        for attempt in 1..=self.max_attempts.max(1) {
+            if attempt > 1 {
+                interruptible_sleep(run_ctx, interval).await?;
+            } else if run_ctx.is_cancelled() {
+                Err(WorkflowError::Interrupted)?;
+            }
+
+            let output = call_tool(run_ctx, &toolset, tool_name, &args).await?;
+
+            // Plain text results can still be matched with "key equals" or expressions
+            let value = serde_json::from_str(&output)
+                .unwrap_or_else(|_| serde_json::Value::String(output.clone()));
+
+            match predicate.test(&run_ctx.transmuter, &value) {
+                Ok(true) => {
+                    tracing::debug!("Poll of {tool_name} satisfied after {attempt} attempts");
+                    return Ok(vec![
+                        Value::text(output),
+                        Value::Json(Arc::new(value)),
+                        Value::Placeholder(ValueKind::Failure),
+                    ]);
+                }
+                Ok(false) => tracing::debug!("Poll #{attempt} of {tool_name} not yet satisfied"),
+                Err(err) => tracing::debug!("Poll #{attempt} of {tool_name} unmatched: {err}"),
+            }
+        }
+
+        Err(WorkflowError::Timeout)
+    }
+}
+
+impl UiNode for PollUntil {
+    fn title(&self) -> &str {
+        "Poll Until"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Calls a tool at an interval until its output satisfies the predicate.\n\
+            Gives up with a timeout failure after the maximum number of attempts."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("tools");
+            }
+            1 => {
+                if remote.is_none() {
+                    squelch(
+                        ui.add(TextEdit::singleline(&mut self.tool_name).hint_text("tool name")),
+                    );
+                } else {
+                    ui.label("tool name");
+                }
+            }
+            2 => {
+                if remote.is_none() {
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            json_editor(ui, &mut self.arguments, Some("arguments"));
+                        });
+                    });
+                } else {
+                    ui.label("arguments");
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("output");
+            }
+            1 => {
+                ui.label("json");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label("every");
+                ui.add(
+                    egui::DragValue::new(&mut self.interval)
+                        .range(100..=3_600_000)
+                        .speed(100)
+                        .suffix(" ms"),
+                );
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("attempts");
+                ui.add(egui::DragValue::new(&mut self.max_attempts).range(1..=10_000));
+            });
+
+            ui.label("until");
+            self.until.show(ui);
+        });
+    }
+}
+
+/// What a [Join] emits once all of its inputs have arrived
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub enum JoinMode {
+    /// The value from the input whose upstream finished last
+    #[default]
+    Last,
+
+    /// All values gathered into a JSON array, in pin order
+    Array,
+
+    /// The value of the input at [Join::index]
+    Index,
+}
+
+impl JoinMode {
+    pub const ALL: [JoinMode; 3] = [JoinMode::Last, JoinMode::Array, JoinMode::Index];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            JoinMode::Last => "last arrived",
+            JoinMode::Array => "json array",
+            JoinMode::Index => "input #",
+        }
+    }
+}
+
+/// Waits for every connected input before passing on a value.
+///
+/// Unlike `Select`, the runner only readies this node once all of its inputs
+/// have non-placeholder values.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Join {
+    count: usize,
+
+    kind: ValueKind,
+
+    pub mode: JoinMode,
+
+    pub index: usize,
+
+    /// Input pin whose value arrived last, set by the runner before execution
+    #[serde(skip)]
+    pub arrived: Option<usize>,
+}
+
+impl std::hash::Hash for Join {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.count.hash(state);
+        self.kind.hash(state);
+        self.mode.hash(state);
+        self.index.hash(state);
+    }
+}
+
+impl PartialEq for Join {
+    fn eq(&self, other: &Self) -> bool {
+        self.count == other.count
+            && self.kind == other.kind
+            && self.mode == other.mode
+            && self.index == other.index
+    }
+}
+
+impl Eq for Join {}
+
+#[typetag::serde]
+impl FlexNode for Join {}
+
+impl DynNode for Join {
+    fn inputs(&self) -> usize {
+        self.count + 1 // Extra slot to add another branch
+    }
+
+    // Allows anything for the first value, but all other inputs
+    // must be of the same kind.
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(if self.count == 0 {
+            ValueKind::all()
+        } else {
+            std::slice::from_ref(&self.kind)
+        })
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        match self.mode {
+            JoinMode::Array => ValueKind::Json,
+            _ => self.kind,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let mut values = inputs
+            .into_iter()
+            .take(self.count)
+            .enumerate()
+            .map(|(pin, value)| {
+                value.ok_or_else(|| {
+                    WorkflowError::Required(vec![format!("Join input #{pin} has not arrived")])
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if values.is_empty() {
+            Err(WorkflowError::Required(vec![
+                "Join needs at least one input".into(),
+            ]))?;
+        }
+
+        let output = match self.mode {
+            JoinMode::Last => {
+                let pin = self.arrived.take().unwrap_or(values.len() - 1);
+                values.swap_remove(pin.min(values.len() - 1))
+            }
+            JoinMode::Array => Value::Json(Arc::new(serde_json::Value::Array(
+                values
+                    .into_iter()
+                    .map(value_to_json)
+                    .collect::<Result<_, _>>()?,
+            ))),
+            JoinMode::Index if self.index < values.len() => values.swap_remove(self.index),
+            JoinMode::Index => Err(WorkflowError::Required(vec![format!(
+                "Join has no input #{} among {}",
+                self.index,
+                values.len()
+            )]))?,
+        };
+
+        Ok(vec![output])
+    }
+}
+
+impl UiNode for Join {
+    fn title(&self) -> &str {
+        "Join"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Waits until every connected input has a value, then passes one on.\n\
+            Used to proceed only after parallel branches have all completed."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        let kind = match &remote {
+            Some(Value::Placeholder(kind)) => Some(*kind),
+            Some(value) => Some(value.kind()),
+            _ => None,
+        };
+
+        if self.count == 0 {
+            if self.kind == ValueKind::Placeholder
+                && let Some(kind) = kind
+            {
+                self.kind = kind;
+
+                ctx.reset_out_pin(OutPinId {
+                    node: ctx.current_node,
+                    output: 0,
+                });
+            } else if kind.is_none() {
+                self.kind = ValueKind::Placeholder;
+            }
+        }
+
+        if pin_id == self.count && remote.is_some() {
+            self.count += 1;
+        } else if pin_id + 1 == self.count && remote.is_none() {
+            self.count -= 1;
+        }
+
+        if pin_id < self.count {
+            ui.label(format!("#{pin_id}"));
+        }
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        if self.count > 0 {
+            ui.label(format!("{}", self.out_kind(pin_id)).to_lowercase());
+        }
+
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        let mode = self.mode;
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("JoinMode")
+                .selected_text(self.mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in JoinMode::ALL {
+                        ui.selectable_value(&mut self.mode, mode, mode.label());
+                    }
+                });
+
+            if self.mode == JoinMode::Index {
+                ui.add(egui::DragValue::new(&mut self.index).range(0..=self.count.max(1) - 1));
+            }
+        });
+
+        // Switching to or from an array changes the kind of the output
+        if self.mode != mode {
+            ctx.reset_out_pin(OutPinId {
+                node: ctx.current_node,
+                output: 0,
+            });
+        }
+    }
+}
+
+fn default_delay() -> E64 {
+    E64::assert(1.0)
+}
+
+/// Passes its input on unchanged after waiting, e.g. to pace calls to a rate-limited service
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Delay {
+    kind: ValueKind,
+
+    /// Time to wait in seconds
+    pub seconds: E64,
+
+    /// Title showing the delay, refreshed when the body is drawn
+    #[serde(skip)]
+    label: String,
+}
+
+impl Default for Delay {
+    fn default() -> Self {
+        Self {
+            kind: ValueKind::Placeholder,
+            seconds: default_delay(),
+            label: Default::default(),
+        }
+    }
+}
+
+impl std::hash::Hash for Delay {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.kind.hash(state);
+        self.seconds.hash(state);
+    }
+}
+
+impl PartialEq for Delay {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind && self.seconds == other.seconds
+    }
+}
+
+impl Eq for Delay {}
+
+#[typetag::serde]
+impl FlexNode for Delay {}
+
+impl DynNode for Delay {
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(ValueKind::all())
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        self.kind
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let Some(value) = inputs.into_iter().next().flatten() else {
+            return Err(WorkflowError::Required(vec!["Delay needs an input".into()]));
+        };
+
+        let duration = Duration::from_secs_f64(self.seconds.into_inner().max(0.0));
+        ctx.runtime.block_on(interruptible_sleep(ctx, duration))?;
+
+        Ok(vec![value])
+    }
+}
+
+impl UiNode for Delay {
+    fn title(&self) -> &str {
+        if self.label.is_empty() {
+            "Delay"
+        } else {
+            self.label.as_str()
+        }
+    }
+
+    fn tooltip(&self) -> &str {
+        "Waits for the given number of seconds, then passes its input on unchanged.\n\
+            Used to pace calls to rate-limited or flaky services."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        let kind = match &remote {
+            Some(Value::Placeholder(kind)) => *kind,
+            Some(value) => value.kind(),
+            None => ValueKind::Placeholder,
+        };
+
+        if self.kind != kind {
+            self.kind = kind;
+
+            ctx.reset_out_pin(OutPinId {
+                node: ctx.current_node,
+                output: 0,
+            });
+        }
+
+        ui.label("input");
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label("output");
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        let mut seconds = self.seconds.into_inner();
+        ui.add(
+            egui::Slider::new(&mut seconds, 0.0..=300.0)
+                .logarithmic(true)
+                .suffix(" s"),
+        );
+        self.seconds = E64::assert(seconds);
+
+        self.label = format!("Delay {seconds:.1}s");
+    }
+}
+
+/// How an [Accumulator] folds in each run
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub enum AccumulateMode {
+    /// Adds the step to an integer
+    #[default]
+    Count,
+
+    /// Appends the input to a JSON array
+    Append,
+}
+
+impl AccumulateMode {
+    pub const ALL: [AccumulateMode; 2] = [AccumulateMode::Count, AccumulateMode::Append];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccumulateMode::Count => "count",
+            AccumulateMode::Append => "append",
+        }
+    }
+}
+
+fn default_step() -> i64 {
+    1
+}
+
+fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
+/// Keeps a running value across workflow runs, stored in the node itself
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Accumulator {
+    pub mode: AccumulateMode,
+
+    /// Amount added to the count on each run
+    pub step: i64,
+
+    #[serde(skip_serializing_if = "is_zero")]
+    pub count: i64,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<serde_json::Value>,
+}
+
+impl Default for Accumulator {
+    fn default() -> Self {
+        Self {
+            mode: Default::default(),
+            step: default_step(),
+            count: 0,
+            items: vec![],
+        }
+    }
+}
+
+impl std::hash::Hash for Accumulator {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.mode.hash(state);
+        self.step.hash(state);
+        self.count.hash(state);
+
+        // JSON values are not hashable, but their text is
+        for item in &self.items {
+            item.to_string().hash(state);
+        }
+    }
+}
+
+impl Accumulator {
+    pub fn reset(&mut self) {
+        self.count = 0;
+        self.items.clear();
+    }
+
+    /// Current running value
+    pub fn current(&self) -> Value {
+        match self.mode {
+            AccumulateMode::Count => Value::Integer(self.count),
+            AccumulateMode::Append => {
+                Value::Json(Arc::new(serde_json::Value::Array(self.items.clone())))
+            }
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for Accumulator {}
+
+impl DynNode for Accumulator {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(ValueKind::all())
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        match self.mode {
+            AccumulateMode::Count => ValueKind::Integer,
+            AccumulateMode::Append => ValueKind::Json,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let mut inputs = inputs.into_iter();
+        let (value, reset) = (inputs.next().flatten(), inputs.next().flatten());
+
+        if reset.is_some() {
+            self.reset();
+        }
+
+        match (self.mode, value) {
+            (AccumulateMode::Count, _) => self.count = self.count.saturating_add(self.step),
+            (AccumulateMode::Append, Some(value)) => self.items.push(value_to_json(value)?),
+            (AccumulateMode::Append, None) if reset.is_some() => {}
+            (AccumulateMode::Append, None) => Err(WorkflowError::Required(vec![
+                "Value to append is required".into(),
+            ]))?,
+        }
+
+        // Runs work on a copy of the graph, so hand the new state back to the editor
+        ctx.event(AppEvent::UpdateNode(
+            ctx.exec_id,
+            node_id,
+            self.clone().into(),
+        ));
+
+        Ok(vec![self.current()])
+    }
+}
+
+impl UiNode for Accumulator {
+    fn title(&self) -> &str {
+        "Accumulator"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Keeps a running count, or a list of inputs, between workflow runs.\n\
+            Any value on the reset input starts over before accumulating.\n\
+            The running value is saved with the workflow."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("value");
+            }
+            1 => {
+                ui.label("reset");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match self.mode {
+            AccumulateMode::Count => ui.label(format!("count: {}", self.count)),
+            AccumulateMode::Append => ui.label(format!("items: {}", self.items.len())),
+        };
+
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        let mode = self.mode;
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("AccumulateMode")
+                .selected_text(self.mode.label())
+                .show_ui(ui, |ui| {
+                    for mode in AccumulateMode::ALL {
+                        ui.selectable_value(&mut self.mode, mode, mode.label());
+                    }
+                });
+
+            if self.mode == AccumulateMode::Count {
+                ui.label("by");
+                ui.add(egui::DragValue::new(&mut self.step));
+            }
+
+            if ui.button("reset").clicked() {
+                self.reset();
+            }
+        });
+
+        // Switching modes changes the kind of the output
+        if self.mode != mode {
+            ctx.reset_out_pin(OutPinId {
+                node: ctx.current_node,
+                output: 0,
+            });
+        }
+    }
+}
+
+fn flow_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos: egui::Pos2) {
+    ui.menu_button("Flow", |ui| {
+        if ui.button("Poll Until").clicked() {
+            snarl.insert_node(pos, PollUntil::default().into());
+            ui.close();
+        }
+
+        if ui.button("Join").clicked() {
+            snarl.insert_node(pos, Join::default().into());
+            ui.close();
+        }
+
+        if ui.button("Delay").clicked() {
+            snarl.insert_node(pos, Delay::default().into());
+            ui.close();
+        }
+
+        if ui.button("Accumulator").clicked() {
+            snarl.insert_node(pos, Accumulator::default().into());
+            ui.close();
+        }
+    });
+}
+
+inventory::submit! {
+    GraphSubmenu("flow", flow_node_menu)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use egui_snarl::NodeId;
+    use serde_yaml_ng as serde_yml;
+
+    use super::*;
+    use crate::workflow::{
+        ShadowGraph,
+        nodes::Number,
+        runner::{ExecState, WorkflowRunner},
+        testing::{meta, run_ctx, wire},
+    };
+
+    #[test]
+    fn test_join_modes() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx = run_ctx(&rt);
+        let inputs = || vec![Some(Value::text("a")), Some(Value::text("b")), None];
+
+        let mut node = Join {
+            count: 2,
+            kind: ValueKind::Text,
+            arrived: Some(0),
+            ..Default::default()
+        };
+        let output = node.execute(&ctx, NodeId(0), inputs()).unwrap();
+        assert_eq!(output, vec![Value::text("a")]);
+
+        node.mode = JoinMode::Array;
+        let output = node.execute(&ctx, NodeId(0), inputs()).unwrap();
+        assert_eq!(
+            output,
+            vec![Value::Json(Arc::new(serde_json::json!(["a", "b"])))]
+        );
+
+        node.mode = JoinMode::Index;
+        node.index = 1;
+        let output = node.execute(&ctx, NodeId(0), inputs()).unwrap();
+        assert_eq!(output, vec![Value::text("b")]);
+
+        node.index = 2;
+        assert!(node.execute(&ctx, NodeId(0), inputs()).is_err());
+
+        // Missing inputs are never joined
+        node.index = 0;
+        let partial = vec![Some(Value::text("a")), None, None];
+        assert!(matches!(
+            node.execute(&ctx, NodeId(0), partial),
+            Err(WorkflowError::Required(_))
+        ));
+
+        let yaml = serde_yml::to_string(&node).unwrap();
+        assert!(yaml.contains("mode: Index"));
+        assert_eq!(serde_yml::from_str::<Join>(&yaml).unwrap(), node);
+    }
+
+    #[test]
+    fn test_delay() {
+        // Timers only advance on a current thread runtime while it is blocked on directly
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+        let ctx = run_ctx(&rt);
+
+        let mut node = Delay {
+            kind: ValueKind::Text,
+            seconds: E64::assert(0.05),
+            ..Default::default()
+        };
+
+        let started = Instant::now();
+        let output = node
+            .execute(&ctx, NodeId(0), vec![Some(Value::text("a"))])
+            .unwrap();
+        assert_eq!(output, vec![Value::text("a")]);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // Stopping the workflow cuts the wait short
+        node.seconds = E64::assert(60.0);
+        ctx.interrupt.store(true, Ordering::Relaxed);
+        assert_eq!(
+            node.execute(&ctx, NodeId(0), vec![Some(Value::text("a"))]),
+            Err(WorkflowError::Interrupted)
+        );
+    }
+
+    #[test]
+    fn test_accumulator() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx = run_ctx(&rt);
+
+        let mut node = Accumulator::default();
+        for expected in 1..=3 {
+            let output = node.execute(&ctx, NodeId(0), vec![None, None]).unwrap();
+            assert_eq!(output, vec![Value::Integer(expected)]);
+        }
+
+        let yaml = serde_yml::to_string(&node).unwrap();
+        assert!(yaml.contains("count: 3"));
+        assert_eq!(serde_yml::from_str::<Accumulator>(&yaml).unwrap(), node);
+
+        let output = node
+            .execute(&ctx, NodeId(0), vec![None, Some(Value::Integer(0))])
+            .unwrap();
+        assert_eq!(output, vec![Value::Integer(1)]);
+
+        node.mode = AccumulateMode::Append;
+        node.execute(&ctx, NodeId(0), vec![Some(Value::text("a")), None])
+            .unwrap();
+        let output = node
+            .execute(&ctx, NodeId(0), vec![Some(Value::Integer(2)), None])
+            .unwrap();
+        assert_eq!(
+            output,
+            vec![Value::Json(Arc::new(serde_json::json!(["a", 2])))]
+        );
+
+        assert!(matches!(
+            node.execute(&ctx, NodeId(0), vec![None, None]),
+            Err(WorkflowError::Required(_))
+        ));
+    }
+
+    #[test]
+    fn test_join_readiness() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let join = Join {
+            count: 3,
+            kind: ValueKind::Number,
+            mode: JoinMode::Array,
+            ..Default::default()
+        };
+
+        // Node 3 is disabled, so the second join never hears from it
+        let graph = ShadowGraph {
+            nodes: im::ordmap! {
+                NodeId(0) => meta(Number::default()),
+                NodeId(1) => meta(Number::default()),
+                NodeId(2) => meta(join.clone()),
+                NodeId(3) => meta(Number::default()),
+                NodeId(4) => meta(join)
+            },
+            wires: im::ordset![
+                wire(0, 2, 0),
+                wire(1, 2, 1),
+                wire(0, 2, 2),
+                wire(0, 4, 0),
+                wire(3, 4, 1)
+            ],
+            disabled: im::ordset![NodeId(3)],
+            ..ShadowGraph::empty()
+        };
+
+        let mut snarl = egui_snarl::Snarl::try_from(graph.clone()).unwrap();
+        let mut exec = WorkflowRunner::builder().run_ctx(run_ctx(&rt)).build();
+        exec.init(&graph);
+
+        // After the first number, the join still waits on the other
+        assert!(exec.step(&mut snarl).unwrap());
+        assert!(matches!(
+            exec.state_view.get(&NodeId(2)),
+            Some(ExecState::Waiting(deps)) if deps.len() == 1
+        ));
+
+        assert!(exec.step(&mut snarl).unwrap());
+        assert_eq!(exec.state_view.get(&NodeId(2)), Some(ExecState::Ready));
+
+        assert!(exec.step(&mut snarl).unwrap());
+        let zero = serde_json::json!(0.0);
+        assert_eq!(
+            exec.state_view.get(&NodeId(2)),
+            Some(ExecState::Done(vec![Value::Json(Arc::new(
+                serde_json::json!([zero, zero, zero])
+            ))]))
+        );
+
+        // Nothing else can run, since the other join is missing an input
+        assert!(exec.step(&mut snarl).is_err());
+        assert_eq!(
+            exec.state_view.get(&NodeId(4)),
+            Some(ExecState::Waiting(im::ordset![NodeId(3)]))
+        );
+    }
+}
@@ -301,6 +301,7 @@ impl DynNode for CreateMessage {
                 Some(match self.kind {
                     MessageKind::Error => ChatContent::Error {
                         err: (*text).clone(),
+                        retry: None,
                     },
                     MessageKind::User => ChatContent::Message(Message::user(&*text)),
                     MessageKind::Assistant => ChatContent::Message(Message::assistant(&*text)),
@@ -319,7 +320,7 @@ impl DynNode for CreateMessage {
 
         let msg = match value {
             ChatContent::Message(message) => message.clone(),
-            ChatContent::Error { err } => Message::user(format!("Error:\n{err:?}")),
+            ChatContent::Error { err, .. } => Message::user(format!("Error:\n{err:?}")),
             _ => unreachable!(),
         };
 
@@ -466,12 +467,180 @@ impl UiNode for ExtendHistory {
     }
 }
 
+/// How [`MergeHistory`] combines the entries of its inputs
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeStrategy {
+    /// Each history in order of its pin
+    #[default]
+    Append,
+
+    /// Alternating turns after the point where the histories diverge
+    Interleave,
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MergeHistory {
+    pub count: usize,
+    pub strategy: MergeStrategy,
+}
+
+#[typetag::serde]
+impl FlexNode for MergeHistory {}
+
+impl DynNode for MergeHistory {
+    fn inputs(&self) -> usize {
+        self.count + 2 // Slot for base history plus empty to add another
+    }
+
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(&[ValueKind::Chat])
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        ValueKind::Chat
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let history = match &inputs[0] {
+            Some(Value::Chat(history)) => history.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "Chat history required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        let others = inputs
+            .iter()
+            .skip(1)
+            .take(self.count)
+            .filter_map(|it| match it {
+                Some(Value::Chat(value)) => Some(value.as_ref()),
+                None => None,
+                _ => unreachable!(),
+            })
+            .collect_vec();
+
+        let merged = match self.strategy {
+            MergeStrategy::Append => history.merge_append(&others)?,
+            MergeStrategy::Interleave => history.merge_interleave(&others)?,
+        };
+        let value = Arc::new(merged.into_owned());
+
+        Ok(vec![Value::Chat(value)])
+    }
+}
+
+impl UiNode for MergeHistory {
+    fn title(&self) -> &str {
+        "Merge History"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Combine conversations that forked from a common history.\n\
+        Shared messages are only kept once."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        if pin_id == self.count + 1 && remote.is_some() {
+            self.count += 1;
+        } else if pin_id != 0 && pin_id == self.count && remote.is_none() {
+            self.count -= 1;
+        }
+
+        if pin_id == 0 {
+            ui.label("history");
+        } else if pin_id < self.count + 1 {
+            ui.label(format!("{pin_id}"));
+        }
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.selectable_value(&mut self.strategy, MergeStrategy::Append, "append")
+                .on_hover_text("Add the new messages of each input in order");
+            ui.selectable_value(&mut self.strategy, MergeStrategy::Interleave, "interleave")
+                .on_hover_text("Alternate turns between inputs after they diverge");
+        });
+    }
+}
+
+/// The session's conversation as it was when the workflow started
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionHistory {}
+
+#[typetag::serde]
+impl FlexNode for SessionHistory {}
+
+impl DynNode for SessionHistory {
+    fn inputs(&self) -> usize {
+        0
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        ValueKind::Chat
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        _inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        Ok(vec![Value::Chat(ctx.history.load_full())])
+    }
+}
+
+impl UiNode for SessionHistory {
+    fn title(&self) -> &str {
+        "Session History"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Outputs the chat of the current session, as it was when the workflow started.\n\
+            Lets any part of the graph, including subgraphs, start from the real conversation."
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label("conversation");
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
 fn history_node_menu(
     ui: &mut egui::Ui,
     snarl: &mut egui_snarl::Snarl<super::WorkNode>,
     pos: egui::Pos2,
 ) {
     ui.menu_button("History", |ui| {
+        if ui.button("Session History").clicked() {
+            snarl.insert_node(pos, SessionHistory::default().into());
+            ui.close();
+        }
+
         if ui.button("Create Message").clicked() {
             snarl.insert_node(pos, CreateMessage::default().into());
             ui.close();
@@ -491,6 +660,11 @@ fn history_node_menu(
             snarl.insert_node(pos, GraftHistory::default().into());
             ui.close();
         }
+
+        if ui.button("Merge History").clicked() {
+            snarl.insert_node(pos, MergeHistory::default().into());
+            ui.close();
+        }
     });
 }
 inventory::submit! {
@@ -0,0 +1,378 @@
+use std::{
+    borrow::Cow,
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use egui::TextEdit;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+use crate::{
+    ui::{resizable_frame, shortcuts::squelch},
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkflowError,
+    },
+};
+
+/// Shared so connections are pooled between requests
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl HttpMethod {
+    pub const ALL: [HttpMethod; 5] = [
+        HttpMethod::Get,
+        HttpMethod::Post,
+        HttpMethod::Put,
+        HttpMethod::Patch,
+        HttpMethod::Delete,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+            HttpMethod::Put => "PUT",
+            HttpMethod::Patch => "PATCH",
+            HttpMethod::Delete => "DELETE",
+        }
+    }
+
+    pub fn is_get(&self) -> bool {
+        *self == Self::Get
+    }
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn is_default_timeout(timeout: &u64) -> bool {
+    *timeout == default_timeout()
+}
+
+/// Calls an external service, returning the response body
+#[skip_serializing_none]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HttpRequest {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+
+    #[serde(default, skip_serializing_if = "HttpMethod::is_get")]
+    pub method: HttpMethod,
+
+    /// JSON object of header names to values
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub headers: String,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub body: String,
+
+    /// Seconds to wait for the whole response
+    #[serde(
+        default = "default_timeout",
+        skip_serializing_if = "is_default_timeout"
+    )]
+    pub timeout: u64,
+
+    pub size: Option<crate::utils::EVec2>,
+}
+
+impl Default for HttpRequest {
+    fn default() -> Self {
+        Self {
+            url: Default::default(),
+            method: Default::default(),
+            headers: Default::default(),
+            body: Default::default(),
+            timeout: default_timeout(),
+            size: None,
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for HttpRequest {}
+
+/// Converts a JSON object into request headers. Non-string values are sent as JSON text.
+pub fn header_map(
+    headers: &serde_json::Value,
+) -> Result<reqwest::header::HeaderMap, WorkflowError> {
+    use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+    let invalid =
+        |err: &dyn std::fmt::Display| WorkflowError::Conversion(format!("Invalid header: {err}"));
+
+    let entries = match headers {
+        serde_json::Value::Object(entries) => entries,
+        serde_json::Value::Null => return Ok(HeaderMap::new()),
+        _ => Err(WorkflowError::Conversion(
+            "Headers must be a JSON object".into(),
+        ))?,
+    };
+
+    let mut map = HeaderMap::new();
+    for (name, value) in entries {
+        let value = match value {
+            serde_json::Value::String(text) => text.clone(),
+            value => value.to_string(),
+        };
+
+        map.insert(
+            HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?,
+            HeaderValue::from_str(&value).map_err(|e| invalid(&e))?,
+        );
+    }
+
+    Ok(map)
+}
+
+impl HttpRequest {
+    pub async fn forward(&self, inputs: Vec<Option<Value>>) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let url = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None if !self.url.is_empty() => self.url.as_str(),
+            None => Err(WorkflowError::Required(vec!["URL is required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let method = match &inputs[1] {
+            Some(Value::Text(text)) => {
+                reqwest::Method::from_bytes(text.trim().to_uppercase().as_bytes())
+                    .map_err(|e| WorkflowError::Conversion(format!("Invalid method: {e}")))?
+            }
+            None => reqwest::Method::from_bytes(self.method.label().as_bytes()).unwrap(),
+            _ => unreachable!(),
+        };
+
+        let headers = match &inputs[2] {
+            Some(Value::Json(value)) => header_map(value)?,
+            None if !self.headers.trim().is_empty() => header_map(
+                &serde_json::from_str(&self.headers)
+                    .map_err(|e| WorkflowError::Conversion(format!("Invalid JSON: {e:?}")))?,
+            )?,
+            None => Default::default(),
+            _ => unreachable!(),
+        };
+
+        let mut request = CLIENT
+            .request(method, url)
+            .headers(headers)
+            .timeout(Duration::from_secs(self.timeout.max(1)));
+
+        request = match &inputs[3] {
+            Some(Value::Text(text)) => request.body(text.as_str().to_owned()),
+            Some(Value::Json(value)) => request.json(value.as_ref()),
+            None if !self.body.is_empty() => request.body(self.body.clone()),
+            None => request,
+            _ => unreachable!(),
+        };
+
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            Err(WorkflowError::Http(format!("{status}\n{text}")))?;
+        }
+
+        // Not every service responds with JSON
+        let json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(value) => Value::Json(Arc::new(value)),
+            Err(_) => Value::Placeholder(ValueKind::Json),
+        };
+
+        Ok(vec![
+            Value::text(text),
+            json,
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl DynNode for HttpRequest {
+    fn inputs(&self) -> usize {
+        4
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            1 => &[ValueKind::Text],
+            2 => &[ValueKind::Json],
+            3 => &[ValueKind::Text, ValueKind::Json],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Json,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        ctx.runtime
+            .clone()
+            .block_on(ctx.cancellable(self.forward(inputs)))
+    }
+}
+
+impl UiNode for HttpRequest {
+    fn title(&self) -> &str {
+        "HTTP Request"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Sends a request to a URL and outputs the response body.\n\
+            The JSON output is only set when the body parses as JSON.\n\
+            Error statuses, network errors and timeouts go to the failure output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    squelch(ui.add(TextEdit::singleline(&mut self.url).hint_text("url")));
+                } else {
+                    ui.label("url");
+                }
+            }
+            1 => {
+                if remote.is_none() {
+                    egui::ComboBox::from_id_salt("HttpRequestMethod")
+                        .selected_text(self.method.label())
+                        .show_ui(ui, |ui| {
+                            for method in HttpMethod::ALL {
+                                ui.selectable_value(&mut self.method, method, method.label());
+                            }
+                        });
+                } else {
+                    ui.label("method");
+                }
+            }
+            2 => {
+                if remote.is_none() {
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            super::json::json_editor(ui, &mut self.headers, Some("headers"));
+                        });
+                    });
+                } else {
+                    ui.label("headers");
+                }
+            }
+            3 => {
+                if remote.is_none() {
+                    let widget = TextEdit::multiline(&mut self.body)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_rows(2)
+                        .hint_text("body");
+
+                    squelch(ui.add(widget));
+                } else {
+                    ui.label("body");
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            1 => {
+                ui.label("json");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.horizontal(|ui| {
+            ui.label("timeout");
+            ui.add(
+                egui::DragValue::new(&mut self.timeout)
+                    .range(1..=3600)
+                    .suffix(" s"),
+            );
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_header_map() {
+        let headers = header_map(&json!({
+            "Authorization": "Bearer abc",
+            "X-Retries": 3,
+        }))
+        .unwrap();
+
+        assert_eq!(headers["authorization"], "Bearer abc");
+        assert_eq!(headers["x-retries"], "3");
+
+        assert!(header_map(&serde_json::Value::Null).unwrap().is_empty());
+        assert!(header_map(&json!(["Accept"])).is_err());
+        assert!(header_map(&json!({"bad header": "x"})).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_url_required() {
+        let node = HttpRequest::default();
+        let result = node.forward(vec![None, None, None, None]).await;
+
+        assert!(matches!(result, Err(WorkflowError::Required(_))));
+    }
+}
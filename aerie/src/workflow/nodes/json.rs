@@ -7,11 +7,14 @@ use serde_json::json;
 use serde_with::skip_serializing_none;
 
 use crate::{
+    ChatHistory,
+    rig::message::{AssistantContent, Message, ToolResultContent, UserContent},
+    transmute::{FilterT, Transmuter},
     ui::{
         resizable_frame,
         shortcuts::{Shortcut, squelch},
     },
-    utils::{extract_json, message_party, message_text},
+    utils::{extract_json, message_party, message_text, repair_json},
     workflow::{
         DynNode, EditContext, FlexNode, RunContext, UiNode, Value, WorkNode, WorkflowError,
         nodes::GraphSubmenu,
@@ -38,6 +41,10 @@ pub struct ParseJson {
 impl FlexNode for ParseJson {}
 
 impl DynNode for ParseJson {
+    fn cacheable(&self) -> bool {
+        true
+    }
+
     fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
         Cow::Borrowed(match in_pin {
             0 => &[ValueKind::Text, ValueKind::Message],
@@ -216,19 +223,48 @@ impl UiNode for ParseJson {
     }
 }
 
-#[skip_serializing_none]
+/// Picks a value out of a JSON document by path
 #[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct ValidateJson {
+pub struct ExtractJson {
+    /// JSON Pointer like `/foo/0/bar`, or JSONPath like `$.foo[*].bar`
     #[serde(default, skip_serializing_if = "String::is_empty")]
-    schema: String,
+    pub path: String,
+}
 
-    size: Option<crate::utils::EVec2>,
+impl ExtractJson {
+    /// Selects by JSON Pointer, or by JSONPath when the path starts with `$`.
+    ///
+    /// A JSONPath matching several values yields them as an array.
+    pub fn extract(&self, value: &serde_json::Value) -> Result<serde_json::Value, WorkflowError> {
+        use jsonpath_rust::JsonPath as _;
+
+        let path = self.path.trim();
+        let missing = || WorkflowError::Conversion(format!("Nothing found at {path}"));
+
+        if path.starts_with('$') {
+            let found = value
+                .query(path)
+                .map_err(|err| WorkflowError::Conversion(format!("Invalid JSONPath: {err}")))?;
+
+            match found.as_slice() {
+                [] => Err(missing()),
+                [single] => Ok((*single).clone()),
+                many => Ok(many.iter().map(|it| (*it).clone()).collect()),
+            }
+        } else if path.is_empty() || path.starts_with('/') {
+            value.pointer(path).cloned().ok_or_else(missing)
+        } else {
+            Err(WorkflowError::Conversion(format!(
+                "Path must start with / or $: {path}"
+            )))
+        }
+    }
 }
 
 #[typetag::serde]
-impl FlexNode for ValidateJson {}
+impl FlexNode for ExtractJson {}
 
-impl DynNode for ValidateJson {
+impl DynNode for ExtractJson {
     fn inputs(&self) -> usize {
         2
     }
@@ -236,19 +272,20 @@ impl DynNode for ValidateJson {
     fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
         Cow::Borrowed(match in_pin {
             0 => &[ValueKind::Json],
-            1 => &[ValueKind::Json, ValueKind::Text, ValueKind::Message],
+            1 => &[ValueKind::Text],
             _ => unreachable!(),
         })
     }
 
     fn outputs(&self) -> usize {
-        2
+        3
     }
 
     fn out_kind(&self, out_pin: usize) -> ValueKind {
         match out_pin {
             0 => ValueKind::Json,
-            1 => ValueKind::Failure,
+            1 => ValueKind::Text,
+            2 => ValueKind::Failure,
             _ => unreachable!(),
         }
     }
@@ -261,52 +298,47 @@ impl DynNode for ValidateJson {
     ) -> Result<Vec<Value>, WorkflowError> {
         self.validate(&inputs)?;
 
-        let schema = match &inputs[0] {
-            Some(Value::Json(schema)) => schema.as_ref().to_owned(),
-            None if !self.schema.is_empty() => serde_json::from_str(&self.schema)
-                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
-            None => Err(WorkflowError::Required(vec!["Schema is required".into()]))?,
+        let value = match &inputs[0] {
+            Some(Value::Json(value)) => value.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "JSON input is required".into(),
+            ]))?,
             _ => unreachable!(),
         };
 
-        let input = match &inputs[1] {
-            Some(Value::Json(input)) => input.as_ref().to_owned(),
-            Some(Value::Text(text)) => serde_json::from_str(text)
-                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
-            Some(Value::Message(msg)) => serde_json::from_str(&message_text(msg))
-                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
-            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+        let node = match &inputs[1] {
+            Some(Value::Text(path)) => Cow::Owned(Self {
+                path: path.to_string(),
+            }),
+            None => Cow::Borrowed(&*self),
             _ => unreachable!(),
         };
 
-        let validator = jsonschema::validator_for(&schema)
-            .map_err(|err| anyhow::anyhow!("Invalid schema: {err:?}"))?;
-
-        validator
-            .validate(&input)
-            .map_err(|err| anyhow::anyhow!("Validation error: {err:?}"))?;
+        let found = node.extract(&value)?;
 
-        let value = Arc::new(input);
+        let text = match &found {
+            serde_json::Value::String(text) => Value::text(text),
+            _ => Value::Placeholder(ValueKind::Text),
+        };
 
         Ok(vec![
-            Value::Json(value),
+            Value::Json(Arc::new(found)),
+            text,
             Value::Placeholder(ValueKind::Failure),
         ])
     }
 }
 
-impl UiNode for ValidateJson {
+impl UiNode for ExtractJson {
     fn title(&self) -> &str {
-        "Validate JSON"
+        "Extract JSON"
     }
 
     fn tooltip(&self) -> &str {
-        "Validates a JSON value against a JSON Schema (as a JSON object).\n\
-            If the value is valid it is passed through to the output."
-    }
-
-    fn help_link(&self) -> &str {
-        "https://json-schema.org/understanding-json-schema/reference"
+        "Selects a value from JSON using a JSON Pointer (/foo/0/bar)\n\
+            or a JSONPath ($.foo[*].bar) expression.\n\
+            String values are also output as text.\n\
+            Paths that match nothing go to the failure output."
     }
 
     fn show_input(
@@ -318,20 +350,12 @@ impl UiNode for ValidateJson {
     ) -> egui_snarl::ui::PinInfo {
         match pin_id {
             0 => {
-                if remote.is_none() {
-                    resizable_frame(&mut self.size, ui, |ui| {
-                        egui::ScrollArea::vertical().show(ui, |ui| {
-                            let hint_text = "JSON Schema";
-                            json_editor(ui, &mut self.schema, Some(hint_text));
-                        });
-                    });
-                } else {
-                    ui.label("schema");
-                }
-            }
-            1 => {
                 ui.label("json");
             }
+            1 if remote.is_some() => {
+                ui.label("path");
+            }
+            1 => {}
             _ => unreachable!(),
         };
 
@@ -349,117 +373,97 @@ impl UiNode for ValidateJson {
                 ui.label("json");
             }
             1 => {
+                ui.label("text");
+            }
+            2 => {
                 ui.label("failure");
             }
             _ => unreachable!(),
         }
         self.out_kind(pin_id).default_pin()
     }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        let widget = egui::TextEdit::singleline(&mut self.path)
+            .font(egui::TextStyle::Monospace)
+            .desired_width(160.0)
+            .hint_text("/path or $.path");
+        squelch(ui.add(widget)).on_hover_text("Overridden by the path input when connected");
+    }
 }
 
 #[skip_serializing_none]
 #[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct TransformJson {
-    filter: String,
+pub struct RepairJson {
+    text: String,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    aggressive: bool,
 
     size: Option<crate::utils::EVec2>,
 }
 
 #[typetag::serde]
-impl FlexNode for TransformJson {}
-
-impl DynNode for TransformJson {
-    fn inputs(&self) -> usize {
-        2
-    }
+impl FlexNode for RepairJson {}
 
+impl DynNode for RepairJson {
     fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
         Cow::Borrowed(match in_pin {
-            0 => &[ValueKind::Text],
-            1 => &[
-                ValueKind::Json,
-                ValueKind::Number,
-                ValueKind::Integer,
-                ValueKind::Text,
-                ValueKind::FloatList,
-                ValueKind::IntList,
-                ValueKind::TextList,
-                ValueKind::Chat,
-                ValueKind::Message,
-                ValueKind::MsgList,
-            ],
+            0 => &[ValueKind::Text, ValueKind::Message],
             _ => unreachable!(),
         })
     }
 
-    fn out_kind(&self, _out_pin: usize) -> ValueKind {
-        ValueKind::Json
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
     }
 
     fn execute(
         &mut self,
-        ctx: &RunContext,
+        _ctx: &RunContext,
         _node_id: egui_snarl::NodeId,
         inputs: Vec<Option<Value>>,
     ) -> Result<Vec<Value>, WorkflowError> {
         self.validate(&inputs)?;
 
-        let filter = match &inputs[0] {
-            Some(Value::Text(text)) => text.as_str(),
-            None => self.filter.as_str(),
-            _ => unreachable!(),
-        };
-
-        let filter = ctx.transmuter.init_filter(filter)?;
-
-        let input = match &inputs[1] {
-            Some(Value::Json(input)) => input.as_ref().to_owned(),
-            Some(Value::Number(value)) => json!(value),
-            Some(Value::FloatList(value)) => json!(value),
-            Some(Value::Integer(value)) => json!(value),
-            Some(Value::IntList(value)) => json!(value),
-            Some(Value::Text(value)) => json!(value),
-            Some(Value::TextList(value)) => json!(value),
-            Some(Value::Chat(value)) => {
-                json!(
-                    value
-                        .iter_msgs()
-                        .map(|m| json!({"author": message_party(&m), "content": message_text(&m)}))
-                        .collect_vec()
-                )
-            }
-            Some(Value::Message(value)) => {
-                json!({"author": message_party(value), "content": message_text(value)})
-            }
-            Some(Value::MsgList(value)) => {
-                json!(
-                    value
-                        .iter()
-                        .map(|m| json!({"author": message_party(m), "content": message_text(m)}))
-                        .collect_vec()
-                )
-            }
-            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => Cow::Borrowed(text.as_str()),
+            Some(Value::Message(message)) => Cow::Owned(message_text(message)),
+            None => Cow::Borrowed(self.text.as_str()),
             _ => unreachable!(),
         };
 
-        let value = ctx.transmuter.run_filter(&filter, input)?;
+        let value = repair_json(&text, self.aggressive).ok_or(WorkflowError::Conversion(
+            "Could not repair the text into valid JSON".into(),
+        ))?;
 
-        Ok(vec![Value::Json(Arc::new(value))])
+        Ok(vec![
+            Value::Json(Arc::new(value)),
+            Value::Placeholder(ValueKind::Failure),
+        ])
     }
 }
 
-impl UiNode for TransformJson {
+impl UiNode for RepairJson {
     fn title(&self) -> &str {
-        "Transform JSON"
+        "Repair JSON"
     }
 
     fn tooltip(&self) -> &str {
-        "Transform a JSON value using jq/jaq filters."
-    }
-
-    fn help_link(&self) -> &str {
-        "https://gedenkt.at/jaq/manual/#corelang"
+        "Parses JSON written by a language model, fixing common mistakes\n\
+            such as code fences and trailing commas."
     }
 
     fn show_input(
@@ -472,61 +476,88 @@ impl UiNode for TransformJson {
         match pin_id {
             0 => {
                 if remote.is_none() {
-                    egui::Frame::new().inner_margin(4).show(ui, |ui| {
-                        resizable_frame(&mut self.size, ui, |ui| {
-                            egui::ScrollArea::vertical().show(ui, |ui| {
-                                let widget = egui::TextEdit::multiline(&mut self.filter)
-                                    .desired_width(f32::INFINITY)
-                                    .hint_text("Filter\u{1F64B}");
-
-                                squelch(ui.add_sized(ui.available_size(), widget));
-                            });
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            json_editor(ui, &mut self.text, None);
                         });
                     });
                 } else {
-                    ui.label("filter");
+                    ui.label("text");
                 }
             }
-            1 => {
-                ui.label("json");
-            }
             _ => unreachable!(),
         };
 
         self.in_kinds(pin_id).first().unwrap().default_pin()
     }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("json");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.checkbox(&mut self.aggressive, "aggressive")
+            .on_hover_text(
+                "Also convert single-quoted strings, quote bare keys\n\
+                and replace Python literals like True and None.\n\
+                May alter text that only looks like syntax.",
+            );
+    }
 }
 
+#[skip_serializing_none]
 #[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct GatherJson {
-    count: usize,
+pub struct ValidateJson {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    schema: String,
+
+    size: Option<crate::utils::EVec2>,
 }
 
 #[typetag::serde]
-impl FlexNode for GatherJson {}
+impl FlexNode for ValidateJson {}
 
-impl DynNode for GatherJson {
+impl DynNode for ValidateJson {
     fn inputs(&self) -> usize {
-        self.count + 1 // Extra slot to add another document
+        2
     }
 
-    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
-        Cow::Borrowed(&[
-            ValueKind::Json,
-            ValueKind::Text,
-            ValueKind::Number,
-            ValueKind::Integer,
-            ValueKind::FloatList,
-            ValueKind::IntList,
-            ValueKind::TextList,
-            ValueKind::Chat,
-            ValueKind::Message,
-            ValueKind::MsgList,
-        ])
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Json],
+            1 => &[ValueKind::Json, ValueKind::Text, ValueKind::Message],
+            _ => unreachable!(),
+        })
     }
 
-    fn out_kind(&self, _out_pin: usize) -> ValueKind {
-        ValueKind::Json
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
     }
 
     fn execute(
@@ -535,108 +566,1699 @@ impl DynNode for GatherJson {
         _node_id: egui_snarl::NodeId,
         inputs: Vec<Option<Value>>,
     ) -> Result<Vec<Value>, WorkflowError> {
-        use crate::utils::message_text;
-        use serde_json::Number;
-
         self.validate(&inputs)?;
 
-        let values = inputs
-            .into_iter()
-            .take(self.count)
-            .map(|it| match it {
-                Some(Value::Json(value)) => value.as_ref().clone(),
-                Some(Value::Text(value)) => serde_json::Value::String((*value).clone()),
-                Some(Value::Number(value)) => {
-                    serde_json::Value::Number(Number::from_f64(value.into_inner()).unwrap())
-                }
-                Some(Value::Integer(value)) => {
-                    serde_json::Value::Number(Number::from_i128(value as i128).unwrap())
-                }
-                Some(Value::FloatList(value)) => json!(value),
-                Some(Value::IntList(value)) => json!(value),
-                Some(Value::TextList(value)) => json!(value),
-                Some(Value::Chat(value)) => {
-                    json!(
-                    value
+        let schema = match &inputs[0] {
+            Some(Value::Json(schema)) => schema.as_ref().to_owned(),
+            None if !self.schema.is_empty() => serde_json::from_str(&self.schema)
+                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
+            None => Err(WorkflowError::Required(vec!["Schema is required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let input = match &inputs[1] {
+            Some(Value::Json(input)) => input.as_ref().to_owned(),
+            Some(Value::Text(text)) => serde_json::from_str(text)
+                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
+            Some(Value::Message(msg)) => serde_json::from_str(&message_text(msg))
+                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
+            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|err| anyhow::anyhow!("Invalid schema: {err:?}"))?;
+
+        validator
+            .validate(&input)
+            .map_err(|err| anyhow::anyhow!("Validation error: {err:?}"))?;
+
+        let value = Arc::new(input);
+
+        Ok(vec![
+            Value::Json(value),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ValidateJson {
+    fn title(&self) -> &str {
+        "Validate JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Validates a JSON value against a JSON Schema (as a JSON object).\n\
+            If the value is valid it is passed through to the output."
+    }
+
+    fn help_link(&self) -> &str {
+        "https://json-schema.org/understanding-json-schema/reference"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            let hint_text = "JSON Schema";
+                            json_editor(ui, &mut self.schema, Some(hint_text));
+                        });
+                    });
+                } else {
+                    ui.label("schema");
+                }
+            }
+            1 => {
+                ui.label("json");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("json");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoerceJson {}
+
+impl CoerceJson {
+    /// Fixes obvious type mismatches against the schema, then validates the result
+    pub fn coerce(
+        &self,
+        schema: &serde_json::Value,
+        input: serde_json::Value,
+    ) -> Result<serde_json::Value, WorkflowError> {
+        let validator = jsonschema::validator_for(schema)
+            .map_err(|err| anyhow::anyhow!("Invalid schema: {err:?}"))?;
+
+        let value = coerce_json(schema, input);
+
+        let violations = validator
+            .iter_errors(&value)
+            .map(|err| format!("{}: {err}", err.instance_path()))
+            .collect_vec();
+
+        if !violations.is_empty() {
+            Err(anyhow::anyhow!(
+                "Validation errors:\n{}",
+                violations.join("\n")
+            ))?;
+        }
+
+        Ok(value)
+    }
+}
+
+/// Converts values to the type the schema asks for, when the conversion is unambiguous.
+///
+/// Handles numbers and booleans written as strings, scalars where strings are expected,
+/// JSON encoded as a string, and single values where arrays are expected.
+/// Recurses through `properties`, `additionalProperties`, `items` and `prefixItems`.
+pub fn coerce_json(schema: &serde_json::Value, value: serde_json::Value) -> serde_json::Value {
+    use serde_json::Value as J;
+
+    let types = match schema.get("type") {
+        Some(J::String(kind)) => vec![kind.as_str()],
+        Some(J::Array(kinds)) => kinds.iter().filter_map(|k| k.as_str()).collect_vec(),
+        _ if schema.get("properties").is_some() => vec!["object"],
+        _ if schema.get("items").is_some() => vec!["array"],
+        _ => vec![],
+    };
+
+    let matches = |value: &J, kind: &str| match kind {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    };
+
+    let value = if types.is_empty() || types.iter().any(|kind| matches(&value, kind)) {
+        value
+    } else {
+        types
+            .iter()
+            .find_map(|kind| coerce_scalar(&value, kind))
+            .unwrap_or(value)
+    };
+
+    match value {
+        J::Object(mut map) => {
+            let properties = schema.get("properties").and_then(|p| p.as_object());
+            let additional = schema.get("additionalProperties").filter(|p| p.is_object());
+
+            for (key, item) in map.iter_mut() {
+                let item_schema = properties.and_then(|p| p.get(key)).or(additional);
+                if let Some(item_schema) = item_schema {
+                    *item = coerce_json(item_schema, item.take());
+                }
+            }
+
+            J::Object(map)
+        }
+        J::Array(items) => {
+            let prefix = schema
+                .get("prefixItems")
+                .and_then(|p| p.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let rest = schema.get("items").filter(|p| p.is_object());
+
+            let items = items
+                .into_iter()
+                .enumerate()
+                .map(|(idx, item)| match prefix.get(idx).or(rest) {
+                    Some(item_schema) => coerce_json(item_schema, item),
+                    None => item,
+                })
+                .collect();
+
+            J::Array(items)
+        }
+        value => value,
+    }
+}
+
+fn coerce_scalar(value: &serde_json::Value, kind: &str) -> Option<serde_json::Value> {
+    use serde_json::Value as J;
+
+    match (kind, value) {
+        ("integer", J::String(text)) => text.trim().parse::<i64>().ok().map(J::from),
+        ("integer", J::Number(num)) => num
+            .as_f64()
+            .filter(|n| n.fract() == 0.0 && n.abs() < i64::MAX as f64)
+            .map(|n| J::from(n as i64)),
+        ("number", J::String(text)) => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(J::Number),
+        ("boolean", J::String(text)) => match text.trim().to_lowercase().as_str() {
+            "true" => Some(J::Bool(true)),
+            "false" => Some(J::Bool(false)),
+            _ => None,
+        },
+        ("string", J::Number(num)) => Some(J::String(num.to_string())),
+        ("string", J::Bool(flag)) => Some(J::String(flag.to_string())),
+        ("null", J::String(text)) if text.trim() == "null" => Some(J::Null),
+        ("object", J::String(text)) => serde_json::from_str(text)
+            .ok()
+            .filter(|v: &J| v.is_object()),
+        ("array", J::String(text)) => serde_json::from_str(text).ok().filter(|v: &J| v.is_array()),
+        ("array", value) if !value.is_null() => Some(J::Array(vec![value.clone()])),
+        _ => None,
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for CoerceJson {}
+
+impl DynNode for CoerceJson {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Json],
+            1 => &[ValueKind::Json, ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let schema = match &inputs[0] {
+            Some(Value::Json(schema)) => schema.clone(),
+            None => Err(WorkflowError::Required(vec!["Schema is required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let input = match &inputs[1] {
+            Some(Value::Json(input)) => input.as_ref().to_owned(),
+            Some(Value::Text(text)) => serde_json::from_str(text)
+                .map_err(|err| WorkflowError::Conversion(err.to_string()))?,
+            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let value = self.coerce(&schema, input)?;
+
+        Ok(vec![
+            Value::Json(Arc::new(value)),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for CoerceJson {
+    fn title(&self) -> &str {
+        "Coerce JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Converts values to the types a JSON Schema expects, then validates.\n\
+            Fixes numbers and booleans written as strings, among other common mistakes."
+    }
+
+    fn help_link(&self) -> &str {
+        "https://json-schema.org/understanding-json-schema/reference"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("schema");
+            }
+            1 => {
+                ui.label("json");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("json");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransformJson {
+    filter: String,
+
+    size: Option<crate::utils::EVec2>,
+}
+
+#[typetag::serde]
+impl FlexNode for TransformJson {}
+
+impl DynNode for TransformJson {
+    fn cacheable(&self) -> bool {
+        true
+    }
+
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            1 => &[
+                ValueKind::Json,
+                ValueKind::Number,
+                ValueKind::Integer,
+                ValueKind::Text,
+                ValueKind::FloatList,
+                ValueKind::IntList,
+                ValueKind::TextList,
+                ValueKind::Chat,
+                ValueKind::Message,
+                ValueKind::MsgList,
+            ],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        ValueKind::Json
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let filter = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => self.filter.as_str(),
+            _ => unreachable!(),
+        };
+
+        let filter = ctx.transmuter.init_filter(filter)?;
+
+        let input = match &inputs[1] {
+            Some(Value::Json(input)) => input.as_ref().to_owned(),
+            Some(Value::Number(value)) => json!(value),
+            Some(Value::FloatList(value)) => json!(value),
+            Some(Value::Integer(value)) => json!(value),
+            Some(Value::IntList(value)) => json!(value),
+            Some(Value::Text(value)) => json!(value),
+            Some(Value::TextList(value)) => json!(value),
+            Some(Value::Chat(value)) => {
+                json!(
+                    value
                         .iter_msgs()
                         .map(|m| json!({"author": message_party(&m), "content": message_text(&m)}))
                         .collect_vec()
                 )
+            }
+            Some(Value::Message(value)) => {
+                json!({"author": message_party(value), "content": message_text(value)})
+            }
+            Some(Value::MsgList(value)) => {
+                json!(
+                    value
+                        .iter()
+                        .map(|m| json!({"author": message_party(m), "content": message_text(m)}))
+                        .collect_vec()
+                )
+            }
+            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let value = ctx.transmuter.run_filter(&filter, input)?;
+
+        Ok(vec![Value::Json(Arc::new(value))])
+    }
+}
+
+impl UiNode for TransformJson {
+    fn title(&self) -> &str {
+        "Transform JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Transform a JSON value using jq/jaq filters."
+    }
+
+    fn help_link(&self) -> &str {
+        "https://gedenkt.at/jaq/manual/#corelang"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    egui::Frame::new().inner_margin(4).show(ui, |ui| {
+                        resizable_frame(&mut self.size, ui, |ui| {
+                            egui::ScrollArea::vertical().show(ui, |ui| {
+                                let widget = egui::TextEdit::multiline(&mut self.filter)
+                                    .desired_width(f32::INFINITY)
+                                    .hint_text("Filter\u{1F64B}");
+
+                                squelch(ui.add_sized(ui.available_size(), widget));
+                            });
+                        });
+                    });
+                } else {
+                    ui.label("filter");
+                }
+            }
+            1 => {
+                ui.label("json");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+}
+
+/// Converts a workflow value into a JSON document, as gathered into arrays
+pub fn value_to_json(value: Value) -> Result<serde_json::Value, WorkflowError> {
+    use serde_json::Number;
+
+    Ok(match value {
+        Value::Json(value) => value.as_ref().clone(),
+        Value::Text(value) => serde_json::Value::String((*value).clone()),
+        Value::Number(value) => {
+            serde_json::Value::Number(Number::from_f64(value.into_inner()).unwrap())
+        }
+        Value::Integer(value) => {
+            serde_json::Value::Number(Number::from_i128(value as i128).unwrap())
+        }
+        Value::FloatList(value) => json!(value),
+        Value::IntList(value) => json!(value),
+        Value::TextList(value) => json!(value),
+        Value::Chat(value) => {
+            json!(
+                value
+                    .iter_msgs()
+                    .map(|m| json!({"author": message_party(&m), "content": message_text(&m)}))
+                    .collect_vec()
+            )
+        }
+        Value::Message(value) => {
+            json!({"author": message_party(&value), "content": message_text(&value)})
+        }
+        Value::MsgList(value) => {
+            json!(
+                value
+                    .iter()
+                    .map(|m| json!({"author": message_party(m), "content": message_text(m)}))
+                    .collect_vec()
+            )
+        }
+        other => Err(WorkflowError::Conversion(format!(
+            "Cannot convert {:?} to JSON",
+            other.kind()
+        )))?,
+    })
+}
+
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatherJson {
+    count: usize,
+}
+
+#[typetag::serde]
+impl FlexNode for GatherJson {}
+
+impl DynNode for GatherJson {
+    fn inputs(&self) -> usize {
+        self.count + 1 // Extra slot to add another document
+    }
+
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(&[
+            ValueKind::Json,
+            ValueKind::Text,
+            ValueKind::Number,
+            ValueKind::Integer,
+            ValueKind::FloatList,
+            ValueKind::IntList,
+            ValueKind::TextList,
+            ValueKind::Chat,
+            ValueKind::Message,
+            ValueKind::MsgList,
+        ])
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        ValueKind::Json
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let values = inputs
+            .into_iter()
+            .take(self.count)
+            .map(|it| it.map(value_to_json).unwrap_or(Ok(serde_json::Value::Null)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let value = Arc::new(serde_json::Value::Array(values));
+
+        Ok(vec![Value::Json(value)])
+    }
+}
+
+impl UiNode for GatherJson {
+    fn title(&self) -> &str {
+        "Gather JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Combine multiple JSON documents into a single array.\n\
+            The output can be transformed using shallow, deep or arbitrary merging"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        if pin_id == self.count && remote.is_some() {
+            self.count += 1;
+        } else if pin_id + 1 == self.count && remote.is_none() {
+            self.count -= 1;
+        }
+
+        if pin_id < self.count {
+            ui.label(format!(".[{pin_id}]"));
+        }
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnwrapJson {
+    kind: ValueKind,
+}
+
+#[typetag::serde]
+impl FlexNode for UnwrapJson {}
+
+impl Default for UnwrapJson {
+    fn default() -> Self {
+        Self {
+            kind: ValueKind::Text,
+        }
+    }
+}
+
+impl DynNode for UnwrapJson {
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(&[ValueKind::Json])
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        if out_pin == 0 {
+            self.kind
+        } else {
+            ValueKind::Failure
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+        use serde_json::Value as JVal;
+
+        let json = match &inputs[0] {
+            Some(Value::Json(value)) => value.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "JSON input is required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        let value = match (self.kind, &*json) {
+            (ValueKind::Text, JVal::String(text)) => Value::text(text.clone()),
+            (ValueKind::TextList, JVal::Array(values)) if values.iter().all(|t| t.is_string()) => {
+                let texts = values
+                    .iter()
+                    .map(|t| t.as_str().unwrap().to_string())
+                    .collect_vec();
+                Value::text_list(texts)
+            }
+            (ValueKind::Number, JVal::Number(value)) if value.is_f64() || value.is_i64() => {
+                Value::float(value.as_f64().unwrap())
+            }
+            (ValueKind::FloatList, JVal::Array(values))
+                if values.iter().all(|t| t.is_f64() || t.is_i64()) =>
+            {
+                let values = values.iter().map(|t| t.as_f64().unwrap()).collect_vec();
+                Value::float_list(values)
+            }
+            (ValueKind::Integer, JVal::Number(value)) if value.is_i64() => {
+                Value::Integer(value.as_i64().unwrap())
+            }
+            (ValueKind::IntList, JVal::Array(values)) if values.iter().all(|t| t.is_i64()) => {
+                let values = values.iter().map(|t| t.as_i64().unwrap()).collect_vec();
+                Value::int_list(values)
+            }
+            _ => Err(WorkflowError::Conversion(format!(
+                "Could not convert {json:?} into a {:?}",
+                self.kind
+            )))?,
+        };
+
+        Ok(vec![value])
+    }
+}
+
+impl UiNode for UnwrapJson {
+    fn title(&self) -> &str {
+        "Unwrap JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Converts a JSON value into a workflow value"
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        let old_kind = self.kind;
+        egui::ComboBox::from_id_salt("UnwrapJsonKind")
+            .selected_text(self.kind.to_string().to_ascii_lowercase())
+            .show_ui(ui, |ui| {
+                let kinds = [
+                    ValueKind::Text,
+                    ValueKind::TextList,
+                    ValueKind::Number,
+                    ValueKind::FloatList,
+                    ValueKind::Integer,
+                    ValueKind::IntList,
+                ];
+
+                for kind in kinds {
+                    let mut label = kind.to_string().to_lowercase();
+                    if kind.is_list() {
+                        label = format!("[{label}]");
+                    }
+
+                    ui.selectable_value(&mut self.kind, kind, label);
+                }
+            });
+
+        if old_kind != self.kind {
+            ctx.reset_out_pin(OutPinId {
+                node: ctx.current_node,
+                output: 0,
+            });
+        }
+    }
+}
+
+/// Text formats that can be converted to and from JSON values
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataFormat {
+    #[default]
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl DataFormat {
+    pub const ALL: [DataFormat; 3] = [DataFormat::Json, DataFormat::Yaml, DataFormat::Toml];
+
+    pub fn is_json(&self) -> bool {
+        *self == Self::Json
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataFormat::Json => "json",
+            DataFormat::Yaml => "yaml",
+            DataFormat::Toml => "toml",
+        }
+    }
+
+    pub fn parse(&self, text: &str) -> Result<serde_json::Value, WorkflowError> {
+        use serde_yaml_ng as serde_yml;
+
+        match self {
+            DataFormat::Json => serde_json::from_str(text)
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid JSON: {e}"))),
+            DataFormat::Yaml => serde_yml::from_str(text)
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid YAML: {e}"))),
+            DataFormat::Toml => toml::from_str(text)
+                .map_err(|e| WorkflowError::Conversion(format!("Invalid TOML: {e}"))),
+        }
+    }
+
+    pub fn render(&self, value: &serde_json::Value, pretty: bool) -> Result<String, WorkflowError> {
+        use serde_yaml_ng as serde_yml;
+
+        match self {
+            DataFormat::Json if pretty => serde_json::to_string_pretty(value)
+                .map_err(|e| WorkflowError::Conversion(e.to_string())),
+            DataFormat::Json => {
+                serde_json::to_string(value).map_err(|e| WorkflowError::Conversion(e.to_string()))
+            }
+            DataFormat::Yaml => {
+                serde_yml::to_string(value).map_err(|e| WorkflowError::Conversion(e.to_string()))
+            }
+            DataFormat::Toml if pretty => toml::to_string_pretty(value).map_err(|e| {
+                WorkflowError::Conversion(format!("Cannot represent value as TOML: {e}"))
+            }),
+            DataFormat::Toml => toml::to_string(value).map_err(|e| {
+                WorkflowError::Conversion(format!("Cannot represent value as TOML: {e}"))
+            }),
+        }
+    }
+
+    fn selector(&mut self, ui: &mut egui::Ui, id_salt: &str) {
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(self.label())
+            .show_ui(ui, |ui| {
+                for format in Self::ALL {
+                    ui.selectable_value(self, format, format.label());
                 }
-                Some(Value::Message(value)) => {
-                    json!({"author": message_party(&value), "content": message_text(&value)})
+            });
+    }
+}
+
+/// Parses JSON, YAML or TOML text into a JSON value
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseStructured {
+    text: String,
+
+    #[serde(default, skip_serializing_if = "DataFormat::is_json")]
+    format: DataFormat,
+
+    size: Option<crate::utils::EVec2>,
+}
+
+#[typetag::serde]
+impl FlexNode for ParseStructured {}
+
+impl DynNode for ParseStructured {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text, ValueKind::Message],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => Cow::Borrowed(text.as_str()),
+            Some(Value::Message(message)) => Cow::Owned(message_text(message)),
+            None => Cow::Borrowed(self.text.as_str()),
+            _ => unreachable!(),
+        };
+
+        let value = self.format.parse(&text)?;
+
+        Ok(vec![
+            Value::Json(Arc::new(value)),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ParseStructured {
+    fn title(&self) -> &str {
+        "Parse Structured"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Parses JSON, YAML or TOML text into a JSON value.\n\
+            Malformed input is sent to the failure output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    resizable_frame(&mut self.size, ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            let widget = egui::TextEdit::multiline(&mut self.text)
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(f32::INFINITY)
+                                .hint_text(self.format.label());
+
+                            squelch(ui.add_sized(ui.available_size(), widget));
+                        });
+                    });
+                } else {
+                    ui.label("text");
                 }
-                Some(Value::MsgList(value)) => {
-                    json!(
-                        value
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("json");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        self.format.selector(ui, "ParseStructuredFormat");
+    }
+}
+
+/// Parses CSV text into an array of row objects keyed by column
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParseCsv {
+    /// Whether the first row names the columns
+    pub header: bool,
+
+    /// Single character between fields, or `\t` for tabs
+    pub delimiter: String,
+}
+
+impl Default for ParseCsv {
+    fn default() -> Self {
+        Self {
+            header: true,
+            delimiter: ",".into(),
+        }
+    }
+}
+
+impl ParseCsv {
+    /// Column names and row objects of the text.
+    ///
+    /// Without a header, columns are named `column_1`, `column_2` and so on.
+    pub fn parse(
+        &self,
+        text: &str,
+    ) -> Result<(Vec<String>, Vec<serde_json::Value>), WorkflowError> {
+        let delimiter = match self.delimiter.as_str() {
+            "" => b',',
+            "\\t" => b'\t',
+            it if it.len() == 1 => it.as_bytes()[0],
+            _ => Err(WorkflowError::Conversion(
+                "CSV delimiter must be a single character".into(),
+            ))?,
+        };
+
+        let invalid = |err: csv::Error| WorkflowError::Conversion(format!("Invalid CSV: {err}"));
+
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(self.header)
+            .from_reader(text.as_bytes());
+
+        let mut columns = if self.header {
+            reader
+                .headers()
+                .map_err(invalid)?
+                .iter()
+                .map(String::from)
+                .collect_vec()
+        } else {
+            vec![]
+        };
+
+        let mut rows = vec![];
+        for record in reader.records() {
+            let record = record.map_err(invalid)?;
+            while columns.len() < record.len() {
+                columns.push(format!("column_{}", columns.len() + 1));
+            }
+
+            let row = columns
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|field| json!(field)))
+                .collect();
+
+            rows.push(serde_json::Value::Object(row));
+        }
+
+        Ok((columns, rows))
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for ParseCsv {}
+
+impl DynNode for ParseCsv {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Json,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => Err(WorkflowError::Required(vec!["CSV text is required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let (columns, rows) = self.parse(text)?;
+
+        Ok(vec![
+            Value::Json(Arc::new(serde_json::Value::Array(rows))),
+            Value::Json(Arc::new(json!(columns))),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ParseCsv {
+    fn title(&self) -> &str {
+        "Parse CSV"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Parses CSV text into a JSON array of row objects keyed by column name.\n\
+            Also outputs the column names. Fields are kept as strings.\n\
+            Malformed input is sent to the failure output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("rows");
+            }
+            1 => {
+                ui.label("columns");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.checkbox(&mut self.header, "header")
+                .on_hover_text("The first row names the columns");
+
+            ui.horizontal(|ui| {
+                ui.label("delimiter");
+                let widget = egui::TextEdit::singleline(&mut self.delimiter)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(24.0)
+                    .char_limit(2);
+                squelch(ui.add(widget)).on_hover_text("Use \\t for tabs");
+            });
+        });
+    }
+}
+
+/// Renders a JSON value as JSON, YAML or TOML text
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerializeJson {
+    #[serde(default, skip_serializing_if = "DataFormat::is_json")]
+    format: DataFormat,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pretty: bool,
+}
+
+#[typetag::serde]
+impl FlexNode for SerializeJson {}
+
+impl DynNode for SerializeJson {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Json],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let value = match &inputs[0] {
+            Some(Value::Json(value)) => value.clone(),
+            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        let text = self.format.render(&value, self.pretty)?;
+
+        Ok(vec![
+            Value::text(text),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for SerializeJson {
+    fn title(&self) -> &str {
+        "Serialize JSON"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Renders a JSON value as JSON, YAML or TOML text.\n\
+            TOML requires an object at the top level and cannot represent nulls."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("json");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            self.format.selector(ui, "SerializeJsonFormat");
+
+            if self.format != DataFormat::Yaml {
+                ui.checkbox(&mut self.pretty, "pretty");
+            }
+        });
+    }
+}
+
+/// Maps a message into one or more OpenAI chat entries.
+///
+/// Tool results become separate `tool` role entries, as in the OpenAI API.
+fn openai_messages(message: &Message) -> Vec<serde_json::Value> {
+    match message {
+        Message::User { content } => {
+            let mut result = vec![];
+            let mut text = vec![];
+
+            for item in content.iter() {
+                match item {
+                    UserContent::Text(t) => text.push(t.text.clone()),
+                    UserContent::ToolResult(tool_result) => {
+                        if !text.is_empty() {
+                            result.push(json!({"role": "user", "content": text.join("\n")}));
+                            text.clear();
+                        }
+
+                        let output = tool_result
+                            .content
                             .iter()
-                            .map(
-                                |m| json!({"author": message_party(m), "content": message_text(m)})
-                            )
-                            .collect_vec()
-                    )
+                            .filter_map(|c| match c {
+                                ToolResultContent::Text(t) => Some(t.text()),
+                                _ => None,
+                            })
+                            .join("\n");
+
+                        result.push(json!({
+                            "role": "tool",
+                            "tool_call_id": tool_result.id,
+                            "content": output,
+                        }));
+                    }
+                    other => tracing::debug!("Skipping unsupported user content {other:?}"),
                 }
-                None => serde_json::Value::Null,
-                _ => unreachable!(),
-            })
-            .collect_vec();
+            }
 
-        let value = Arc::new(serde_json::Value::Array(values));
+            if !text.is_empty() {
+                result.push(json!({"role": "user", "content": text.join("\n")}));
+            }
 
-        Ok(vec![Value::Json(value)])
+            result
+        }
+        Message::Assistant { content, .. } => {
+            let mut text = vec![];
+            let mut tool_calls = vec![];
+
+            for item in content.iter() {
+                match item {
+                    AssistantContent::Text(t) => text.push(t.text.clone()),
+                    AssistantContent::ToolCall(tool_call) => tool_calls.push(json!({
+                        "id": tool_call.id,
+                        "type": "function",
+                        "function": {
+                            "name": tool_call.function.name,
+                            "arguments": tool_call.function.arguments.to_string(),
+                        },
+                    })),
+                    _ => {}
+                }
+            }
+
+            let mut result = json!({
+                "role": "assistant",
+                "content": if text.is_empty() { serde_json::Value::Null } else { text.join("\n").into() },
+            });
+
+            if !tool_calls.is_empty() {
+                result["tool_calls"] = tool_calls.into();
+            }
+
+            vec![result]
+        }
+    }
+}
+
+/// Converts the current branch of a chat into an OpenAI-style messages array
+pub fn chat_to_messages(history: &ChatHistory) -> serde_json::Value {
+    history
+        .iter_msgs()
+        .flat_map(|message| openai_messages(&message))
+        .collect_vec()
+        .into()
+}
+
+/// Exports a chat as the `[{role, content}]` array used by OpenAI-compatible APIs
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatToMessages {}
+
+#[typetag::serde]
+impl FlexNode for ChatToMessages {}
+
+impl DynNode for ChatToMessages {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Chat],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let history = match &inputs[0] {
+            Some(Value::Chat(history)) => history.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "Chat history required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        Ok(vec![Value::Json(Arc::new(chat_to_messages(&history)))])
+    }
+}
+
+impl UiNode for ChatToMessages {
+    fn title(&self) -> &str {
+        "Chat to Messages"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Converts a chat history into an OpenAI-style messages array.\n\
+            Tool calls and results use the standard tool_calls and tool role fields."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("chat");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("messages");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+/// Test applied to each element by [`FilterJson`]
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PredicateKind {
+    #[default]
+    Exists,
+    Equals,
+    Compare,
+    Expression,
+}
+
+impl PredicateKind {
+    pub const ALL: [PredicateKind; 4] = [
+        PredicateKind::Exists,
+        PredicateKind::Equals,
+        PredicateKind::Compare,
+        PredicateKind::Expression,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PredicateKind::Exists => "key exists",
+            PredicateKind::Equals => "key equals",
+            PredicateKind::Compare => "compare",
+            PredicateKind::Expression => "expression",
+        }
+    }
+}
+
+/// Numeric comparison between an element's field and a constant
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Lt,
+    Le,
+    #[default]
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    pub const ALL: [Comparison; 6] = [
+        Comparison::Lt,
+        Comparison::Le,
+        Comparison::Gt,
+        Comparison::Ge,
+        Comparison::Eq,
+        Comparison::Ne,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+        }
+    }
+
+    pub fn apply(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+        }
+    }
+}
+
+/// Looks up a field by object key, or by JSON pointer when the key starts with '/'
+fn lookup<'a>(
+    element: &'a serde_json::Value,
+    key: &str,
+) -> Result<Option<&'a serde_json::Value>, WorkflowError> {
+    if key.starts_with('/') {
+        Ok(element.pointer(key))
+    } else if let Some(object) = element.as_object() {
+        Ok(object.get(key))
+    } else {
+        Err(WorkflowError::Conversion(format!(
+            "Cannot look up {key:?} in {element}"
+        )))
+    }
+}
+
+/// A predicate prepared once per run and applied to each value
+pub(crate) enum Predicate {
+    Exists(String),
+    Equals(String, serde_json::Value),
+    Compare(String, Comparison, f64),
+    Expression(FilterT),
+}
+
+impl Predicate {
+    pub(crate) fn test(
+        &self,
+        transmuter: &Transmuter,
+        element: &serde_json::Value,
+    ) -> Result<bool, WorkflowError> {
+        match self {
+            Predicate::Exists(key) => Ok(lookup(element, key)?.is_some()),
+            Predicate::Equals(key, value) => Ok(lookup(element, key)? == Some(value)),
+            Predicate::Compare(key, comparison, rhs) => {
+                let lhs = lookup(element, key)?
+                    .ok_or_else(|| WorkflowError::Conversion(format!("Missing key {key:?}")))?
+                    .as_f64()
+                    .ok_or_else(|| {
+                        WorkflowError::Conversion(format!("Value at {key:?} is not a number"))
+                    })?;
+
+                Ok(comparison.apply(lhs, *rhs))
+            }
+            Predicate::Expression(filter) => {
+                match transmuter.run_filter(filter, element.clone())? {
+                    serde_json::Value::Bool(keep) => Ok(keep),
+                    other => Err(WorkflowError::Conversion(format!(
+                        "Expression must produce a boolean, got {other}"
+                    ))),
+                }
+            }
+        }
     }
 }
 
-impl UiNode for GatherJson {
-    fn title(&self) -> &str {
-        "Gather JSON"
-    }
+/// Editable settings for a [`Predicate`] on JSON values
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonPredicate {
+    #[serde(default)]
+    pub predicate: PredicateKind,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub key: String,
+
+    #[serde(default)]
+    pub comparison: Comparison,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub value: String,
+
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub expression: String,
+}
+
+impl JsonPredicate {
+    pub(crate) fn prepare(&self, transmuter: &Transmuter) -> Result<Predicate, WorkflowError> {
+        if self.predicate != PredicateKind::Expression && self.key.is_empty() {
+            return Err(WorkflowError::Required(vec!["Key is required".into()]));
+        }
 
-    fn tooltip(&self) -> &str {
-        "Combine multiple JSON documents into a single array.\n\
-            The output can be transformed using shallow, deep or arbitrary merging"
+        let key = self.key.clone();
+
+        Ok(match self.predicate {
+            PredicateKind::Exists => Predicate::Exists(key),
+            PredicateKind::Equals => {
+                // Bare words are compared as strings
+                let value = serde_json::from_str(&self.value)
+                    .unwrap_or_else(|_| serde_json::Value::String(self.value.clone()));
+                Predicate::Equals(key, value)
+            }
+            PredicateKind::Compare => {
+                let value = self.value.trim().parse().map_err(|_| {
+                    WorkflowError::Conversion(format!("Not a number: {:?}", self.value))
+                })?;
+                Predicate::Compare(key, self.comparison, value)
+            }
+            PredicateKind::Expression => {
+                if self.expression.is_empty() {
+                    return Err(WorkflowError::Required(vec![
+                        "Expression is required".into(),
+                    ]));
+                }
+                Predicate::Expression(transmuter.init_filter(&self.expression)?)
+            }
+        })
     }
 
-    fn show_input(
-        &mut self,
-        ui: &mut egui::Ui,
-        _ctx: &EditContext,
-        pin_id: usize,
-        remote: Option<Value>,
-    ) -> egui_snarl::ui::PinInfo {
-        if pin_id == self.count && remote.is_some() {
-            self.count += 1;
-        } else if pin_id + 1 == self.count && remote.is_none() {
-            self.count -= 1;
-        }
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_salt("JsonPredicateKind")
+            .selected_text(self.predicate.label())
+            .show_ui(ui, |ui| {
+                for kind in PredicateKind::ALL {
+                    ui.selectable_value(&mut self.predicate, kind, kind.label());
+                }
+            });
 
-        if pin_id < self.count {
-            ui.label(format!(".[{pin_id}]"));
-        }
+        match self.predicate {
+            PredicateKind::Expression => {
+                let widget = egui::TextEdit::multiline(&mut self.expression)
+                    .font(egui::TextStyle::Monospace)
+                    .desired_rows(2)
+                    .hint_text(".score > 0.5");
+                squelch(ui.add(widget));
+            }
+            kind => {
+                let widget =
+                    egui::TextEdit::singleline(&mut self.key).hint_text("key or /json/pointer");
+                squelch(ui.add(widget));
+
+                if kind == PredicateKind::Compare {
+                    egui::ComboBox::from_id_salt("JsonPredicateComparison")
+                        .selected_text(self.comparison.label())
+                        .show_ui(ui, |ui| {
+                            for op in Comparison::ALL {
+                                ui.selectable_value(&mut self.comparison, op, op.label());
+                            }
+                        });
+                }
 
-        self.in_kinds(pin_id).first().unwrap().default_pin()
+                if kind != PredicateKind::Exists {
+                    let widget = egui::TextEdit::singleline(&mut self.value).hint_text("value");
+                    squelch(ui.add(widget));
+                }
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
-pub struct UnwrapJson {
-    kind: ValueKind,
+/// Keeps the elements of a JSON array that satisfy a predicate
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterJson {
+    #[serde(flatten)]
+    predicate: JsonPredicate,
+
+    /// Send the run to the failure output when the predicate errors on an element
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    strict: bool,
 }
 
 #[typetag::serde]
-impl FlexNode for UnwrapJson {}
+impl FlexNode for FilterJson {}
+
+impl FilterJson {
+    fn filter(
+        &self,
+        transmuter: &Transmuter,
+        input: &serde_json::Value,
+    ) -> Result<serde_json::Value, WorkflowError> {
+        let serde_json::Value::Array(elements) = input else {
+            return Err(WorkflowError::Conversion(format!(
+                "Expected a JSON array, got {input}"
+            )));
+        };
 
-impl Default for UnwrapJson {
-    fn default() -> Self {
-        Self {
-            kind: ValueKind::Text,
+        let predicate = self.predicate.prepare(transmuter)?;
+        let mut kept = Vec::with_capacity(elements.len());
+
+        for (index, element) in elements.iter().enumerate() {
+            match predicate.test(transmuter, element) {
+                Ok(true) => kept.push(element.clone()),
+                Ok(false) => {}
+                Err(err) if self.strict => {
+                    return Err(WorkflowError::Conversion(format!(
+                        "Predicate failed on element {index}: {err}"
+                    )));
+                }
+                Err(err) => tracing::debug!("Dropping element {index}: {err}"),
+            }
         }
+
+        Ok(serde_json::Value::Array(kept))
     }
 }
 
-impl DynNode for UnwrapJson {
-    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
-        Cow::Borrowed(&[ValueKind::Json])
+impl DynNode for FilterJson {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Json],
+            _ => unreachable!(),
+        })
     }
 
     fn outputs(&self) -> usize {
@@ -644,108 +2266,97 @@ impl DynNode for UnwrapJson {
     }
 
     fn out_kind(&self, out_pin: usize) -> ValueKind {
-        if out_pin == 0 {
-            self.kind
-        } else {
-            ValueKind::Failure
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
         }
     }
 
     fn execute(
         &mut self,
-        _ctx: &RunContext,
+        ctx: &RunContext,
         _node_id: egui_snarl::NodeId,
         inputs: Vec<Option<Value>>,
     ) -> Result<Vec<Value>, WorkflowError> {
         self.validate(&inputs)?;
-        use serde_json::Value as JVal;
 
-        let json = match &inputs[0] {
+        let input = match &inputs[0] {
             Some(Value::Json(value)) => value.clone(),
-            None => Err(WorkflowError::Required(vec![
-                "JSON input is required".into(),
-            ]))?,
+            None => Err(WorkflowError::Required(vec!["JSON input required".into()]))?,
             _ => unreachable!(),
         };
 
-        let value = match (self.kind, &*json) {
-            (ValueKind::Text, JVal::String(text)) => Value::text(text.clone()),
-            (ValueKind::TextList, JVal::Array(values)) if values.iter().all(|t| t.is_string()) => {
-                let texts = values
-                    .iter()
-                    .map(|t| t.as_str().unwrap().to_string())
-                    .collect_vec();
-                Value::text_list(texts)
-            }
-            (ValueKind::Number, JVal::Number(value)) if value.is_f64() || value.is_i64() => {
-                Value::float(value.as_f64().unwrap())
-            }
-            (ValueKind::FloatList, JVal::Array(values))
-                if values.iter().all(|t| t.is_f64() || t.is_i64()) =>
-            {
-                let values = values.iter().map(|t| t.as_f64().unwrap()).collect_vec();
-                Value::float_list(values)
-            }
-            (ValueKind::Integer, JVal::Number(value)) if value.is_i64() => {
-                Value::Integer(value.as_i64().unwrap())
-            }
-            (ValueKind::IntList, JVal::Array(values)) if values.iter().all(|t| t.is_i64()) => {
-                let values = values.iter().map(|t| t.as_i64().unwrap()).collect_vec();
-                Value::int_list(values)
-            }
-            _ => Err(WorkflowError::Conversion(format!(
-                "Could not convert {json:?} into a {:?}",
-                self.kind
-            )))?,
-        };
+        let value = self.filter(&ctx.transmuter, &input)?;
 
-        Ok(vec![value])
+        Ok(vec![
+            Value::Json(Arc::new(value)),
+            Value::Placeholder(ValueKind::Failure),
+        ])
     }
 }
 
-impl UiNode for UnwrapJson {
+impl UiNode for FilterJson {
     fn title(&self) -> &str {
-        "Unwrap JSON"
+        "Filter JSON"
     }
 
     fn tooltip(&self) -> &str {
-        "Converts a JSON value into a workflow value"
+        "Keeps the elements of a JSON array that satisfy a predicate.\n\
+            Elements the predicate cannot be applied to are dropped, \
+            or sent to the failure output in strict mode."
     }
 
-    fn has_body(&self) -> bool {
-        true
+    fn help_link(&self) -> &str {
+        "https://gedenkt.at/jaq/manual/#corelang"
     }
 
-    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
-        let old_kind = self.kind;
-        egui::ComboBox::from_id_salt("UnwrapJsonKind")
-            .selected_text(self.kind.to_string().to_ascii_lowercase())
-            .show_ui(ui, |ui| {
-                let kinds = [
-                    ValueKind::Text,
-                    ValueKind::TextList,
-                    ValueKind::Number,
-                    ValueKind::FloatList,
-                    ValueKind::Integer,
-                    ValueKind::IntList,
-                ];
-
-                for kind in kinds {
-                    let mut label = kind.to_string().to_lowercase();
-                    if kind.is_list() {
-                        label = format!("[{label}]");
-                    }
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("array");
+            }
+            _ => unreachable!(),
+        };
 
-                    ui.selectable_value(&mut self.kind, kind, label);
-                }
-            });
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
 
-        if old_kind != self.kind {
-            ctx.reset_out_pin(OutPinId {
-                node: ctx.current_node,
-                output: 0,
-            });
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("array");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
         }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            self.predicate.show(ui);
+
+            ui.checkbox(&mut self.strict, "strict")
+                .on_hover_text("Fail instead of dropping elements the predicate errors on");
+        });
     }
 }
 
@@ -756,6 +2367,16 @@ fn json_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, po
             ui.close();
         }
 
+        if ui.button("Extract JSON").clicked() {
+            snarl.insert_node(pos, ExtractJson::default().into());
+            ui.close();
+        }
+
+        if ui.button("Repair JSON").clicked() {
+            snarl.insert_node(pos, RepairJson::default().into());
+            ui.close();
+        }
+
         if ui.button("Gather JSON").clicked() {
             snarl.insert_node(pos, GatherJson::default().into());
             ui.close();
@@ -766,18 +2387,263 @@ fn json_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, po
             ui.close();
         }
 
+        if ui.button("Coerce JSON").clicked() {
+            snarl.insert_node(pos, CoerceJson::default().into());
+            ui.close();
+        }
+
         if ui.button("Transform JSON").clicked() {
             snarl.insert_node(pos, TransformJson::default().into());
             ui.close();
         }
 
+        if ui.button("Filter JSON").clicked() {
+            snarl.insert_node(pos, FilterJson::default().into());
+            ui.close();
+        }
+
         if ui.button("Unwrap JSON").clicked() {
             snarl.insert_node(pos, UnwrapJson::default().into());
             ui.close();
         }
+
+        if ui.button("Parse Structured").clicked() {
+            snarl.insert_node(pos, ParseStructured::default().into());
+            ui.close();
+        }
+
+        if ui.button("Parse CSV").clicked() {
+            snarl.insert_node(pos, ParseCsv::default().into());
+            ui.close();
+        }
+
+        if ui.button("Serialize JSON").clicked() {
+            snarl.insert_node(pos, SerializeJson::default().into());
+            ui.close();
+        }
+
+        if ui.button("Chat to Messages").clicked() {
+            snarl.insert_node(pos, ChatToMessages::default().into());
+            ui.close();
+        }
+
+        if ui.button("HTTP Request").clicked() {
+            snarl.insert_node(pos, super::HttpRequest::default().into());
+            ui.close();
+        }
     });
 }
 
 inventory::submit! {
     GraphSubmenu("json", json_node_menu)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let node = ParseCsv::default();
+        let (columns, rows) = node.parse("name,age\nbob,42\n\"smith, al\",7\n").unwrap();
+        assert_eq!(columns, vec!["name", "age"]);
+        assert_eq!(
+            json!(rows),
+            json!([{"name": "bob", "age": "42"}, {"name": "smith, al", "age": "7"}])
+        );
+
+        let node = ParseCsv {
+            header: false,
+            delimiter: r"\t".into(),
+        };
+        let (columns, rows) = node.parse("a\tb\n").unwrap();
+        assert_eq!(columns, vec!["column_1", "column_2"]);
+        assert_eq!(json!(rows), json!([{"column_1": "a", "column_2": "b"}]));
+
+        // Rows must all have the same number of fields
+        assert!(ParseCsv::default().parse("a,b\n1,2,3\n").is_err());
+
+        let node = ParseCsv {
+            delimiter: "::".into(),
+            ..Default::default()
+        };
+        assert!(node.parse("a").is_err());
+    }
+
+    #[test]
+    fn test_extract_json() {
+        let doc = json!({"foo": [{"bar": "x"}, {"bar": 2}], "empty": null});
+        let node = |path: &str| ExtractJson { path: path.into() };
+
+        assert_eq!(node("/foo/0/bar").extract(&doc).unwrap(), json!("x"));
+        assert_eq!(node("").extract(&doc).unwrap(), doc);
+        assert_eq!(node("/empty").extract(&doc).unwrap(), json!(null));
+        assert!(node("/foo/5").extract(&doc).is_err());
+
+        assert_eq!(node("$.foo[1].bar").extract(&doc).unwrap(), json!(2));
+        assert_eq!(node("$.foo[*].bar").extract(&doc).unwrap(), json!(["x", 2]));
+        assert!(node("$.missing").extract(&doc).is_err());
+        assert!(node("$.foo[").extract(&doc).is_err());
+
+        assert!(node("foo").extract(&doc).is_err());
+    }
+
+    fn node(predicate: PredicateKind, key: &str, value: &str) -> FilterJson {
+        FilterJson {
+            predicate: JsonPredicate {
+                predicate,
+                key: key.into(),
+                value: value.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_coerce_json() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "count": {"type": "integer"},
+                "ratio": {"type": "number"},
+                "done": {"type": "boolean"},
+                "label": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "inner": {
+                    "type": "object",
+                    "properties": {"size": {"type": "number"}, "ok": {"type": "boolean"}},
+                },
+            },
+            "required": ["count"],
+        });
+
+        let node = CoerceJson::default();
+        let coerced = node
+            .coerce(
+                &schema,
+                json!({
+                    "count": "5",
+                    "ratio": " 0.5 ",
+                    "done": "True",
+                    "label": 12,
+                    "tags": "solo",
+                    "inner": "{\"size\": \"3\", \"ok\": \"false\"}",
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(
+            coerced,
+            json!({
+                "count": 5,
+                "ratio": 0.5,
+                "done": true,
+                "label": "12",
+                "tags": ["solo"],
+                "inner": {"size": 3.0, "ok": false},
+            })
+        );
+
+        // Whole floats become integers, but fractions are violations
+        assert_eq!(
+            node.coerce(&schema, json!({"count": 2.0})).unwrap(),
+            json!({"count": 2})
+        );
+        let err = node.coerce(&schema, json!({"count": "2.5"})).unwrap_err();
+        assert!(err.to_string().contains("/count"));
+    }
+
+    #[test]
+    fn test_filter_predicates() {
+        let transmuter = Transmuter::default();
+        let input = json!([{"a": 1, "b": "x"}, {"a": 5}, {"b": "y"}, 3]);
+
+        let exists = node(PredicateKind::Exists, "b", "");
+        assert_eq!(
+            exists.filter(&transmuter, &input).unwrap(),
+            json!([{"a": 1, "b": "x"}, {"b": "y"}])
+        );
+
+        let equals = node(PredicateKind::Equals, "b", "y");
+        assert_eq!(
+            equals.filter(&transmuter, &input).unwrap(),
+            json!([{"b": "y"}])
+        );
+
+        let compare = node(PredicateKind::Compare, "/a", "2");
+        assert_eq!(
+            compare.filter(&transmuter, &input).unwrap(),
+            json!([{"a": 5}])
+        );
+
+        let expression = FilterJson {
+            predicate: JsonPredicate {
+                predicate: PredicateKind::Expression,
+                expression: ".a == 1".into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            expression.filter(&transmuter, &input).unwrap(),
+            json!([{"a": 1, "b": "x"}])
+        );
+    }
+
+    #[test]
+    fn test_filter_strict() {
+        let transmuter = Transmuter::default();
+        let input = json!([{"a": 1}, {"a": "one"}]);
+
+        let mut compare = node(PredicateKind::Compare, "a", "0");
+        assert_eq!(
+            compare.filter(&transmuter, &input).unwrap(),
+            json!([{"a": 1}])
+        );
+
+        compare.strict = true;
+        assert!(compare.filter(&transmuter, &input).is_err());
+        assert!(compare.filter(&transmuter, &json!({"a": 1})).is_err());
+    }
+
+    #[test]
+    fn test_chat_to_messages() -> anyhow::Result<()> {
+        use crate::{ChatContent, rig::OneOrMany};
+
+        let history = ChatHistory::default();
+        let history = history.extend([
+            ChatContent::Message(Message::user("What's the weather?")),
+            ChatContent::Message(Message::Assistant {
+                id: None,
+                content: OneOrMany::many([
+                    AssistantContent::text("Let me check."),
+                    AssistantContent::tool_call("call_1", "weather", json!({"city": "Paris"})),
+                ])?,
+            }),
+            ChatContent::Message(Message::tool_result("call_1", "sunny")),
+            ChatContent::Message(Message::assistant("It's sunny.")),
+        ])?;
+
+        let messages = chat_to_messages(&history);
+        assert_eq!(
+            messages,
+            json!([
+                {"role": "user", "content": "What's the weather?"},
+                {
+                    "role": "assistant",
+                    "content": "Let me check.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": {"name": "weather", "arguments": "{\"city\":\"Paris\"}"},
+                    }],
+                },
+                {"role": "tool", "tool_call_id": "call_1", "content": "sunny"},
+                {"role": "assistant", "content": "It's sunny."},
+            ])
+        );
+
+        Ok(())
+    }
+}
@@ -1,16 +1,25 @@
 use std::{
     borrow::Cow,
-    sync::{Arc, LazyLock},
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
 };
 
+use cached::proc_macro::cached;
+use itertools::Itertools as _;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_with::skip_serializing_none;
+use tiktoken_rs::CoreBPE;
+
+use egui_phosphor::regular::{MINUS, PLUS};
 
 use crate::{
-    ui::{resizable_frame, shortcuts::squelch},
+    ui::{AppEvent, resizable_frame, shortcuts::squelch},
     utils::{message_party, message_text},
-    workflow::{DynNode, EditContext, FlexNode, RunContext, UiNode, Value, WorkflowError},
+    workflow::{
+        AnyPin, DynNode, EditContext, FlexNode, GraphId, RunContext, UiNode, Value, WorkflowError,
+    },
 };
 
 use super::ValueKind;
@@ -87,6 +96,10 @@ pub struct TemplateNode {
 impl FlexNode for TemplateNode {}
 
 impl DynNode for TemplateNode {
+    fn cacheable(&self) -> bool {
+        true
+    }
+
     fn inputs(&self) -> usize {
         2
     }
@@ -123,7 +136,6 @@ impl DynNode for TemplateNode {
         _node_id: egui_snarl::NodeId,
         inputs: Vec<Option<Value>>,
     ) -> Result<Vec<Value>, WorkflowError> {
-        use itertools::Itertools as _;
         self.validate(&inputs)?;
 
         let template = match &inputs[0] {
@@ -272,3 +284,1441 @@ impl UiNode for EnvironmentNode {
         "Gets the current set of environment variables"
     }
 }
+
+/// Reads a single environment variable, e.g. an API key kept out of the workflow file
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvVar {
+    /// Only the name is saved, never the value
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+}
+
+impl EnvVar {
+    pub fn read(&self) -> Result<String, WorkflowError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            Err(WorkflowError::Required(vec![
+                "Variable name is required".into(),
+            ]))?;
+        }
+
+        std::env::var(name).map_err(|err| match err {
+            std::env::VarError::NotPresent => {
+                WorkflowError::Required(vec![format!("Environment variable {name} is not set")])
+            }
+            std::env::VarError::NotUnicode(_) => WorkflowError::Conversion(format!(
+                "Environment variable {name} is not valid unicode"
+            )),
+        })
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for EnvVar {}
+
+impl DynNode for EnvVar {
+    fn inputs(&self) -> usize {
+        0
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        _inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        Ok(vec![
+            Value::text(self.read()?),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for EnvVar {
+    fn title(&self) -> &str {
+        "Env Var"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Gets the value of an environment variable when the workflow runs.\n\
+            Only the variable name is saved with the workflow, so secrets stay out of it.\n\
+            Unset variables go to the failure output."
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("value");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        let widget = egui::TextEdit::singleline(&mut self.name)
+            .font(egui::TextStyle::Monospace)
+            .desired_width(120.0)
+            .hint_text("VARIABLE_NAME");
+        squelch(ui.add(widget));
+    }
+}
+
+/// A fenced code block found in markdown
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeBlock {
+    pub lang: String,
+    pub code: String,
+}
+
+/// Collects fenced code blocks from markdown text, in order of appearance.
+///
+/// The language is the first word of the fence's info string, e.g. `rust` in ` ```rust,ignore `.
+pub fn code_blocks(markdown: &str) -> Vec<CodeBlock> {
+    use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+    let mut blocks = vec![];
+    let mut current: Option<CodeBlock> = None;
+
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let lang = info
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .next()
+                    .unwrap_or_default();
+
+                current = Some(CodeBlock {
+                    lang: lang.to_string(),
+                    code: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(block) = &mut current {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some(block) = current.take() {
+                    blocks.push(block);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+/// Pulls fenced code blocks out of markdown
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractCode {
+    /// Only keep blocks in this language. Empty keeps all blocks.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub lang: String,
+}
+
+impl ExtractCode {
+    pub fn extract(&self, markdown: &str, lang: &str) -> Vec<CodeBlock> {
+        let lang = lang.trim();
+        code_blocks(markdown)
+            .into_iter()
+            .filter(|block| lang.is_empty() || block.lang.eq_ignore_ascii_case(lang))
+            .collect()
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for ExtractCode {}
+
+impl DynNode for ExtractCode {
+    fn inputs(&self) -> usize {
+        2
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            1 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Text,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let markdown = match &inputs[0] {
+            Some(Value::Text(text)) => text.clone(),
+            None => Err(WorkflowError::Required(vec![
+                "Markdown input required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        let lang = match &inputs[1] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => self.lang.as_str(),
+            _ => unreachable!(),
+        };
+
+        let blocks = self.extract(&markdown, lang);
+        let Some(first) = blocks.first() else {
+            return Err(WorkflowError::Conversion(if lang.is_empty() {
+                "No fenced code blocks found".into()
+            } else {
+                format!("No fenced {lang} code blocks found")
+            }));
+        };
+
+        Ok(vec![
+            Value::Json(Arc::new(json!(blocks))),
+            Value::text(first.code.clone()),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for ExtractCode {
+    fn title(&self) -> &str {
+        "Extract Code"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Finds fenced code blocks in markdown.\n\
+            Outputs every block as a list of {lang, code} objects, and the code of the first one.\n\
+            Set a language to only keep blocks tagged with it.\n\
+            If no block matches, the error goes to the failure output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("markdown");
+            }
+            1 => {
+                if remote.is_none() {
+                    let widget = egui::TextEdit::singleline(&mut self.lang)
+                        .desired_width(80.0)
+                        .hint_text("any language");
+                    squelch(ui.add(widget));
+                } else {
+                    ui.label("language");
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("blocks");
+            }
+            1 => {
+                ui.label("first");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+#[cached(size = 64, result = true)]
+fn compile_regex(pattern: String) -> Result<Regex, regex::Error> {
+    Regex::new(&pattern)
+}
+
+/// Pulls substrings matching a regular expression out of text
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegexExtract {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pattern: String,
+}
+
+impl RegexExtract {
+    /// Every match in order, reduced to its capture groups when the pattern has any.
+    ///
+    /// Patterns with a single group yield that group's text, while several groups yield an array.
+    pub fn extract(&self, text: &str) -> Result<Vec<serde_json::Value>, WorkflowError> {
+        let rx = compile_regex(self.pattern.clone())
+            .map_err(|err| WorkflowError::Conversion(format!("Invalid pattern: {err}")))?;
+
+        let text_of = |m: Option<regex::Match>| match m {
+            Some(m) => json!(m.as_str()),
+            None => serde_json::Value::Null,
+        };
+
+        Ok(rx
+            .captures_iter(text)
+            .map(|caps| match caps.len() {
+                1 => text_of(caps.get(0)),
+                2 => text_of(caps.get(1)),
+                _ => caps.iter().skip(1).map(text_of).collect(),
+            })
+            .collect())
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for RegexExtract {}
+
+impl DynNode for RegexExtract {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            1 => ValueKind::Json,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.clone(),
+            None => Err(WorkflowError::Required(vec!["Text input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        if self.pattern.is_empty() {
+            Err(WorkflowError::Required(vec!["Pattern is required".into()]))?;
+        }
+
+        let matches = self.extract(&text)?;
+        let Some(first) = matches.first() else {
+            return Err(WorkflowError::Conversion(format!(
+                "No matches for /{}/",
+                self.pattern
+            )));
+        };
+
+        let first = match first {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Array(groups) => match groups.first() {
+                Some(serde_json::Value::String(text)) => text.clone(),
+                _ => String::new(),
+            },
+            _ => String::new(),
+        };
+
+        Ok(vec![
+            Value::text(first),
+            Value::Json(Arc::new(serde_json::Value::Array(matches))),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for RegexExtract {
+    fn title(&self) -> &str {
+        "Regex Extract"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Finds matches of a regular expression in the text.\n\
+            Outputs the first capture group of the first match, and a list of every match.\n\
+            Invalid patterns and text without matches go to the failure output."
+    }
+
+    fn help_link(&self) -> &str {
+        "https://docs.rs/regex/latest/regex/#syntax"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("first");
+            }
+            1 => {
+                ui.label("matches");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        let widget = egui::TextEdit::singleline(&mut self.pattern)
+            .font(egui::TextStyle::Monospace)
+            .hint_text("pattern");
+        let resp = squelch(ui.add(widget));
+
+        if let Err(err) = compile_regex(self.pattern.clone()) {
+            resp.on_hover_text(err.to_string());
+            ui.colored_label(ui.visuals().error_fg_color, "invalid pattern");
+        }
+    }
+}
+
+/// Joins several pieces of text with a separator
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Concat {
+    pub count: usize,
+
+    /// Placed between pieces, with `\n` and `\t` standing for newlines and tabs
+    pub separator: String,
+}
+
+impl Default for Concat {
+    fn default() -> Self {
+        Self {
+            count: 2,
+            separator: Default::default(),
+        }
+    }
+}
+
+impl Concat {
+    pub fn join<'a>(&self, parts: impl IntoIterator<Item = &'a str>) -> String {
+        let separator = self.separator.replace("\\n", "\n").replace("\\t", "\t");
+        parts.into_iter().join(&separator)
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for Concat {}
+
+impl DynNode for Concat {
+    fn inputs(&self) -> usize {
+        self.count
+    }
+
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(&[ValueKind::Text])
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        // Unconnected pins are skipped rather than leaving doubled separators
+        let parts = inputs
+            .iter()
+            .flatten()
+            .map(|value| match value {
+                Value::Text(text) => text.as_str(),
+                _ => unreachable!(),
+            })
+            .collect_vec();
+
+        if parts.is_empty() {
+            Err(WorkflowError::Required(vec![
+                "Concat needs at least one input".into(),
+            ]))?;
+        }
+
+        Ok(vec![Value::text(self.join(parts))])
+    }
+}
+
+impl UiNode for Concat {
+    fn title(&self) -> &str {
+        "Concat"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Joins text inputs in pin order, placing the separator between them.\n\
+            Unconnected inputs are skipped."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label(format!("#{pin_id}"));
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label("text");
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        ui.vertical(|ui| {
+            let widget = egui::TextEdit::singleline(&mut self.separator)
+                .desired_width(80.0)
+                .hint_text("separator");
+            squelch(ui.add(widget)).on_hover_text("Use \\n for a newline and \\t for a tab");
+
+            ui.horizontal(|ui| {
+                if ui.button(PLUS).on_hover_text("Add an input").clicked() {
+                    self.count += 1;
+                }
+
+                ui.add_enabled_ui(self.count > 1, |ui| {
+                    if ui
+                        .button(MINUS)
+                        .on_hover_text("Remove the last input")
+                        .clicked()
+                    {
+                        // Lets the graph drop the wire of the removed pin
+                        ctx.events.insert(AppEvent::PinRemoved(
+                            ctx.current_graph,
+                            AnyPin::input(ctx.current_node, self.count - 1),
+                        ));
+
+                        self.count -= 1;
+                    }
+                });
+            });
+        });
+    }
+}
+
+/// Where a [Split] breaks its text
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SplitDelimiter {
+    #[default]
+    Newline,
+    Comma,
+    Text,
+    Regex,
+}
+
+impl SplitDelimiter {
+    pub const ALL: [SplitDelimiter; 4] = [
+        SplitDelimiter::Newline,
+        SplitDelimiter::Comma,
+        SplitDelimiter::Text,
+        SplitDelimiter::Regex,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SplitDelimiter::Newline => "newline",
+            SplitDelimiter::Comma => "comma",
+            SplitDelimiter::Text => "text",
+            SplitDelimiter::Regex => "regex",
+        }
+    }
+
+    pub fn has_pattern(&self) -> bool {
+        matches!(self, SplitDelimiter::Text | SplitDelimiter::Regex)
+    }
+}
+
+/// Breaks text into a JSON array of pieces
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Split {
+    pub delimiter: SplitDelimiter,
+
+    /// Separator for the text and regex delimiters
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub pattern: String,
+
+    pub trim: bool,
+
+    pub drop_empty: bool,
+
+    /// Most pieces to produce, with the last holding the rest of the text. Zero for no limit.
+    pub max: usize,
+}
+
+impl Split {
+    pub fn split(&self, text: &str) -> Result<Vec<String>, WorkflowError> {
+        let limit = if self.max == 0 { usize::MAX } else { self.max };
+
+        if self.delimiter.has_pattern() && self.pattern.is_empty() {
+            Err(WorkflowError::Required(vec![
+                "Delimiter pattern is required".into(),
+            ]))?;
+        }
+
+        let pieces = match self.delimiter {
+            SplitDelimiter::Newline => text
+                .splitn(limit, '\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .collect_vec(),
+            SplitDelimiter::Comma => text.splitn(limit, ',').collect_vec(),
+            SplitDelimiter::Text => text.splitn(limit, self.pattern.as_str()).collect_vec(),
+            SplitDelimiter::Regex => compile_regex(self.pattern.clone())
+                .map_err(|err| WorkflowError::Conversion(format!("Invalid pattern: {err}")))?
+                .splitn(text, limit)
+                .collect_vec(),
+        };
+
+        Ok(pieces
+            .into_iter()
+            .map(|piece| if self.trim { piece.trim() } else { piece })
+            .filter(|piece| !(self.drop_empty && piece.is_empty()))
+            .map(String::from)
+            .collect())
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for Split {}
+
+impl DynNode for Split {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => Err(WorkflowError::Required(vec![
+                "Text to split is required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        let pieces = self.split(text)?;
+
+        Ok(vec![Value::Json(Arc::new(json!(pieces)))])
+    }
+}
+
+impl UiNode for Split {
+    fn title(&self) -> &str {
+        "Split Text"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Breaks text apart at each delimiter, outputting the pieces as a JSON array.\n\
+            Pairs with an iterative subgraph to process each piece."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("pieces");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_salt("SplitDelimiter")
+                    .selected_text(self.delimiter.label())
+                    .show_ui(ui, |ui| {
+                        for delimiter in SplitDelimiter::ALL {
+                            ui.selectable_value(&mut self.delimiter, delimiter, delimiter.label());
+                        }
+                    });
+
+                if self.delimiter.has_pattern() {
+                    let widget = egui::TextEdit::singleline(&mut self.pattern)
+                        .desired_width(80.0)
+                        .hint_text(self.delimiter.label());
+                    squelch(ui.add(widget));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("max");
+                ui.add(egui::DragValue::new(&mut self.max))
+                    .on_hover_text("Most pieces to produce. Zero for no limit.");
+            });
+
+            ui.checkbox(&mut self.trim, "trim")
+                .on_hover_text("Trim whitespace around each piece");
+
+            ui.checkbox(&mut self.drop_empty, "drop empty")
+                .on_hover_text("Leave out empty pieces, such as after a trailing delimiter");
+        });
+    }
+}
+
+/// Tidies whitespace and markdown in model output
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanText {
+    #[serde(default)]
+    pub trim: bool,
+
+    #[serde(default)]
+    pub collapse_blank: bool,
+
+    #[serde(default)]
+    pub strip_markdown: bool,
+
+    #[serde(default)]
+    pub dedent: bool,
+
+    pub size: Option<crate::utils::EVec2>,
+
+    /// Key of the last input, kept for the preview
+    #[serde(default)]
+    pub uuid: GraphId,
+}
+
+impl Default for CleanText {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            collapse_blank: true,
+            strip_markdown: false,
+            dedent: false,
+            size: None,
+            uuid: GraphId::new(),
+        }
+    }
+}
+
+impl std::hash::Hash for CleanText {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.trim.hash(state);
+        self.collapse_blank.hash(state);
+        self.strip_markdown.hash(state);
+        self.dedent.hash(state);
+        self.size.hash(state);
+    }
+}
+
+impl PartialEq for CleanText {
+    fn eq(&self, other: &Self) -> bool {
+        self.trim == other.trim
+            && self.collapse_blank == other.collapse_blank
+            && self.strip_markdown == other.strip_markdown
+            && self.dedent == other.dedent
+            && self.size == other.size
+    }
+}
+
+impl Eq for CleanText {}
+
+impl CleanText {
+    /// Applies the enabled transforms: strip markdown, dedent, collapse blank lines, then trim
+    pub fn clean(&self, text: &str) -> String {
+        let mut text = if self.strip_markdown {
+            markdown_to_plain(text)
+        } else {
+            text.to_string()
+        };
+
+        if self.dedent {
+            text = dedent(&text);
+        }
+
+        if self.collapse_blank {
+            let mut blank = false;
+            text = text
+                .lines()
+                .filter(|line| {
+                    let was_blank = blank;
+                    blank = line.trim().is_empty();
+                    !(blank && was_blank)
+                })
+                .join("\n");
+        }
+
+        if self.trim {
+            text = text
+                .lines()
+                .map(str::trim_end)
+                .join("\n")
+                .trim()
+                .to_string();
+        }
+
+        text
+    }
+}
+
+/// Renders markdown as plain text, keeping line breaks between blocks
+pub fn markdown_to_plain(markdown: &str) -> String {
+    use pulldown_cmark::{Event, Parser, TagEnd};
+
+    let mut result = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => result.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => result.push('\n'),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => {
+                if !result.ends_with('\n') {
+                    result.push('\n');
+                }
+                result.push('\n');
+            }
+            Event::End(TagEnd::Item) | Event::Rule => {
+                if !result.ends_with('\n') {
+                    result.push('\n');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+/// Removes the leading whitespace common to all non-blank lines
+pub fn dedent(text: &str) -> String {
+    let indent = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or_default();
+
+    text.lines()
+        .map(|line| line.get(indent..).unwrap_or(line.trim_start()))
+        .join("\n")
+}
+
+#[typetag::serde]
+impl FlexNode for CleanText {}
+
+impl DynNode for CleanText {
+    fn uuid(&self) -> Option<uuid::Uuid> {
+        Some(self.uuid.0)
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Text,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let text = match &inputs[0] {
+            Some(Value::Text(text)) => text.clone(),
+            None => Err(WorkflowError::Required(vec!["Text input required".into()]))?,
+            _ => unreachable!(),
+        };
+
+        ctx.previews.update(self.uuid.0, Value::Text(text.clone()));
+
+        Ok(vec![Value::text(self.clean(&text))])
+    }
+}
+
+impl UiNode for CleanText {
+    fn on_paste(&mut self) {
+        self.uuid = GraphId::new();
+    }
+
+    fn title(&self) -> &str {
+        "Clean Text"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Tidies text before passing it on.\n\
+            Can strip markdown to plain text, remove common indentation,\n\
+            collapse runs of blank lines and trim surrounding whitespace.\n\
+            The body previews the result for the last input."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("text");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut self.strip_markdown, "markdown")
+                    .on_hover_text("Strip markdown formatting");
+                ui.toggle_value(&mut self.dedent, "dedent")
+                    .on_hover_text("Remove indentation shared by all lines");
+                ui.toggle_value(&mut self.collapse_blank, "blanks")
+                    .on_hover_text("Collapse runs of blank lines into one");
+                ui.toggle_value(&mut self.trim, "trim")
+                    .on_hover_text("Trim whitespace around the text and at line ends");
+            });
+
+            let Some(Value::Text(input)) = ctx.previews.value(self.uuid.0) else {
+                ui.weak("Run the workflow to preview");
+                return;
+            };
+
+            resizable_frame(&mut self.size, ui, |ui| {
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        let preview = self.clean(&input);
+                        ui.add(egui::Label::new(egui::RichText::new(preview).monospace()).wrap());
+                    });
+            });
+        });
+    }
+}
+
+/// Loaded tokenizers by name, since building one takes a while
+static TOKENIZERS: LazyLock<Mutex<HashMap<String, Arc<CoreBPE>>>> = LazyLock::new(Default::default);
+
+/// Finds a tokenizer by encoding (e.g. cl100k_base) or by the model using it (e.g. gpt-4o)
+pub fn tokenizer(name: &str) -> Result<Arc<CoreBPE>, WorkflowError> {
+    let name = name.trim();
+    let mut tokenizers = TOKENIZERS.lock().unwrap();
+    if let Some(bpe) = tokenizers.get(name) {
+        return Ok(bpe.clone());
+    }
+
+    let bpe = match name {
+        "" | "cl100k_base" => tiktoken_rs::cl100k_base(),
+        "o200k_base" => tiktoken_rs::o200k_base(),
+        "p50k_base" => tiktoken_rs::p50k_base(),
+        "p50k_edit" => tiktoken_rs::p50k_edit(),
+        "r50k_base" => tiktoken_rs::r50k_base(),
+        model => tiktoken_rs::get_bpe_from_model(model),
+    }
+    .map_err(|err| WorkflowError::Conversion(format!("Unknown tokenizer {name}: {err}")))?;
+
+    let bpe = Arc::new(bpe);
+    tokenizers.insert(name.to_string(), bpe.clone());
+
+    Ok(bpe)
+}
+
+/// Counts the tokens of text or a chat, for keeping prompts within budget
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenCount {
+    /// Encoding or model name. Empty for cl100k_base.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub tokenizer: String,
+
+    /// Key of the last count, kept for the body label
+    #[serde(default)]
+    pub uuid: GraphId,
+}
+
+impl Default for TokenCount {
+    fn default() -> Self {
+        Self {
+            tokenizer: Default::default(),
+            uuid: GraphId::new(),
+        }
+    }
+}
+
+impl std::hash::Hash for TokenCount {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.tokenizer.hash(state);
+    }
+}
+
+impl PartialEq for TokenCount {
+    fn eq(&self, other: &Self) -> bool {
+        self.tokenizer == other.tokenizer
+    }
+}
+
+impl Eq for TokenCount {}
+
+impl TokenCount {
+    pub fn count(&self, text: &str) -> Result<usize, WorkflowError> {
+        Ok(tokenizer(&self.tokenizer)?
+            .encode_with_special_tokens(text)
+            .len())
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for TokenCount {}
+
+impl DynNode for TokenCount {
+    fn uuid(&self) -> Option<uuid::Uuid> {
+        Some(self.uuid.0)
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text, ValueKind::Chat],
+            _ => unreachable!(),
+        })
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Integer,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        // Message framing differs between providers, so only the content is counted
+        let count = match &inputs[0] {
+            Some(Value::Text(text)) => self.count(text)?,
+            Some(Value::Chat(chat)) => chat
+                .iter_msgs()
+                .map(|msg| self.count(&message_text(&msg)))
+                .sum::<Result<usize, _>>()?,
+            None => Err(WorkflowError::Required(vec![
+                "Text or chat required".into(),
+            ]))?,
+            _ => unreachable!(),
+        };
+
+        let count = Value::Integer(count as i64);
+        ctx.previews.update(self.uuid.0, count.clone());
+
+        Ok(vec![count])
+    }
+}
+
+impl UiNode for TokenCount {
+    fn on_paste(&mut self) {
+        self.uuid = GraphId::new();
+    }
+
+    fn title(&self) -> &str {
+        "Token Count"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Counts the tokens in text, or in the messages of a chat.\n\
+            The tokenizer is an encoding like cl100k_base or o200k_base,\n\
+            or the name of an OpenAI model using one.\n\
+            Counts are estimates for models with other tokenizers."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("content");
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("tokens");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        ui.vertical(|ui| {
+            let widget = egui::TextEdit::singleline(&mut self.tokenizer)
+                .desired_width(120.0)
+                .hint_text("cl100k_base");
+            squelch(ui.add(widget)).on_hover_text("Encoding or model name");
+
+            match ctx.previews.value(self.uuid.0) {
+                Some(Value::Integer(count)) => ui.label(format!("{count} tokens")),
+                _ => ui.weak("Run the workflow to count"),
+            };
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var() {
+        let node = |name: &str| EnvVar { name: name.into() };
+
+        assert_eq!(node("PATH").read().unwrap(), std::env::var("PATH").unwrap());
+        assert!(matches!(
+            node("AERIE_TEST_UNSET_VARIABLE").read(),
+            Err(WorkflowError::Required(_))
+        ));
+        assert!(node(" ").read().is_err());
+
+        let yaml = serde_yaml_ng::to_string(&node("API_KEY")).unwrap();
+        assert_eq!(yaml.trim(), "name: API_KEY");
+    }
+
+    #[test]
+    fn test_token_count() {
+        let mut node = TokenCount::default();
+        assert_eq!(node.count("hello world").unwrap(), 2);
+        assert_eq!(node.count("").unwrap(), 0);
+
+        node.tokenizer = "gpt-4o".into();
+        assert_eq!(node.count("hello world").unwrap(), 2);
+
+        node.tokenizer = "no-such-model".into();
+        assert!(node.count("hello").is_err());
+    }
+
+    #[test]
+    fn test_split() {
+        let mut node = Split::default();
+        assert_eq!(node.split("a\r\nb\n").unwrap(), vec!["a", "b", ""]);
+
+        node.drop_empty = true;
+        assert_eq!(node.split("a\r\nb\n").unwrap(), vec!["a", "b"]);
+
+        node.delimiter = SplitDelimiter::Comma;
+        node.trim = true;
+        assert_eq!(node.split("a, b ,, c").unwrap(), vec!["a", "b", "c"]);
+
+        node.max = 2;
+        assert_eq!(node.split("a, b, c").unwrap(), vec!["a", "b, c"]);
+
+        node.max = 0;
+        node.delimiter = SplitDelimiter::Regex;
+        node.pattern = r"\s*;\s*".into();
+        assert_eq!(node.split("x ; y;z").unwrap(), vec!["x", "y", "z"]);
+
+        node.pattern = "(".into();
+        assert!(node.split("x").is_err());
+
+        node.delimiter = SplitDelimiter::Text;
+        node.pattern.clear();
+        assert!(matches!(node.split("x"), Err(WorkflowError::Required(_))));
+    }
+
+    #[test]
+    fn test_concat() {
+        let mut node = Concat {
+            count: 3,
+            separator: ", ".into(),
+        };
+        let inputs = vec![Some(Value::text("a")), None, Some(Value::text("c"))];
+        assert_eq!(node.join(["a", "b"]), "a, b");
+
+        node.separator = r"\n".into();
+        assert_eq!(node.join(["a", "b"]), "a\nb");
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx = crate::workflow::testing::run_ctx(&rt);
+
+        let output = node.execute(&ctx, egui_snarl::NodeId(0), inputs).unwrap();
+        assert_eq!(output, vec![Value::text("a\nc")]);
+
+        assert!(
+            node.execute(&ctx, egui_snarl::NodeId(0), vec![None, None, None])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_regex_extract() {
+        let node = |pattern: &str| RegexExtract {
+            pattern: pattern.into(),
+        };
+        let text = "id=12, id=7 and name=bob";
+
+        assert_eq!(
+            node(r"id=\d+").extract(text).unwrap(),
+            vec![json!("id=12"), json!("id=7")]
+        );
+        assert_eq!(
+            node(r"id=(\d+)").extract(text).unwrap(),
+            vec![json!("12"), json!("7")]
+        );
+        assert_eq!(
+            node(r"(\w+)=(\d+)?").extract(text).unwrap(),
+            vec![
+                json!(["id", "12"]),
+                json!(["id", "7"]),
+                json!(["name", null])
+            ]
+        );
+
+        assert!(node(r"nothing").extract(text).unwrap().is_empty());
+        assert!(matches!(
+            node(r"(unclosed").extract(text),
+            Err(WorkflowError::Conversion(_))
+        ));
+    }
+
+    #[test]
+    fn test_extract_code() {
+        let markdown = "Here you go:\n\n\
+            ```rust,ignore\nfn main() {}\n```\n\n\
+            Some `inline` code and an indented block:\n\n    not fenced\n\n\
+            ```Python\nprint(\"```\")\n```\n\n\
+            ~~~\nplain\n~~~\n";
+
+        let blocks = code_blocks(markdown);
+        assert_eq!(
+            blocks,
+            vec![
+                CodeBlock {
+                    lang: "rust".into(),
+                    code: "fn main() {}\n".into()
+                },
+                CodeBlock {
+                    lang: "Python".into(),
+                    code: "print(\"```\")\n".into()
+                },
+                CodeBlock {
+                    lang: "".into(),
+                    code: "plain\n".into()
+                },
+            ]
+        );
+
+        let node = ExtractCode::default();
+        assert_eq!(node.extract(markdown, "python").len(), 1);
+        assert_eq!(node.extract(markdown, "").len(), 3);
+        assert!(node.extract(markdown, "go").is_empty());
+    }
+
+    #[test]
+    fn test_clean_text() {
+        let text = "  \n    # Title\n\n\n\n    Some *emphasis*   \n      and `code`\n\n";
+
+        let node = CleanText::default();
+        assert_eq!(
+            node.clean(text),
+            "# Title\n\n    Some *emphasis*\n      and `code`"
+        );
+
+        let node = CleanText {
+            dedent: true,
+            ..Default::default()
+        };
+        assert_eq!(node.clean(text), "# Title\n\nSome *emphasis*\n  and `code`");
+
+        let node = CleanText {
+            strip_markdown: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            node.clean("# Title\n\nSome *emphasis*\nand `code`\n\n- one\n- two\n"),
+            "Title\n\nSome emphasis\nand code\n\none\ntwo"
+        );
+    }
+}
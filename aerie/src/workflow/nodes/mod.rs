@@ -9,17 +9,28 @@ use std::{
 
 pub mod agent;
 pub mod chat;
+pub mod clipboard;
+pub mod encoding;
+pub mod files;
+pub mod flow;
 pub mod history;
+pub mod http;
 pub mod json;
 pub mod misc;
 pub mod primatives;
 pub mod scaffold;
 pub mod scripting;
+pub mod search;
 pub mod subgraph;
 
 pub use agent::*;
 pub use chat::*;
+pub use clipboard::*;
+pub use encoding::*;
+pub use files::*;
+pub use flow::*;
 pub use history::*;
+pub use http::*;
 pub use json::*;
 pub use misc::*;
 pub use primatives::*;
@@ -128,6 +139,12 @@ impl WorkNode {
     pub fn is_eager(&self) -> bool {
         self.0.as_ref().downcast_ref::<Select>().is_some()
     }
+
+    /// Whether the node must wait for non-placeholder values on all of its inputs
+    #[inline]
+    pub fn is_barrier(&self) -> bool {
+        self.0.as_ref().downcast_ref::<Join>().is_some()
+    }
 }
 
 pub struct GraphSubmenu(
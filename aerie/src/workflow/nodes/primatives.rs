@@ -4,6 +4,8 @@ use decorum::E64;
 use egui::RichText;
 use egui_commonmark::CommonMarkCache;
 use egui_phosphor::regular::{BRACKETS_SQUARE, NUMPAD};
+use evalexpr::{ContextWithMutableVariables as _, DefaultNumericTypes, HashMapContext};
+use itertools::Itertools as _;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
@@ -234,6 +236,363 @@ impl UiNode for Number {
     }
 }
 
+/// What a [`Random`] node produces
+#[derive(Debug, Clone, Copy, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RandomKind {
+    /// A number within a range
+    #[default]
+    Number,
+
+    /// One line picked from a list
+    Choice,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Random {
+    pub kind: RandomKind,
+
+    pub min: E64,
+    pub max: E64,
+
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub integer: bool,
+
+    /// Candidates for a choice, one per line
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub choices: String,
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self {
+            kind: Default::default(),
+            min: E64::assert(0.0),
+            max: E64::assert(1.0),
+            integer: false,
+            choices: Default::default(),
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for Random {}
+
+impl Random {
+    /// Draws a value from the generator according to the node's settings
+    pub fn sample(&self, rng: &mut fastrand::Rng) -> Result<Value, WorkflowError> {
+        let (min, max) = (self.min.into_inner(), self.max.into_inner());
+        if min > max {
+            Err(WorkflowError::Conversion(format!(
+                "Minimum {min} is greater than maximum {max}"
+            )))?;
+        }
+
+        Ok(match self.kind {
+            RandomKind::Number if self.integer => {
+                let (low, high) = (min.ceil(), max.floor());
+                if low > high {
+                    Err(WorkflowError::Conversion(format!(
+                        "No integer lies between {min} and {max}"
+                    )))?;
+                }
+
+                Value::Integer(rng.i64(low as i64..=high as i64))
+            }
+            RandomKind::Number => Value::float(min + rng.f64() * (max - min)),
+            RandomKind::Choice => {
+                let choices = self
+                    .choices
+                    .lines()
+                    .map(str::trim)
+                    .filter(|it| !it.is_empty())
+                    .collect::<Vec<_>>();
+
+                if choices.is_empty() {
+                    Err(WorkflowError::Required(vec![
+                        "At least one choice is required".into(),
+                    ]))?;
+                }
+
+                Value::text(choices[rng.usize(..choices.len())])
+            }
+        })
+    }
+}
+
+impl DynNode for Random {
+    fn inputs(&self) -> usize {
+        0
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        match self.kind {
+            RandomKind::Number if self.integer => ValueKind::Integer,
+            RandomKind::Number => ValueKind::Number,
+            RandomKind::Choice => ValueKind::Text,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let mut rng = ctx.rng(node_id);
+        Ok(vec![self.sample(&mut rng)?])
+    }
+}
+
+impl UiNode for Random {
+    fn title(&self) -> &str {
+        "Random"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Produces a random number or picks a line from a list.\n\
+        When a seed is set in the settings, every run produces the same values."
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.kind, RandomKind::Number, "number");
+                ui.selectable_value(&mut self.kind, RandomKind::Choice, "choice");
+            });
+
+            match self.kind {
+                RandomKind::Number => {
+                    ui.horizontal(|ui| {
+                        let mut min = self.min.into_inner();
+                        let mut max = self.max.into_inner();
+                        ui.add(
+                            egui::DragValue::new(&mut min)
+                                .speed(0.1)
+                                .update_while_editing(false),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut max)
+                                .speed(0.1)
+                                .update_while_editing(false),
+                        );
+                        self.min = E64::assert(min);
+                        self.max = E64::assert(max.max(min));
+
+                        ui.toggle_value(&mut self.integer, NUMPAD)
+                            .on_hover_text("integer value");
+                    });
+                }
+                RandomKind::Choice => {
+                    let widget = egui::TextEdit::multiline(&mut self.choices)
+                        .desired_rows(4)
+                        .hint_text("One choice per line");
+                    squelch(ui.add(widget));
+                }
+            }
+        });
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label("value");
+        self.out_kind(pin_id).default_pin()
+    }
+}
+
+/// Names of the inputs of a [Math] node, in pin order
+pub const MATH_VARIABLES: [&str; 3] = ["a", "b", "c"];
+
+/// Evaluates an arithmetic expression over up to three numbers
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Math {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub expression: String,
+}
+
+#[typetag::serde]
+impl FlexNode for Math {}
+
+impl Math {
+    /// Parses the expression, making sure it only reads inputs that have values
+    pub fn compile(
+        &self,
+        available: &[bool],
+    ) -> Result<evalexpr::Node<DefaultNumericTypes>, WorkflowError> {
+        if self.expression.trim().is_empty() {
+            Err(WorkflowError::Required(vec![
+                "Expression is required".into(),
+            ]))?;
+        }
+
+        let tree = evalexpr::build_operator_tree::<DefaultNumericTypes>(&self.expression)
+            .map_err(|err| WorkflowError::Conversion(format!("Invalid expression: {err}")))?;
+
+        let mut missing = vec![];
+        for name in tree.iter_variable_identifiers().unique() {
+            match MATH_VARIABLES.iter().position(|it| *it == name) {
+                Some(i) if available.get(i).copied().unwrap_or_default() => {}
+                Some(_) => missing.push(format!("Input {name} is used by the expression")),
+                None => Err(WorkflowError::Conversion(format!(
+                    "Unknown variable {name}. Only a, b and c are available"
+                )))?,
+            }
+        }
+
+        if !missing.is_empty() {
+            Err(WorkflowError::Required(missing))?;
+        }
+
+        Ok(tree)
+    }
+
+    pub fn evaluate(&self, values: &[Option<f64>]) -> Result<f64, WorkflowError> {
+        let available = values.iter().map(Option::is_some).collect_vec();
+        let tree = self.compile(&available)?;
+
+        let mut context = HashMapContext::<DefaultNumericTypes>::new();
+        for (name, value) in MATH_VARIABLES.iter().zip(values) {
+            if let Some(value) = value {
+                context
+                    .set_value(name.to_string(), evalexpr::Value::Float(*value))
+                    .map_err(|err| WorkflowError::Unknown(err.to_string()))?;
+            }
+        }
+
+        let result = tree
+            .eval_number_with_context(&context)
+            .map_err(|err| WorkflowError::Conversion(format!("Cannot evaluate: {err}")))?;
+
+        // E64 has no room for NaN, and infinities are rarely wanted downstream
+        if !result.is_finite() {
+            Err(WorkflowError::Conversion(format!(
+                "Expression evaluated to {result}"
+            )))?;
+        }
+
+        Ok(result)
+    }
+}
+
+impl DynNode for Math {
+    fn inputs(&self) -> usize {
+        MATH_VARIABLES.len()
+    }
+
+    fn in_kinds(&'_ self, _in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(&[ValueKind::Number])
+    }
+
+    fn outputs(&self) -> usize {
+        2
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Number,
+            1 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let values = inputs
+            .iter()
+            .map(|it| match it {
+                Some(Value::Number(num)) => Some(num.into_inner()),
+                None => None,
+                _ => unreachable!(),
+            })
+            .collect_vec();
+
+        Ok(vec![
+            Value::Number(E64::assert(self.evaluate(&values)?)),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl UiNode for Math {
+    fn title(&self) -> &str {
+        "Math"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Evaluates an expression over the numbers a, b and c, e.g. a * 2 + b.\n\
+            Only the inputs used by the expression need to be connected.\n\
+            Invalid expressions and results that are not finite go to the failure output."
+    }
+
+    fn help_link(&self) -> &str {
+        "https://docs.rs/evalexpr/latest/evalexpr/#features"
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label(MATH_VARIABLES[pin_id]);
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("value");
+            }
+            1 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        let widget = egui::TextEdit::singleline(&mut self.expression)
+            .font(egui::TextStyle::Monospace)
+            .hint_text("a * 2 + b");
+        squelch(ui.add(widget));
+
+        // Wiring is only known at run time, so just check the syntax and names
+        match self.compile(&[true; MATH_VARIABLES.len()]) {
+            Err(WorkflowError::Required(_)) | Ok(_) => {}
+            Err(err) => {
+                ui.colored_label(ui.visuals().error_fg_color, err.to_string());
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TextSplit {
     Lines,
@@ -256,6 +615,10 @@ pub struct Text {
 impl FlexNode for Text {}
 
 impl DynNode for Text {
+    fn cacheable(&self) -> bool {
+        true
+    }
+
     fn value(&self, _out_pin: usize) -> Value {
         Value::Text(self.value.clone())
     }
@@ -632,3 +995,117 @@ impl UiNode for Panic {
         "Aborts run if the input is non-empty"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use egui_snarl::NodeId;
+
+    use super::*;
+    use crate::workflow::testing::run_ctx;
+
+    #[test]
+    fn test_math() {
+        let node = |expression: &str| Math {
+            expression: expression.into(),
+        };
+
+        assert_eq!(
+            node("a * 2 + b").evaluate(&[Some(1.5), Some(4.0), None]),
+            Ok(7.0)
+        );
+        assert_eq!(node("a / 4").evaluate(&[Some(2.0), None, None]), Ok(0.5));
+        assert_eq!(
+            node("max(a, c)").evaluate(&[Some(2.0), None, Some(3.0)]),
+            Ok(3.0)
+        );
+
+        // Unconnected inputs are fine until the expression reads them
+        assert!(matches!(
+            node("a + c").evaluate(&[Some(1.0), Some(2.0), None]),
+            Err(WorkflowError::Required(_))
+        ));
+        assert!(matches!(
+            node("a + d").evaluate(&[Some(1.0), None, None]),
+            Err(WorkflowError::Conversion(_))
+        ));
+        assert!(node("a +").evaluate(&[Some(1.0), None, None]).is_err());
+        assert!(
+            node("a / b")
+                .evaluate(&[Some(0.0), Some(0.0), None])
+                .is_err()
+        );
+        assert!(node("").evaluate(&[None, None, None]).is_err());
+    }
+
+    #[test]
+    fn test_random_seeded() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let seeded = |seed| RunContext {
+            rng_seed: Some(seed),
+            ..run_ctx(&rt)
+        };
+        let draw = |node: &Random, ctx: &RunContext, node_id| {
+            let mut rng = ctx.rng(NodeId(node_id));
+            (0..8)
+                .map(|_| node.sample(&mut rng).unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let node = Random {
+            min: E64::assert(-2.0),
+            max: E64::assert(3.0),
+            ..Default::default()
+        };
+
+        // Runs with the same seed repeat each node's values, which differ between nodes
+        let values = draw(&node, &seeded(42), 0);
+        assert_eq!(values, draw(&node, &seeded(42), 0));
+        assert_ne!(values, draw(&node, &seeded(42), 1));
+        assert_ne!(values, draw(&node, &seeded(43), 0));
+        assert!(
+            values.iter().all(
+                |it| matches!(it, Value::Number(num) if (-2.0..3.0).contains(&num.into_inner()))
+            )
+        );
+
+        let node = Random {
+            kind: RandomKind::Choice,
+            choices: "red\n\ngreen\n blue \n".into(),
+            ..Default::default()
+        };
+        let values = draw(&node, &seeded(7), 0);
+        assert_eq!(values, draw(&node, &seeded(7), 0));
+        assert!(values.iter().all(|it| matches!(it, Value::Text(text) if ["red", "green", "blue"].contains(&text.as_str()))));
+
+        let empty = Random {
+            kind: RandomKind::Choice,
+            ..Default::default()
+        };
+        assert!(empty.sample(&mut seeded(0).rng(NodeId(0))).is_err());
+    }
+
+    #[test]
+    fn test_random_integer() {
+        let mut rng = fastrand::Rng::with_seed(0);
+        let node = |min: f64, max: f64| Random {
+            min: E64::assert(min),
+            max: E64::assert(max),
+            integer: true,
+            ..Default::default()
+        };
+
+        assert_eq!(node(0.5, 1.5).sample(&mut rng).unwrap(), Value::Integer(1));
+        assert_eq!(
+            node(-2.0, -2.0).sample(&mut rng).unwrap(),
+            Value::Integer(-2)
+        );
+
+        // A range holding no whole number fails instead of panicking
+        assert!(matches!(
+            node(0.2, 0.8).sample(&mut rng),
+            Err(WorkflowError::Conversion(_))
+        ));
+    }
+}
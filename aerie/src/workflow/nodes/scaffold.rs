@@ -1,7 +1,7 @@
 use std::{borrow::Cow, convert::identity, sync::Arc};
 
 use egui::RichText;
-use egui_phosphor::regular::{ARROW_CIRCLE_DOWN, ARROW_CIRCLE_UP, TRASH};
+use egui_phosphor::regular::{ARROW_CIRCLE_DOWN, ARROW_CIRCLE_UP, PLUS, TRASH};
 use egui_snarl::{InPinId, OutPinId};
 use im::vector;
 use itertools::Itertools;
@@ -26,6 +26,18 @@ fn root_start_fields() -> im::Vector<(String, ValueKind)> {
     ]
 }
 
+/// Kinds a root workflow parameter can take
+pub const PARAMETER_KINDS: &[ValueKind] = &[
+    ValueKind::Text,
+    ValueKind::TextList,
+    ValueKind::Number,
+    ValueKind::Integer,
+    ValueKind::Json,
+];
+
+/// Name of the parameter that receives the user's prompt when present
+pub const PROMPT_PARAMETER: &str = "prompt";
+
 fn is_default_start(value: &im::Vector<(String, ValueKind)>) -> bool {
     *value == root_start_fields()
 }
@@ -34,6 +46,19 @@ fn root_finish_fields() -> im::Vector<(String, ValueKind)> {
     vector![("conversation".into(), ValueKind::Chat)]
 }
 
+/// Kinds a root workflow result can take
+pub const RESULT_KINDS: &[ValueKind] = &[
+    ValueKind::Text,
+    ValueKind::TextList,
+    ValueKind::Number,
+    ValueKind::FloatList,
+    ValueKind::Integer,
+    ValueKind::IntList,
+    ValueKind::Json,
+    ValueKind::Message,
+    ValueKind::MsgList,
+];
+
 fn is_default_finish(value: &im::Vector<(String, ValueKind)>) -> bool {
     *value == root_finish_fields()
 }
@@ -52,6 +77,63 @@ pub struct Start {
 #[typetag::serde]
 impl FlexNode for Start {}
 
+impl Start {
+    /// Named inputs of a root workflow declared after the built-in fields
+    pub fn parameters(&self) -> impl Iterator<Item = &(String, ValueKind)> {
+        self.fields.iter().skip(root_start_fields().len())
+    }
+
+    /// The name itself if no field has it yet, otherwise the name with the first free suffix
+    pub fn unique_name(&self, name: &str) -> String {
+        let taken = |candidate: &str| self.fields.iter().any(|(n, _)| n == candidate);
+
+        (1..)
+            .map(|i| match i {
+                1 => name.to_string(),
+                _ => format!("{name}_{i}"),
+            })
+            .find(|candidate| !taken(candidate))
+            .unwrap()
+    }
+
+    /// Kind of the named parameter, failing when several parameters share the name
+    pub fn parameter_kind(&self, name: &str) -> Result<Option<ValueKind>, WorkflowError> {
+        self.parameters()
+            .filter(|(n, _)| n == name)
+            .map(|(_, kind)| *kind)
+            .at_most_one()
+            .map_err(|_| {
+                WorkflowError::Conversion(format!("Several parameters are named {name:?}"))
+            })
+    }
+
+    /// Converts text, e.g. from the command line, into a value for a parameter of this kind
+    pub fn parse_parameter(kind: ValueKind, text: &str) -> Result<Value, WorkflowError> {
+        let invalid = |err: &dyn std::fmt::Display| {
+            WorkflowError::Conversion(format!("Invalid {kind:?} parameter {text:?}: {err}"))
+        };
+
+        Ok(match kind {
+            ValueKind::Text => Value::text(text),
+            ValueKind::Number => match text.trim().parse::<f64>() {
+                Ok(num) if num.is_finite() => Value::float(num),
+                Ok(num) => Err(invalid(&num))?,
+                Err(err) => Err(invalid(&err))?,
+            },
+            ValueKind::Integer => Value::Integer(text.trim().parse().map_err(|e| invalid(&e))?),
+            ValueKind::Json => Value::Json(Arc::new(
+                serde_json::from_str(text).map_err(|e| invalid(&e))?,
+            )),
+            ValueKind::TextList => Value::text_list(
+                serde_json::from_str::<Vec<String>>(text).map_err(|e| invalid(&e))?,
+            ),
+            _ => Err(WorkflowError::Conversion(format!(
+                "Parameters cannot be of kind {kind:?}"
+            )))?,
+        })
+    }
+}
+
 impl std::hash::Hash for Start {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         "Start".hash(state);
@@ -101,13 +183,22 @@ impl UiNode for Start {
         ctx: &EditContext,
         pin_id: usize,
     ) -> egui_snarl::ui::PinInfo {
-        if ctx.edit_pin.load().as_ref() == &Some(AnyPin::output(ctx.current_node, pin_id)) {
+        // Built-in fields of the root workflow are fixed; only parameters can be edited
+        let first = if ctx.parent_id.is_none() {
+            root_start_fields().len()
+        } else {
+            0
+        };
+
+        if pin_id >= first
+            && ctx.edit_pin.load().as_ref() == &Some(AnyPin::output(ctx.current_node, pin_id))
+        {
             ui.spacing_mut().item_spacing.x = 4.0;
             let name = self.fields.get_mut(pin_id).unwrap();
             let widget = egui::TextEdit::singleline(&mut name.0).desired_width(100.0);
             let resp = squelch(ui.add(widget));
 
-            ui.add_enabled_ui(pin_id > 0, |ui| {
+            ui.add_enabled_ui(pin_id > first, |ui| {
                 if ui.button(ARROW_CIRCLE_UP).clicked() {
                     ctx.events.insert(AppEvent::SwapOutputs(
                         ctx.current_graph,
@@ -196,6 +287,18 @@ impl UiNode for Start {
 
             if resp.lost_focus() {
                 ctx.edit_pin.store(Arc::new(None));
+
+                // Parameters are looked up by name, so a rename onto another field is suffixed
+                if let Some((name, _)) = self.fields.get(pin_id)
+                    && self
+                        .fields
+                        .iter()
+                        .enumerate()
+                        .any(|(i, (n, _))| i != pin_id && n == name)
+                {
+                    let name = self.unique_name(name);
+                    self.fields[pin_id].0 = name;
+                }
             }
 
             resp.request_focus();
@@ -212,6 +315,7 @@ impl UiNode for Start {
                 .add(widget)
                 .interact(egui::Sense::click())
                 .double_clicked()
+                && pin_id >= first
             {
                 ctx.edit_pin
                     .store(Arc::new(Some(AnyPin::output(ctx.current_node, pin_id))));
@@ -226,7 +330,32 @@ impl UiNode for Start {
     }
 
     fn show_footer(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
-        if ctx.parent_id.is_some() {
+        if ctx.parent_id.is_none() {
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                ui.menu_button("+param", |ui| {
+                    for kind in PARAMETER_KINDS {
+                        let mut label = kind.to_string().to_lowercase();
+                        if kind.is_list() {
+                            label = format!("[{label}]");
+                        }
+                        if ui.button(&label).clicked() {
+                            let name = if self.parameters().any(|(n, _)| n == PROMPT_PARAMETER) {
+                                self.unique_name(&label)
+                            } else {
+                                PROMPT_PARAMETER.to_string()
+                            };
+                            self.fields.push_back((name, *kind));
+                        }
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Add a named input to the workflow.\n\
+                        Double click a parameter to rename it.\n\
+                        A parameter named \"prompt\" receives the user's prompt.",
+                );
+            });
+        } else {
             ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
                 ui.menu_button("+new", |ui| {
                     let kinds = if let Some(flavor) = ctx.flavor
@@ -264,8 +393,8 @@ impl UiNode for Start {
                             label = format!("[{label}]");
                         }
                         if ui.button(&label).clicked() {
-                            self.fields = self.fields.clone();
-                            self.fields.push_back((label, *kind));
+                            let name = self.unique_name(&label);
+                            self.fields.push_back((name, *kind));
                         }
                     }
                 });
@@ -286,6 +415,16 @@ pub struct Finish {
 #[typetag::serde]
 impl FlexNode for Finish {}
 
+impl Finish {
+    /// Named results of a root workflow declared after the built-in fields, with their pins
+    pub fn results(&self) -> impl Iterator<Item = (usize, &(String, ValueKind))> {
+        self.fields
+            .iter()
+            .enumerate()
+            .skip(root_finish_fields().len())
+    }
+}
+
 impl DynNode for Finish {
     fn priority(&self) -> usize {
         2000
@@ -335,13 +474,22 @@ impl UiNode for Finish {
         pin_id: usize,
         _remote: Option<Value>,
     ) -> egui_snarl::ui::PinInfo {
-        if ctx.edit_pin.load().as_ref() == &Some(AnyPin::output(ctx.current_node, pin_id)) {
+        // The conversation of the root workflow is fixed; only results can be edited
+        let first = if ctx.parent_id.is_none() {
+            root_finish_fields().len()
+        } else {
+            0
+        };
+
+        if pin_id >= first
+            && ctx.edit_pin.load().as_ref() == &Some(AnyPin::output(ctx.current_node, pin_id))
+        {
             ui.spacing_mut().item_spacing.x = 4.0;
             let name = self.fields.get_mut(pin_id).unwrap();
             let widget = egui::TextEdit::singleline(&mut name.0).desired_width(100.0);
             let resp = squelch(ui.add(widget));
 
-            ui.add_enabled_ui(pin_id > 0, |ui| {
+            ui.add_enabled_ui(pin_id > first, |ui| {
                 if ui.button(ARROW_CIRCLE_UP).clicked() {
                     ctx.events.insert(AppEvent::SwapInputs(
                         ctx.current_graph,
@@ -446,6 +594,7 @@ impl UiNode for Finish {
                 .add(widget)
                 .interact(egui::Sense::click())
                 .double_clicked()
+                && pin_id >= first
             {
                 ctx.edit_pin
                     .store(Arc::new(Some(AnyPin::output(ctx.current_node, pin_id))));
@@ -459,7 +608,25 @@ impl UiNode for Finish {
     }
 
     fn show_footer(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
-        if ctx.parent_id.is_some() {
+        if ctx.parent_id.is_none() {
+            ui.menu_button("+result", |ui| {
+                for kind in RESULT_KINDS {
+                    let mut label = kind.to_string().to_lowercase();
+                    if kind.is_list() {
+                        label = format!("[{label}]");
+                    }
+                    if ui.button(&label).clicked() {
+                        self.fields.push_back((label, *kind));
+                    }
+                }
+            })
+            .response
+            .on_hover_text(
+                "Add a named result to the workflow.\n\
+                    Double click a result to rename it.\n\
+                    Every result must be connected before running.",
+            );
+        } else {
             ui.menu_button("+new", |ui| {
                 // TODO: implement flattening in subgraph
                 let kinds = [
@@ -997,6 +1164,204 @@ impl UiNode for Matcher {
     }
 }
 
+/// Routes its input to the output of the case label it equals, e.g. a classifier's label
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Switch {
+    kind: ValueKind,
+
+    cases: im::Vector<String>,
+}
+
+#[typetag::serde]
+impl FlexNode for Switch {}
+
+impl Default for Switch {
+    fn default() -> Self {
+        Self {
+            kind: ValueKind::Text,
+            cases: vector![String::new()],
+        }
+    }
+}
+
+impl Switch {
+    /// Position of the first case equal to the key, ignoring case and surrounding whitespace
+    pub fn case_of(&self, key: &str) -> Option<usize> {
+        let key = key.trim();
+        self.cases
+            .iter()
+            .position(|case| case.trim().eq_ignore_ascii_case(key))
+    }
+
+    /// Places the value on the output of its case, leaving the other branches skipped
+    pub fn route(&self, value: Value) -> Result<Vec<Value>, WorkflowError> {
+        let key = match &value {
+            Value::Text(text) => text.to_string(),
+            Value::Json(json) => match json.as_ref() {
+                serde_json::Value::String(text) => text.clone(),
+                json => json.to_string(),
+            },
+            value => Err(WorkflowError::Conversion(format!(
+                "Cannot switch on {:?}",
+                value.kind()
+            )))?,
+        };
+
+        let mut result = vec![Value::Placeholder(value.kind()); self.outputs()];
+        let out_pin = self.case_of(&key).unwrap_or(self.cases.len());
+        result[out_pin] = value;
+
+        Ok(result)
+    }
+}
+
+impl DynNode for Switch {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text, ValueKind::Json],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        self.cases.len() + 1
+    }
+
+    fn out_kind(&self, _out_pin: usize) -> ValueKind {
+        self.kind
+    }
+
+    fn execute(
+        &mut self,
+        _ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        match inputs.into_iter().next().flatten() {
+            Some(value) => self.route(value),
+            None => Err(WorkflowError::Required(vec!["Input is required".into()])),
+        }
+    }
+}
+
+impl UiNode for Switch {
+    fn title(&self) -> &str {
+        "Switch"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Sends the input to the output of the case it equals, ignoring case.\n\
+            JSON strings are compared by their contents.\n\
+            If no case matches, the input goes to the default output."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                let in_kind = match &remote {
+                    Some(Value::Placeholder(kind)) => *kind,
+                    Some(value) => value.kind(),
+                    None => ValueKind::Text,
+                };
+
+                if self.kind != in_kind {
+                    self.kind = in_kind;
+                    for output in 0..self.outputs() {
+                        ctx.reset_out_pin(OutPinId {
+                            node: ctx.current_node,
+                            output,
+                        });
+                    }
+                }
+
+                ui.label("input");
+            }
+            _ => unreachable!(),
+        }
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        if pin_id >= self.cases.len() {
+            ui.weak("(default)")
+                .on_hover_text("If none of the cases match, output to this pin");
+        } else {
+            let case = &self.cases[pin_id];
+            let text = if case.is_empty() {
+                RichText::new("(empty)").weak()
+            } else {
+                RichText::new(case)
+            };
+            ui.add(egui::Label::new(text).truncate());
+        }
+
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &EditContext) {
+        ui.vertical(|ui| {
+            let last = self.cases.len().saturating_sub(1);
+            let mut removed = false;
+
+            for (i, case) in self.cases.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    let widget = egui::TextEdit::singleline(case)
+                        .desired_width(120.0)
+                        .hint_text("label");
+                    squelch(ui.add(widget));
+
+                    // Only the last case can be removed so other wires stay put
+                    if i == last && i > 0 && ui.button(TRASH).clicked() {
+                        ctx.events.insert(AppEvent::PinRemoved(
+                            ctx.current_graph,
+                            AnyPin::output(ctx.current_node, i),
+                        ));
+                        removed = true;
+                    }
+                });
+            }
+
+            if removed {
+                self.cases.pop_back();
+            }
+
+            if ui.button(PLUS).on_hover_text("Add a case").clicked() {
+                ctx.events.insert(AppEvent::SwapOutputs(
+                    ctx.current_graph,
+                    OutPinId {
+                        node: ctx.current_node,
+                        output: self.cases.len(),
+                    },
+                    OutPinId {
+                        node: ctx.current_node,
+                        output: self.cases.len() + 1,
+                    },
+                ));
+
+                self.cases.push_back(Default::default());
+            }
+        });
+    }
+}
+
 // a la I/O select
 #[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Select {
@@ -1306,3 +1671,82 @@ impl UiNode for GateNode {
         self.kind.default_pin()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch() {
+        let node = Switch {
+            cases: vector!["positive".into(), "negative".into()],
+            ..Default::default()
+        };
+
+        let result = node.route(Value::text(" Negative\n")).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                Value::Placeholder(ValueKind::Text),
+                Value::text(" Negative\n"),
+                Value::Placeholder(ValueKind::Text),
+            ]
+        );
+
+        let result = node.route(Value::text("neutral")).unwrap();
+        assert_eq!(result[2], Value::text("neutral"));
+
+        let label = Value::Json(Arc::new(serde_json::json!("positive")));
+        let result = node.route(label.clone()).unwrap();
+        assert_eq!(result[0], label);
+        assert_eq!(result[1], Value::Placeholder(ValueKind::Json));
+    }
+
+    #[test]
+    fn test_parse_parameter() {
+        assert_eq!(
+            Start::parse_parameter(ValueKind::Integer, " 42 ").unwrap(),
+            Value::Integer(42)
+        );
+        assert_eq!(
+            Start::parse_parameter(ValueKind::TextList, r#"["a", "b"]"#).unwrap(),
+            Value::text_list(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            Start::parse_parameter(ValueKind::Text, "1.5").unwrap(),
+            Value::text("1.5")
+        );
+
+        assert!(Start::parse_parameter(ValueKind::Number, "NaN").is_err());
+        assert!(Start::parse_parameter(ValueKind::Integer, "1.5").is_err());
+        assert!(Start::parse_parameter(ValueKind::Json, "{").is_err());
+        assert!(Start::parse_parameter(ValueKind::Chat, "").is_err());
+    }
+
+    #[test]
+    fn test_parameter_names() {
+        let mut start = Start {
+            fields: root_start_fields(),
+        };
+        let builtin = start.fields.len();
+
+        for _ in 0..3 {
+            let name = start.unique_name("text");
+            start.fields.push_back((name, ValueKind::Text));
+        }
+
+        assert_eq!(
+            start.parameters().map(|(n, _)| n.as_str()).collect_vec(),
+            ["text", "text_2", "text_3"]
+        );
+        assert_eq!(
+            start.parameter_kind("text_2").unwrap(),
+            Some(ValueKind::Text)
+        );
+        assert_eq!(start.parameter_kind("missing").unwrap(), None);
+
+        // Older workflows may still hold duplicates, which cannot be told apart
+        start.fields[builtin + 1].0 = "text".into();
+        assert!(start.parameter_kind("text").is_err());
+    }
+}
@@ -0,0 +1,378 @@
+#![cfg(feature = "search")]
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use emberlain::search::{SearchOptions, semantic_search};
+use fastembed::{EmbeddingModel, InitOptions, TextEmbedding};
+use itertools::Itertools as _;
+use qdrant_client::{
+    Qdrant,
+    qdrant::{Condition, Filter, with_payload_selector::SelectorOptions},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::GraphSubmenu;
+use crate::{
+    config::ConfigExt as _,
+    ui::shortcuts::squelch,
+    workflow::{
+        DynNode, EditContext, FlexNode, RunContext, UiNode, Value, ValueKind, WorkNode,
+        WorkflowError,
+    },
+};
+
+/// Payload fields of indexed snippets returned with each result
+const RESULT_FIELDS: [&str; 9] = [
+    "path",
+    "interface",
+    "class",
+    "name",
+    "kind",
+    "start_line",
+    "end_line",
+    "summary",
+    "body",
+];
+
+/// Loaded models by name, since loading takes a while
+static EMBEDDERS: LazyLock<Mutex<HashMap<String, Arc<Mutex<TextEmbedding>>>>> =
+    LazyLock::new(Default::default);
+
+/// Finds a supported model by its code (e.g. Xenova/all-MiniLM-L6-v2) or enum name (e.g. AllMiniLML6V2)
+pub fn embedding_model(name: &str) -> Option<EmbeddingModel> {
+    let name = name.trim().to_lowercase();
+    TextEmbedding::list_supported_models()
+        .into_iter()
+        .find(|model| {
+            model.model_code.to_lowercase().ends_with(&name)
+                || format!("{:?}", model.model).to_lowercase().ends_with(&name)
+        })
+        .map(|info| info.model)
+}
+
+fn embedder(name: &str) -> Result<Arc<Mutex<TextEmbedding>>, WorkflowError> {
+    let mut embedders = EMBEDDERS.lock().unwrap();
+    if let Some(embedder) = embedders.get(name) {
+        return Ok(embedder.clone());
+    }
+
+    let model = embedding_model(name).ok_or_else(|| {
+        WorkflowError::Conversion(format!("The embedding model '{name}' is not supported"))
+    })?;
+
+    let mut options = InitOptions::new(model);
+    if let Some(cache) = dirs::cache_dir() {
+        options = options.with_cache_dir(cache.join("fastembed"));
+    }
+
+    let embedding = TextEmbedding::try_new(options)?;
+    let embedder = Arc::new(Mutex::new(embedding));
+    embedders.insert(name.to_string(), embedder.clone());
+
+    Ok(embedder)
+}
+
+/// Joins search results into a single block of text for an agent's context
+pub fn render_context(results: &[serde_json::Value]) -> String {
+    results
+        .iter()
+        .map(|result| {
+            let payload = &result["payload"];
+            let field = |key: &str| payload[key].as_str().unwrap_or_default();
+
+            let name = [field("class"), field("name")]
+                .into_iter()
+                .filter(|it| !it.is_empty())
+                .join("::");
+
+            let location = match (payload["start_line"].as_u64(), payload["end_line"].as_u64()) {
+                (Some(start), Some(end)) => format!("{}:{start}-{end}", field("path")),
+                _ => field("path").to_string(),
+            };
+
+            let mut text = format!("## {name} ({location})\n\n");
+            if !field("summary").is_empty() {
+                text.push_str(field("summary").trim());
+                text.push_str("\n\n");
+            }
+
+            if !field("body").is_empty() {
+                text.push_str(&format!("```\n{}\n```\n", field("body").trim_end()));
+            }
+
+            text
+        })
+        .join("\n")
+}
+
+/// Semantic search over a repository indexed by emberlain
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SearchRepo {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub query: String,
+
+    /// Overrides the collection from settings
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub collection: String,
+
+    #[serde(default = "default_limit")]
+    pub limit: u64,
+}
+
+fn default_limit() -> u64 {
+    5
+}
+
+impl Default for SearchRepo {
+    fn default() -> Self {
+        Self {
+            query: Default::default(),
+            collection: Default::default(),
+            limit: default_limit(),
+        }
+    }
+}
+
+#[typetag::serde]
+impl FlexNode for SearchRepo {}
+
+impl SearchRepo {
+    pub async fn forward(
+        &self,
+        ctx: &RunContext,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let query = match &inputs[0] {
+            Some(Value::Text(text)) => text.as_str(),
+            None => self.query.as_str(),
+            _ => unreachable!(),
+        };
+
+        if query.trim().is_empty() {
+            Err(WorkflowError::Required(vec!["Query is required".into()]))?;
+        }
+
+        let (url, collection, model) = ctx.agent_factory.settings.view(|s| {
+            (
+                s.qdrant_url.clone(),
+                Some(self.collection.clone())
+                    .filter(|it| !it.is_empty())
+                    .or(s.search_collection.clone()),
+                s.embed_model.clone(),
+            )
+        });
+
+        let mut missing = vec![];
+        if collection.is_none() {
+            missing.push("Collection to search".to_string());
+        }
+        if model.is_none() {
+            missing.push("Embedding model in settings".to_string());
+        }
+        if !missing.is_empty() {
+            Err(WorkflowError::Required(missing))?;
+        }
+
+        let (collection, model) = (collection.unwrap(), model.unwrap());
+        let url = url.unwrap_or_else(|| "http://localhost:6334".into());
+
+        let client = Qdrant::from_url(&url)
+            .build()
+            .map_err(|err| WorkflowError::Provider(err.into()))?;
+
+        let opts = SearchOptions::builder()
+            .limit(self.limit.max(1))
+            .filter(Filter::must([Condition::is_empty("__removed")]))
+            .payload(SelectorOptions::Include(
+                RESULT_FIELDS.map(String::from).to_vec().into(),
+            ))
+            .build();
+
+        let embedder = embedder(&model)?;
+        let points = semantic_search(&client, &collection, &embedder, query, &opts)
+            .await
+            .map_err(WorkflowError::Provider)?;
+
+        let results = points
+            .iter()
+            .map(|point| json!({"payload": &point.payload, "score": point.score}))
+            .collect_vec();
+
+        let context = render_context(&results);
+
+        Ok(vec![
+            Value::Json(Arc::new(serde_json::Value::Array(results))),
+            Value::text(context),
+            Value::Placeholder(ValueKind::Failure),
+        ])
+    }
+}
+
+impl DynNode for SearchRepo {
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Borrowed(match in_pin {
+            0 => &[ValueKind::Text],
+            _ => unreachable!(),
+        })
+    }
+
+    fn outputs(&self) -> usize {
+        3
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match out_pin {
+            0 => ValueKind::Json,
+            1 => ValueKind::Text,
+            2 => ValueKind::Failure,
+            _ => unreachable!(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &RunContext,
+        _node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        ctx.runtime
+            .clone()
+            .block_on(ctx.cancellable(self.forward(ctx, inputs)))
+    }
+}
+
+impl UiNode for SearchRepo {
+    fn title(&self) -> &str {
+        "Search Repo"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Finds code snippets indexed by emberlain that are similar to the query.\n\
+            Outputs the results as JSON and as text to add to an agent's context.\n\
+            The qdrant server, collection and embedding model are set in the settings."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+        remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                if remote.is_none() {
+                    squelch(ui.add(egui::TextEdit::singleline(&mut self.query).hint_text("query")));
+                } else {
+                    ui.label("query");
+                }
+            }
+            _ => unreachable!(),
+        };
+
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match pin_id {
+            0 => {
+                ui.label("results");
+            }
+            1 => {
+                ui.label("context");
+            }
+            2 => {
+                ui.label("failure");
+            }
+            _ => unreachable!(),
+        }
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, _ctx: &EditContext) {
+        ui.vertical(|ui| {
+            let widget = egui::TextEdit::singleline(&mut self.collection)
+                .desired_width(120.0)
+                .hint_text("collection");
+            squelch(ui.add(widget))
+                .on_hover_text("Leave empty to use the collection from settings");
+
+            ui.horizontal(|ui| {
+                ui.label("top");
+                ui.add(egui::DragValue::new(&mut self.limit).range(1..=100));
+            });
+        });
+    }
+}
+
+fn search_node_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos: egui::Pos2) {
+    ui.menu_button("Search", |ui| {
+        if ui.button("Search Repo").clicked() {
+            snarl.insert_node(pos, SearchRepo::default().into());
+            ui.close();
+        }
+    });
+}
+
+inventory::submit! {
+    GraphSubmenu("search", search_node_menu)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_model() {
+        assert_eq!(
+            embedding_model("all-MiniLM-L6-v2"),
+            Some(EmbeddingModel::AllMiniLML6V2)
+        );
+        assert_eq!(
+            embedding_model("AllMiniLML6V2"),
+            Some(EmbeddingModel::AllMiniLML6V2)
+        );
+        assert_eq!(embedding_model("no-such-model"), None);
+    }
+
+    #[test]
+    fn test_render_context() {
+        let results = vec![
+            json!({"payload": {
+                "path": "src/config.rs",
+                "class": "Config",
+                "name": "load",
+                "start_line": 10,
+                "end_line": 12,
+                "summary": "Loads the configuration.\n",
+                "body": "fn load() {}\n",
+            }, "score": 0.9}),
+            json!({"payload": {"path": "src/main.rs", "name": "main"}, "score": 0.5}),
+        ];
+
+        assert_eq!(
+            render_context(&results),
+            "## Config::load (src/config.rs:10-12)\n\n\
+                Loads the configuration.\n\n\
+                ```\nfn load() {}\n```\n\
+                \n\
+                ## main (src/main.rs)\n\n"
+        );
+        assert_eq!(render_context(&[]), "");
+    }
+}
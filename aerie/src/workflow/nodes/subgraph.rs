@@ -1,10 +1,7 @@
-use std::{
-    borrow::Cow,
-    sync::{Arc, atomic::Ordering},
-};
+use std::{borrow::Cow, iter, sync::Arc};
 
 use egui::{Sense, UiBuilder};
-use egui_phosphor::regular::{GRAPH, LINE_SEGMENTS};
+use egui_phosphor::regular::{ARROWS_SPLIT, FUNNEL, GRAPH, LINE_SEGMENTS};
 use im::vector;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
@@ -12,10 +9,11 @@ use serde_with::skip_serializing_none;
 use serde_yaml_ng as serde_yml;
 
 use crate::{
+    config::ConfigExt as _,
     ui::AppEvent,
     workflow::{
-        DynNode, FlexNode, ShadowGraph, UiNode, Value, ValueKind, WorkNode, WorkflowError,
-        runner::WorkflowRunner,
+        DynNode, FlexNode, RootContext, ShadowGraph, UiNode, Value, ValueKind, WorkNode, Workflow,
+        WorkflowError, runner::WorkflowRunner, store::WorkflowStore as _,
     },
 };
 
@@ -28,6 +26,12 @@ pub enum Flavor {
     Simple,
 
     Iterative,
+
+    /// Runs items concurrently, gathering each output into a JSON array in input order
+    Map,
+
+    /// Folds items into the first Start/Finish pin, one run after another
+    Reduce,
 }
 
 impl Flavor {
@@ -48,9 +52,24 @@ pub struct Subgraph {
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub parallel: bool,
 
+    /// Most items of a map running at once
+    #[serde(
+        default = "default_concurrency",
+        skip_serializing_if = "is_default_concurrency"
+    )]
+    pub concurrency: usize,
+
     pub graph: ShadowGraph<WorkNode>,
 }
 
+fn default_concurrency() -> usize {
+    4
+}
+
+fn is_default_concurrency(concurrency: &usize) -> bool {
+    *concurrency == default_concurrency()
+}
+
 #[typetag::serde]
 impl FlexNode for Subgraph {}
 
@@ -63,6 +82,7 @@ impl Default for Subgraph {
             title: "Subgraph".to_string(),
             flavor: Flavor::Simple,
             parallel: false,
+            concurrency: default_concurrency(),
             graph,
         }
     }
@@ -73,6 +93,19 @@ impl Subgraph {
         Self { flavor, ..self }
     }
 
+    /// A reduce subgraph with an accumulator and item already on its Start node
+    pub fn reducer() -> Self {
+        let bytes = include_bytes!("./default_reduce.yml");
+        let graph = serde_yml::from_slice::<ShadowGraph<WorkNode>>(bytes).unwrap();
+
+        Self {
+            title: "Reduce".to_string(),
+            flavor: Flavor::Reduce,
+            graph,
+            ..Default::default()
+        }
+    }
+
     fn exec_simple(
         &mut self,
         ctx: &super::RunContext,
@@ -94,13 +127,12 @@ impl Subgraph {
             .build();
 
         exec.init(&self.graph);
-        let interrupt = ctx.interrupt.clone();
 
         let mut target = egui_snarl::Snarl::try_from(self.graph.clone())?;
         tracing::debug!("About to execute subgraph {:?}", self.graph.uuid);
 
         loop {
-            if interrupt.load(Ordering::Relaxed) {
+            if ctx.is_cancelled() {
                 break;
             }
 
@@ -151,41 +183,9 @@ impl Subgraph {
         let num_iters = if lengths.is_empty() { 1 } else { lengths[0] };
         ctx.event(AppEvent::ProgressBegin(graph_id.0, num_iters));
         for i in 0..num_iters {
-            let sliced = par_slice(&inputs, i);
-            let exec_id = ctx.exec_id.scope(self.graph.uuid, i);
-            let state_view = ctx.node_state.view(exec_id);
-            state_view.clear();
-            let mut exec = WorkflowRunner::builder()
-                .inputs(sliced)
-                .run_ctx(ctx.with_exec_id(exec_id))
-                .state_view(state_view)
-                .build();
-
-            exec.init(&self.graph);
-            let interrupt = ctx.interrupt.clone();
-
-            let mut target = egui_snarl::Snarl::try_from(self.graph.clone())?;
-            tracing::debug!("About to execute subgraph");
-
-            loop {
-                if interrupt.load(Ordering::Relaxed) {
-                    Err(WorkflowError::Interrupted)?;
-                }
-
-                match exec.step(&mut target) {
-                    Ok(false) => {
-                        break;
-                    }
-                    Ok(true) => {
-                        tracing::trace!("Stepped subgraph");
-                    }
-                    Err(err) => Err(WorkflowError::Subgraph(err))?,
-                }
-            }
+            let outputs = run_item(&ctx, &self.graph, i, par_slice(&inputs, i))?;
 
-            ctx.event(AppEvent::ProgressAdd(graph_id.0, 1));
-
-            for (res, val) in results.iter_mut().zip(exec.outputs.into_iter()) {
+            for (res, val) in results.iter_mut().zip(outputs.into_iter()) {
                 push_values(res, val);
             }
         }
@@ -222,52 +222,10 @@ impl Subgraph {
         let num_iters = if lengths.is_empty() { 1 } else { lengths[0] };
         ctx.event(AppEvent::ProgressBegin(graph_id.0, num_iters));
 
+        let graph = &self.graph;
         let all_out = (0..num_iters)
             .into_par_iter()
-            .map(|i| {
-                tracing::trace!("Initializing runner for {graph_id:?} pass {i}");
-                let sliced = par_slice(&inputs, i);
-                let exec_id = ctx.exec_id.scope(self.graph.uuid, i);
-                let state_view = ctx.node_state.view(exec_id);
-                state_view.clear();
-                let mut exec = WorkflowRunner::builder()
-                    .inputs(sliced)
-                    .run_ctx(ctx.with_exec_id(exec_id))
-                    .state_view(state_view)
-                    .build();
-
-                exec.init(&self.graph);
-                exec
-            })
-            .map(|mut exec| -> Result<Vec<Option<Value>>, WorkflowError> {
-                let interrupt = ctx.interrupt.clone();
-                tracing::trace!(
-                    "Running graph {graph_id:?} exec {:?}",
-                    exec.state_view.exec_id
-                );
-
-                let mut target = egui_snarl::Snarl::try_from(self.graph.clone())?;
-                tracing::debug!("About to execute subgraph {:?}", self.graph.uuid);
-
-                loop {
-                    if interrupt.load(Ordering::Relaxed) {
-                        Err(WorkflowError::Interrupted)?;
-                    }
-
-                    match exec.step(&mut target) {
-                        Ok(false) => {
-                            break;
-                        }
-                        Ok(true) => {
-                            tracing::trace!("Stepped subgraph");
-                        }
-                        Err(err) => Err(WorkflowError::Subgraph(err))?,
-                    }
-                }
-
-                ctx.event(AppEvent::ProgressAdd(graph_id.0, 1));
-                Ok(exec.outputs)
-            })
+            .map(|i| run_item(&ctx, graph, i, par_slice(&inputs, i)))
             .try_fold(
                 || results.clone(),
                 |mut acc: Vec<Value>, item| -> Result<_, WorkflowError> {
@@ -294,6 +252,132 @@ impl Subgraph {
         Ok(results)
     }
 
+    /// Runs the graph on every item with a bounded number in flight at once.
+    ///
+    /// Unlike [Self::par_foreach], each item contributes exactly one element to every output,
+    /// so outputs line up with the input list even when items produce arrays.
+    fn map_foreach(
+        &mut self,
+        ctx: &super::RunContext,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        use rayon::prelude::*;
+
+        let mut ctx = ctx.clone();
+        ctx.is_subgraph = true;
+
+        let graph_id = self.graph.uuid;
+
+        let lengths = input_lengths(&inputs);
+
+        if !lengths.iter().all(|s| *s == lengths[0]) {
+            Err(WorkflowError::Conversion(format!(
+                "List inputs are not the same length: {lengths:?}"
+            )))?;
+        }
+
+        // A dedicated pool caps the items in flight, e.g. to respect provider rate limits
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(self.concurrency.max(1))
+            .build()
+            .map_err(|err| WorkflowError::Unknown(format!("Could not start map workers: {err}")))?;
+
+        let num_iters = if lengths.is_empty() { 1 } else { lengths[0] };
+        ctx.event(AppEvent::ProgressBegin(graph_id.0, num_iters));
+
+        let graph = &self.graph;
+        let items = pool.install(|| {
+            (0..num_iters)
+                .into_par_iter()
+                .map(|i| run_item(&ctx, graph, i, par_slice(&inputs, i)))
+                .collect::<Result<Vec<_>, _>>()
+        });
+
+        ctx.event(AppEvent::ProgressEnd(graph_id.0));
+
+        let mut results = gather_json(items?, self.outputs() - 1)?;
+        results.push(Value::Placeholder(ValueKind::Failure));
+
+        Ok(results)
+    }
+
+    /// Runs the graph once per item, feeding the first output back into the first input.
+    ///
+    /// The first input seeds the accumulator and is never sliced, even if it is a list.
+    /// Outputs are those of the last run, so the final accumulator comes first.
+    fn exec_reduce(
+        &mut self,
+        ctx: &super::RunContext,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        let mut ctx = ctx.clone();
+        ctx.is_subgraph = true;
+
+        let graph_id = self.graph.uuid;
+
+        let items = inputs.get(1..).unwrap_or_default();
+        let lengths = input_lengths(items);
+
+        if !lengths.iter().all(|s| *s == lengths[0]) {
+            Err(WorkflowError::Conversion(format!(
+                "List inputs are not the same length: {lengths:?}"
+            )))?;
+        }
+
+        let mut acc = inputs.first().cloned().flatten();
+        let mut outputs = vec![];
+
+        let num_iters = if lengths.is_empty() { 1 } else { lengths[0] };
+        ctx.event(AppEvent::ProgressBegin(graph_id.0, num_iters));
+        for i in 0..num_iters {
+            let sliced = iter::once(acc.clone())
+                .chain(par_slice(items, i))
+                .collect_vec();
+            outputs = run_item(&ctx, &self.graph, i, sliced)?;
+
+            acc = outputs.first().cloned().flatten();
+            if acc.is_none() {
+                Err(WorkflowError::Required(vec![format!(
+                    "Accumulator output of run {i}"
+                )]))?;
+            }
+        }
+
+        ctx.event(AppEvent::ProgressEnd(graph_id.0));
+
+        let mut results = (0..self.outputs() - 1)
+            .map(|i| outputs.get(i).cloned().flatten().unwrap_or_default())
+            .collect_vec();
+
+        // No items leaves the seed untouched
+        if let (Some(first), Some(acc)) = (results.first_mut(), acc) {
+            *first = acc;
+        }
+
+        results.push(Value::Placeholder(ValueKind::Failure));
+
+        Ok(results)
+    }
+
+    /// Spreads chat histories wired to Message pins into the list of their messages
+    fn expand_chats(&self, inputs: Vec<Option<Value>>) -> Vec<Option<Value>> {
+        let Some(start) = self.graph.start_node() else {
+            return inputs;
+        };
+
+        inputs
+            .into_iter()
+            .enumerate()
+            .map(|(i, it)| match it {
+                Some(Value::Chat(history)) if start.out_kind(i) == ValueKind::Message => {
+                    let messages = history.iter_msgs().map(|msg| Arc::new(msg.into_owned()));
+                    Some(Value::MsgList(messages.collect()))
+                }
+                value => value,
+            })
+            .collect_vec()
+    }
+
     fn init_outputs(&mut self) -> Vec<Value> {
         use Value::*;
         (0..self.outputs() - 1)
@@ -309,6 +393,52 @@ impl Subgraph {
     }
 }
 
+/// Runs the graph on the inputs of a single item, returning the values reaching its Finish node
+fn run_item(
+    ctx: &super::RunContext,
+    graph: &ShadowGraph<WorkNode>,
+    i: usize,
+    inputs: Vec<Option<Value>>,
+) -> Result<Vec<Option<Value>>, WorkflowError> {
+    if ctx.is_cancelled() {
+        Err(WorkflowError::Interrupted)?;
+    }
+
+    let graph_id = graph.uuid;
+    let exec_id = ctx.exec_id.scope(graph_id, i);
+    let state_view = ctx.node_state.view(exec_id);
+    state_view.clear();
+    let mut exec = WorkflowRunner::builder()
+        .inputs(inputs)
+        .run_ctx(ctx.with_exec_id(exec_id))
+        .state_view(state_view)
+        .build();
+
+    exec.init(graph);
+
+    let mut target = egui_snarl::Snarl::try_from(graph.clone())?;
+    tracing::debug!("About to run item {i} with subgraph {graph_id:?}");
+
+    loop {
+        if ctx.is_cancelled() {
+            Err(WorkflowError::Interrupted)?;
+        }
+
+        match exec.step(&mut target) {
+            Ok(false) => {
+                break;
+            }
+            Ok(true) => {
+                tracing::trace!("Stepped subgraph");
+            }
+            Err(err) => Err(WorkflowError::Subgraph(err))?,
+        }
+    }
+
+    ctx.event(AppEvent::ProgressAdd(graph_id.0, 1));
+    Ok(exec.outputs)
+}
+
 fn input_lengths(inputs: &[Option<Value>]) -> Vec<usize> {
     use Value::*;
     inputs
@@ -344,7 +474,8 @@ impl DynNode for Subgraph {
             return Cow::Borrowed(&[]);
         };
 
-        if self.flavor.is_simple() {
+        // The accumulator of a reduction is passed whole
+        if self.flavor.is_simple() || (self.flavor == Flavor::Reduce && in_pin == 0) {
             return Cow::Owned(vec![start.out_kind(in_pin)]);
         }
 
@@ -352,7 +483,7 @@ impl DynNode for Subgraph {
             Text => Cow::Owned(vec![TextList, Text]),
             Integer => Cow::Owned(vec![IntList, Integer]),
             Number => Cow::Owned(vec![FloatList, Number]),
-            Message => Cow::Owned(vec![MsgList, Message]),
+            Message => Cow::Owned(vec![MsgList, Message, Chat]),
             kind => Cow::Owned(vec![kind]),
         }
     }
@@ -374,7 +505,9 @@ impl DynNode for Subgraph {
 
         if out_pin == finish.inputs() {
             ValueKind::Failure
-        } else if self.flavor.is_simple() {
+        } else if self.flavor == Flavor::Map {
+            Json
+        } else if self.flavor.is_simple() || self.flavor == Flavor::Reduce {
             finish.in_kinds(out_pin)[0]
         } else {
             match finish.in_kinds(out_pin)[0] {
@@ -397,8 +530,22 @@ impl DynNode for Subgraph {
 
         match &self.flavor {
             Flavor::Simple => self.exec_simple(ctx, inputs),
-            Flavor::Iterative if self.parallel => self.par_foreach(ctx, inputs),
-            Flavor::Iterative => self.ser_foreach(ctx, inputs),
+            Flavor::Iterative if self.parallel => {
+                let inputs = self.expand_chats(inputs);
+                self.par_foreach(ctx, inputs)
+            }
+            Flavor::Iterative => {
+                let inputs = self.expand_chats(inputs);
+                self.ser_foreach(ctx, inputs)
+            }
+            Flavor::Map => {
+                let inputs = self.expand_chats(inputs);
+                self.map_foreach(ctx, inputs)
+            }
+            Flavor::Reduce => {
+                let inputs = self.expand_chats(inputs);
+                self.exec_reduce(ctx, inputs)
+            }
         }
     }
 }
@@ -439,8 +586,21 @@ impl UiNode for Subgraph {
                 "Runs a workflow for every item in the input list(s).\n\
                     All input lists must have the same length.\n\
                     Any scalar values will be broadcast to each run.\n\
+                    A chat on a message input runs once per message.\n\
                     Output values will be collected into output lists."
             }
+            Flavor::Map => {
+                "Runs a workflow for every item in the input list(s), several at a time.\n\
+                    All input lists must have the same length.\n\
+                    Any scalar values will be broadcast to each run.\n\
+                    Each output is a JSON array with one entry per item, in input order."
+            }
+            Flavor::Reduce => {
+                "Runs a workflow for every item in the input list(s), one after another.\n\
+                    The first input seeds the accumulator on the first Start pin.\n\
+                    The first Finish pin becomes the accumulator of the next run.\n\
+                    Outputs are those of the last run, starting with the final accumulator."
+            }
             _ => {
                 "Contains a workflow that executes independently when this node is run.\n\
                     Double click the icon to edit the internal graph.\n\
@@ -510,6 +670,14 @@ impl UiNode for Subgraph {
                                 .label(egui::RichText::new(LINE_SEGMENTS).size(128.0))
                                 .interact(egui::Sense::click())
                                 .double_clicked(),
+                            Flavor::Map => ui
+                                .label(egui::RichText::new(ARROWS_SPLIT).size(128.0))
+                                .interact(egui::Sense::click())
+                                .double_clicked(),
+                            Flavor::Reduce => ui
+                                .label(egui::RichText::new(FUNNEL).size(128.0))
+                                .interact(egui::Sense::click())
+                                .double_clicked(),
                         }
                     })
                     .inner
@@ -524,6 +692,11 @@ impl UiNode for Subgraph {
             if self.flavor == Flavor::Iterative {
                 ui.checkbox(&mut self.parallel, "parallel");
             }
+
+            if self.flavor == Flavor::Map {
+                ui.add(egui::Slider::new(&mut self.concurrency, 1..=32).text("at once"))
+                    .on_hover_text("Most items running at the same time");
+            }
         });
     }
 }
@@ -597,6 +770,33 @@ fn concat_values(acc: &mut Value, items: Value) {
     }
 }
 
+/// Turns the outputs of each map item into one JSON array per output pin.
+///
+/// Items that produced nothing for a pin leave a null in its place.
+fn gather_json(
+    items: Vec<Vec<Option<Value>>>,
+    outputs: usize,
+) -> Result<Vec<Value>, WorkflowError> {
+    let mut arrays = vec![Vec::with_capacity(items.len()); outputs];
+
+    for item in items {
+        for (array, value) in arrays
+            .iter_mut()
+            .zip(item.into_iter().chain(iter::repeat(None)))
+        {
+            array.push(match value {
+                Some(Value::Placeholder(_)) | None => serde_json::Value::Null,
+                Some(value) => super::json::value_to_json(value)?,
+            });
+        }
+    }
+
+    Ok(arrays
+        .into_iter()
+        .map(|array| Value::Json(Arc::new(serde_json::Value::Array(array))))
+        .collect())
+}
+
 fn par_slice(inputs: &[Option<Value>], i: usize) -> Vec<Option<Value>> {
     use Value::*;
     inputs
@@ -617,6 +817,261 @@ fn par_slice(inputs: &[Option<Value>], i: usize) -> Vec<Option<Value>> {
         .collect_vec()
 }
 
+/// Runs a saved workflow by name, as a subroutine
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, Hash, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CallWorkflow {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub workflow: String,
+
+    /// Parameters of the called workflow when it was last seen in the editor
+    #[serde(default, skip_serializing_if = "im::Vector::is_empty")]
+    pub params: im::Vector<(String, ValueKind)>,
+
+    /// Results of the called workflow when it was last seen in the editor
+    #[serde(default, skip_serializing_if = "im::Vector::is_empty")]
+    pub results: im::Vector<(String, ValueKind)>,
+}
+
+#[typetag::serde]
+impl FlexNode for CallWorkflow {}
+
+impl CallWorkflow {
+    /// Matches the pins to the Start parameters and Finish results of the called workflow
+    pub fn sync_pins(&mut self, workflow: &Workflow) {
+        let params: im::Vector<_> = workflow
+            .graph
+            .start_node()
+            .map(|start| start.parameters().cloned().collect())
+            .unwrap_or_default();
+
+        let results: im::Vector<_> = workflow
+            .graph
+            .finish_node()
+            .map(|finish| finish.results().map(|(_, it)| it.clone()).collect())
+            .unwrap_or_default();
+
+        if self.params != params {
+            self.params = params;
+        }
+
+        if self.results != results {
+            self.results = results;
+        }
+    }
+
+    /// Adds the called workflow to the call stack, unless it is already running
+    pub fn enter(
+        &self,
+        call_stack: &im::Vector<String>,
+    ) -> Result<im::Vector<String>, WorkflowError> {
+        if self.workflow.is_empty() {
+            Err(WorkflowError::Required(vec!["Workflow to call".into()]))?;
+        }
+
+        if call_stack.contains(&self.workflow) {
+            let chain = call_stack
+                .iter()
+                .chain(std::iter::once(&self.workflow))
+                .join(" → ");
+            Err(WorkflowError::Recursion(chain))?;
+        }
+
+        let mut call_stack = call_stack.clone();
+        call_stack.push_back(self.workflow.clone());
+        Ok(call_stack)
+    }
+}
+
+impl DynNode for CallWorkflow {
+    fn inputs(&self) -> usize {
+        self.params.len()
+    }
+
+    fn in_kinds(&'_ self, in_pin: usize) -> Cow<'_, [ValueKind]> {
+        Cow::Owned(vec![self.params[in_pin].1])
+    }
+
+    fn outputs(&self) -> usize {
+        self.results.len() + 1
+    }
+
+    fn out_kind(&self, out_pin: usize) -> ValueKind {
+        match self.results.get(out_pin) {
+            Some((_, kind)) => *kind,
+            None => ValueKind::Failure,
+        }
+    }
+
+    fn execute(
+        &mut self,
+        ctx: &super::RunContext,
+        node_id: egui_snarl::NodeId,
+        inputs: Vec<Option<Value>>,
+    ) -> Result<Vec<Value>, WorkflowError> {
+        self.validate(&inputs)?;
+
+        let call_stack = self.enter(&ctx.call_stack)?;
+
+        // Always load the latest version, so edits to the called workflow apply everywhere
+        let Some(workflow) = ctx
+            .agent_factory
+            .store
+            .as_ref()
+            .and_then(|store| store.get(&self.workflow))
+        else {
+            Err(WorkflowError::Unknown(format!(
+                "Workflow {:?} not found",
+                self.workflow
+            )))?
+        };
+
+        // Inputs are matched to parameters by name in case the pins are out of date
+        let params = self
+            .params
+            .iter()
+            .zip(inputs)
+            .filter_map(|((name, _), value)| Some((name.clone(), value?)))
+            .collect();
+
+        let (model, temperature) = ctx
+            .agent_factory
+            .settings
+            .view(|s| (s.llm_model.clone(), s.temperature));
+
+        let root_inputs = RootContext::builder()
+            .workflow(workflow.clone())
+            .model(model)
+            .temperature(temperature)
+            .history(ctx.history.clone())
+            .params(params)
+            .build()
+            .inputs()?;
+
+        let mut ctx = ctx.clone();
+        ctx.is_subgraph = true;
+        ctx.metadata = workflow.metadata.clone();
+        ctx.call_stack = call_stack;
+
+        let exec_id = ctx.exec_id.scope(workflow.graph.uuid, node_id.0);
+        let state_view = ctx.node_state.view(exec_id);
+        state_view.clear();
+
+        let mut exec = WorkflowRunner::builder()
+            .inputs(root_inputs)
+            .run_ctx(ctx.with_exec_id(exec_id))
+            .state_view(state_view)
+            .build();
+
+        exec.init(&workflow.graph);
+
+        let mut target = egui_snarl::Snarl::try_from(workflow.graph.as_ref().clone())?;
+        tracing::debug!("About to call workflow {:?}", self.workflow);
+
+        loop {
+            if ctx.is_cancelled() {
+                Err(WorkflowError::Interrupted)?;
+            }
+
+            match exec.step(&mut target) {
+                Ok(false) => {
+                    break;
+                }
+                Ok(true) => {
+                    tracing::trace!("Stepped called workflow");
+                }
+                Err(err) => Err(WorkflowError::Subgraph(err))?,
+            }
+        }
+
+        let produced = workflow
+            .graph
+            .finish_node()
+            .into_iter()
+            .flat_map(|finish| finish.results())
+            .filter_map(|(pin, (name, _))| Some((name.clone(), exec.outputs.get(pin).cloned()??)))
+            .collect::<im::HashMap<_, _>>();
+
+        let mut results = self
+            .results
+            .iter()
+            .map(|(name, kind)| {
+                produced
+                    .get(name)
+                    .cloned()
+                    .unwrap_or(Value::Placeholder(*kind))
+            })
+            .collect_vec();
+
+        results.push(Value::Placeholder(ValueKind::Failure));
+
+        Ok(results)
+    }
+}
+
+impl UiNode for CallWorkflow {
+    fn title(&self) -> &str {
+        "Call Workflow"
+    }
+
+    fn tooltip(&self) -> &str {
+        "Runs a saved workflow and returns its results.\n\
+            Inputs are the parameters of the called workflow's Start node,\n\
+            outputs the results of its Finish node.\n\
+            A workflow cannot call itself, directly or indirectly."
+    }
+
+    fn show_input(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &super::EditContext,
+        pin_id: usize,
+        _remote: Option<Value>,
+    ) -> egui_snarl::ui::PinInfo {
+        ui.label(&self.params[pin_id].0);
+        self.in_kinds(pin_id).first().unwrap().default_pin()
+    }
+
+    fn show_output(
+        &mut self,
+        ui: &mut egui::Ui,
+        _ctx: &super::EditContext,
+        pin_id: usize,
+    ) -> egui_snarl::ui::PinInfo {
+        match self.results.get(pin_id) {
+            Some((name, _)) => ui.label(name),
+            None => ui.label("failure"),
+        };
+
+        self.out_kind(pin_id).default_pin()
+    }
+
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    fn show_body(&mut self, ui: &mut egui::Ui, ctx: &super::EditContext) {
+        let Some(store) = &ctx.store else {
+            return;
+        };
+
+        egui::ComboBox::from_id_salt(("CallWorkflow", ctx.current_node))
+            .selected_text(&self.workflow)
+            .show_ui(ui, |ui| {
+                for name in store.names().filter(|name| !name.is_empty()) {
+                    let name = name.into_owned();
+                    ui.selectable_value(&mut self.workflow, name.clone(), name);
+                }
+            });
+
+        if let Some(workflow) = store.get(&self.workflow) {
+            self.sync_pins(&workflow);
+        } else if !self.workflow.is_empty() {
+            ui.colored_label(egui::Color32::RED, "missing");
+        }
+    }
+}
+
 fn subgraph_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos: egui::Pos2) {
     ui.menu_button("Subgraph", |ui| {
         if ui.button("Simple").clicked() {
@@ -629,6 +1084,25 @@ fn subgraph_menu(ui: &mut egui::Ui, snarl: &mut egui_snarl::Snarl<WorkNode>, pos
                 Subgraph::default().with_flavor(Flavor::Iterative).into(),
             );
         }
+
+        if ui.button("Map").clicked() {
+            snarl.insert_node(
+                pos,
+                Subgraph {
+                    title: "Map".into(),
+                    ..Subgraph::default().with_flavor(Flavor::Map)
+                }
+                .into(),
+            );
+        }
+
+        if ui.button("Reduce").clicked() {
+            snarl.insert_node(pos, Subgraph::reducer().into());
+        }
+
+        if ui.button("Call Workflow").clicked() {
+            snarl.insert_node(pos, CallWorkflow::default().into());
+        }
     });
 }
 
@@ -638,7 +1112,13 @@ inventory::submit! {
 
 #[cfg(test)]
 mod tests {
+    use egui_snarl::NodeId;
+
     use super::*;
+    use crate::workflow::{
+        nodes::{Concat, Finish, Start},
+        testing::{graph, run_ctx, wire, wire_pins},
+    };
     use Value::*;
 
     #[test]
@@ -675,6 +1155,158 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expand_chats() {
+        let yaml = r#"
+nodes:
+  0:
+    value:
+      Start:
+        fields:
+          - - message
+            - Message
+          - - chat
+            - Chat
+    pos: { x: 0, y: 0 }
+    open: true
+  1:
+    value:
+      Finish:
+        fields:
+          - - output
+            - Message
+    pos: { x: 2000, y: 0 }
+    open: true
+wires: []
+start: 0
+finish: 1
+"#;
+        let node = Subgraph {
+            graph: serde_yml::from_str(yaml).unwrap(),
+            ..Subgraph::default().with_flavor(Flavor::Iterative)
+        };
+        assert!(node.in_kinds(0).contains(&ValueKind::Chat));
+
+        let history = Arc::new(
+            crate::ChatHistory::default()
+                .extend([
+                    crate::ChatContent::Message(crate::rig::message::Message::user("hello")),
+                    crate::ChatContent::Message(crate::rig::message::Message::assistant("hi")),
+                ])
+                .unwrap()
+                .into_owned(),
+        );
+
+        let inputs = vec![Some(Chat(history.clone())), Some(Chat(history.clone()))];
+        let expanded = node.expand_chats(inputs);
+
+        assert_eq!(
+            expanded[0],
+            Some(Value::msg_list([
+                crate::rig::message::Message::user("hello"),
+                crate::rig::message::Message::assistant("hi"),
+            ]))
+        );
+
+        // Chat parameters are broadcast as is
+        assert_eq!(expanded[1], Some(Chat(history)));
+        assert_eq!(input_lengths(&expanded), vec![2]);
+    }
+
+    #[test]
+    fn test_gather_json() {
+        let items = vec![
+            vec![Some(Value::text("a")), Some(Value::int_list([1, 2]))],
+            vec![Some(Value::Placeholder(ValueKind::Text)), None],
+            vec![Some(Value::text("c"))],
+        ];
+
+        assert_eq!(
+            gather_json(items, 2).unwrap(),
+            vec![
+                Json(Arc::new(serde_json::json!(["a", null, "c"]))),
+                Json(Arc::new(serde_json::json!([[1, 2], null, null]))),
+            ]
+        );
+
+        let node = Subgraph::default().with_flavor(Flavor::Map);
+        assert_eq!(node.out_kind(0), ValueKind::Json);
+        assert_eq!(node.out_kind(node.outputs() - 1), ValueKind::Failure);
+    }
+
+    #[test]
+    fn test_reducer_kinds() {
+        let node = Subgraph::reducer();
+
+        assert_eq!(node.inputs(), 2);
+        assert_eq!(node.in_kinds(0).as_ref(), &[ValueKind::Text]);
+        assert_eq!(node.in_kinds(1).as_ref(), &[ValueKind::Json]);
+        assert_eq!(node.outputs(), 2);
+        assert_eq!(node.out_kind(0), ValueKind::Text);
+        assert_eq!(node.out_kind(1), ValueKind::Failure);
+    }
+
+    fn reducer(acc: ValueKind, fold: Option<WorkNode>) -> Subgraph {
+        let fields = |it: &[(&str, ValueKind)]| {
+            it.iter()
+                .map(|(name, kind)| (name.to_string(), *kind))
+                .collect()
+        };
+        let start = Start {
+            fields: fields(&[("acc", acc), ("item", ValueKind::Text)]),
+        };
+        let finish = Finish {
+            fields: fields(&[("acc", acc)]),
+        };
+
+        // Without a folding node the accumulator passes straight through
+        let graph = match fold {
+            Some(fold) => graph(
+                [start.into(), finish.into(), fold],
+                [wire(0, 2, 0), wire_pins(0, 1, 2, 1), wire(2, 1, 0)],
+            ),
+            None => graph([start.into(), finish.into()], [wire(0, 1, 0)]),
+        };
+
+        Subgraph {
+            graph,
+            ..Subgraph::reducer()
+        }
+    }
+
+    #[test]
+    fn test_reduce_fold() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let ctx = run_ctx(&rt);
+
+        let concat = Concat {
+            count: 2,
+            separator: "".into(),
+        };
+        let mut node = reducer(ValueKind::Text, Some(concat.into()));
+
+        let inputs = vec![
+            Some(Value::text(">")),
+            Some(Value::text_list(["a", "b", "c"])),
+        ];
+        assert_eq!(
+            node.execute(&ctx, NodeId(0), inputs).unwrap(),
+            vec![Value::text(">abc"), Value::Placeholder(ValueKind::Failure)]
+        );
+
+        // A list seed is passed whole rather than sliced, even when empty
+        let mut node = reducer(ValueKind::Json, None);
+        let seed = Json(Arc::new(serde_json::json!([])));
+
+        let inputs = vec![Some(seed.clone()), Some(Value::text_list(["a", "b", "c"]))];
+        assert_eq!(
+            node.execute(&ctx, NodeId(0), inputs).unwrap(),
+            vec![seed, Value::Placeholder(ValueKind::Failure)]
+        );
+    }
+
     #[test]
     fn test_push_replace() {
         let mut acc = Value::Placeholder(ValueKind::Integer);
@@ -717,6 +1349,23 @@ mod tests {
         assert_eq!(left, Value::int_list(vec![0]));
     }
 
+    #[test]
+    fn test_call_recursion() {
+        let node = CallWorkflow {
+            workflow: "inner".into(),
+            ..Default::default()
+        };
+
+        let stack = node.enter(&im::vector!["outer".to_string()]).unwrap();
+        assert_eq!(stack, im::vector!["outer".to_string(), "inner".to_string()]);
+
+        assert_eq!(
+            node.enter(&stack),
+            Err(WorkflowError::Recursion("outer → inner → inner".into()))
+        );
+        assert!(CallWorkflow::default().enter(&stack).is_err());
+    }
+
     #[test]
     fn test_concat_more() {
         let mut left = Value::int_list(vec![24]);
@@ -1,14 +1,18 @@
 use arc_swap::ArcSwap;
 use chrono::{DateTime, Local};
+use downcast_rs::Downcast as _;
 use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
 use im::OrdSet;
 use itertools::{EitherOrBoth, Itertools};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{BTreeMap, BTreeSet, BinaryHeap},
-    hash::{DefaultHasher, Hash as _, Hasher as _},
+    hash::{DefaultHasher, Hash as _, Hasher},
     ops::Deref,
-    sync::{Arc, RwLock},
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     time::Duration,
 };
 use typed_builder::TypedBuilder;
@@ -16,7 +20,7 @@ use uuid::Uuid;
 
 use crate::workflow::{
     ShadowGraph, ValueKind, Wire, WorkflowError,
-    nodes::{Fallback, Select},
+    nodes::{Fallback, Join, Select},
 };
 
 use super::{GraphId, RunContext, Value, WorkNode};
@@ -247,6 +251,224 @@ impl<T: Ord> Ord for Prioritized<T> {
     }
 }
 
+/// Most node outputs kept at once, beyond which the least recently used are dropped
+pub const NODE_CACHE_CAPACITY: usize = 256;
+
+/// Outputs of cacheable nodes, keyed by a hash of the node and its inputs
+#[derive(Clone, Default)]
+pub struct NodeCache {
+    entries: Arc<ArcSwap<im::HashMap<u64, CacheEntry>>>,
+
+    /// Ticks on every lookup and insert, ordering entries by last use
+    clock: Arc<AtomicU64>,
+}
+
+#[derive(Clone)]
+struct CacheEntry {
+    used: u64,
+    values: Vec<Value>,
+}
+
+impl NodeCache {
+    /// Identifies a node's configuration with a set of inputs.
+    ///
+    /// None if an input cannot be hashed faithfully, in which case the node always runs.
+    pub fn key(node: &WorkNode, inputs: &[Option<Value>]) -> Option<u64> {
+        let mut s = DefaultHasher::new();
+        node.0.as_any().type_id().hash(&mut s);
+        node.hash(&mut s);
+
+        for input in inputs {
+            input.is_some().hash(&mut s);
+            if let Some(value) = input {
+                hash_value(value, &mut s)?;
+            }
+        }
+
+        Some(s.finish())
+    }
+
+    pub fn get(&self, key: u64) -> Option<Vec<Value>> {
+        let values = self.entries.load().get(&key)?.values.clone();
+
+        let used = self.clock.fetch_add(1, Ordering::Relaxed);
+        self.entries.rcu(|data| match data.get(&key) {
+            Some(entry) => data.update(
+                key,
+                CacheEntry {
+                    used,
+                    ..entry.clone()
+                },
+            ),
+            None => data.as_ref().clone(),
+        });
+
+        Some(values)
+    }
+
+    pub fn insert(&self, key: u64, values: Vec<Value>) {
+        let used = self.clock.fetch_add(1, Ordering::Relaxed);
+        self.entries.rcu(|data| {
+            let mut data = data.as_ref().clone();
+            if data.len() >= NODE_CACHE_CAPACITY && !data.contains_key(&key) {
+                let oldest = data.iter().min_by_key(|(_, entry)| entry.used);
+                if let Some((&oldest, _)) = oldest {
+                    data.remove(&oldest);
+                }
+            }
+
+            data.insert(
+                key,
+                CacheEntry {
+                    used,
+                    values: values.clone(),
+                },
+            );
+            data
+        });
+    }
+
+    pub fn clear(&self) {
+        self.entries.store(Default::default());
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.load().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.load().is_empty()
+    }
+}
+
+/// Feeds a value into a cache key, or returns None for values without a faithful hash
+fn hash_value(value: &Value, state: &mut impl Hasher) -> Option<()> {
+    std::mem::discriminant(value).hash(state);
+
+    match value {
+        Value::Placeholder(kind) => kind.hash(state),
+        Value::Failure(err) => err.to_string().hash(state),
+        Value::Text(text) => text.hash(state),
+        Value::Number(num) => num.hash(state),
+        Value::Integer(num) => num.hash(state),
+        Value::TextList(items) => items.hash(state),
+        Value::FloatList(items) => items.hash(state),
+        Value::IntList(items) => items.hash(state),
+        Value::Json(json) => json.hash(state),
+        Value::Agent(spec) => spec.hash(state),
+        Value::Tools(tools) => tools.hash(state),
+        Value::Chat(history) => history.hash(state),
+        // Messages from rig do not implement Hash
+        Value::Message(_) | Value::MsgList(_) => return None,
+    }
+
+    Some(())
+}
+
+/// Most nodes run in one concurrent pass, so a wide graph doesn't spawn a thread per node
+fn max_batch() -> usize {
+    std::thread::available_parallelism().map_or(4, usize::from)
+}
+
+/// Executes a node, reusing earlier outputs of cacheable nodes given the same inputs
+fn execute_node(
+    ctx: &RunContext,
+    node: &mut WorkNode,
+    node_id: NodeId,
+    inputs: Vec<Option<Value>>,
+) -> Result<Vec<Value>, WorkflowError> {
+    let key = if node.as_dyn().cacheable() {
+        NodeCache::key(node, &inputs)
+    } else {
+        None
+    };
+
+    if let Some(key) = key
+        && let Some(values) = ctx.cache.get(key)
+    {
+        tracing::debug!("Reusing cached outputs of {node_id:?}: {}", node.kind());
+        return Ok(values);
+    }
+
+    let values = execute_limited(ctx, node, node_id, inputs)?;
+
+    if let Some(key) = key {
+        ctx.cache.insert(key, values.clone());
+    }
+
+    Ok(values)
+}
+
+/// Executes a node, failing with [WorkflowError::Timeout] if it runs past its time limit.
+///
+/// A node that times out has its cancel flag raised and is left to wind down in the background.
+/// Its results are discarded and its successors never run.
+fn execute_limited(
+    ctx: &RunContext,
+    node: &mut WorkNode,
+    node_id: NodeId,
+    inputs: Vec<Option<Value>>,
+) -> Result<Vec<Value>, WorkflowError> {
+    let Some(seconds) = node
+        .as_dyn()
+        .timeout()
+        .or(ctx.node_timeout)
+        .filter(|it| *it > 0)
+    else {
+        return node.execute(ctx, node_id, inputs);
+    };
+
+    // The node gets its own flag, so stopping it leaves the rest of the run alone
+    let cancel = Arc::new(AtomicBool::new(false));
+    let mut detached = node.clone();
+    let task_ctx = RunContext {
+        cancel: cancel.clone(),
+        ..ctx.clone()
+    };
+    let task = ctx.runtime.spawn_blocking(move || {
+        let result = detached.execute(&task_ctx, node_id, inputs);
+        (detached, result)
+    });
+
+    let deadline = tokio::time::timeout(Duration::from_secs(seconds), task);
+    match ctx
+        .runtime
+        .block_on(ctx.cancellable(async { Ok(deadline.await) }))
+    {
+        Ok(Ok(Ok((updated, result)))) => {
+            *node = updated;
+            result
+        }
+        Ok(Ok(Err(err))) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+        Ok(Ok(Err(err))) => Err(WorkflowError::Unknown(format!(
+            "Node did not finish: {err}"
+        ))),
+        Ok(Err(_)) => {
+            tracing::warn!("Node {node_id:?} timed out after {seconds}s");
+            cancel.store(true, Ordering::Relaxed);
+            Err(WorkflowError::Timeout)
+        }
+        // An enclosing node was cancelled while this one was still running
+        Err(err) => {
+            cancel.store(true, Ordering::Relaxed);
+            Err(err)
+        }
+    }
+}
+
+/// What a running node needs to update its successors once it finishes
+struct Dispatch {
+    node_id: NodeId,
+    num_outs: usize,
+    single_out: bool,
+
+    /// Nodes wired to each output pin
+    out_remotes: Vec<BTreeSet<NodeId>>,
+
+    /// Nodes wired to the failure output
+    fail_handlers: BTreeSet<NodeId>,
+}
+
 #[derive(TypedBuilder)]
 pub struct WorkflowRunner {
     #[builder(default)]
@@ -266,6 +488,10 @@ pub struct WorkflowRunner {
     #[builder(default)]
     pub ready_nodes: BinaryHeap<Prioritized<NodeId>>,
 
+    /// Order in which nodes finished during this run
+    #[builder(default)]
+    pub arrivals: BTreeMap<NodeId, usize>,
+
     #[builder(default)]
     pub inputs: Vec<Option<Value>>,
 
@@ -391,13 +617,8 @@ impl WorkflowRunner {
                 .unwrap_or(&Default::default())
                 .values()
             {
-                if let Some(ExecState::Done(outputs)) = self.state_view.get(&remote.node)
-                    && remote.output < outputs.len()
-                {
-                    let value = &outputs[remote.output];
-                    if value.kind() != ValueKind::Placeholder {
-                        deps.remove(&remote.node);
-                    }
+                if self.is_satisfied(remote) {
+                    deps.remove(&remote.node);
                 }
             }
 
@@ -413,6 +634,45 @@ impl WorkflowRunner {
         false
     }
 
+    /// Whether the remote output pin holds a non-placeholder value
+    fn is_satisfied(&self, remote: &OutPinId) -> bool {
+        if let Some(ExecState::Done(outputs)) = self.state_view.get(&remote.node)
+            && remote.output < outputs.len()
+        {
+            outputs[remote.output].kind() != ValueKind::Placeholder
+        } else {
+            false
+        }
+    }
+
+    /// Upstream nodes that have yet to provide a value to one of the node's inputs
+    fn unsatisfied_deps(&self, node: NodeId) -> OrdSet<NodeId> {
+        self.dependencies
+            .get(&node)
+            .unwrap_or(&Default::default())
+            .values()
+            .filter(|remote| !self.is_satisfied(remote))
+            .map(|remote| remote.node)
+            .collect()
+    }
+
+    /// The input pin of the node whose upstream finished most recently
+    fn last_arrival(&self, node: NodeId) -> Option<usize> {
+        self.dependencies
+            .get(&node)?
+            .iter()
+            .max_by_key(|(_, remote)| self.arrivals.get(&remote.node))
+            .map(|(in_pin, _)| *in_pin)
+    }
+
+    /// Lets a [Join] know which of its inputs arrived last
+    fn prepare_join(&self, snarl: &mut Snarl<WorkNode>, node_id: NodeId) {
+        let arrived = self.last_arrival(node_id);
+        if let Some(join) = snarl[node_id].as_node_mut::<Join>() {
+            join.arrived = arrived;
+        }
+    }
+
     #[inline]
     fn mark_ready(&mut self, ready_node: NodeId) {
         let priority = self
@@ -438,35 +698,127 @@ impl WorkflowRunner {
     pub fn step(&mut self, snarl: &mut Snarl<WorkNode>) -> Result<bool, Arc<WorkflowError>> {
         tracing::trace!("Priority queue: {:?}", &self.ready_nodes);
 
+        if self.run_ctx.concurrent && self.ready_nodes.len() > 1 {
+            return self.step_concurrent(snarl);
+        }
+
         let Some(ready_node) = self.ready_nodes.pop() else {
-            // Nothing ready to run, halt
-            tracing::info!("No more nodes ready.");
-            self.outputs = self.outputs();
+            return self.halt();
+        };
 
-            let finish_state = self
-                .graph
-                .finish
-                .as_ref()
-                .and_then(|f| self.state_view.get(f))
-                .unwrap_or(ExecState::Waiting(Default::default()));
+        let node_id = ready_node.payload;
+        let (dispatch, inputs) = self.prepare(snarl, node_id);
+        let result = inputs
+            .and_then(|inputs| execute_node(&self.run_ctx, &mut snarl[node_id], node_id, inputs));
+        self.complete(snarl, dispatch, result)?;
 
-            tracing::trace!(
-                "Finishing {:?} with node {:?}: {:?}",
-                self.state_view.exec_id,
-                self.graph.finish,
-                self.state_view,
-                // std::backtrace::Backtrace::force_capture(),
-            );
+        Ok(true)
+    }
 
-            if !matches!(finish_state, ExecState::Done(_)) {
-                tracing::warn!("Unfinished business: {:?}", self.state_view);
-                Err(WorkflowError::Unfinished(finish_state))?;
+    /// Runs up to [max_batch] ready nodes at once, then schedules their successors for the
+    /// next pass.
+    ///
+    /// Nodes block on the runtime inside [super::DynNode::execute], so each gets its own thread.
+    /// Results are applied as they arrive and the first unhandled error is returned
+    /// once the whole batch has finished. Nodes finishing after the run is stopped count as
+    /// interrupted, so their successors never run.
+    fn step_concurrent(&mut self, snarl: &mut Snarl<WorkNode>) -> Result<bool, Arc<WorkflowError>> {
+        let batch = std::iter::from_fn(|| self.ready_nodes.pop())
+            .take(max_batch())
+            .map(|ready| ready.payload)
+            .collect_vec();
+
+        tracing::debug!("Dispatching {} nodes at once: {batch:?}", batch.len());
+
+        let mut pending = BTreeMap::new();
+        let mut jobs = Vec::with_capacity(batch.len());
+        for node_id in batch {
+            let (dispatch, inputs) = self.prepare(snarl, node_id);
+            pending.insert(node_id, dispatch);
+            jobs.push((node_id, snarl[node_id].clone(), inputs));
+        }
+
+        let ctx = self.run_ctx.clone();
+        let (tx, rx) = std::sync::mpsc::channel();
+
+        std::thread::scope(|scope| {
+            for (node_id, mut node, inputs) in jobs {
+                let tx = tx.clone();
+                let ctx = &ctx;
+                scope.spawn(move || {
+                    let result = if ctx.is_cancelled() {
+                        Err(WorkflowError::Interrupted)
+                    } else {
+                        inputs.and_then(|inputs| execute_node(ctx, &mut node, node_id, inputs))
+                    };
+
+                    let _ = tx.send((node_id, node, result));
+                });
             }
-            return Ok(false);
-        };
+            drop(tx);
 
-        let node_id = ready_node.payload;
+            let mut first_err = None;
+            for (node_id, node, result) in rx {
+                snarl[node_id] = node;
+
+                let result = result.and_then(|values| {
+                    if ctx.is_cancelled() {
+                        Err(WorkflowError::Interrupted)
+                    } else {
+                        Ok(values)
+                    }
+                });
+
+                let Some(dispatch) = pending.remove(&node_id) else {
+                    continue;
+                };
 
+                if let Err(err) = self.complete(snarl, dispatch, result) {
+                    first_err.get_or_insert(err);
+                }
+            }
+
+            match first_err {
+                Some(err) => Err(err),
+                None => Ok(true),
+            }
+        })
+    }
+
+    /// Wraps up the run once nothing is left to execute
+    fn halt(&mut self) -> Result<bool, Arc<WorkflowError>> {
+        // Nothing ready to run, halt
+        tracing::info!("No more nodes ready.");
+        self.outputs = self.outputs();
+
+        let finish_state = self
+            .graph
+            .finish
+            .as_ref()
+            .and_then(|f| self.state_view.get(f))
+            .unwrap_or(ExecState::Waiting(Default::default()));
+
+        tracing::trace!(
+            "Finishing {:?} with node {:?}: {:?}",
+            self.state_view.exec_id,
+            self.graph.finish,
+            self.state_view,
+            // std::backtrace::Backtrace::force_capture(),
+        );
+
+        if !matches!(finish_state, ExecState::Done(_)) {
+            tracing::warn!("Unfinished business: {:?}", self.state_view);
+            Err(WorkflowError::Unfinished(finish_state))?;
+        }
+        Ok(false)
+    }
+
+    /// Marks the node as running and gathers what it needs to execute
+    fn prepare(
+        &mut self,
+        snarl: &mut Snarl<WorkNode>,
+        node_id: NodeId,
+    ) -> (Dispatch, Result<Vec<Option<Value>>, WorkflowError>) {
         self.state_view.insert(node_id, ExecState::Running);
 
         tracing::debug!(
@@ -479,6 +831,7 @@ impl WorkflowRunner {
 
         let inputs = self.gather_inputs(node_id);
         let inputs = self.inject_failure(snarl, node_id, inputs);
+        self.prepare_join(snarl, node_id);
 
         // Find this node's connected failure output pin
         let out_fail = (0..num_outs).find_map(|pin| {
@@ -520,16 +873,47 @@ impl WorkflowRunner {
             })
             .collect_vec();
 
-        // When a pin outputs a placeholder, don't allow its remotes to become ready
-        let mut blacklist: BTreeSet<NodeId> = Default::default();
-
         if Some(node_id) == self.graph.finish {
             tracing::trace!("Setting graph {:?} outputs to {inputs:?}", self.graph.uuid);
             self.outputs = inputs.clone();
         }
 
         // Update run state of current node
-        let succeeded = match snarl[node_id].execute(&self.run_ctx, node_id, inputs) {
+        let inputs = self
+            .run_ctx
+            .transmuter
+            .coerce_inputs(snarl[node_id].as_dyn(), inputs);
+
+        let dispatch = Dispatch {
+            node_id,
+            num_outs,
+            single_out,
+            out_remotes,
+            fail_handlers,
+        };
+
+        (dispatch, inputs)
+    }
+
+    /// Records the result of a node and readies any successors it unblocks
+    fn complete(
+        &mut self,
+        snarl: &mut Snarl<WorkNode>,
+        dispatch: Dispatch,
+        result: Result<Vec<Value>, WorkflowError>,
+    ) -> Result<(), Arc<WorkflowError>> {
+        let Dispatch {
+            node_id,
+            num_outs,
+            single_out,
+            out_remotes,
+            fail_handlers,
+        } = dispatch;
+
+        // When a pin outputs a placeholder, don't allow its remotes to become ready
+        let mut blacklist: BTreeSet<NodeId> = Default::default();
+
+        let succeeded = match result {
             Ok(values) => {
                 for tooth in (0..num_outs).zip_longest(values.iter()) {
                     match tooth {
@@ -553,6 +937,9 @@ impl WorkflowRunner {
 
                 tracing::trace!("Values: {values:?}");
                 self.state_view.insert(node_id, ExecState::Done(values));
+
+                let arrival = self.arrivals.values().max().map_or(0, |n| n + 1);
+                self.arrivals.insert(node_id, arrival);
                 true
             }
             Err(err) => {
@@ -613,10 +1000,15 @@ impl WorkflowRunner {
             if let Some(state) = self.state_view.get(successor)
                 && let ExecState::Waiting(deps) = state
             {
-                let deps = deps
-                    .into_iter()
-                    .filter(|v| *v != node_id)
-                    .collect::<OrdSet<NodeId>>();
+                // Barriers keep waiting on any upstream that has yet to provide a value,
+                // including failed routers and placeholder outputs.
+                let deps = if snarl[*successor].is_barrier() {
+                    self.unsatisfied_deps(*successor)
+                } else {
+                    deps.into_iter()
+                        .filter(|v| *v != node_id)
+                        .collect::<OrdSet<NodeId>>()
+                };
 
                 let is_eager = snarl[*successor].is_eager();
                 let next_state = if deps.is_empty() || is_eager {
@@ -648,7 +1040,46 @@ impl WorkflowRunner {
             }
         }
 
-        Ok(true)
+        Ok(())
+    }
+
+    /// Executes a single node with the latest results of its upstream nodes.
+    ///
+    /// Expects [Self::calculate_deps] to have been called on the graph.
+    /// Unlike [Self::step], the state of successors is left untouched.
+    pub fn run_node(
+        &mut self,
+        snarl: &mut Snarl<WorkNode>,
+        node_id: NodeId,
+    ) -> Result<(), Arc<WorkflowError>> {
+        self.state_view.insert(node_id, ExecState::Running);
+
+        tracing::debug!(
+            "Executing single node {node_id:?}: {}",
+            snarl[node_id].kind()
+        );
+
+        let inputs = self.gather_inputs(node_id);
+        let inputs = self.inject_failure(snarl, node_id, inputs);
+        self.prepare_join(snarl, node_id);
+
+        match self
+            .run_ctx
+            .transmuter
+            .coerce_inputs(snarl[node_id].as_dyn(), inputs)
+            .and_then(|inputs| execute_node(&self.run_ctx, &mut snarl[node_id], node_id, inputs))
+        {
+            Ok(values) => {
+                self.state_view.insert(node_id, ExecState::Done(values));
+                Ok(())
+            }
+            Err(err) => {
+                let err = Arc::new(err);
+                self.state_view
+                    .insert(node_id, ExecState::Failed(err.clone()));
+                Err(err)
+            }
+        }
     }
 
     fn gather_inputs(&self, node_id: NodeId) -> Vec<Option<Value>> {
@@ -772,6 +1203,234 @@ impl WorkflowRunner {
             }
         }
 
+        if let Some(finish) = self.graph.finish_node() {
+            for (pin, (name, _)) in finish.results() {
+                if let Some(Some(value)) = inputs.get(pin) {
+                    ctx.outputs
+                        .sender()
+                        .send((name.clone(), value.clone()))
+                        .map_err(|err| {
+                            WorkflowError::Unknown(format!("Couldn't send result: {err:?}"))
+                        })?;
+                }
+            }
+        }
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::workflow::{
+        nodes::{Delay, Number, Text},
+        testing::{graph, run_ctx, wire},
+    };
+
+    fn join(count: usize) -> WorkNode {
+        serde_json::from_value::<Join>(json!({"count": count, "kind": "Number", "mode": "Array"}))
+            .unwrap()
+            .into()
+    }
+
+    fn delay(seconds: f64) -> WorkNode {
+        serde_json::from_value::<Delay>(json!({"kind": "Number", "seconds": seconds}))
+            .unwrap()
+            .into()
+    }
+
+    fn text(value: &str) -> WorkNode {
+        Text {
+            value: Arc::new(value.into()),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_concurrent_step() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let graph = graph(
+            [Number::default().into(), Number::default().into(), join(2)],
+            [wire(0, 2, 0), wire(1, 2, 1)],
+        );
+
+        let mut snarl = Snarl::try_from(graph.clone()).unwrap();
+        let ctx = RunContext {
+            concurrent: true,
+            ..run_ctx(&rt)
+        };
+        let mut exec = WorkflowRunner::builder().run_ctx(ctx).build();
+        exec.init(&graph);
+
+        // Both numbers run in the same pass, readying the join for the next
+        assert!(exec.step(&mut snarl).unwrap());
+        assert!(matches!(
+            exec.state_view.get(&NodeId(0)),
+            Some(ExecState::Done(_))
+        ));
+        assert!(matches!(
+            exec.state_view.get(&NodeId(1)),
+            Some(ExecState::Done(_))
+        ));
+        assert_eq!(exec.state_view.get(&NodeId(2)), Some(ExecState::Ready));
+
+        assert!(exec.step(&mut snarl).unwrap());
+        assert!(matches!(
+            exec.state_view.get(&NodeId(2)),
+            Some(ExecState::Done(_))
+        ));
+
+        // Stopping fails every node of the pass
+        let ctx = RunContext {
+            concurrent: true,
+            ..run_ctx(&rt)
+        };
+        ctx.interrupt.store(true, Ordering::Relaxed);
+        let mut exec = WorkflowRunner::builder().run_ctx(ctx).build();
+        exec.init(&graph);
+
+        assert!(matches!(
+            exec.step(&mut snarl).unwrap_err().as_ref(),
+            WorkflowError::Interrupted
+        ));
+    }
+
+    #[test]
+    fn test_concurrent_batch() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let graph = graph((0..max_batch() + 2).map(|_| Number::default().into()), []);
+
+        let mut snarl = Snarl::try_from(graph.clone()).unwrap();
+        let ctx = RunContext {
+            concurrent: true,
+            ..run_ctx(&rt)
+        };
+        let mut exec = WorkflowRunner::builder().run_ctx(ctx).build();
+        exec.init(&graph);
+
+        // A pass is capped, leaving the rest of the ready nodes queued
+        assert!(exec.step(&mut snarl).unwrap());
+        assert_eq!(exec.ready_nodes.len(), 2);
+
+        assert!(exec.step(&mut snarl).unwrap());
+        assert!(exec.ready_nodes.is_empty());
+    }
+
+    #[test]
+    fn test_node_timeout() {
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+
+        let graph = graph(
+            [Number::default().into(), delay(60.0), join(1)],
+            [wire(0, 1, 0), wire(1, 2, 0)],
+        );
+
+        let mut snarl = Snarl::try_from(graph.clone()).unwrap();
+        let ctx = RunContext {
+            node_timeout: Some(1),
+            ..run_ctx(&rt)
+        };
+        let mut exec = WorkflowRunner::builder().run_ctx(ctx.clone()).build();
+        exec.init(&graph);
+
+        assert!(exec.step(&mut snarl).unwrap());
+
+        let started = Instant::now();
+        assert!(matches!(
+            exec.step(&mut snarl).unwrap_err().as_ref(),
+            WorkflowError::Timeout
+        ));
+        assert!(started.elapsed() < Duration::from_secs(30));
+        assert!(matches!(
+            exec.state_view.get(&NodeId(1)),
+            Some(ExecState::Failed(_))
+        ));
+
+        // Downstream of the timed out node never runs
+        assert!(matches!(
+            exec.state_view.get(&NodeId(2)),
+            Some(ExecState::Waiting(_))
+        ));
+
+        // The abandoned delay stops on its own, without stopping the whole run. Dropping the
+        // runtime waits for its blocking tasks, so it would otherwise take the full minute.
+        assert!(!ctx.interrupt.load(Ordering::Relaxed));
+        let started = Instant::now();
+        drop(rt);
+        assert!(started.elapsed() < Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_node_cache() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let key = NodeCache::key(&text("a"), &[None]);
+        assert!(key.is_some());
+        assert_eq!(key, NodeCache::key(&text("a"), &[None]));
+        assert_ne!(key, NodeCache::key(&text("b"), &[None]));
+        assert_ne!(key, NodeCache::key(&text("a"), &[Some(Value::text("x"))]));
+
+        let graph = graph([text("a")], []);
+
+        let ctx = run_ctx(&rt);
+        let run = |ctx: &RunContext| {
+            let mut snarl = Snarl::try_from(graph.clone()).unwrap();
+            let mut exec = WorkflowRunner::builder().run_ctx(ctx.clone()).build();
+            exec.init(&graph);
+            assert!(exec.step(&mut snarl).unwrap());
+            exec.state_view.get(&NodeId(0))
+        };
+
+        assert_eq!(run(&ctx), Some(ExecState::Done(vec![Value::text("a")])));
+        assert_eq!(ctx.cache.len(), 1);
+
+        // A later run reuses the stored outputs instead of executing the node
+        ctx.cache.insert(key.unwrap(), vec![Value::text("cached")]);
+        assert_eq!(
+            run(&ctx),
+            Some(ExecState::Done(vec![Value::text("cached")]))
+        );
+
+        ctx.cache.clear();
+        assert!(ctx.cache.is_empty());
+
+        // Messages have no hash, so nodes given them always run
+        let message = Value::Message(crate::rig::message::Message::user("hi"));
+        assert_eq!(NodeCache::key(&text("a"), &[Some(message)]), None);
+    }
+
+    #[test]
+    fn test_node_cache_capacity() {
+        let cache = NodeCache::default();
+        for key in 0..NODE_CACHE_CAPACITY as u64 {
+            cache.insert(key, vec![Value::Integer(key as i64)]);
+        }
+
+        // Using the oldest entry spares it, so the next oldest makes room instead
+        assert!(cache.get(0).is_some());
+        cache.insert(u64::MAX, vec![]);
+
+        assert_eq!(cache.len(), NODE_CACHE_CAPACITY);
+        assert!(cache.get(0).is_some());
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(u64::MAX).is_some());
+    }
+}
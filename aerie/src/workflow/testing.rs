@@ -0,0 +1,72 @@
+//! Fixtures shared by tests of nodes and the runner
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+use egui_snarl::{InPinId, NodeId, OutPinId};
+
+use crate::{
+    AgentFactory,
+    workflow::{MetaNode, RunContext, ShadowGraph, Wire, WorkNode, runner::ExecId},
+};
+
+/// A run context with default settings and no tools
+pub fn run_ctx(rt: &tokio::runtime::Runtime) -> RunContext {
+    let agent_factory = AgentFactory::builder()
+        .rt(rt.handle().clone())
+        .settings(Arc::new(ArcSwap::from_pointee(Default::default())))
+        .tools(None)
+        .build();
+
+    RunContext::builder()
+        .runtime(rt.handle().clone())
+        .exec_id(ExecId(0, 0))
+        .agent_factory(agent_factory)
+        .metadata(Default::default())
+        .build()
+}
+
+/// Places a node at the origin of the canvas
+pub fn meta(value: impl Into<WorkNode>) -> MetaNode<WorkNode> {
+    MetaNode {
+        value: value.into(),
+        pos: egui::pos2(0.0, 0.0),
+        open: true,
+    }
+}
+
+/// Connects the first output of one node to an input of another
+pub fn wire(from: usize, to: usize, input: usize) -> Wire {
+    wire_pins(from, 0, to, input)
+}
+
+/// Connects any output of one node to an input of another
+pub fn wire_pins(from: usize, output: usize, to: usize, input: usize) -> Wire {
+    (
+        OutPinId {
+            node: NodeId(from),
+            output,
+        },
+        InPinId {
+            node: NodeId(to),
+            input,
+        },
+    )
+        .into()
+}
+
+/// A graph of the nodes, numbered in order, connected by the wires
+pub fn graph(
+    nodes: impl IntoIterator<Item = WorkNode>,
+    wires: impl IntoIterator<Item = Wire>,
+) -> ShadowGraph<WorkNode> {
+    ShadowGraph {
+        nodes: nodes
+            .into_iter()
+            .enumerate()
+            .map(|(i, node)| (NodeId(i), meta(node)))
+            .collect(),
+        wires: wires.into_iter().collect(),
+        ..ShadowGraph::empty()
+    }
+}
@@ -1,6 +1,6 @@
 use polars::prelude::*;
 
-pub use caching::*;
+pub use emberlain::search::get_vectors_config;
 
 #[macro_export]
 macro_rules! pydict {
@@ -105,36 +105,84 @@ where
     builder.finish().into_series()
 }
 
-pub mod caching {
-    use anyhow::Context as _;
-    use cached::proc_macro::cached;
-    use qdrant_client::{Qdrant, qdrant::vectors_config::Config as VecConfig};
-    use std::time::Duration;
-
-    // TODO: common lib
-    #[cached(
-        convert = r##"{ format!("{collection}") }"##,
-        key = "String",
-        time = 10,
-        result = true
-    )]
-    pub async fn get_vectors_config(
-        client: &Qdrant,
-        collection: &str,
-    ) -> anyhow::Result<VecConfig> {
-        let meta = client.collection_info(collection).await?;
-        let vectors_config: VecConfig = meta
-            .result
-            .context("No result")?
-            .config
-            .context("No config")?
-            .params
-            .context("No params")?
-            .vectors_config
-            .context("No vectors config")?
-            .config
-            .context("No config")?;
-
-        Ok(vectors_config)
+/// Like [`make_list_series`], but for lists of strings, e.g. tags
+pub fn make_list_series_str<'a>(
+    name: &str,
+    height: usize,
+    width: usize,
+    vals: impl IntoIterator<Item = impl IntoIterator<Item = &'a str>>,
+) -> Series {
+    let mut builder = ListStringChunkedBuilder::new(name.into(), height, height * width);
+    for row in vals {
+        builder.append_values_iter(row.into_iter());
+    }
+
+    builder.finish().into_series()
+}
+
+/// Like [`make_list_series`], but for lists of booleans
+pub fn make_list_series_bool(
+    name: &str,
+    height: usize,
+    width: usize,
+    vals: impl IntoIterator<Item = impl AsRef<[bool]>>,
+) -> Series {
+    let mut builder = ListBooleanChunkedBuilder::new(name.into(), height, height * width);
+    for row in vals {
+        builder.append_iter(row.as_ref().iter().copied().map(Some));
+    }
+
+    builder.finish().into_series()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_series_str() -> PolarsResult<()> {
+        let tags = vec![vec!["parse", "io"], vec![], vec!["ui"]];
+        let series = make_list_series_str("tags", tags.len(), 2, tags.clone());
+
+        assert_eq!(series.name().as_str(), "tags");
+        assert_eq!(series.dtype(), &DataType::List(Box::new(DataType::String)));
+
+        let mut round_trip = Vec::new();
+        for row in series.list()?.into_iter() {
+            let row = row.unwrap();
+            round_trip.push(row.str()?.into_no_null_iter().collect::<Vec<_>>());
+        }
+        assert_eq!(round_trip, tags);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_series_bool() -> PolarsResult<()> {
+        let flags = vec![vec![true, false], vec![false]];
+        let series = make_list_series_bool("flags", flags.len(), 2, &flags);
+
+        let mut round_trip = Vec::new();
+        for row in series.list()?.into_iter() {
+            let row = row.unwrap();
+            round_trip.push(row.bool()?.into_no_null_iter().collect::<Vec<_>>());
+        }
+        assert_eq!(round_trip, flags);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_series_numeric() -> PolarsResult<()> {
+        let series = make_list_series::<Float32Type>("x", 2, 2, [[1.0, 2.0], [3.0, 4.0]]);
+
+        assert_eq!(series.len(), 2);
+        let row = series.list()?.get_as_series(1).unwrap();
+        assert_eq!(
+            row.f32()?.into_no_null_iter().collect::<Vec<_>>(),
+            [3.0, 4.0]
+        );
+
+        Ok(())
     }
 }
@@ -3,32 +3,44 @@ use cached::proc_macro::cached;
 use egui::emath::Numeric;
 use fastembed::{EmbeddingModel, TextEmbedding};
 use itertools::{Itertools, izip};
+use qdrant_client::Qdrant;
 use qdrant_client::qdrant::point_id::PointIdOptions;
 use qdrant_client::qdrant::vectors_output::VectorsOptions;
-use qdrant_client::qdrant::{GetPointsBuilder, PointId, QueryPointsBuilder, ScrollPointsBuilder};
-use qdrant_client::{Qdrant, qdrant::vectors_config::Config as VecConfig};
+use qdrant_client::qdrant::{
+    Condition, CountPointsBuilder, Filter, GetPointsBuilder, PointId, RetrievedPoint,
+    ScrollPointsBuilder,
+};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU16, Ordering};
 use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
 
 use polars::prelude::*;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
 use pyo3_polars::PyDataFrame;
 use tokio::runtime::Runtime;
 
 use eframe::egui;
 use egui::{
     Align, CollapsingHeader, Color32, Frame, KeyboardShortcut, Layout, RichText, ScrollArea, Sense,
-    Style, UiBuilder, Visuals,
+    Style, TextEdit, UiBuilder, Visuals,
+};
+use egui_plot::{Line, MarkerShape, Plot, PlotPoints, PlotResponse, Points};
+
+use embasee::{get_vectors_config, optzip, pydict};
+use emberlain::search::{
+    DEFAULT_VECTOR, SearchOptions, dense_vectors, semantic_query, vector_size,
 };
-use egui_plot::{MarkerShape, Plot, PlotResponse, Points};
 
-use embasee::{get_vectors_config, optzip, pydict, pyimport};
+mod reduce;
+use reduce::{Reducer, ReducerKind, UMAP, UMAP_NEIGHBORS};
 
 const SHORTCUT_QUIT: KeyboardShortcut = KeyboardShortcut {
     modifiers: egui::Modifiers::CTRL,
@@ -49,34 +61,210 @@ static FASTEMBED_CACHE_DIR: LazyLock<String> = LazyLock::new(|| {
         .unwrap_or_else(fastembed::get_cache_dir)
 });
 
-static UMAP: LazyLock<Py<PyAny>> = LazyLock::new(|| pyimport!("umap", "UMAP").unwrap());
+/// Directory for fitted projections saved between runs. Nothing is saved when unset.
+static UMAP_CACHE_DIR: LazyLock<Option<PathBuf>> =
+    LazyLock::new(|| env::var_os("UMAP_CACHE_DIR").map(PathBuf::from));
 
-static UMAP_NEIGHBORS: LazyLock<u64> = LazyLock::new(|| {
-    env::var("UMAP_NEIGHBORS")
-        .ok()
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(3)
-});
+/// A saved projection is refit once the number of points drifts by more than this fraction
+const UMAP_STALE_RATIO: f64 = 0.2;
 
-const PALETTE: colorous::Gradient = colorous::ORANGE_RED;
-static VECSTORE_URL: LazyLock<String> =
-    LazyLock::new(|| env::var("VECSTORE_URL").unwrap_or("http://localhost:6334".to_string()));
+/// Where a fitted projection is pickled, and whether a previously saved one may be reused
+#[derive(Debug, Clone)]
+struct UmapCache {
+    path: PathBuf,
+    reuse: bool,
+}
+
+impl UmapCache {
+    /// Keyed by collection, embedding model, reducer, dimensions, neighbor count and anchors
+    fn new(
+        collection: &str,
+        model: Option<&EmbeddingModel>,
+        reducer: &dyn Reducer,
+        n_components: usize,
+        anchors: &[String],
+        reuse: bool,
+    ) -> Option<Self> {
+        let dir = UMAP_CACHE_DIR.as_ref()?;
+
+        let mut hasher = DefaultHasher::new();
+        anchors.hash(&mut hasher);
+
+        let model = model.map(|m| format!("{m:?}")).unwrap_or("none".into());
+        let name = format!(
+            "{collection}-{model}-{}-d{n_components}-n{}-{:016x}.pkl",
+            reducer.name(),
+            *UMAP_NEIGHBORS,
+            hasher.finish()
+        );
+
+        Some(Self {
+            path: dir.join(name),
+            reuse,
+        })
+    }
+
+    /// Unpickles a fitted UMAP, unless the number of points changed too much since
+    fn load<'py>(&self, py: Python<'py>, num_points: usize) -> Option<Bound<'py, PyAny>> {
+        let bytes = std::fs::read(&self.path).ok()?;
+
+        let loaded = py
+            .import("pickle")
+            .and_then(|pickle| pickle.call_method1("loads", (PyBytes::new(py, &bytes),)))
+            .and_then(|loaded| loaded.extract::<(usize, Bound<PyAny>)>());
+
+        let (fitted_points, umap) = match loaded {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                log::warn!("Could not load umap from {:?}: {err}", self.path);
+                return None;
+            }
+        };
+
+        let drift = fitted_points.abs_diff(num_points) as f64 / fitted_points.max(1) as f64;
+        if drift > UMAP_STALE_RATIO {
+            log::info!("Saved umap was fitted to {fitted_points} points, now {num_points}");
+            return None;
+        }
+
+        log::info!("Loaded umap instance from {:?}", self.path);
+        Some(umap)
+    }
+
+    fn save(&self, py: Python<'_>, umap: &Bound<'_, PyAny>, num_points: usize) {
+        let result = (|| -> anyhow::Result<()> {
+            let bytes: Vec<u8> = py
+                .import("pickle")?
+                .call_method1("dumps", ((num_points, umap),))?
+                .extract()?;
+
+            if let Some(dir) = self.path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::write(&self.path, bytes)?;
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            log::warn!("Could not save umap to {:?}: {err}", self.path);
+        }
+    }
+}
+
+/// Marker colors kept out of every palette so they stand out from match scores
+const QUERY_COLOR: Color32 = Color32::from_rgb(0, 255, 255);
+const SELECT_COLOR: Color32 = Color32::WHITE;
+
+/// Points without a value for the field being colored by
+const UNCATEGORIZED_COLOR: Color32 = Color32::GRAY;
+
+/// Gradients available for coloring points by match score
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Palette {
+    #[default]
+    Viridis,
+    Cividis,
+    Inferno,
+    Plasma,
+    OrangeRed,
+}
 
-static ANCHOR_QUERIES: LazyLock<Vec<String>> = LazyLock::new(|| {
-    fn anchors() -> anyhow::Result<Vec<String>> {
-        let fname = env::var("ANCHOR_QUERIES")?;
+impl Palette {
+    const ALL: [Palette; 5] = [
+        Palette::Viridis,
+        Palette::Cividis,
+        Palette::Inferno,
+        Palette::Plasma,
+        Palette::OrangeRed,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Palette::Viridis => "Viridis",
+            Palette::Cividis => "Cividis (colorblind-safe)",
+            Palette::Inferno => "Inferno",
+            Palette::Plasma => "Plasma",
+            Palette::OrangeRed => "Orange-Red",
+        }
+    }
 
-        let file = File::open(fname)?;
-        let buf = BufReader::new(file);
-        let lines = buf.lines().map_while(Result::ok).collect::<Vec<_>>();
-        Ok(lines)
+    fn gradient(&self) -> colorous::Gradient {
+        match self {
+            Palette::Viridis => colorous::VIRIDIS,
+            Palette::Cividis => colorous::CIVIDIS,
+            Palette::Inferno => colorous::INFERNO,
+            Palette::Plasma => colorous::PLASMA,
+            Palette::OrangeRed => colorous::ORANGE_RED,
+        }
     }
+}
+
+/// User preferences persisted between runs
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Settings {
+    palette: Palette,
+
+    /// Payload field whose values color the points. Match scores are used when unset.
+    color_field: Option<String>,
+
+    /// Reference phrases projected alongside the points
+    anchors: Vec<String>,
+
+    /// Leave the anchors out of the projection without discarding them
+    ignore_anchors: bool,
+
+    /// Last selected collection, reopened on launch
+    collection: Option<String>,
+}
+
+static VECSTORE_URL: LazyLock<String> =
+    LazyLock::new(|| env::var("VECSTORE_URL").unwrap_or("http://localhost:6334".to_string()));
 
-    anchors().unwrap_or_default()
+/// Most points fetched from a collection. The rest are left off the plot.
+static MAX_POINTS: LazyLock<usize> = LazyLock::new(|| {
+    env::var("MAX_POINTS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(100_000)
+});
+
+/// Number of recent queries fitted into the projection. Zero disables the history.
+static QUERY_HISTORY: LazyLock<usize> = LazyLock::new(|| {
+    env::var("QUERY_HISTORY")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(20)
 });
 
-// TODO: also log and embed query history to improve reduction.
-// Don't display points for queries though.
+/// Number of points fetched by each scroll request
+const SCROLL_PAGE: usize = 1_000;
+
+/// Payload fields kept alongside the projection, for coloring and exporting
+const PAYLOAD_FIELDS: [&str; 4] = ["path", "class", "name", "kind"];
+
+/// File of anchor phrases, one per line. Overrides saved anchors and receives edits.
+static ANCHOR_FILE: LazyLock<Option<PathBuf>> =
+    LazyLock::new(|| env::var_os("ANCHOR_QUERIES").map(PathBuf::from));
+
+/// Quiet period after editing anchors before they are re-embedded
+const ANCHOR_DEBOUNCE: Duration = Duration::from_millis(1500);
+
+/// How long to wait for the vector store when checking the remembered collection
+const RESTORE_TIMEOUT: Duration = Duration::from_secs(3);
+
+fn read_anchors(path: &Path) -> anyhow::Result<Vec<String>> {
+    Ok(parse_anchors(&std::fs::read_to_string(path)?))
+}
+
+/// Splits anchor text into phrases, ignoring blank lines
+fn parse_anchors(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
 
 fn main() -> anyhow::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -117,7 +305,8 @@ struct SemanticQuery {
     text: String,
     embed_model: Option<EmbeddingModel>,
     matched_ids: Arc<BTreeMap<String, f32>>,
-    query_point: Option<(f64, f64)>,
+    /// Projected coordinates of the last query, one per plotted axis
+    query_point: Option<Vec<f64>>,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -126,13 +315,48 @@ struct AppState {
     hash_to_uuid: HashMap<egui::Id, String>,
     hover_point: Option<String>,
     select_point: Option<String>,
+
+    /// Points enclosed by the last box or lasso selection
+    multi_select: HashSet<String>,
     point_details: BTreeMap<String, Value>,
     semantic: SemanticQuery,
     available_collections: Arc<Vec<String>>,
     collection_name: Option<String>,
     embed_dims: usize,
+
+    /// Sizes of the named vectors in the collection, if it has any
+    vector_sizes: Arc<BTreeMap<String, u64>>,
+
+    /// Named vector plotted and queried
+    vector_name: Option<String>,
+
+    /// Points in the collection, which may be more than are fetched
+    total_points: Option<u64>,
+
+    /// Points fetched so far by a refresh in progress
+    fetched_points: usize,
+
+    /// Projects the embeddings onto the plot
+    reducer: ReducerKind,
+
+    /// Project onto three axes and draw a rotatable scatter instead of a flat plot
+    three_d: bool,
+
+    /// Full embeddings of the last fetched points, for refitting without another scroll
+    embed_df: Option<DataFrame>,
+
+    /// Payload fields of the last fetched points, joined to each new projection
+    payload_df: Option<DataFrame>,
+
+    /// Last embedded anchors, keyed by model and phrases
+    anchor_cache: Option<(AnchorKey, DataFrame)>,
+
+    /// Embeddings of recent queries, fitted along with the anchors but never plotted
+    query_history: VecDeque<(EmbeddingModel, Vec<f32>)>,
 }
 
+type AnchorKey = (Option<EmbeddingModel>, Vec<String>);
+
 impl AppState {
     pub fn new() -> Self {
         let umap_df = df! {
@@ -147,6 +371,214 @@ impl AppState {
             ..Default::default()
         }
     }
+
+    /// Fresh state for another collection, keeping the query text, history and reducer
+    pub fn reset(&mut self, collection_name: Option<String>) {
+        let text = std::mem::take(&mut self.semantic.text);
+        let query_history = std::mem::take(&mut self.query_history);
+        let (reducer, three_d) = (self.reducer, self.three_d);
+
+        *self = Self::new();
+        self.collection_name = collection_name;
+        self.semantic.text = text;
+        self.query_history = query_history;
+        self.reducer = reducer;
+        self.three_d = three_d;
+    }
+
+    /// Number of axes projected onto
+    pub fn n_components(&self) -> usize {
+        if self.three_d { 3 } else { 2 }
+    }
+
+    /// Remembers a query embedding, forgetting the oldest beyond `QUERY_HISTORY`
+    pub fn record_query(&mut self, model: EmbeddingModel, embedding: Vec<f32>) {
+        self.query_history.push_back((model, embedding));
+        while self.query_history.len() > *QUERY_HISTORY {
+            self.query_history.pop_front();
+        }
+    }
+
+    /// Adds newly projected points to the current projection
+    pub fn append_projection(&mut self, df_proj: DataFrame) {
+        let columns = self
+            .umap_df
+            .get_column_names()
+            .into_iter()
+            .filter(|name| *name == "uuid" || name.starts_with("umap"))
+            .cloned()
+            .collect_vec();
+
+        let combined = self
+            .umap_df
+            .select(columns)
+            .and_then(|df| df.vstack(&df_proj));
+
+        match combined {
+            Ok(df) => self.set_projection(df),
+            Err(err) => log::warn!("Could not append to the projection: {err}"),
+        }
+    }
+
+    /// Replaces the projection, joined with the payload fields of its points
+    pub fn set_projection(&mut self, df_proj: DataFrame) {
+        self.umap_df = match self.payload_df.as_ref() {
+            Some(payload_df) => df_proj
+                .clone()
+                .lazy()
+                .join(
+                    payload_df.clone().lazy(),
+                    [col("uuid")],
+                    [col("uuid")],
+                    JoinArgs::new(JoinType::Left),
+                )
+                .collect()
+                .unwrap_or_else(|err| {
+                    log::warn!("Could not join payload fields: {err}");
+                    df_proj
+                }),
+            None => df_proj,
+        };
+    }
+
+    /// Drops references to points that disappeared from the collection after a refresh.
+    ///
+    /// A selection that still exists has its details fetched again.
+    pub fn retain_points(&mut self, ids: &HashSet<String>) {
+        self.multi_select.retain(|id| ids.contains(id));
+
+        let matched_ids = &self.semantic.matched_ids;
+        if matched_ids.keys().any(|id| !ids.contains(id)) {
+            self.semantic.matched_ids = Arc::new(
+                matched_ids
+                    .iter()
+                    .filter(|(id, _)| ids.contains(*id))
+                    .map(|(id, score)| (id.clone(), *score))
+                    .collect(),
+            );
+        }
+
+        if self
+            .hover_point
+            .as_ref()
+            .is_some_and(|id| !ids.contains(id))
+        {
+            self.hover_point = None;
+        }
+
+        if self
+            .select_point
+            .as_ref()
+            .is_some_and(|id| !ids.contains(id))
+        {
+            log::info!("Selected point {:?} no longer exists", self.select_point);
+            self.select_point = None;
+        }
+
+        // Payloads may have changed. Clearing makes the next frame fetch them again.
+        self.point_details.clear();
+    }
+}
+
+/// Orbits the center of the 3D scatter
+#[derive(Debug, Clone, Copy)]
+struct Camera {
+    yaw: f32,
+    pitch: f32,
+    zoom: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            yaw: 0.6,
+            pitch: 0.4,
+            zoom: 1.0,
+        }
+    }
+}
+
+/// How a point is drawn, shared by the 2D and 3D plots
+#[derive(Debug, Clone, Copy)]
+struct PointStyle {
+    color: Color32,
+    radius: f32,
+    diamond: bool,
+    selected: bool,
+}
+
+/// Snapshot of the state that determines how each point is drawn in a frame
+struct PointStyler {
+    palette: colorous::Gradient,
+    categories: Option<StringChunked>,
+    category_colors: Option<HashMap<String, Color32>>,
+    matched_ids: Arc<BTreeMap<String, f32>>,
+    select_point: Option<String>,
+    multi_select: HashSet<String>,
+    details_id: Option<Value>,
+}
+
+impl PointStyler {
+    fn new(app_state: &AppState, settings: &Settings) -> Self {
+        let categories = settings
+            .color_field
+            .as_deref()
+            .and_then(|field| app_state.umap_df.column(field).ok())
+            .and_then(|column| column.str().ok().cloned());
+        let category_colors = categories.as_ref().map(category_colors);
+
+        Self {
+            palette: settings.palette.gradient(),
+            categories,
+            category_colors,
+            matched_ids: app_state.semantic.matched_ids.clone(),
+            select_point: app_state.select_point.clone(),
+            multi_select: app_state.multi_select.clone(),
+            details_id: app_state.point_details.get("id").cloned(),
+        }
+    }
+
+    /// Style of the point in row `i` of the projection
+    fn style(&self, i: usize, id: &str) -> PointStyle {
+        let match_score = self.matched_ids.get(id);
+        let is_detail = self.details_id.as_ref().is_some_and(|v| v == id);
+        let selected = self.select_point.as_deref() == Some(id);
+        let is_multi = self.multi_select.contains(id);
+
+        let radius = match true {
+            _ if selected => 8.0,
+            _ if is_detail => 5.0,
+            _ if is_multi => 4.0,
+            _ => 3.0,
+        };
+
+        let alpha = match true {
+            _ if is_detail => 255,
+            _ if match_score.is_some() => 196,
+            _ => 128,
+        };
+
+        let color = if let Some((values, colors)) =
+            optzip!(self.categories.as_ref(), self.category_colors.as_ref())
+        {
+            values
+                .get(i)
+                .and_then(|value| colors.get(value))
+                .copied()
+                .unwrap_or(UNCATEGORIZED_COLOR)
+        } else {
+            let score = match_score.map_or(0.0, |score| score.to_f64());
+            let color = self.palette.eval_continuous(score);
+            Color32::from_rgb(color.r, color.g, color.b)
+        };
+
+        PointStyle {
+            color: Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), alpha),
+            radius,
+            diamond: selected || is_multi,
+            selected,
+        }
+    }
 }
 
 struct MyEguiApp {
@@ -158,6 +590,18 @@ struct MyEguiApp {
     // TODO: refactor into Reduction
     umap: Arc<Mutex<Option<Py<PyAny>>>>,
     // reduction: Arc<Mutex<Reduction>>,
+    settings: Settings,
+    anchor_text: String,
+    anchor_edited: Option<Instant>,
+
+    /// Plot coordinates traced by a selection drag in progress
+    lasso: Vec<[f64; 2]>,
+
+    /// Viewpoint of the 3D scatter
+    camera: Camera,
+
+    /// Outcome of the last export, shown in the status line
+    status: Option<Result<String, String>>,
 }
 
 impl MyEguiApp {
@@ -177,6 +621,20 @@ impl MyEguiApp {
 
         let qdclient = Arc::new(Qdrant::from_url(VECSTORE_URL.as_str()).build().unwrap());
 
+        let mut settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+
+        if let Some(path) = ANCHOR_FILE.as_ref() {
+            match read_anchors(path) {
+                Ok(anchors) => settings.anchors = anchors,
+                Err(err) => log::warn!("Could not read anchors from {path:?}: {err}"),
+            }
+        }
+
+        let anchor_text = settings.anchors.join("\n");
+
         let mut this = Self {
             rt,
             qdclient,
@@ -184,14 +642,48 @@ impl MyEguiApp {
             task_count: Default::default(),
             umap: Arc::new(Mutex::new(None)),
             // reduction: Arc::new(Mutex::new(Default::default())),
+            settings,
+            anchor_text,
+            anchor_edited: None,
+            lasso: Vec::new(),
+            camera: Camera::default(),
+            status: None,
         };
 
+        this.restore_collection();
         this.refresh_points();
         this.refresh_collections();
 
         this
     }
 
+    /// Selects the collection from the last session, if the store still has it
+    fn restore_collection(&mut self) {
+        let Some(name) = self.settings.collection.clone() else {
+            return;
+        };
+
+        let qdclient = self.qdclient.clone();
+        let exists = self.rt.block_on(tokio::time::timeout(
+            RESTORE_TIMEOUT,
+            qdclient.collection_exists(name.as_str()),
+        ));
+
+        match exists {
+            Ok(Ok(true)) => {
+                if let Ok(mut app_state) = self.app_state.lock() {
+                    app_state.collection_name = self.settings.collection.clone();
+                }
+            }
+            Ok(Ok(false)) => {
+                log::warn!("Collection {name:?} no longer exists");
+                self.settings.collection = None;
+            }
+            Ok(Err(err)) => log::warn!("Could not check collection {name:?}: {err}"),
+            Err(_) => log::warn!("Timed out checking collection {name:?}"),
+        }
+    }
+
     fn refresh_collections(&mut self) {
         let app_state = self.app_state.clone();
         let qdclient = self.qdclient.clone();
@@ -217,7 +709,10 @@ impl MyEguiApp {
         });
     }
 
-    // TODO: only get vectors for new IDs
+    /// Fetches and projects the points of the selected collection.
+    ///
+    /// Once a mapping is fitted, only points that aren't loaded yet are fetched and placed
+    /// with it. Everything is reloaded when points were removed or nothing is loaded.
     fn refresh_points(&mut self) {
         let rt = self.rt.handle().to_owned();
         let app_lock = self.app_state.clone();
@@ -225,11 +720,20 @@ impl MyEguiApp {
         let task_count = self.task_count.clone();
         let umap_lock = self.umap.clone();
 
-        let model_id = if let Ok(app_state) = self.app_state.lock() {
-            app_state.semantic.embed_model.clone()
-        } else {
-            return;
-        };
+        let (model_id, reducer, n_components, vector_name) =
+            if let Ok(app_state) = self.app_state.lock() {
+                (
+                    app_state.semantic.embed_model.clone(),
+                    app_state.reducer.reducer(),
+                    app_state.n_components(),
+                    app_state
+                        .vector_name
+                        .clone()
+                        .unwrap_or(DEFAULT_VECTOR.to_string()),
+                )
+            } else {
+                return;
+            };
 
         let collection_name = if let Ok(app_state) = self.app_state.lock() {
             app_state.collection_name.clone()
@@ -245,71 +749,79 @@ impl MyEguiApp {
         }
 
         let collection_name = collection_name.unwrap();
-        task_count.fetch_add(1, Ordering::Relaxed);
 
-        self.rt.handle().spawn(async move {
-            // TODO: lazy
-            let anchor_embeds = rt
-                .spawn_blocking({
-                    move || {
-                        if ANCHOR_QUERIES.is_empty() {
-                            return Default::default();
-                        }
+        let fitted = reducer.inductive() && self.umap.lock().is_ok_and(|umap| umap.is_some());
+        let loaded = if let Ok(app_state) = self.app_state.lock()
+            && fitted
+        {
+            optzip!(app_state.embed_df.clone(), app_state.payload_df.clone())
+        } else {
+            None
+        };
 
-                        let model = model_id.and_then(|m| {
-                            TextEmbedding::try_new(
-                                fastembed::InitOptions::new(m)
-                                    .with_show_download_progress(true)
-                                    .with_cache_dir(FASTEMBED_CACHE_DIR.as_str().into()),
-                            )
-                            .ok()
-                        });
+        let anchors = self.active_anchors();
+        let umap_cache = UmapCache::new(
+            &collection_name,
+            model_id.as_ref(),
+            reducer,
+            n_components,
+            &anchors,
+            true,
+        );
+        let cache_lock = self.app_state.clone();
+        task_count.fetch_add(1, Ordering::Relaxed);
 
-                        let embeddings =
-                            model.and_then(|mut m| m.embed(ANCHOR_QUERIES.clone(), None).ok());
+        self.rt.handle().spawn(async move {
+            let anchor_df = rt
+                .spawn_blocking(move || projection_context(&cache_lock, model_id, anchors))
+                .await
+                .ok()
+                .flatten();
 
-                        embeddings.unwrap_or_default()
-                    }
-                })
+            let total_points = qdclient
+                .count(CountPointsBuilder::new(collection_name.as_str()).exact(true))
                 .await
-                .unwrap_or_default();
+                .ok()
+                .and_then(|resp| resp.result)
+                .map(|result| result.count);
 
-            let anchor_df = {
-                let points = anchor_embeds
-                    .iter()
-                    .enumerate()
-                    .map(|(i, v)| (format!("anchor_{i:04}"), v))
-                    .collect();
-                anchor_embeds
-                    .first()
-                    .map(|a| a.len())
-                    .map(|dims| points_to_dataframe(dims, points))
-            };
+            if let Ok(mut app_state) = app_lock.lock() {
+                app_state.total_points = total_points;
+            }
 
-            let resp = qdclient
-                .scroll(
-                    ScrollPointsBuilder::new(collection_name.as_str())
-                        .limit(10_000)
-                        .with_payload(true)
-                        .with_vectors(true),
-                )
-                .await;
+            let known_ids = loaded
+                .as_ref()
+                .map(|(embed_df, _)| dataframe_point_ids(embed_df))
+                .unwrap_or_default();
+            let num_known = known_ids.len();
+
+            let filter = (num_known > 0).then(|| Filter::must_not([Condition::has_id(known_ids)]));
+            let max_points = MAX_POINTS.saturating_sub(num_known);
+            let mut points =
+                scroll_points(&qdclient, &collection_name, filter, max_points, &app_lock).await;
+
+            // Removed points only show up as a shortfall in the total
+            let mut loaded = loaded;
+            if loaded.is_some()
+                && total_points.is_some_and(|total| total < (num_known + points.len()) as u64)
+            {
+                log::info!("Points were removed from {collection_name:?}. Reloading");
+                loaded = None;
+                points =
+                    scroll_points(&qdclient, &collection_name, None, *MAX_POINTS, &app_lock).await;
+            }
 
-            let num_points = resp.as_ref().unwrap().result.len();
-            log::info!("Found {num_points} results");
+            let num_points = points.len();
+            log::info!("Found {num_points} new results of {total_points:?}");
 
             if num_points > 0 {
-                let point_vecs: Vec<_> = resp
-                    .as_ref()
-                    .unwrap()
-                    .result
+                let point_vecs: Vec<_> = points
                     .iter()
                     .filter_map(|p| p.id.as_ref().zip(p.vectors.as_ref()))
                     .filter_map(|(k, v)| match v.vectors_options.as_ref().unwrap() {
                         VectorsOptions::Vector(vector) => Some((k, &vector.data)),
                         VectorsOptions::Vectors(vectors) => {
-                            // TODO: config for "default" vector
-                            vectors.vectors.get("default").map(|d| (k, &d.data))
+                            vectors.vectors.get(&vector_name).map(|d| (k, &d.data))
                         }
                     })
                     .filter_map(|(k, v)| match k.point_id_options.as_ref() {
@@ -326,23 +838,52 @@ impl MyEguiApp {
 
                 let hash_to_uuid = points_to_hover_lookup(&point_vecs);
 
-                if let Ok(mut app_state) = app_lock.lock() {
-                    app_state.hash_to_uuid = hash_to_uuid;
-                }
+                let ids = point_vecs.iter().map(|(id, _)| id.clone()).collect();
 
                 let df = points_to_dataframe(embed_dims, point_vecs);
+                let payload_df = payloads_to_dataframe(&points);
 
                 dbg!(&df);
 
+                let append = loaded.is_some();
+                if let Ok(mut app_state) = app_lock.lock() {
+                    if let Some((loaded_df, loaded_payloads)) = loaded {
+                        app_state.hash_to_uuid.extend(hash_to_uuid);
+                        app_state.embed_df = loaded_df.vstack(&df).ok();
+                        app_state.payload_df = loaded_payloads.vstack(&payload_df).ok();
+                    } else {
+                        app_state.hash_to_uuid = hash_to_uuid;
+                        app_state.retain_points(&ids);
+                        app_state.embed_df = Some(df.clone());
+                        app_state.payload_df = Some(payload_df);
+                    }
+                }
+
                 rt.spawn_blocking({
                     let task_count = task_count.clone();
                     task_count.fetch_add(1, Ordering::Relaxed);
 
                     move || {
-                        let df_proj = project_embeddings(umap_lock, df, anchor_df);
+                        // The fitted mapping places new points without refitting
+                        let df_proj = if append {
+                            project_embeddings(umap_lock, reducer, n_components, df, None, None)
+                        } else {
+                            project_embeddings(
+                                umap_lock,
+                                reducer,
+                                n_components,
+                                df,
+                                anchor_df,
+                                umap_cache,
+                            )
+                        };
 
                         if let Ok(mut app_state) = app_lock.lock() {
-                            app_state.umap_df = df_proj;
+                            if append {
+                                app_state.append_projection(df_proj);
+                            } else {
+                                app_state.set_projection(df_proj);
+                            }
                         } else {
                             log::warn!("Could not access app state");
                         }
@@ -350,6 +891,10 @@ impl MyEguiApp {
                         task_count.fetch_sub(1, Ordering::Relaxed);
                     }
                 });
+            } else if loaded.is_none()
+                && let Ok(mut app_state) = app_lock.lock()
+            {
+                app_state.retain_points(&HashSet::new());
             }
 
             task_count.fetch_sub(1, Ordering::Relaxed);
@@ -363,7 +908,7 @@ impl MyEguiApp {
             return;
         };
 
-        if ANCHOR_QUERIES.is_empty() {
+        if self.active_anchors().is_empty() {
             // Nothing to do
             return;
         }
@@ -373,11 +918,95 @@ impl MyEguiApp {
             return;
         }
 
+        self.refit_projection(true);
+    }
+
+    /// Re-embeds the anchors and refits the projection over the points already fetched.
+    ///
+    /// A projection saved for the same anchors is loaded instead, if `reuse_cache` is set.
+    fn refit_projection(&mut self, reuse_cache: bool) {
         if let Ok(mut umap) = self.umap.lock() {
             *umap = None;
         }
 
-        self.refresh_points();
+        let (collection_name, model_id, reducer, n_components, df) =
+            if let Ok(app_state) = self.app_state.lock() {
+                (
+                    app_state.collection_name.clone(),
+                    app_state.semantic.embed_model.clone(),
+                    app_state.reducer.reducer(),
+                    app_state.n_components(),
+                    app_state.embed_df.clone(),
+                )
+            } else {
+                return;
+            };
+
+        let Some(df) = df else {
+            self.refresh_points();
+            return;
+        };
+
+        let app_lock = self.app_state.clone();
+        let task_count = self.task_count.clone();
+        let umap_lock = self.umap.clone();
+        let anchors = self.active_anchors();
+        let umap_cache = collection_name.and_then(|collection| {
+            UmapCache::new(
+                &collection,
+                model_id.as_ref(),
+                reducer,
+                n_components,
+                &anchors,
+                reuse_cache,
+            )
+        });
+
+        task_count.fetch_add(1, Ordering::Relaxed);
+        self.rt.spawn_blocking(move || {
+            let anchor_df = projection_context(&app_lock, model_id, anchors);
+            let df_proj =
+                project_embeddings(umap_lock, reducer, n_components, df, anchor_df, umap_cache);
+
+            if let Ok(mut app_state) = app_lock.lock() {
+                app_state.set_projection(df_proj);
+            } else {
+                log::warn!("Could not access app state");
+            }
+
+            task_count.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Anchors to fold into the projection, which are none while they're ignored
+    fn active_anchors(&self) -> Vec<String> {
+        if self.settings.ignore_anchors {
+            vec![]
+        } else {
+            self.settings.anchors.clone()
+        }
+    }
+
+    /// Saves edited anchors and refits the projection if they changed
+    fn commit_anchors(&mut self) {
+        let anchors = parse_anchors(&self.anchor_text);
+        if anchors == self.settings.anchors {
+            return;
+        }
+
+        self.settings.anchors = anchors;
+
+        if let Some(path) = ANCHOR_FILE.as_ref() {
+            let mut text = self.settings.anchors.join("\n");
+            text.push('\n');
+            if let Err(err) = std::fs::write(path, text) {
+                log::warn!("Could not save anchors to {path:?}: {err}");
+            }
+        }
+
+        if !self.settings.ignore_anchors {
+            self.refit_projection(true);
+        }
     }
 
     fn trigger_semantic_query(&self) {
@@ -387,16 +1016,17 @@ impl MyEguiApp {
         let task_count = self.task_count.clone();
         let umap_lock = self.umap.clone();
 
-        let (collection_name, model_id, query_string) = if let Ok(app_state) = self.app_state.lock()
-        {
-            (
-                app_state.collection_name.clone(),
-                app_state.semantic.embed_model.clone(),
-                app_state.semantic.text.clone(),
-            )
-        } else {
-            return;
-        };
+        let (collection_name, model_id, query_string, vector_name) =
+            if let Ok(app_state) = self.app_state.lock() {
+                (
+                    app_state.collection_name.clone(),
+                    app_state.semantic.embed_model.clone(),
+                    app_state.semantic.text.clone(),
+                    app_state.vector_name.clone(),
+                )
+            } else {
+                return;
+            };
 
         if model_id.is_none() {
             log::info!("No embedding model");
@@ -424,6 +1054,7 @@ impl MyEguiApp {
             let embedding = rt
                 .spawn_blocking({
                     let task_count = task_count.clone();
+                    let model_id = model_id.clone();
                     move || {
                         let mut model = TextEmbedding::try_new(
                             fastembed::InitOptions::new(model_id)
@@ -453,6 +1084,10 @@ impl MyEguiApp {
 
             let embedding = embedding.unwrap();
 
+            if let Ok(mut app_state) = app_state.lock() {
+                app_state.record_query(model_id, embedding.clone());
+            }
+
             // map embedding to a point and display in a background thread
             rt.spawn_blocking({
                 let app_state = app_state.clone();
@@ -466,8 +1101,7 @@ impl MyEguiApp {
                         Python::attach(|py| {
                             let umap = umap.bind(py);
                             let x_u = umap.call_method1("transform", (vec![&embedding],)).unwrap();
-                            // TODO extract result to query_point
-                            let x_u: Vec<[f32; 2]> = x_u.extract()?;
+                            let x_u: Vec<Vec<f32>> = x_u.extract()?;
 
                             Ok::<_, PyErr>(x_u)
                         })
@@ -481,36 +1115,23 @@ impl MyEguiApp {
                     {
                         // This doesn't trigger a UI redraw.
                         // It's actually the spinner animation instead of data changes.
-                        app_state.semantic.query_point = Some((x[0].to_f64(), x[1].to_f64()));
+                        app_state.semantic.query_point =
+                            Some(x.iter().map(|v| v.to_f64()).collect());
                     }
 
                     task_count.fetch_sub(1, Ordering::Relaxed);
                 }
             });
 
-            let vec_config = get_vectors_config(qdclient.as_ref(), &collection_name)
+            // Continue async coro by querying Qdrant to get n_neighbors
+            let mut opts = SearchOptions::builder().limit(10).build();
+            opts.using = vector_name;
+            let result = semantic_query(qdclient.as_ref(), &collection_name, embedding, &opts)
                 .await
-                .map_err(|e| e.to_string())
                 .unwrap();
 
-            // Continue async coro by querying Qdrant to get n_neighbors
-            let query = QueryPointsBuilder::new(collection_name.as_str())
-                .query(embedding.clone())
-                .limit(10);
-
-            let query = if let VecConfig::ParamsMap(_params) = vec_config {
-                // TODO: pull alias from config
-                // TODO: Check params has key
-                query.using("aliases")
-            } else {
-                query
-            };
-
-            let resp = qdclient.query(query).await.unwrap();
-
             // Stringify ids of neighbors
-            let matched_ids = resp
-                .result
+            let matched_ids = result
                 .iter()
                 .map(
                     |pv| match pv.id.as_ref().unwrap().point_id_options.as_ref().unwrap() {
@@ -537,10 +1158,20 @@ impl MyEguiApp {
         Frame::new().inner_margin(8.0).show(ui, |ui| {
             ui.vertical_centered(|ui| {
                 ui.add_enabled_ui(self.task_count.load(Ordering::Relaxed) < 1, |ui| {
-                    if ui.button("Refresh").clicked() {
-                        self.refresh_points();
-                    }
-                });
+                    ui.horizontal(|ui| {
+                        if ui.button("Refresh").clicked() {
+                            self.refresh_points();
+                        }
+
+                        if ui
+                            .button("Refit")
+                            .on_hover_text("Fit the projection again, replacing any saved one")
+                            .clicked()
+                        {
+                            self.refit_projection(false);
+                        }
+                    });
+                });
             });
         });
 
@@ -552,6 +1183,59 @@ impl MyEguiApp {
             //     ui.heading("Query");
             // });
 
+            let vector_changed = ui
+                .add_enabled_ui(self.task_count.load(Ordering::Relaxed) < 1, |ui| {
+                    let mut app_state = self.app_state.lock().unwrap();
+                    if app_state.vector_sizes.len() < 2 {
+                        return false;
+                    }
+
+                    let start_vector = app_state.vector_name.clone();
+                    let vector_sizes = app_state.vector_sizes.clone();
+
+                    ui.label("Vector");
+                    egui::ComboBox::from_id_salt("vector_name")
+                        .selected_text(start_vector.as_deref().unwrap_or_default())
+                        .width(ui.available_width())
+                        .show_ui(ui, |ui| {
+                            for (name, size) in vector_sizes.iter() {
+                                ui.selectable_value(
+                                    &mut app_state.vector_name,
+                                    Some(name.clone()),
+                                    name,
+                                )
+                                .on_hover_text(format!("{size} dimensions"));
+                            }
+                        });
+
+                    ui.add_space(8.0);
+
+                    start_vector != app_state.vector_name
+                })
+                .inner;
+
+            if vector_changed {
+                if let Ok(mut app_state) = self.app_state.lock() {
+                    if let Some(size) = app_state
+                        .vector_name
+                        .as_ref()
+                        .and_then(|name| app_state.vector_sizes.get(name))
+                    {
+                        app_state.embed_dims = *size as usize;
+                    }
+
+                    // Points are fetched again rather than appended to another vector's
+                    app_state.embed_df = None;
+                    app_state.payload_df = None;
+                }
+
+                if let Ok(mut umap) = self.umap.lock() {
+                    *umap = None;
+                }
+
+                self.refresh_points();
+            }
+
             let (model_changed, want_semantic_query) = ui
                 .add_enabled_ui(self.task_count.load(Ordering::Relaxed) < 1, |ui| {
                     let mut app_state = self.app_state.lock().unwrap();
@@ -613,6 +1297,42 @@ impl MyEguiApp {
                 self.trigger_semantic_query();
             }
 
+            ui.add_space(8.0);
+
+            CollapsingHeader::new("Anchors")
+                .default_open(!self.anchor_text.is_empty())
+                .show(ui, |ui| {
+                    let toggle = ui
+                        .checkbox(&mut self.settings.ignore_anchors, "Ignore anchors")
+                        .on_hover_text("Fit the projection without anchors. They stay loaded.");
+
+                    if toggle.changed() && !self.settings.anchors.is_empty() {
+                        self.refit_projection(true);
+                    }
+
+                    ui.vertical_centered_justified(|ui| {
+                        let editor = ui.add(
+                            TextEdit::multiline(&mut self.anchor_text)
+                                .hint_text("Reference phrases, one per line"),
+                        );
+
+                        if editor.changed() {
+                            self.anchor_edited = Some(Instant::now());
+                        }
+                    });
+                });
+
+            if let Some(edited) = self.anchor_edited {
+                let elapsed = edited.elapsed();
+                if elapsed < ANCHOR_DEBOUNCE || self.task_count.load(Ordering::Relaxed) > 0 {
+                    ui.ctx()
+                        .request_repaint_after(ANCHOR_DEBOUNCE.saturating_sub(elapsed));
+                } else {
+                    self.anchor_edited = None;
+                    self.commit_anchors();
+                }
+            }
+
             // Grid does not honor justification
             // TODO: try the table in egui_extras instead
             egui::Grid::new("semantic_matches")
@@ -693,7 +1413,33 @@ impl MyEguiApp {
 
         ScrollArea::vertical().show(ui, |ui| {
             ui.vertical(|ui| {
-                let app_state = self.app_state.lock().unwrap();
+                let mut app_state = self.app_state.lock().unwrap();
+
+                if !app_state.multi_select.is_empty() {
+                    let count = app_state.multi_select.len();
+                    CollapsingHeader::new(format!("{count} selected points"))
+                        .id_salt("multi_select")
+                        .show(ui, |ui| {
+                            if ui.button("Clear").clicked() {
+                                app_state.multi_select.clear();
+                            }
+
+                            let ids = app_state
+                                .multi_select
+                                .iter()
+                                .sorted()
+                                .cloned()
+                                .collect_vec();
+                            for id in ids {
+                                ui.selectable_value(
+                                    &mut app_state.select_point,
+                                    Some(id.clone()),
+                                    id,
+                                );
+                            }
+                        });
+                }
+
                 for (k, v) in &app_state.point_details {
                     let collapsed =
                         matches!(v.as_str().map(|s| s.len()), Some(length) if length > 128);
@@ -749,88 +1495,15 @@ impl MyEguiApp {
 
     fn render_plot(&mut self, ui: &mut egui::Ui) -> anyhow::Result<()> {
         egui::CentralPanel::default().show_inside(ui, |ui| {
-            let PlotResponse {
-                hovered_plot_item, ..
-            } = Plot::new("My Plot")
-                // .height(500.0)
-                // .legend(Legend::default())
-                .show(ui, |plot_ui| {
-                    let (proj_df, select_point, details_id, matched_ids) = {
-                        let app_state = self.app_state.lock().unwrap();
-                        (
-                            app_state.umap_df.clone(),
-                            app_state.select_point.clone(),
-                            app_state.point_details.get("id").cloned(),
-                            app_state.semantic.matched_ids.clone(),
-                        )
-                    };
-
-                    let uuid = proj_df["uuid"].str().unwrap();
-                    let x0 = extract_f64(&proj_df, "umap0").unwrap();
-                    let x1 = extract_f64(&proj_df, "umap1").unwrap();
-
-                    izip!(uuid.iter(), x0.iter(), x1.iter())
-                        .filter_map(|(uuid, x0, x1)| optzip!(uuid, x0, x1))
-                        .for_each(|(uuid, x0, x1)| {
-                            let id = uuid.to_string();
-                            let name = uuid.to_string();
-
-                            let match_score = matched_ids.get(&id);
-                            let is_detail = details_id.as_ref().map(|v| v == &id).unwrap_or(false);
-                            let is_select =
-                                select_point.as_ref().map(|v| v == &id).unwrap_or(false);
-
-                            let radius = match true {
-                                _ if is_select => 8.0,
-                                _ if is_detail => 5.0,
-                                _ => 3.0,
-                            };
-
-                            let shape = if is_select {
-                                MarkerShape::Diamond
-                            } else {
-                                MarkerShape::Circle
-                            };
-
-                            let alpha = match true {
-                                _ if is_detail => 255,
-                                _ if match_score.is_some() => 196,
-                                _ => 128,
-                            };
-
-                            let color = if let Some(score) = match_score {
-                                PALETTE.eval_continuous(score.to_f64())
-                            } else {
-                                PALETTE.eval_continuous(0.0)
-                            };
-                            let color =
-                                Color32::from_rgba_unmultiplied(color.r, color.g, color.b, alpha);
-                            let points = Points::new(name.clone(), vec![[x0, x1]])
-                                .id(id.clone())
-                                .shape(shape)
-                                .radius(radius)
-                                .filled(true)
-                                .color(color);
-
-                            plot_ui.points(points);
-                        });
-                    if let Ok(app_state) = self.app_state.lock()
-                        && let Some((x, y)) = &app_state.semantic.query_point
-                    {
-                        plot_ui.points(
-                            Points::new("Query", vec![[*x, *y]])
-                                .shape(MarkerShape::Cross)
-                                .radius(10.0)
-                                .color(Color32::RED),
-                        )
-                    }
-                });
+            let three_d = self.app_state.lock().is_ok_and(|s| s.three_d);
+            let hovered_id = if three_d {
+                self.render_plot3d(ui)
+            } else {
+                self.render_plot2d(ui)
+            };
 
             let refresh_point = {
                 let mut app_state = self.app_state.lock().unwrap();
-                let hovered_id = hovered_plot_item
-                    .and_then(|h| app_state.hash_to_uuid.get(&h))
-                    .cloned();
 
                 hovered_id
                     .as_ref()
@@ -882,13 +1555,7 @@ impl MyEguiApp {
                 rt.spawn(async move {
                     task_count.fetch_add(1, Ordering::Relaxed);
 
-                    // Not ideal. We really should be tracking the type in the dataframe column,
-                    // but acceptable for non-critical code.
-                    let point_id: PointId = uuid
-                        .as_str()
-                        .parse::<u64>()
-                        .map(|f| f.into())
-                        .unwrap_or_else(|_| uuid.as_str().into());
+                    let point_id = to_point_id(&uuid);
                     let request = GetPointsBuilder::new(collection_name.as_str(), vec![point_id]);
                     let resp = qdclient
                         .get_points(request.with_payload(true))
@@ -914,6 +1581,247 @@ impl MyEguiApp {
         Ok(())
     }
 
+    /// Draws the projection on a 2D plot with box and lasso selection. Returns the hovered point.
+    fn render_plot2d(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        // Shift-drag selects a box of points, adding alt traces a freeform lasso
+        let (selecting, freeform) = ui.input(|i| (i.modifiers.shift, i.modifiers.alt));
+
+        let PlotResponse {
+            hovered_plot_item, ..
+        } = Plot::new("My Plot")
+            // .height(500.0)
+            // .legend(Legend::default())
+            .allow_drag(!selecting)
+            .show(ui, |plot_ui| {
+                let (proj_df, styler, query_point) = {
+                    let app_state = self.app_state.lock().unwrap();
+                    (
+                        app_state.umap_df.clone(),
+                        PointStyler::new(&app_state, &self.settings),
+                        app_state.semantic.query_point.clone(),
+                    )
+                };
+
+                let uuid = proj_df["uuid"].str().unwrap();
+                let x0 = extract_f64(&proj_df, "umap0").unwrap();
+                let x1 = extract_f64(&proj_df, "umap1").unwrap();
+
+                izip!(uuid.iter(), x0.iter(), x1.iter())
+                    .enumerate()
+                    .filter_map(|(i, (uuid, x0, x1))| Some((i, optzip!(uuid, x0, x1)?)))
+                    .for_each(|(i, (uuid, x0, x1))| {
+                        let id = uuid.to_string();
+                        let name = uuid.to_string();
+                        let style = styler.style(i, &id);
+
+                        let shape = if style.diamond {
+                            MarkerShape::Diamond
+                        } else {
+                            MarkerShape::Circle
+                        };
+
+                        let points = Points::new(name.clone(), vec![[x0, x1]])
+                            .id(id.clone())
+                            .shape(shape)
+                            .radius(style.radius)
+                            .filled(true)
+                            .color(style.color);
+
+                        plot_ui.points(points);
+
+                        // Outline the selection so it stays visible on any palette
+                        if style.selected {
+                            plot_ui.points(
+                                Points::new(format!("{name} selected"), vec![[x0, x1]])
+                                    .shape(MarkerShape::Diamond)
+                                    .radius(style.radius + 2.0)
+                                    .filled(false)
+                                    .allow_hover(false)
+                                    .color(SELECT_COLOR),
+                            );
+                        }
+                    });
+                if let Some([x, y, ..]) = query_point.as_deref() {
+                    plot_ui.points(
+                        Points::new("Query", vec![[*x, *y]])
+                            .shape(MarkerShape::Cross)
+                            .radius(10.0)
+                            .color(QUERY_COLOR),
+                    )
+                }
+
+                let response = plot_ui.response().clone();
+                if selecting
+                    && response.dragged_by(egui::PointerButton::Primary)
+                    && let Some(pos) = plot_ui.pointer_coordinate()
+                {
+                    self.lasso.push([pos.x, pos.y]);
+                }
+
+                if self.lasso.len() > 1 {
+                    let outline = lasso_outline(&self.lasso, freeform);
+                    plot_ui.line(
+                        Line::new("Selection", PlotPoints::from(outline.clone()))
+                            .color(SELECT_COLOR)
+                            .allow_hover(false),
+                    );
+
+                    if response.drag_stopped() {
+                        let ids = points_within(&proj_df, &outline);
+                        log::info!("Selected {} points", ids.len());
+
+                        if let Ok(mut app_state) = self.app_state.lock() {
+                            app_state.multi_select = ids;
+                        }
+                    }
+                }
+
+                if !response.dragged() {
+                    self.lasso.clear();
+                }
+            });
+
+        let app_state = self.app_state.lock().ok()?;
+        hovered_plot_item
+            .and_then(|h| app_state.hash_to_uuid.get(&h))
+            .cloned()
+    }
+
+    /// Draws the projection as a 3D scatter, rotated by dragging and zoomed by scrolling.
+    ///
+    /// Points are projected onto the screen each frame and hit-tested there.
+    /// Returns the hovered point.
+    fn render_plot3d(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), Sense::click_and_drag());
+        let rect = response.rect;
+
+        if response.dragged_by(egui::PointerButton::Primary) {
+            let delta = response.drag_delta();
+            self.camera.yaw += delta.x * 0.01;
+            self.camera.pitch = (self.camera.pitch + delta.y * 0.01)
+                .clamp(-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2);
+        }
+
+        if response.hovered() {
+            let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+            self.camera.zoom = (self.camera.zoom * (scroll * 0.002).exp()).clamp(0.1, 20.0);
+        }
+
+        let (proj_df, styler, query_point) = {
+            let app_state = self.app_state.lock().ok()?;
+            (
+                app_state.umap_df.clone(),
+                PointStyler::new(&app_state, &self.settings),
+                app_state.semantic.query_point.clone(),
+            )
+        };
+
+        let (Ok(uuid), Ok(x0), Ok(x1), Ok(x2)) = (
+            proj_df["uuid"].str(),
+            extract_f64(&proj_df, "umap0"),
+            extract_f64(&proj_df, "umap1"),
+            extract_f64(&proj_df, "umap2"),
+        ) else {
+            return None;
+        };
+
+        let points = izip!(uuid.iter(), x0.iter(), x1.iter(), x2.iter())
+            .enumerate()
+            .filter_map(|(i, (uuid, x0, x1, x2))| {
+                let (uuid, x0, x1, x2) = optzip!(uuid, x0, x1, x2)?;
+                Some((i, uuid, [x0, x1, x2]))
+            })
+            .collect_vec();
+
+        if points.is_empty() {
+            return None;
+        }
+
+        // Center the cloud and scale it to fill the view
+        let n = points.len() as f64;
+        let center: [f64; 3] =
+            std::array::from_fn(|axis| points.iter().map(|(_, _, p)| p[axis]).sum::<f64>() / n);
+        let extent = points
+            .iter()
+            .map(|(_, _, p)| {
+                (0..3)
+                    .map(|axis| (p[axis] - center[axis]).powi(2))
+                    .sum::<f64>()
+            })
+            .fold(0.0, f64::max)
+            .sqrt()
+            .max(1e-9);
+        let scale = rect.width().min(rect.height()) * 0.45 * self.camera.zoom / extent as f32;
+
+        let camera = self.camera;
+        let project = |p: &[f64]| {
+            let [x, y, z]: [f32; 3] = std::array::from_fn(|axis| (p[axis] - center[axis]) as f32);
+            let (sin_yaw, cos_yaw) = camera.yaw.sin_cos();
+            let (sin_pitch, cos_pitch) = camera.pitch.sin_cos();
+
+            let (x, z) = (x * cos_yaw + z * sin_yaw, z * cos_yaw - x * sin_yaw);
+            let (y, depth) = (y * cos_pitch - z * sin_pitch, y * sin_pitch + z * cos_pitch);
+
+            (rect.center() + egui::vec2(x, -y) * scale, depth)
+        };
+
+        let mut projected = points
+            .iter()
+            .map(|(i, uuid, p)| {
+                let (pos, depth) = project(p);
+                (pos, depth, styler.style(*i, uuid), *uuid)
+            })
+            .collect_vec();
+
+        // Paint the farthest points first so nearer ones cover them
+        projected.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        for (pos, _, style, _) in &projected {
+            if style.diamond {
+                let r = style.radius;
+                let corners = vec![
+                    *pos + egui::vec2(0.0, -r),
+                    *pos + egui::vec2(r, 0.0),
+                    *pos + egui::vec2(0.0, r),
+                    *pos + egui::vec2(-r, 0.0),
+                ];
+                painter.add(egui::Shape::convex_polygon(
+                    corners,
+                    style.color,
+                    egui::Stroke::NONE,
+                ));
+            } else {
+                painter.circle_filled(*pos, style.radius, style.color);
+            }
+
+            // Outline the selection so it stays visible on any palette
+            if style.selected {
+                painter.circle_stroke(
+                    *pos,
+                    style.radius + 2.0,
+                    egui::Stroke::new(1.5, SELECT_COLOR),
+                );
+            }
+        }
+
+        if let Some(query) = query_point.as_deref().filter(|q| q.len() >= 3) {
+            let (pos, _) = project(query);
+            let r = 10.0;
+            let stroke = egui::Stroke::new(2.0, QUERY_COLOR);
+            painter.line_segment([pos - egui::vec2(r, r), pos + egui::vec2(r, r)], stroke);
+            painter.line_segment([pos - egui::vec2(r, -r), pos + egui::vec2(r, -r)], stroke);
+        }
+
+        // The nearest point under the pointer, favoring the ones painted on top
+        let pointer = response.hover_pos()?;
+        projected
+            .iter()
+            .rev()
+            .filter(|(pos, _, style, _)| pos.distance(pointer) <= style.radius + 3.0)
+            .min_by(|a, b| a.0.distance(pointer).total_cmp(&b.0.distance(pointer)))
+            .map(|(_, _, _, uuid)| uuid.to_string())
+    }
+
     fn render_navbar(&mut self, ui: &mut egui::Ui) {
         ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
             Frame::new().inner_margin(8.0).show(ui, |ui| {
@@ -946,9 +1854,10 @@ impl MyEguiApp {
                         });
 
                     if resp.inner.unwrap_or(false) {
+                        self.settings.collection = dummy.clone();
+
                         if let Ok(mut app_state) = self.app_state.lock() {
-                            *app_state = AppState::new();
-                            app_state.collection_name = dummy;
+                            app_state.reset(dummy);
                         }
 
                         if let Ok(mut umap) = self.umap.lock() {
@@ -959,30 +1868,182 @@ impl MyEguiApp {
                         self.refresh_collections();
                     }
                 });
+
+                ui.add_space(16.0);
+
+                if ui
+                    .add_enabled(enabled, egui::Button::new("Export"))
+                    .on_hover_text("Save the projection as CSV or Parquet")
+                    .clicked()
+                {
+                    self.export_projection();
+                }
+
+                ui.add_space(16.0);
+
+                ui.add_enabled_ui(enabled, |ui| {
+                    let mut three_d = self.app_state.lock().unwrap().three_d;
+                    let toggle = ui
+                        .checkbox(&mut three_d, "3D")
+                        .on_hover_text("Project onto three axes. Drag to rotate, scroll to zoom.");
+
+                    if toggle.changed() {
+                        if let Ok(mut app_state) = self.app_state.lock() {
+                            app_state.three_d = three_d;
+                            app_state.semantic.query_point = None;
+                        }
+
+                        self.refit_projection(true);
+                    }
+                });
+
+                ui.add_space(16.0);
+
+                ui.add_enabled_ui(enabled, |ui| {
+                    let start_reducer = self.app_state.lock().unwrap().reducer;
+                    let mut reducer = start_reducer;
+
+                    egui::ComboBox::from_label("Projection")
+                        .selected_text(reducer.label())
+                        .show_ui(ui, |ui| {
+                            for kind in ReducerKind::ALL {
+                                ui.selectable_value(&mut reducer, kind, kind.label());
+                            }
+                        });
+
+                    if reducer != start_reducer {
+                        if let Ok(mut app_state) = self.app_state.lock() {
+                            app_state.reducer = reducer;
+                        }
+
+                        if let Ok(mut umap) = self.umap.lock() {
+                            *umap = None;
+                        }
+
+                        self.refresh_points();
+                    }
+                });
+
+                ui.add_space(16.0);
+
+                egui::ComboBox::from_label("Color by")
+                    .selected_text(
+                        self.settings
+                            .color_field
+                            .as_deref()
+                            .unwrap_or("Match score"),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.settings.color_field, None, "Match score");
+                        for field in PAYLOAD_FIELDS {
+                            ui.selectable_value(
+                                &mut self.settings.color_field,
+                                Some(field.to_string()),
+                                field,
+                            );
+                        }
+                    });
+
+                ui.add_space(16.0);
+
+                egui::ComboBox::from_label("Palette")
+                    .selected_text(self.settings.palette.label())
+                    .show_ui(ui, |ui| {
+                        for palette in Palette::ALL {
+                            ui.selectable_value(
+                                &mut self.settings.palette,
+                                palette,
+                                palette.label(),
+                            );
+                        }
+                    });
             });
             // });
         });
     }
 
+    /// Asks for a destination and writes the projection there, by file extension
+    fn export_projection(&mut self) {
+        let (collection_name, mut df) = if let Ok(app_state) = self.app_state.lock() {
+            (app_state.collection_name.clone(), app_state.umap_df.clone())
+        } else {
+            return;
+        };
+
+        let file_name = format!(
+            "{}-projection.csv",
+            collection_name.as_deref().unwrap_or("points")
+        );
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(file_name)
+            .add_filter("CSV", &["csv"])
+            .add_filter("Parquet", &["parquet"])
+            .save_file()
+        else {
+            return;
+        };
+
+        self.status = Some(match write_dataframe(&path, &mut df) {
+            Ok(()) => {
+                log::info!("Exported projection to {path:?}");
+                Ok(format!("Exported {}", path.display()))
+            }
+            Err(err) => {
+                log::warn!("Could not export projection to {path:?}: {err}");
+                Err(format!("Export failed: {err}"))
+            }
+        });
+    }
+
     fn render_status_line(&mut self, ui: &mut egui::Ui) {
         ui.columns(3, |cols| {
             // TODO: Settings modal dialog
-            cols[0].horizontal(|_| {
+            cols[0].horizontal(|ui| {
                 // if ui.button("⚙").clicked() {
                 //     log::warn!("Not implemented!");
                 // }
+
+                match &self.status {
+                    Some(Ok(message)) => {
+                        ui.add(egui::Label::new(message.as_str()).truncate());
+                    }
+                    Some(Err(message)) => {
+                        ui.add(
+                            egui::Label::new(RichText::new(message.as_str()).color(Color32::RED))
+                                .truncate(),
+                        );
+                    }
+                    None => {}
+                }
             });
             cols[1].horizontal(|ui| {
                 // TODO: only calculate this on change
-                let num_points = self.app_state.lock().ok().map(|s| s.umap_df.height());
-                if let Some(count) = num_points {
-                    ui.label(format!("{count} points"));
+                let num_points = self
+                    .app_state
+                    .lock()
+                    .ok()
+                    .map(|s| (s.umap_df.height(), s.total_points));
+
+                match num_points {
+                    Some((count, Some(total))) if (count as u64) < total => {
+                        ui.label(format!("{count} of {total} points"));
+                    }
+                    Some((count, _)) => {
+                        ui.label(format!("{count} points"));
+                    }
+                    None => {}
                 }
             });
             cols[2].with_layout(Layout::right_to_left(Align::Center), |ui| {
                 if self.task_count.load(Ordering::Relaxed) > 0 {
+                    let fetched = self.app_state.lock().map_or(0, |s| s.fetched_points);
+
                     ui.spinner();
-                    ui.label("Loading");
+                    if fetched > 0 {
+                        ui.label(format!("Loading {fetched} points"));
+                    } else {
+                        ui.label("Loading");
+                    }
                 } else {
                     let builder = UiBuilder::new()
                         .id_salt("ready_refresh_widget")
@@ -1008,6 +2069,10 @@ impl MyEguiApp {
 }
 
 impl eframe::App for MyEguiApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings);
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if ctx.input_mut(|i| i.consume_shortcut(&SHORTCUT_QUIT)) {
             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
@@ -1036,6 +2101,64 @@ impl eframe::App for MyEguiApp {
     }
 }
 
+/// Closed outline of a selection drag: the traced path if freeform, else its bounding box
+fn lasso_outline(path: &[[f64; 2]], freeform: bool) -> Vec<[f64; 2]> {
+    let mut outline = if freeform {
+        path.to_vec()
+    } else {
+        let ([x0, y0], [x1, y1]) = (path[0], path[path.len() - 1]);
+        vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1]]
+    };
+
+    outline.push(outline[0]);
+    outline
+}
+
+/// Uuids of the projected points inside a closed polygon, by ray casting
+fn points_within(df: &DataFrame, polygon: &[[f64; 2]]) -> HashSet<String> {
+    let contains = |[x, y]: [f64; 2]| {
+        polygon
+            .iter()
+            .tuple_windows()
+            .filter(|([xa, ya], [xb, yb])| {
+                (ya > &y) != (yb > &y) && x < xa + (y - ya) * (xb - xa) / (yb - ya)
+            })
+            .count()
+            % 2
+            == 1
+    };
+
+    let (Ok(uuid), Ok(x0), Ok(x1)) = (
+        df["uuid"].str(),
+        extract_f64(df, "umap0"),
+        extract_f64(df, "umap1"),
+    ) else {
+        return HashSet::new();
+    };
+
+    izip!(uuid.iter(), x0.iter(), x1.iter())
+        .filter_map(|(uuid, x0, x1)| optzip!(uuid, x0, x1))
+        .filter(|(_, x0, x1)| contains([*x0, *x1]))
+        .map(|(uuid, _, _)| uuid.to_string())
+        .collect()
+}
+
+/// Distinct hues for each value of a categorical column, spaced by the golden ratio
+fn category_colors(values: &StringChunked) -> HashMap<String, Color32> {
+    values
+        .iter()
+        .flatten()
+        .unique()
+        .sorted()
+        .enumerate()
+        .map(|(i, value)| {
+            let hue = (i as f32 * 0.618_034).fract();
+            let color = egui::ecolor::Hsva::new(hue, 0.75, 0.95, 1.0);
+            (value.to_string(), Color32::from(color))
+        })
+        .collect()
+}
+
 fn extract_f64(df: &DataFrame, colname: &str) -> Result<Float64Chunked> {
     Ok(df
         .column(colname)?
@@ -1044,28 +2167,173 @@ fn extract_f64(df: &DataFrame, colname: &str) -> Result<Float64Chunked> {
         .to_owned())
 }
 
+/// Writes Parquet for a `.parquet` extension and CSV otherwise
+fn write_dataframe(path: &Path, df: &mut DataFrame) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("parquet") => {
+            ParquetWriter::new(file).finish(df)?;
+        }
+        _ => {
+            CsvWriter::new(file).finish(df)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Collects the payload fields in `PAYLOAD_FIELDS` into columns, keyed by uuid
+fn payloads_to_dataframe(points: &[RetrievedPoint]) -> DataFrame {
+    let ids = points
+        .iter()
+        .map(
+            |p| match p.id.as_ref().and_then(|id| id.point_id_options.as_ref()) {
+                Some(PointIdOptions::Num(id)) => Some(format!("{id}")),
+                Some(PointIdOptions::Uuid(id)) => Some(id.to_string()),
+                None => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let mut df = DataFrame::default();
+    df.with_column(Series::new("uuid".into(), ids)).unwrap();
+
+    for field in PAYLOAD_FIELDS {
+        let values = points
+            .iter()
+            .map(|p| p.payload.get(field).and_then(|v| v.as_str()).cloned())
+            .collect::<Vec<_>>();
+
+        df.with_column(Series::new(field.into(), values)).unwrap();
+    }
+
+    df
+}
+
+/// Parses a stringified point ID back into a numeric or UUID one
+fn to_point_id(uuid: &str) -> PointId {
+    // Not ideal. We really should be tracking the type in the dataframe column,
+    // but acceptable for non-critical code.
+    uuid.parse::<u64>()
+        .map(|f| f.into())
+        .unwrap_or_else(|_| uuid.into())
+}
+
+/// IDs of the points in a DataFrame with a uuid column
+fn dataframe_point_ids(df: &DataFrame) -> Vec<PointId> {
+    df.column("uuid")
+        .and_then(|column| column.str().cloned())
+        .map(|uuids| uuids.iter().flatten().map(to_point_id).collect())
+        .unwrap_or_default()
+}
+
+/// Scrolls through the points matching a filter a page at a time, up to `max_points`.
+///
+/// Progress is reported through `fetched_points`, which is cleared once done.
+async fn scroll_points(
+    qdclient: &Qdrant,
+    collection_name: &str,
+    filter: Option<Filter>,
+    max_points: usize,
+    app_state: &Mutex<AppState>,
+) -> Vec<RetrievedPoint> {
+    let mut points = Vec::new();
+    let mut offset: Option<PointId> = None;
+
+    while points.len() < max_points {
+        let limit = SCROLL_PAGE.min(max_points - points.len());
+        let mut request = ScrollPointsBuilder::new(collection_name)
+            .limit(limit as u32)
+            .with_payload(true)
+            .with_vectors(true);
+
+        if let Some(filter) = filter.clone() {
+            request = request.filter(filter);
+        }
+
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+
+        let resp = match qdclient.scroll(request).await {
+            Ok(resp) => resp,
+            Err(err) => {
+                log::warn!("Could not scroll {collection_name:?}: {err}");
+                break;
+            }
+        };
+
+        points.extend(resp.result);
+
+        if let Ok(mut app_state) = app_state.lock() {
+            app_state.fetched_points = points.len();
+        }
+
+        offset = resp.next_page_offset;
+        if offset.is_none() {
+            break;
+        }
+    }
+
+    if offset.is_some() {
+        log::warn!(
+            "Stopped fetching at {} points. Raise MAX_POINTS for more.",
+            points.len()
+        );
+    }
+
+    if let Ok(mut app_state) = app_state.lock() {
+        app_state.fetched_points = 0;
+    }
+
+    points
+}
+
 async fn refresh_collection_info(app_state: Arc<Mutex<AppState>>, qdclient: Arc<Qdrant>) {
     let selected_collection = app_state
         .lock()
         .ok()
         .and_then(|s| s.collection_name.clone());
 
-    let embed_dims = if let Some(collection_name) = selected_collection {
-        match get_vectors_config(qdclient.as_ref(), &collection_name).await {
-            Ok(VecConfig::Params(params)) => Some(params.size),
-            Ok(VecConfig::ParamsMap(params)) if params.map.contains_key("default") => {
-                params.map.get("default").map(|p| p.size)
-            }
-            _ => None,
-        }
+    let config = if let Some(collection_name) = selected_collection {
+        get_vectors_config(qdclient.as_ref(), &collection_name)
+            .await
+            .ok()
     } else {
         None
     };
 
-    if let Some(size) = embed_dims
-        && let Ok(mut app_state) = app_state.lock()
-    {
-        app_state.embed_dims = dbg!(size as usize);
+    let Some(config) = config else {
+        return;
+    };
+
+    let vector_sizes = dense_vectors(&config);
+
+    if let Ok(mut app_state) = app_state.lock() {
+        // Keep the chosen vector while the collection still has it
+        if app_state
+            .vector_name
+            .as_ref()
+            .is_none_or(|name| !vector_sizes.contains_key(name))
+        {
+            app_state.vector_name = if vector_sizes.contains_key(DEFAULT_VECTOR) {
+                Some(DEFAULT_VECTOR.to_string())
+            } else {
+                vector_sizes.keys().next().cloned()
+            };
+        }
+
+        let embed_dims = match app_state.vector_name.as_ref() {
+            Some(name) => vector_sizes.get(name).copied(),
+            None => vector_size(&config),
+        };
+
+        if let Some(size) = embed_dims {
+            app_state.embed_dims = dbg!(size as usize);
+        }
+
+        app_state.vector_sizes = Arc::new(vector_sizes);
     }
 }
 
@@ -1113,40 +2381,141 @@ fn points_to_hover_lookup(point_vecs: &Vec<(String, &Vec<f32>)>) -> HashMap<egui
         .collect()
 }
 
-/// Project embeddings from a DataFrame into 2-D coordinates using UMAP.
+/// Like [`embed_anchors`], but reuses the last result when the model and phrases are unchanged
+fn embed_anchors_cached(
+    app_lock: &Mutex<AppState>,
+    model_id: Option<EmbeddingModel>,
+    anchors: Vec<String>,
+) -> Option<DataFrame> {
+    let key = (model_id.clone(), anchors.clone());
+    if let Ok(app_state) = app_lock.lock()
+        && let Some((cached, df)) = &app_state.anchor_cache
+        && *cached == key
+    {
+        return Some(df.clone());
+    }
+
+    let df = embed_anchors(model_id, anchors)?;
+    if let Ok(mut app_state) = app_lock.lock() {
+        app_state.anchor_cache = Some((key, df.clone()));
+    }
+
+    Some(df)
+}
+
+/// Rows fitted alongside the points but not plotted: the anchors and recent queries.
+///
+/// Only queries embedded by `model_id` are included.
+fn projection_context(
+    app_lock: &Mutex<AppState>,
+    model_id: Option<EmbeddingModel>,
+    anchors: Vec<String>,
+) -> Option<DataFrame> {
+    let anchor_df = embed_anchors_cached(app_lock, model_id.clone(), anchors);
+
+    let history = app_lock
+        .lock()
+        .ok()
+        .map(|app_state| {
+            app_state
+                .query_history
+                .iter()
+                .filter(|(model, _)| Some(model) == model_id.as_ref())
+                .map(|(_, embedding)| embedding.clone())
+                .collect_vec()
+        })
+        .unwrap_or_default();
+
+    let Some(dims) = history.first().map(Vec::len) else {
+        return anchor_df;
+    };
+
+    let points = history
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("query_{i:04}"), v))
+        .collect();
+    let history_df = points_to_dataframe(dims, points);
+
+    match anchor_df {
+        Some(anchor_df) => Some(anchor_df.vstack(&history_df).unwrap_or(anchor_df)),
+        None => Some(history_df),
+    }
+}
+
+/// Embeds anchor phrases into a DataFrame laid out like [`points_to_dataframe`]
+fn embed_anchors(model_id: Option<EmbeddingModel>, anchors: Vec<String>) -> Option<DataFrame> {
+    if anchors.is_empty() {
+        return None;
+    }
+
+    let mut model = TextEmbedding::try_new(
+        fastembed::InitOptions::new(model_id?)
+            .with_show_download_progress(true)
+            .with_cache_dir(FASTEMBED_CACHE_DIR.as_str().into()),
+    )
+    .ok()?;
+
+    let embeddings = model.embed(anchors, None).ok()?;
+    let dims = embeddings.first()?.len();
+
+    let points = embeddings
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (format!("anchor_{i:04}"), v))
+        .collect();
+
+    Some(points_to_dataframe(dims, points))
+}
+
+/// Project embeddings from a DataFrame into 2-D or 3-D coordinates using a reducer.
 ///
 /// If `umap` is Some, then the existing mapping will be used to transform the embeddings.
-/// Otherwise, a new mapping will be initialized and fitted to the given data.
+/// Otherwise, a mapping is loaded from the cache or a new one is fitted to the given data.
+/// Reducers that can't transform unseen points are fitted to all of them every time.
 ///
 /// # Arguments:
-/// * `umap` - maybe a handle to a UMAP instance
+/// * `umap` - maybe a handle to a fitted estimator
+/// * `reducer` - constructs new estimators
+/// * `n_components` - number of axes projected onto, named `umap0`, `umap1`, etc
 /// * `df` - a DataFrame where each column is an embedding dimension
+/// * `anchors` - embedded anchor phrases and past queries included in a new fit
+/// * `cache` - where to load a saved mapping from and save a newly fitted one
 /// # Returns
-/// * A new DataFrame containing the points project onto a 2-D plane
+/// * A new DataFrame containing the projected points
 fn project_embeddings(
     umap: Arc<Mutex<Option<Py<PyAny>>>>,
+    reducer: &dyn Reducer,
+    n_components: usize,
     df: DataFrame,
     anchors: Option<DataFrame>,
+    cache: Option<UmapCache>,
 ) -> DataFrame {
     let x_umap = Python::attach(|py| {
+        if !reducer.inductive() {
+            log::info!("Fitting {} to all points", reducer.name());
+            return fit_transform(py, reducer, n_components, &df, anchors);
+        }
+
         let mut umap_guard = umap.lock().unwrap();
 
-        let umap = match umap_guard.as_ref() {
-            Some(umap) => {
+        let saved = match (umap_guard.as_ref(), cache.as_ref()) {
+            (None, Some(cache)) if cache.reuse => cache.load(py, df.height()),
+            _ => None,
+        };
+
+        let umap = match (umap_guard.as_ref(), saved) {
+            (Some(umap), _) => {
                 log::info!("Reusing existing umap instance");
                 umap.bind(py).clone()
             }
-            _ => {
-                log::info!("Fitting new umap instance");
-                let umap = UMAP
-                    .bind(py)
-                    .call(
-                        (),
-                        Some(&pydict! { py;
-                            "n_neighbors" => *UMAP_NEIGHBORS
-                        }),
-                    )
-                    .unwrap();
+            (None, Some(umap)) => {
+                *umap_guard = Some(umap.clone().unbind());
+                umap
+            }
+            (None, None) => {
+                log::info!("Fitting new {} instance", reducer.name());
+                let num_points = df.height();
 
                 let df = if let Some(anchor_df) = anchors {
                     dbg!(anchor_df.vstack(&df)).unwrap()
@@ -1162,8 +2531,13 @@ fn project_embeddings(
                     df
                 };
 
+                let umap = reducer.estimator(py, df.height(), n_components)?;
                 let umap = umap.call_method1("fit", (PyDataFrame(df),)).unwrap();
 
+                if let Some(cache) = cache.as_ref() {
+                    cache.save(py, &umap, num_points);
+                }
+
                 *umap_guard = Some(umap.clone().unbind());
                 umap
             }
@@ -1174,21 +2548,48 @@ fn project_embeddings(
             .call_method1("transform", (PyDataFrame(df.clone()),))
             .unwrap();
 
-        let x_u: Vec<[f32; 2]> = x_u.extract()?;
+        let x_u: Vec<Vec<f32>> = x_u.extract()?;
 
         Ok::<_, PyErr>(x_u)
     })
     .unwrap();
 
-    let (umap0, umap1): (Vec<_>, Vec<_>) = x_umap.into_iter().map(|[a, b]| (a, b)).unzip();
-
     // dbg!((&df_proj,));
-    df! {
-        "uuid" => df.column("uuid").unwrap().as_series().unwrap(),
-        "umap0" => umap0,
-        "umap1" => umap1,
+    let mut df_proj = df.select(["uuid"]).unwrap();
+    for axis in 0..n_components {
+        let values = x_umap.iter().map(|x| x[axis]).collect::<Vec<_>>();
+        df_proj
+            .with_column(Series::new(format!("umap{axis}").into(), values))
+            .unwrap();
     }
-    .unwrap()
+
+    df_proj
+}
+
+/// Projects embeddings with a reducer that can't transform points it wasn't fitted to.
+///
+/// Anchors are fitted along with the points, but left out of the result.
+fn fit_transform(
+    py: Python<'_>,
+    reducer: &dyn Reducer,
+    n_components: usize,
+    df: &DataFrame,
+    anchors: Option<DataFrame>,
+) -> PyResult<Vec<Vec<f32>>> {
+    let num_anchors = anchors.as_ref().map_or(0, |a| a.height());
+    let df = if let Some(anchor_df) = anchors {
+        anchor_df.vstack(df).unwrap()
+    } else {
+        df.clone()
+    };
+
+    let df = df.drop("uuid").unwrap();
+    let x_u = reducer
+        .estimator(py, df.height(), n_components)?
+        .call_method1("fit_transform", (PyDataFrame(df),))?;
+
+    let mut x_u: Vec<Vec<f32>> = x_u.extract()?;
+    Ok(x_u.split_off(num_anchors))
 }
 
 /// Explodes embeddings from arrays into DataFrame columns
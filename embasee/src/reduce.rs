@@ -0,0 +1,147 @@
+use std::env;
+use std::sync::LazyLock;
+
+use pyo3::prelude::*;
+
+use embasee::{pydict, pyimport};
+
+pub static UMAP: LazyLock<Py<PyAny>> = LazyLock::new(|| pyimport!("umap", "UMAP").unwrap());
+
+static PCA: LazyLock<Py<PyAny>> =
+    LazyLock::new(|| pyimport!("sklearn.decomposition", "PCA").unwrap());
+
+static TSNE: LazyLock<Py<PyAny>> = LazyLock::new(|| pyimport!("sklearn.manifold", "TSNE").unwrap());
+
+pub static UMAP_NEIGHBORS: LazyLock<u64> = LazyLock::new(|| {
+    env::var("UMAP_NEIGHBORS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(3)
+});
+
+/// A python estimator projecting embeddings onto a few axes for plotting
+pub trait Reducer: Send + Sync {
+    /// Identifies fitted models of this reducer, e.g. in cache file names
+    fn name(&self) -> &'static str;
+
+    /// A new, unfitted estimator for `num_rows` samples, projecting onto `n_components` axes
+    fn estimator<'py>(
+        &self,
+        py: Python<'py>,
+        num_rows: usize,
+        n_components: usize,
+    ) -> PyResult<Bound<'py, PyAny>>;
+
+    /// Whether a fitted estimator can place points it was not fitted to, like queries.
+    ///
+    /// Otherwise, every projection is a fresh `fit_transform` over all the points.
+    fn inductive(&self) -> bool {
+        true
+    }
+}
+
+pub struct Umap;
+
+impl Reducer for Umap {
+    fn name(&self) -> &'static str {
+        "umap"
+    }
+
+    fn estimator<'py>(
+        &self,
+        py: Python<'py>,
+        _num_rows: usize,
+        n_components: usize,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        UMAP.bind(py).call(
+            (),
+            Some(&pydict! { py;
+                "n_neighbors" => *UMAP_NEIGHBORS,
+                "n_components" => n_components
+            }),
+        )
+    }
+}
+
+/// Linear and deterministic, so layouts are stable between refreshes
+pub struct Pca;
+
+impl Reducer for Pca {
+    fn name(&self) -> &'static str {
+        "pca"
+    }
+
+    fn estimator<'py>(
+        &self,
+        py: Python<'py>,
+        _num_rows: usize,
+        n_components: usize,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        PCA.bind(py).call(
+            (),
+            Some(&pydict! { py;
+                "n_components" => n_components
+            }),
+        )
+    }
+}
+
+pub struct Tsne;
+
+impl Reducer for Tsne {
+    fn name(&self) -> &'static str {
+        "tsne"
+    }
+
+    fn estimator<'py>(
+        &self,
+        py: Python<'py>,
+        num_rows: usize,
+        n_components: usize,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        // Perplexity must be less than the number of samples
+        let perplexity = (num_rows.saturating_sub(1) as f64).clamp(1.0, 30.0);
+
+        TSNE.bind(py).call(
+            (),
+            Some(&pydict! { py;
+                "n_components" => n_components,
+                "init" => "pca",
+                "perplexity" => perplexity
+            }),
+        )
+    }
+
+    fn inductive(&self) -> bool {
+        false
+    }
+}
+
+/// Reducers selectable in the UI
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReducerKind {
+    #[default]
+    Umap,
+    Pca,
+    Tsne,
+}
+
+impl ReducerKind {
+    pub const ALL: [ReducerKind; 3] = [ReducerKind::Umap, ReducerKind::Pca, ReducerKind::Tsne];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReducerKind::Umap => "UMAP",
+            ReducerKind::Pca => "PCA",
+            ReducerKind::Tsne => "t-SNE",
+        }
+    }
+
+    pub fn reducer(&self) -> &'static dyn Reducer {
+        match self {
+            ReducerKind::Umap => &Umap,
+            ReducerKind::Pca => &Pca,
+            ReducerKind::Tsne => &Tsne,
+        }
+    }
+}
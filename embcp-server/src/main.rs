@@ -1,13 +1,9 @@
-use anyhow::Context as _;
-use cached::proc_macro::cached;
+use emberlain::search::{SearchOptions, semantic_search};
 use fastembed::{RerankInitOptions, RerankerModel, TextEmbedding, TextRerank};
 use itertools::Itertools;
 use qdrant_client::{
     Qdrant,
-    qdrant::{
-        Condition, Filter, QueryPointsBuilder, vectors_config::Config as VecConfig,
-        with_payload_selector::SelectorOptions,
-    },
+    qdrant::{Condition, Filter, with_payload_selector::SelectorOptions},
 };
 use rmcp::{
     Json, ServiceExt,
@@ -20,10 +16,7 @@ use rmcp::{
     transport::stdio,
 };
 use serde_with::skip_serializing_none;
-use std::{
-    sync::{Arc, Mutex},
-    time::Duration,
-};
+use std::sync::{Arc, Mutex};
 use typed_builder::TypedBuilder;
 
 use crate::config::{Config, get_embed_info};
@@ -39,19 +32,87 @@ struct SearchRequest {
     /// Number of results to return (default: 5)
     limit: Option<u64>,
 
-    /// Additional number of points to fetch. If non zero, will rerank results. (default: 5)
+    /// Additional number of points to fetch as candidates for reranking (default: 5)
     overfetch: Option<u64>,
 
+    /// Whether to rerank the fetched points before keeping the top `limit` (default: true if overfetch is above zero)
+    rerank: Option<bool>,
+
     /// Exclude points that have these attribute, delimited by ";" (default: "test; tokio::test")
     exclude: Option<String>,
 
     /// Payload keys to return, delimited by ";" (default: "interface; class; name; path; summary")
     fields: Option<String>,
+
+    /// Result format: "json" for payloads or "locations" for "path:start_line:end_line" strings (default: "json")
+    format: Option<SearchFormat>,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum SearchFormat {
+    #[default]
+    Json,
+    Locations,
+}
+
+#[skip_serializing_none]
 #[derive(Serialize, Deserialize, JsonSchema)]
 struct SearchResponse {
-    data: Vec<Value>,
+    /// Matching points with their selected payload fields.
+    ///
+    /// `score` is the vector similarity to the query. When results are reranked, `rerank_score`
+    /// holds the reranker's relevance, which determines the order of results.
+    data: Option<Vec<Value>>,
+
+    locations: Option<Vec<String>>,
+
+    note: Option<String>,
+}
+
+/// How many points to fetch for a request and whether to rerank them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FetchPlan {
+    /// Number of results returned
+    limit: u64,
+
+    /// Number of points fetched from the vector search
+    fetch: u64,
+
+    rerank: bool,
+}
+
+impl FetchPlan {
+    fn new(limit: Option<u64>, overfetch: Option<u64>, rerank: Option<bool>) -> Self {
+        let limit = limit.unwrap_or(5);
+        let overfetch = overfetch.unwrap_or(5);
+
+        Self {
+            limit,
+            fetch: limit + overfetch,
+            rerank: rerank.unwrap_or(overfetch > 0),
+        }
+    }
+
+    /// Keeps the top points in vector order, for when reranking is skipped
+    fn truncate(&self, mut data: Vec<Value>) -> Vec<Value> {
+        data.truncate(self.limit as usize);
+        data
+    }
+}
+
+/// Formats a point as "path:start_line:end_line", or just "path" without line numbers
+fn point_location(point: &Value) -> Option<(String, bool)> {
+    let payload = point.get("payload")?;
+    let path = payload.get("path")?.as_str()?;
+    let start = payload.get("start_line").and_then(|v| v.as_u64());
+    let end = payload.get("end_line").and_then(|v| v.as_u64());
+
+    match (start, end) {
+        (Some(start), Some(end)) => Some((format!("{path}:{start}:{end}"), true)),
+        (Some(start), None) => Some((format!("{path}:{start}:{start}"), true)),
+        _ => Some((path.to_string(), false)),
+    }
 }
 
 #[derive(TypedBuilder)]
@@ -68,29 +129,6 @@ pub struct QdrantTool {
     collection: String,
 }
 
-#[cached(
-    convert = r##"{ format!("{collection}") }"##,
-    key = "String",
-    time = 10,
-    result = true
-)]
-async fn get_vectors_config(client: &Qdrant, collection: String) -> anyhow::Result<VecConfig> {
-    let meta = client.collection_info(collection).await?;
-    let vectors_config: VecConfig = meta
-        .result
-        .context("No result")?
-        .config
-        .context("No config")?
-        .params
-        .context("No params")?
-        .vectors_config
-        .context("No vectors config")?
-        .config
-        .context("No config")?;
-
-    Ok(vectors_config)
-}
-
 #[tool_router]
 impl QdrantTool {
     #[tool]
@@ -103,24 +141,15 @@ impl QdrantTool {
             text,
             limit,
             overfetch,
+            rerank,
             exclude,
             fields,
+            format,
         }) = params;
 
-        let vec_config = get_vectors_config(&self.client, self.collection.clone())
-            .await
-            .map_err(|e| e.to_string())?;
-        let mut embeds = {
-            let mut embedder = self.embedder.lock().map_err(|e| e.to_string())?;
-            embedder
-                .embed(vec![text.clone()], None)
-                .map_err(|e| e.to_string())?
-        };
+        let format = format.unwrap_or_default();
 
-        let embedding = embeds.remove(0);
-
-        let num_results = limit.unwrap_or(5);
-        let num_fetch = num_results + overfetch.unwrap_or(5);
+        let plan = FetchPlan::new(limit, overfetch, rerank);
 
         let excluded_attrs = exclude
             .as_deref()
@@ -135,50 +164,50 @@ impl QdrantTool {
             ..Default::default()
         };
 
-        let field_selector = fields
+        let mut field_selector = fields
             .as_deref()
             .unwrap_or("interface; class; name; path; summary")
             .split(";")
             .map(|s| s.trim().to_string())
             .collect_vec();
 
-        let query = QueryPointsBuilder::new(self.collection.as_str())
-            .query(embedding.clone())
-            .with_payload(SelectorOptions::Include(field_selector.into()))
-            .filter(point_filter)
-            .limit(num_fetch);
-
-        let query = if let VecConfig::ParamsMap(_params) = vec_config {
-            // TODO: pull alias from config
-            // TODO: Check params has key
-            query.using("aliases")
-        } else {
-            query
-        };
+        if format == SearchFormat::Locations {
+            for key in ["path", "start_line", "end_line"] {
+                if !field_selector.iter().any(|f| f == key) {
+                    field_selector.push(key.to_string());
+                }
+            }
+        }
 
-        let resp = self.client.query(query).await.unwrap();
+        let opts = SearchOptions::builder()
+            .limit(plan.fetch)
+            .filter(point_filter)
+            .payload(SelectorOptions::Include(field_selector.into()))
+            .build();
 
-        let texts = resp
-            .result
-            .iter()
-            .map(|p| {
-                p.payload
-                    .get("summary")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or(&"".to_string())
-                    .to_string()
-            })
-            .collect_vec();
+        let result = semantic_search(&self.client, &self.collection, &self.embedder, &text, &opts)
+            .await
+            .map_err(|e| e.to_string())?;
 
-        let mut data = resp
-            .result
+        let mut data = result
             .iter()
             .filter_map(|point| {
                 serde_json::to_value(json!({"payload": &point.payload, "score": point.score})).ok()
             })
             .collect_vec();
 
-        let data = if num_fetch > num_results {
+        let data = if plan.rerank {
+            let texts = result
+                .iter()
+                .map(|p| {
+                    p.payload
+                        .get("summary")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or(&"".to_string())
+                        .to_string()
+                })
+                .collect_vec();
+
             let mut reranker = self.reranker.lock().map_err(|e| e.to_string())?;
             let results = reranker
                 .rerank(text, texts, true, None)
@@ -186,14 +215,47 @@ impl QdrantTool {
 
             results
                 .iter()
-                .map(|r| data[r.index].take())
-                .take(num_results as usize)
+                .map(|r| {
+                    let mut point = data[r.index].take();
+                    if let Some(entry) = point.as_object_mut() {
+                        entry.insert("rerank_score".into(), json!(r.score));
+                    }
+                    point
+                })
+                .take(plan.limit as usize)
                 .collect_vec()
         } else {
-            data
+            plan.truncate(data)
         };
 
-        Ok(Json(SearchResponse { data }))
+        let response = match format {
+            SearchFormat::Json => SearchResponse {
+                data: Some(data),
+                locations: None,
+                note: None,
+            },
+            SearchFormat::Locations => {
+                let (locations, has_lines): (Vec<_>, Vec<_>) =
+                    data.iter().filter_map(point_location).unzip();
+
+                let note = if has_lines.iter().all(|b| *b) {
+                    None
+                } else {
+                    Some(
+                        "Line numbers are not available for some results. Those locations contain only the file path."
+                            .to_string(),
+                    )
+                };
+
+                SearchResponse {
+                    data: None,
+                    locations: Some(locations),
+                    note,
+                }
+            }
+        };
+
+        Ok(Json(response))
     }
 }
 
@@ -258,3 +320,59 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fetch_plan() {
+        // Defaults rerank the overfetched points
+        let plan = FetchPlan::new(None, None, None);
+        assert_eq!(
+            plan,
+            FetchPlan {
+                limit: 5,
+                fetch: 10,
+                rerank: true,
+            }
+        );
+
+        assert!(!FetchPlan::new(Some(3), Some(0), None).rerank);
+        assert!(FetchPlan::new(Some(3), Some(0), Some(true)).rerank);
+
+        // Skipping the reranker keeps the vector order, cut down to the limit
+        let plan = FetchPlan::new(Some(2), Some(4), Some(false));
+        assert_eq!(plan.fetch, 6);
+        assert!(!plan.rerank);
+
+        let data = (0..6).map(|i| json!({"score": i})).collect_vec();
+        assert_eq!(
+            plan.truncate(data),
+            vec![json!({"score": 0}), json!({"score": 1})]
+        );
+    }
+
+    #[test]
+    fn test_empty_response() {
+        let response = SearchResponse {
+            data: Some(vec![]),
+            locations: None,
+            note: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"data": []})
+        );
+
+        let response = SearchResponse {
+            data: None,
+            locations: Some(vec![]),
+            note: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"locations": []})
+        );
+    }
+}
@@ -1,4 +1,6 @@
 use anyhow::Result;
+use emberlain::export::export_collection;
+use emberlain::manifest::{FileStamp, Manifest, ManifestUpdate};
 use emberlain::template::Templater;
 use emberlain::workers::pathfinder::Pathfinder;
 use emberlain::workers::progress::ProgressWorker;
@@ -8,7 +10,6 @@ use emberlain::{AgentFactory, LanguageMap};
 use fastembed::{EmbeddingModel, ModelInfo, TextEmbedding};
 use indicatif_log_bridge::LogWrapper;
 use log::debug;
-use qdrant_client::Qdrant;
 use std::process::exit;
 use std::sync::{Arc, LazyLock, Mutex};
 use tokio::task::JoinSet;
@@ -17,7 +18,7 @@ use tracing_log::LogTracer;
 use tracing_subscriber::EnvFilter;
 
 use emberlain::{
-    Config, Progressor, SourceWalker, init_collection,
+    Config, Progressor, STDIN_PATH, SourceWalker, connect_qdrant, init_collection,
     workers::{
         dedup::DedupWorker, embed::EmbeddingWorker, extract::ExtractingWorker,
         summarize::SummaryWorker,
@@ -57,7 +58,9 @@ async fn main() -> Result<()> {
     if CONFIG.dump_config.filter(|b| *b).is_some() {
         let config_out = Config {
             dump_config: None,
+            export: None,
             dry_run: None,
+            llm_api_key: None,
             ..CONFIG.clone()
         };
 
@@ -75,7 +78,7 @@ async fn main() -> Result<()> {
         CONFIG
             .progress
             .filter(|t| *t)
-            .map(|_| Progressor::default()),
+            .map(|_| Progressor::new(CONFIG.progress_unit.unwrap_or_default())),
     );
 
     if let Some(bars) = progressor.as_ref() {
@@ -93,12 +96,33 @@ async fn main() -> Result<()> {
         LogTracer::init()?;
     }
 
+    if let Some(out) = CONFIG.export.as_ref() {
+        let qdrant_client = connect_qdrant(CONFIG.qdrant_url.as_ref().unwrap()).await?;
+        let count = export_collection(&qdrant_client, COLLECTION_NAME.as_str(), out).await?;
+        log::info!("Exported {count} snippets to {out:?}");
+
+        return Ok(());
+    }
+
     let target_path = CONFIG.target_path.clone().unwrap();
+    let from_stdin = target_path.as_os_str() == STDIN_PATH;
+    if from_stdin && CONFIG.lang.is_none() {
+        anyhow::bail!("Reading from stdin requires --lang");
+    }
+
     let target_path = std::fs::canonicalize(&target_path).unwrap_or(target_path);
 
+    // Discover the repository from the directory containing a single file
+    let from_file = target_path.is_file();
+    let target_dir = if from_file {
+        target_path.parent().unwrap_or(&target_path).to_path_buf()
+    } else {
+        target_path.clone()
+    };
+
     let repo_root = CONFIG.repo_root.clone().unwrap_or_else(|| {
         let repo = git2::Repository::open_ext(
-            &target_path,
+            &target_dir,
             git2::RepositoryOpenFlags::empty(),
             &[] as &[&std::ffi::OsStr],
         )
@@ -107,32 +131,76 @@ async fn main() -> Result<()> {
         repo.as_ref()
             .and_then(|r| r.workdir())
             .map(|p| p.to_path_buf())
-            .unwrap_or(target_path.clone())
+            .unwrap_or(target_dir.clone())
     });
 
     log::info!("Target dir: {target_path:?} repo root: {repo_root:?}");
 
     // Configure and build all workers
-    let lang_specs: String = if let Some(path) = &CONFIG.lang_spec {
-        std::fs::read_to_string(path)?
-    } else {
-        include_str!("../../etc/languages.yml").to_string()
-    };
+    let lang_specs = LanguageMap::load(
+        CONFIG.lang_spec.as_deref(),
+        Some(&Config::config_dir().join("languages.yml")),
+    )?;
 
-    let src_walker: SourceWalker = lang_specs.as_str().try_into()?;
-    let lang_specs: Arc<LanguageMap> = Arc::new(serde_yml::from_str(&lang_specs)?);
+    let src_walker =
+        SourceWalker::from(lang_specs.clone()).with_profile(CONFIG.query_profile.clone());
+    let lang_specs: Arc<LanguageMap> = Arc::new(lang_specs);
     let templater = Templater::new(lang_specs.clone())?;
 
-    let qdrant_client = Qdrant::from_url(CONFIG.qdrant_url.as_ref().unwrap()).build()?;
-    init_collection(&qdrant_client, COLLECTION_NAME.as_str(), *EMBED_DIMS as u64).await?;
+    let qdrant_client = connect_qdrant(CONFIG.qdrant_url.as_ref().unwrap()).await?;
+    let created = init_collection(
+        &qdrant_client,
+        COLLECTION_NAME.as_str(),
+        *EMBED_DIMS as u64,
+        &CONFIG.collection_options()?,
+    )
+    .await?;
+
+    let dry_run = CONFIG.dry_run.unwrap_or_default();
+    let manifest_path = Manifest::path_for(
+        CONFIG.fastembed_cache.as_ref().unwrap(),
+        CONFIG.qdrant_url.as_ref().unwrap(),
+        COLLECTION_NAME.as_str(),
+    );
+
+    // Nothing from a previous run is in a collection that was just created
+    let manifest = if created {
+        Manifest::default()
+    } else {
+        Manifest::load(&manifest_path)?
+    };
+    let manifest_update = Arc::new(ManifestUpdate::default());
+
+    // A single file skips the pathfinder, so its stamp is recorded here
+    let single_file = from_file.then(|| {
+        target_path
+            .strip_prefix(&repo_root)
+            .unwrap_or(&target_path)
+            .to_path_buf()
+    });
+
+    if let Some(file_path) = single_file.as_ref() {
+        match FileStamp::of(&target_path) {
+            Ok(stamp) => manifest_update.stage(file_path, stamp),
+            Err(err) => log::warn!("Unable to hash {file_path:?}: {err:?}"),
+        }
+    }
 
     let pathfinder = Pathfinder::builder()
         .types(src_walker.get_types()?)
         .qdrant(qdrant_client.clone())
         .collection(CONFIG.collection.clone().unwrap())
+        .manifest(Arc::new(manifest.clone()))
+        .update(manifest_update.clone())
+        .force(CONFIG.force.unwrap_or_default() || CONFIG.reprocess.unwrap_or_default())
         .build();
 
-    let mut extractor = ExtractingWorker::builder().walker(src_walker).build();
+    let mut extractor = ExtractingWorker::builder()
+        .walker(src_walker)
+        .manifest(manifest_update.clone())
+        .min_size(CONFIG.min_size())
+        .concurrency(CONFIG.parse_concurrency.unwrap_or(1))
+        .build();
 
     let deduper = DedupWorker::builder()
         .templater(templater)
@@ -146,15 +214,17 @@ async fn main() -> Result<()> {
 
     let summarizers = (0..CONFIG.summary_workers.unwrap_or(1))
         .map(|_| {
-            let agent = agent_factory.summarizer();
-            let agent = agent.unwrap().build();
-            SummaryWorker::builder()
+            let agent = agent_factory.summarizer()?.build();
+            Ok(SummaryWorker::builder()
                 .agent(agent)
+                .concurrency(CONFIG.summarize_concurrency.unwrap_or(1))
                 .reprocess(CONFIG.reprocess.unwrap_or_default())
                 .dry_run(CONFIG.dry_run.unwrap_or_default())
-                .build()
+                .progressor(progressor.clone())
+                .manifest(manifest_update.clone())
+                .build())
         })
-        .collect::<Vec<_>>();
+        .collect::<Result<Vec<_>>>()?;
 
     let embed_model = TextEmbedding::try_new(
         fastembed::InitOptions::new(EMBED_MODEL.clone())
@@ -174,9 +244,13 @@ async fn main() -> Result<()> {
         .embedding(embed_model)
         .qdrant(qdrant_client.clone())
         .collection(CONFIG.collection.clone().unwrap())
+        .manifest(manifest_update.clone())
+        .batch_size(CONFIG.batch_size.unwrap_or(32))
         .build();
 
-    let progress_worker = ProgressWorker::builder().build();
+    let progress_worker = ProgressWorker::builder()
+        .progressor(progressor.clone())
+        .build();
 
     let pruner = CONFIG.pruning_cutoff()?.map(|dt| {
         PruningWorker::builder()
@@ -187,19 +261,12 @@ async fn main() -> Result<()> {
     });
 
     // Preliminary book keeping
-    let total_count = if CONFIG.progress.filter(|t| *t).is_some() {
-        pathfinder
-            .count_files(CONFIG.target_path.as_ref().unwrap())
-            .await
-            .ok()
-    } else {
-        None
-    };
-
     if let Some(bars) = progressor.as_ref()
-        && let Some(count) = total_count
+        && let Ok(total) = pathfinder
+            .total_work(CONFIG.target_path.as_ref().unwrap(), bars.unit)
+            .await
     {
-        bars.file_progress.set_length(count as u64);
+        bars.file_progress.set_length(total);
     }
 
     let local = task::LocalSet::new();
@@ -217,6 +284,10 @@ async fn main() -> Result<()> {
         let target_path = target_path.clone();
 
         spawn(async move {
+            if from_stdin || from_file {
+                return; // Nothing to walk, which closes the path channel
+            }
+
             if let Err(err) = pathfinder
                 .run(progressor, path_tx, repo_root, target_path)
                 .await
@@ -232,7 +303,18 @@ async fn main() -> Result<()> {
         let path_rx = path_rx.clone();
         let repo_root = repo_root.clone();
         local.spawn_local(async move {
-            if let Err(err) = extractor.run(path_rx, snippet_tx, repo_root).await {
+            let result = if from_stdin {
+                let lang = CONFIG.lang.as_deref().unwrap();
+                extractor
+                    .run_reader(tokio::io::stdin(), lang, snippet_tx)
+                    .await
+            } else if let Some(file_path) = single_file {
+                extractor.run_file(repo_root, file_path, snippet_tx).await
+            } else {
+                extractor.run(path_rx, snippet_tx, repo_root).await
+            };
+
+            if let Err(err) = result {
                 log::error!("{err:?}");
                 exit(1);
             }
@@ -313,6 +395,17 @@ async fn main() -> Result<()> {
 
     debug!("Progress worker done: {:?}", progress_task.await.err());
 
+    // Only record files once all of their snippets have made it into qdrant
+    if !dry_run {
+        let mut manifest = manifest;
+        manifest.apply(&manifest_update);
+        manifest.save(&manifest_path)?;
+        log::info!(
+            "Saved manifest of {} files to {manifest_path:?}",
+            manifest.files.len()
+        );
+    }
+
     if let Some(pruner) = pruner {
         pruner.run().await?;
     }
@@ -8,9 +8,12 @@ use figment::{
     providers::{Env, Format as _, Serialized, Toml},
 };
 use humantime::parse_duration;
+use qdrant_client::qdrant::Distance;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+use crate::{CollectionOptions, MinSize, ProgressUnit};
+
 /// Crawls a source repository, generating summaries to insert into a semantic search database.
 #[skip_serializing_none] // This is the solution!
 #[derive(Clone, Parser, Debug, Serialize, Deserialize)]
@@ -24,14 +27,30 @@ pub struct Config {
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub progress: Option<bool>,
 
+    /// What the overall progress bar counts: files, bytes or lines
+    #[arg(long, value_enum)]
+    pub progress_unit: Option<ProgressUnit>,
+
     /// Print the effective configuration and exit
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub dump_config: Option<bool>,
 
+    /// Write the snippets of the collection to this directory, with a JSON manifest, and exit
+    ///
+    /// Entries marked as removed are skipped.
+    #[arg(long)]
+    pub export: Option<PathBuf>,
+
     /// Re-summarize and embed previously processed snippets
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub reprocess: Option<bool>,
 
+    /// Process every file, even those unchanged since the last successful run
+    ///
+    /// Otherwise, files whose content hash matches the manifest under the fastembed cache are skipped.
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub force: Option<bool>,
+
     /// Remove stale entries after walking the repo
     ///
     /// Can be "all" or a duration like "30days", "1month", etc.
@@ -46,6 +65,32 @@ pub struct Config {
     #[arg(long)]
     pub summary_workers: Option<u32>,
 
+    /// Number of snippets each summarization task may have awaiting the LLM at once
+    #[arg(long)]
+    pub summarize_concurrency: Option<usize>,
+
+    /// Number of source files parsed at once
+    #[arg(long)]
+    pub parse_concurrency: Option<usize>,
+
+    /// Only compile these entries of each language's queries, separated by commas.
+    ///
+    /// All queries are used by default.
+    #[arg(long)]
+    pub query_profile: Option<String>,
+
+    /// Snippets with fewer lines are skipped rather than summarized
+    #[arg(long)]
+    pub min_lines: Option<usize>,
+
+    /// Snippets with fewer characters are skipped rather than summarized
+    #[arg(long)]
+    pub min_chars: Option<usize>,
+
+    /// Number of snippets embedded and inserted into qdrant at a time
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+
     /// Augment each entry with synthetic queries to improve semantic searches.
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub synthetics: Option<bool>,
@@ -56,10 +101,17 @@ pub struct Config {
     #[arg(long)]
     pub persona: Option<String>,
 
-    /// Base URL of the language model server
+    /// Provider of the language model, e.g. "ollama", "openai" or "openrouter"
     #[arg(long)]
     pub llm_provider: Option<String>,
 
+    /// API key for a hosted provider.
+    ///
+    /// Otherwise taken from the provider's conventional variable, e.g. OPENAI_API_KEY.
+    /// Prefer setting EMB_LLM_API_KEY over passing it as an argument.
+    #[arg(long)]
+    pub llm_api_key: Option<String>,
+
     /// Name of the language model for summarizing code snippets
     #[arg(long)]
     pub llm_model: Option<String>,
@@ -80,7 +132,21 @@ pub struct Config {
     #[arg(long)]
     pub collection: Option<String>,
 
-    /// Path to the language specification YAML file
+    /// Distance metric of a newly created collection: "cosine", "dot" or "euclidean"
+    #[arg(long)]
+    pub distance: Option<String>,
+
+    /// Store vectors and payloads of a newly created collection on disk rather than in memory
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub on_disk: Option<bool>,
+
+    /// Enable int8 scalar quantization on a newly created collection
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub quantize: Option<bool>,
+
+    /// Path to the language specification YAML file, replacing the built-in languages.
+    ///
+    /// Either way, entries in `languages.yml` under the config dir are merged on top.
     #[arg(long)]
     pub lang_spec: Option<PathBuf>,
 
@@ -90,7 +156,11 @@ pub struct Config {
     #[arg(long)]
     pub repo_root: Option<PathBuf>,
 
-    /// Path of the repository to index
+    /// Language of the source code read from stdin, when the target path is "-"
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Path of the repository, subdirectory or single file to index. Use "-" to read stdin.
     pub target_path: Option<PathBuf>,
 }
 
@@ -99,41 +169,86 @@ impl Default for Config {
         Self {
             dry_run: Default::default(),
             progress: Default::default(),
+            progress_unit: Some(ProgressUnit::Files),
             dump_config: Default::default(),
+            export: Default::default(),
             reprocess: Default::default(),
+            force: Default::default(),
             prune: Default::default(),
             summary_workers: Some(1),
+            summarize_concurrency: Some(1),
+            parse_concurrency: Some(1),
+            query_profile: None,
+            min_lines: Some(5),
+            min_chars: Some(0),
+            batch_size: Some(32),
             synthetics: Default::default(),
             persona: None,
             llm_provider: Some("ollama".into()),
+            llm_api_key: None,
             llm_model: Some("devstral:latest".into()),
             collection: Some("myproject".into()),
             qdrant_url: Some("http://localhost:6334".into()),
+            distance: Some("cosine".into()),
+            on_disk: Default::default(),
+            quantize: Default::default(),
             embed_model: Default::default(),
             fastembed_cache: dirs::cache_dir().map(|d| d.join("fastembed")),
             lang_spec: Default::default(),
             repo_root: None,
+            lang: None,
             target_path: Some("./".into()),
         }
     }
 }
 
 impl Config {
+    /// Directory holding `config.toml` and user overrides like `languages.yml`
+    pub fn config_dir() -> PathBuf {
+        dirs::config_dir()
+            .map(|p| p.join("emberlain"))
+            .unwrap_or_default()
+    }
+
     pub fn load() -> Result<Self> {
         Ok(Figment::new()
             .merge(Serialized::defaults(Config::default()))
-            .merge(Toml::file(
-                dirs::config_dir()
-                    .map(|p| p.join("emberlain"))
-                    .unwrap_or_default()
-                    .join("config.toml"),
-            ))
+            .merge(Toml::file(Self::config_dir().join("config.toml")))
             .merge(Env::prefixed("EMB_"))
             .merge(Serialized::defaults(Config::parse()))
             .select(std::env::var("EMB_PROFILE").unwrap_or_default())
             .extract()?)
     }
 
+    /// Smallest snippets extracted for summarizing
+    pub fn min_size(&self) -> MinSize {
+        let default = MinSize::default();
+        MinSize {
+            lines: self.min_lines.unwrap_or(default.lines),
+            chars: self.min_chars.unwrap_or(default.chars),
+        }
+    }
+
+    /// Parameters applied when creating the qdrant collection
+    pub fn collection_options(&self) -> Result<CollectionOptions> {
+        let distance = match self.distance.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("cosine") => Distance::Cosine,
+            Some("dot") => Distance::Dot,
+            Some("euclid" | "euclidean") => Distance::Euclid,
+            Some(other) => {
+                anyhow::bail!(
+                    "Unknown distance metric {other:?}. Expected cosine, dot or euclidean"
+                )
+            }
+        };
+
+        Ok(CollectionOptions {
+            distance,
+            on_disk: self.on_disk.unwrap_or_default(),
+            quantize: self.quantize.unwrap_or_default(),
+        })
+    }
+
     pub fn pruning_cutoff(&self) -> Result<Option<DateTime<Utc>>> {
         if let Some(dur) = self.prune.as_ref() {
             if dur == "all" || dur == "now" {
@@ -148,3 +263,45 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collection_options() {
+        let config = Config {
+            distance: Some("Dot".into()),
+            quantize: Some(true),
+            ..Default::default()
+        };
+        let opts = config.collection_options().unwrap();
+        assert_eq!(opts.distance, Distance::Dot);
+        assert!(opts.quantize);
+        assert!(!opts.on_disk);
+
+        let config = Config {
+            distance: None,
+            ..Default::default()
+        };
+        assert_eq!(
+            config.collection_options().unwrap().distance,
+            Distance::Cosine
+        );
+
+        let config = Config {
+            distance: Some("euclidean".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.collection_options().unwrap().distance,
+            Distance::Euclid
+        );
+
+        let config = Config {
+            distance: Some("manhattan".into()),
+            ..Default::default()
+        };
+        assert!(config.collection_options().is_err());
+    }
+}
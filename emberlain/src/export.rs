@@ -0,0 +1,147 @@
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::Result;
+use qdrant_client::{
+    Qdrant,
+    qdrant::{Condition, Filter, PointId, ScrollPointsBuilder, point_id::PointIdOptions},
+};
+use serde_json::{Map, Value};
+
+const PAGE_SIZE: u32 = 256;
+
+/// Writes the body of every live snippet in a collection under `out`.
+///
+/// Snippets go in `snippets/`, in directories mirroring their source paths.
+/// `manifest.json` maps point ids to the rest of their payloads.
+/// Returns the number of snippets exported.
+pub async fn export_collection(qdrant: &Qdrant, collection: &str, out: &Path) -> Result<usize> {
+    std::fs::create_dir_all(out)?;
+
+    let mut manifest = BTreeMap::new();
+    let mut offset: Option<PointId> = None;
+
+    loop {
+        let mut request = ScrollPointsBuilder::new(collection)
+            .filter(Filter::must([Condition::is_empty("__removed")]))
+            .limit(PAGE_SIZE)
+            .with_payload(true)
+            // Only payloads are needed, regardless of how the vectors are named
+            .with_vectors(false);
+
+        if let Some(offset) = offset.take() {
+            request = request.offset(offset);
+        }
+
+        let resp = qdrant.scroll(request).await?;
+
+        for point in resp.result {
+            let Some(id) = point.id.as_ref().and_then(point_id_string) else {
+                continue;
+            };
+
+            let mut payload: Map<String, Value> = point
+                .payload
+                .into_iter()
+                .map(|(k, v)| (k, v.into_json()))
+                .collect();
+
+            let body = payload
+                .remove("body")
+                .and_then(|body| body.as_str().map(str::to_string))
+                .unwrap_or_default();
+
+            let file = snippet_file(&payload, &id);
+            let dest = out.join(&file);
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&dest, body)?;
+
+            payload.insert("file".into(), Value::String(file.display().to_string()));
+            manifest.insert(id, Value::Object(payload));
+        }
+
+        match resp.next_page_offset {
+            Some(next) => offset = Some(next),
+            None => break,
+        }
+    }
+
+    std::fs::write(
+        out.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    Ok(manifest.len())
+}
+
+fn point_id_string(id: &PointId) -> Option<String> {
+    match id.point_id_options.as_ref()? {
+        PointIdOptions::Num(num) => Some(num.to_string()),
+        PointIdOptions::Uuid(uuid) => Some(uuid.clone()),
+    }
+}
+
+/// Relative location of an exported snippet, like `snippets/src/lib.rs/Config.load.1a2b3c4d.rs`.
+///
+/// The id keeps snippets with the same name apart.
+/// Only plain components of the source path are kept, so files stay under the output dir.
+fn snippet_file(payload: &Map<String, Value>, id: &str) -> PathBuf {
+    let field = |key: &str| payload.get(key).and_then(|v| v.as_str());
+    let source = Path::new(field("path").unwrap_or_default());
+
+    let name = match (field("class"), field("name")) {
+        (Some(class), Some(name)) => format!("{class}.{name}"),
+        (None, Some(name)) => name.to_string(),
+        (_, None) => "unnamed".to_string(),
+    };
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "_-.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    let short_id = &id[..id.len().min(8)];
+    let ext = source
+        .extension()
+        .map(|ext| format!(".{}", ext.display()))
+        .unwrap_or_default();
+
+    let mut file = PathBuf::from("snippets");
+    file.extend(source.components().filter_map(|part| match part {
+        Component::Normal(part) => Some(part),
+        _ => None,
+    }));
+    file.push(format!("{name}.{short_id}{ext}"));
+
+    file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_snippet_file() {
+        let payload = json!({"path": "src/lib.rs", "class": "Config", "name": "load"});
+        let file = snippet_file(payload.as_object().unwrap(), "1a2b3c4d-5e6f");
+        assert_eq!(
+            file,
+            Path::new("snippets/src/lib.rs/Config.load.1a2b3c4d.rs")
+        );
+
+        // Stays inside the output directory
+        let payload = json!({"path": "/etc/../passwd", "name": "operator<<"});
+        let file = snippet_file(payload.as_object().unwrap(), "42");
+        assert_eq!(file, Path::new("snippets/etc/passwd/operator__.42"));
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use async_trait::async_trait;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use indoc::indoc;
@@ -6,10 +6,16 @@ use qdrant_client::{
     Qdrant,
     qdrant::{
         CreateCollectionBuilder, CreateFieldIndexCollectionBuilder, Distance, FieldType,
-        MultiVectorComparator, MultiVectorConfigBuilder, VectorParamsBuilder, VectorsConfigBuilder,
+        MultiVectorComparator, MultiVectorConfigBuilder, ScalarQuantizationBuilder,
+        VectorParamsBuilder, VectorsConfigBuilder, vectors_config::Config as VecConfig,
     },
 };
-use rig::{agent::Agent, completion::Prompt, extractor::Extractor};
+use rig::{
+    agent::{Agent, AgentBuilder},
+    client::ProviderValue,
+    completion::Prompt,
+    extractor::Extractor,
+};
 use rig::{
     client::{
         builder::{BoxAgentBuilder, DynClientBuilder},
@@ -19,10 +25,21 @@ use rig::{
 };
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 pub mod config;
+pub mod export;
+pub mod manifest;
 pub mod parse;
+pub mod search;
 pub mod snippet;
 pub mod template;
 pub mod traverse;
@@ -56,30 +73,129 @@ const SUMMARY_PREAMBLE: &str = indoc! {r##"
     Keep your explanation in paragraph format, using complete sentences.
 "##};
 
+/// What the overall progress bar counts as files complete
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProgressUnit {
+    #[default]
+    Files,
+    Bytes,
+    Lines,
+}
+
+impl ProgressUnit {
+    /// Amount of work a file contributes to the overall bar. Unreadable files count as zero.
+    pub fn measure(&self, path: impl AsRef<Path>) -> u64 {
+        let path = path.as_ref();
+        match self {
+            ProgressUnit::Files => 1,
+            ProgressUnit::Bytes => path.metadata().map(|m| m.len()).unwrap_or_default(),
+            ProgressUnit::Lines => std::fs::read(path)
+                .map(|bytes| bytes.iter().filter(|b| **b == b'\n').count() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
 pub struct Progressor {
     pub multi: MultiProgress,
     pub file_progress: ProgressBar,
+    pub unit: ProgressUnit,
+
+    /// Measure of each file still in the pipeline, credited to the overall bar once done
+    weights: Mutex<HashMap<PathBuf, u64>>,
+
+    /// Snippets extracted but not yet picked up for summarization
+    pub pending_summary: QueueDepth,
+
+    /// Snippets past summarization but not yet inserted into Qdrant
+    pub pending_insert: QueueDepth,
 }
 
 impl Default for Progressor {
     fn default() -> Self {
+        Self::new(ProgressUnit::default())
+    }
+}
+
+impl Progressor {
+    pub fn new(unit: ProgressUnit) -> Self {
         let multi = MultiProgress::new();
         let file_progress = multi.add(ProgressBar::no_length());
 
         // Something funny going on with the duration calculation:
         // Fluctuates between seconds and days, even at half way point.
         // It's based off of steps per second instead of elapsed time and percent complete
-        file_progress.set_style(
-            ProgressStyle::with_template("[{elapsed_precise}] {wide_bar} {pos}/{len} ({percent}%)")
-                .unwrap(),
-        );
+        let template = match unit {
+            ProgressUnit::Bytes => {
+                "[{elapsed_precise}] {wide_bar} {decimal_bytes}/{decimal_total_bytes} ({percent}%)"
+            }
+            _ => "[{elapsed_precise}] {wide_bar} {pos}/{len} ({percent}%)",
+        };
+        file_progress.set_style(ProgressStyle::with_template(template).unwrap());
         file_progress.enable_steady_tick(Duration::from_secs(5));
 
+        let pending_summary = QueueDepth::new(&multi, "pending summary");
+        let pending_insert = QueueDepth::new(&multi, "pending insert");
+
         Self {
             multi,
             file_progress,
+            unit,
+            weights: Default::default(),
+            pending_summary,
+            pending_insert,
         }
     }
+
+    /// Remembers how much a file sent down the pipeline counts towards the overall bar
+    pub fn start_file(&self, file_path: impl Into<PathBuf>, weight: u64) {
+        self.weights
+            .lock()
+            .unwrap()
+            .insert(file_path.into(), weight);
+    }
+
+    /// Credits a finished file to the overall bar
+    pub fn finish_file(&self, file_path: impl AsRef<Path>) {
+        let weight = self.weights.lock().unwrap().remove(file_path.as_ref());
+        self.file_progress.inc(weight.unwrap_or(1));
+    }
+}
+
+/// Counts snippets waiting between two stages of the pipeline.
+///
+/// Helps tell whether the LLM or Qdrant is the bottleneck.
+pub struct QueueDepth {
+    count: AtomicUsize,
+    bar: ProgressBar,
+}
+
+impl QueueDepth {
+    fn new(multi: &MultiProgress, label: &'static str) -> Self {
+        let bar = multi.add(ProgressBar::no_length());
+        bar.set_style(ProgressStyle::with_template("{prefix:>16}: {pos}").unwrap());
+        bar.set_prefix(label);
+
+        Self {
+            count: Default::default(),
+            bar,
+        }
+    }
+
+    pub fn push(&self) {
+        let count = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+        self.bar.set_position(count as u64);
+    }
+
+    pub fn pop(&self) {
+        let count = self
+            .count
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .map(|n| n - 1)
+            .unwrap_or_default();
+        self.bar.set_position(count as u64);
+    }
 }
 
 pub enum SnippetProgress {
@@ -97,6 +213,7 @@ pub enum SnippetProgress {
         clean: bool,
     },
     EndOfFile {
+        file_path: PathBuf,
         progressor: Arc<Option<Progressor>>,
         progress: Option<ProgressBar>,
     },
@@ -159,12 +276,18 @@ impl<T: AsyncFn() -> Result<()> + Send + Sync> DynChecker for T {
     }
 }
 
+/// Providers that run locally and need no API key
+const KEYLESS_PROVIDERS: &[&str] = &["ollama"];
+
 #[derive(Clone, Debug, Default)]
 pub struct AgentFactory {
     provider: String,
 
     model: String,
 
+    /// Overrides the key the client would read from the environment
+    api_key: Option<String>,
+
     summary_preamble: Option<String>,
 
     synth_preamble: Option<String>,
@@ -176,6 +299,7 @@ impl AgentFactory {
         Self {
             provider: config.llm_provider.clone().unwrap(),
             model: config.llm_model.clone().unwrap(),
+            api_key: config.llm_api_key.clone(),
             summary_preamble: config.persona.clone(),
             // TODO: config extractor PREAMBLE
             ..Default::default()
@@ -184,7 +308,25 @@ impl AgentFactory {
 
     pub fn model(&self) -> anyhow::Result<CompletionModelHandle<'static>> {
         let client = DynClientBuilder::new();
-        let model = client.completion(&self.provider, &self.model)?;
+        let provider = self.provider.as_str();
+
+        let model = if let Some(key) = &self.api_key {
+            client
+                .build_val(provider, ProviderValue::Simple(key.clone()))?
+                .as_completion()
+                .with_context(|| format!("Provider {provider} does not support completions"))?
+                .completion_model(&self.model)
+        } else {
+            // Hosted providers otherwise panic deep in the client when their key is unset
+            let env_var = format!("{}_API_KEY", provider.to_uppercase());
+            if !KEYLESS_PROVIDERS.contains(&provider) && std::env::var_os(&env_var).is_none() {
+                anyhow::bail!(
+                    "Provider {provider} requires an API key. Set EMB_LLM_API_KEY or {env_var}"
+                );
+            }
+
+            client.completion(provider, &self.model)?
+        };
 
         Ok(CompletionModelHandle {
             inner: Arc::from(model),
@@ -192,7 +334,7 @@ impl AgentFactory {
     }
 
     pub fn agent(&self) -> anyhow::Result<BoxAgentBuilder<'static>> {
-        Ok(DynClientBuilder::new().agent(&self.provider, &self.model)?)
+        Ok(AgentBuilder::new(self.model()?))
     }
 
     pub fn summarizer(&self) -> anyhow::Result<BoxAgentBuilder<'static>> {
@@ -212,26 +354,74 @@ impl AgentFactory {
     }
 }
 
-pub async fn init_collection(client: &Qdrant, collection: &str, dims: u64) -> Result<()> {
-    if !client.collection_exists(collection).await? {
-        // let vectors_config = VectorParamsBuilder::new(dims, Distance::Cosine);
+/// Creates a client for the qdrant server and checks that it is reachable
+pub async fn connect_qdrant(url: &str) -> Result<Qdrant> {
+    let client = Qdrant::from_url(url)
+        .build()
+        .with_context(|| format!("Invalid qdrant URL {url:?}"))?;
+
+    client.health_check().await.with_context(|| {
+        format!("Unable to connect to qdrant at {url:?}. Check `qdrant_url` or EMB_QDRANT_URL")
+    })?;
+
+    Ok(client)
+}
+
+/// Parameters applied when a collection is first created
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollectionOptions {
+    pub distance: Distance,
+    pub on_disk: bool,
+    pub quantize: bool,
+}
+
+impl Default for CollectionOptions {
+    fn default() -> Self {
+        Self {
+            distance: Distance::Cosine,
+            on_disk: false,
+            quantize: false,
+        }
+    }
+}
+
+/// Creates the collection and its payload indices if needed.
+///
+/// Returns true when the collection did not exist before.
+pub async fn init_collection(
+    client: &Qdrant,
+    collection: &str,
+    dims: u64,
+    opts: &CollectionOptions,
+) -> Result<bool> {
+    let created = !client.collection_exists(collection).await?;
+    if created {
         let mut vectors_config = VectorsConfigBuilder::default();
         vectors_config.add_named_vector_params(
-            "default",
-            VectorParamsBuilder::new(dims, Distance::Cosine).build(),
+            search::DEFAULT_VECTOR,
+            VectorParamsBuilder::new(dims, opts.distance)
+                .on_disk(opts.on_disk)
+                .build(),
         );
         vectors_config.add_named_vector_params(
-            "aliases",
-            VectorParamsBuilder::new(dims, Distance::Cosine)
+            search::ALIAS_VECTOR,
+            VectorParamsBuilder::new(dims, opts.distance)
+                .on_disk(opts.on_disk)
                 .multivector_config(MultiVectorConfigBuilder::new(MultiVectorComparator::MaxSim))
                 .build(),
         );
 
-        client
-            .create_collection(
-                CreateCollectionBuilder::new(collection).vectors_config(vectors_config),
-            )
-            .await?;
+        let mut builder = CreateCollectionBuilder::new(collection)
+            .vectors_config(vectors_config)
+            .on_disk_payload(opts.on_disk);
+
+        if opts.quantize {
+            builder = builder.quantization_config(ScalarQuantizationBuilder::default());
+        }
+
+        client.create_collection(builder).await?;
+    } else {
+        check_collection(client, collection, opts).await?;
     }
 
     for field in ["path", "name", "hash", "attributes"] {
@@ -253,6 +443,70 @@ pub async fn init_collection(client: &Qdrant, collection: &str, dims: u64) -> Re
         ))
         .await?;
 
+    Ok(created)
+}
+
+/// Warns when an existing collection was created with different options than requested
+async fn check_collection(
+    client: &Qdrant,
+    collection: &str,
+    opts: &CollectionOptions,
+) -> Result<()> {
+    let Some(config) = client
+        .collection_info(collection)
+        .await?
+        .result
+        .and_then(|info| info.config)
+    else {
+        return Ok(());
+    };
+
+    let quantized = config.quantization_config.is_some();
+    if quantized != opts.quantize {
+        log::warn!(
+            "Collection {collection} has quantization {quantized}, requested {}",
+            opts.quantize
+        );
+    }
+
+    let Some(params) = config.params else {
+        return Ok(());
+    };
+
+    if params.on_disk_payload != opts.on_disk {
+        log::warn!(
+            "Collection {collection} has on-disk payload {}, requested {}",
+            params.on_disk_payload,
+            opts.on_disk
+        );
+    }
+
+    let vectors = match params.vectors_config.and_then(|c| c.config) {
+        Some(VecConfig::Params(params)) => vec![params],
+        Some(VecConfig::ParamsMap(params)) => params.map.into_values().collect(),
+        None => vec![],
+    };
+
+    for vector in vectors {
+        let distance = Distance::try_from(vector.distance).unwrap_or_default();
+        log::info!("Collection {collection} uses {distance:?} distance");
+        if distance != opts.distance {
+            log::warn!(
+                "Collection {collection} uses {:?} distance, requested {:?}",
+                distance,
+                opts.distance
+            );
+        }
+
+        let on_disk = vector.on_disk.unwrap_or_default();
+        if on_disk != opts.on_disk {
+            log::warn!(
+                "Collection {collection} has on-disk vectors {on_disk}, requested {}",
+                opts.on_disk
+            );
+        }
+    }
+
     Ok(())
 }
 
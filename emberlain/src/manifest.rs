@@ -0,0 +1,189 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Content hash and modification time of a source file when it was indexed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    /// Hex encoded blake3 hash of the file contents
+    pub hash: String,
+
+    /// Seconds since the epoch, when available
+    pub mtime: Option<u64>,
+}
+
+impl FileStamp {
+    pub fn of(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)?;
+        let mtime = path
+            .metadata()?
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs());
+
+        Ok(Self {
+            hash: blake3::hash(&bytes).to_hex().to_string(),
+            mtime,
+        })
+    }
+}
+
+/// Files successfully indexed into a collection by previous runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub files: BTreeMap<PathBuf, FileStamp>,
+}
+
+impl Manifest {
+    /// Location of the manifest for a collection, under the fastembed cache dir.
+    ///
+    /// Keyed by the server URL too, since collections on different servers can share a name.
+    pub fn path_for(cache_dir: impl AsRef<Path>, qdrant_url: &str, collection: &str) -> PathBuf {
+        let server = blake3::hash(qdrant_url.as_bytes()).to_hex();
+        cache_dir
+            .as_ref()
+            .join("emberlain")
+            .join(format!("{collection}-{}.manifest.json", &server[..16]))
+    }
+
+    /// Reads the manifest, or starts an empty one if it doesn't exist yet
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        match std::fs::read(path.as_ref()) {
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes to a temporary file first so an interrupted save leaves the old manifest intact
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+
+        Ok(())
+    }
+
+    pub fn is_unchanged(&self, file_path: impl AsRef<Path>, stamp: &FileStamp) -> bool {
+        self.files
+            .get(file_path.as_ref())
+            .is_some_and(|prev| prev.hash == stamp.hash)
+    }
+
+    /// Records the files of a finished run, except those that failed along the way
+    pub fn apply(&mut self, update: &ManifestUpdate) {
+        let ManifestUpdateInner {
+            staged,
+            failed,
+            missing,
+        } = std::mem::take(&mut *update.0.lock().unwrap());
+
+        for file_path in missing.iter().chain(failed.iter()) {
+            self.files.remove(file_path);
+        }
+
+        for (file_path, stamp) in staged {
+            if !failed.contains(&file_path) {
+                self.files.insert(file_path, stamp);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ManifestUpdateInner {
+    staged: BTreeMap<PathBuf, FileStamp>,
+    failed: BTreeSet<PathBuf>,
+    missing: BTreeSet<PathBuf>,
+}
+
+/// Changes to the manifest collected by workers during a run.
+///
+/// Only applied once the pipeline has drained, so an interrupted run never marks
+/// a file as indexed before its snippets have been inserted.
+#[derive(Debug, Default)]
+pub struct ManifestUpdate(Mutex<ManifestUpdateInner>);
+
+impl ManifestUpdate {
+    /// A file about to be indexed
+    pub fn stage(&self, file_path: impl Into<PathBuf>, stamp: FileStamp) {
+        let mut inner = self.0.lock().unwrap();
+        inner.staged.insert(file_path.into(), stamp);
+    }
+
+    /// A file with a snippet that could not be parsed, summarized or inserted
+    pub fn fail(&self, file_path: impl Into<PathBuf>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.failed.insert(file_path.into());
+    }
+
+    /// A file that no longer exists in the repository
+    pub fn forget(&self, file_path: impl Into<PathBuf>) {
+        let mut inner = self.0.lock().unwrap();
+        inner.missing.insert(file_path.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stamp(hash: &str) -> FileStamp {
+        FileStamp {
+            hash: hash.into(),
+            mtime: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_update() {
+        let mut manifest = Manifest::default();
+        manifest.files.insert("a.rs".into(), stamp("a0"));
+        manifest.files.insert("b.rs".into(), stamp("b0"));
+        manifest.files.insert("c.rs".into(), stamp("c0"));
+
+        let update = ManifestUpdate::default();
+        update.stage("a.rs", stamp("a1"));
+        update.stage("b.rs", stamp("b1"));
+        update.fail("b.rs");
+        update.forget("c.rs");
+        update.stage("d.rs", stamp("d1"));
+        manifest.apply(&update);
+
+        assert!(manifest.is_unchanged("a.rs", &stamp("a1")));
+        assert!(!manifest.is_unchanged("a.rs", &stamp("a0")));
+        // Failed files are retried on the next run
+        assert!(!manifest.files.contains_key(Path::new("b.rs")));
+        assert!(!manifest.files.contains_key(Path::new("c.rs")));
+        assert!(manifest.is_unchanged("d.rs", &stamp("d1")));
+    }
+
+    #[test]
+    fn test_path_per_server() {
+        let local = Manifest::path_for("/cache", "http://localhost:6334", "code");
+        let remote = Manifest::path_for("/cache", "http://qdrant:6334", "code");
+
+        assert_ne!(local, remote);
+        assert_eq!(
+            local,
+            Manifest::path_for("/cache", "http://localhost:6334", "code")
+        );
+        assert_ne!(
+            local,
+            Manifest::path_for("/cache", "http://localhost:6334", "docs")
+        );
+    }
+}
@@ -0,0 +1,240 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use anyhow::Context as _;
+use cached::proc_macro::cached;
+use fastembed::TextEmbedding;
+use qdrant_client::{
+    Qdrant,
+    qdrant::{
+        Filter, QueryPointsBuilder, ScoredPoint, vectors_config::Config as VecConfig,
+        with_payload_selector::SelectorOptions,
+    },
+};
+use typed_builder::TypedBuilder;
+
+/// Named vector holding the embedding of each snippet's summary
+pub const DEFAULT_VECTOR: &str = "default";
+
+/// Named multi-vector holding the summary along with its synthetic queries
+pub const ALIAS_VECTOR: &str = "aliases";
+
+/// Parameters for [`semantic_query`] and [`semantic_search`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct SearchOptions {
+    /// Number of points to return
+    #[builder(default = 10)]
+    pub limit: u64,
+
+    #[builder(default, setter(strip_option))]
+    pub filter: Option<Filter>,
+
+    /// Payload fields to include in results. Payloads are omitted when unset.
+    #[builder(default, setter(strip_option, into))]
+    pub payload: Option<SelectorOptions>,
+
+    /// Named vector to search. Prefers aliases, then default vectors, when unset.
+    #[builder(default, setter(strip_option, into))]
+    pub using: Option<String>,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+#[cached(
+    convert = r##"{ format!("{collection}") }"##,
+    key = "String",
+    time = 10,
+    result = true
+)]
+pub async fn get_vectors_config(client: &Qdrant, collection: &str) -> anyhow::Result<VecConfig> {
+    let meta = client.collection_info(collection).await?;
+    let vectors_config: VecConfig = meta
+        .result
+        .context("No result")?
+        .config
+        .context("No config")?
+        .params
+        .context("No params")?
+        .vectors_config
+        .context("No vectors config")?
+        .config
+        .context("No config")?;
+
+    Ok(vectors_config)
+}
+
+/// Dimensions of the embeddings stored in a collection
+pub fn vector_size(config: &VecConfig) -> Option<u64> {
+    match config {
+        VecConfig::Params(params) => Some(params.size),
+        VecConfig::ParamsMap(params) => params.map.get(DEFAULT_VECTOR).map(|p| p.size),
+    }
+}
+
+/// Sizes of the named single vectors, excluding multi-vectors like aliases
+pub fn dense_vectors(config: &VecConfig) -> BTreeMap<String, u64> {
+    match config {
+        VecConfig::Params(_) => BTreeMap::new(),
+        VecConfig::ParamsMap(params) => params
+            .map
+            .iter()
+            .filter(|(_, p)| p.multivector_config.is_none())
+            .map(|(name, p)| (name.clone(), p.size))
+            .collect(),
+    }
+}
+
+/// Selects the named vector to query, or none for collections with a single unnamed vector
+pub fn query_vector(config: &VecConfig, requested: Option<&str>) -> anyhow::Result<Option<String>> {
+    let VecConfig::ParamsMap(params) = config else {
+        return Ok(None);
+    };
+
+    if let Some(name) = requested {
+        anyhow::ensure!(params.map.contains_key(name), "No vector named {name}");
+        return Ok(Some(name.to_string()));
+    }
+
+    [ALIAS_VECTOR, DEFAULT_VECTOR]
+        .into_iter()
+        .find(|name| params.map.contains_key(*name))
+        .map(|name| Some(name.to_string()))
+        .context("No suitable named vector in collection")
+}
+
+/// Finds the points nearest to an embedding
+pub async fn semantic_query(
+    client: &Qdrant,
+    collection: &str,
+    embedding: Vec<f32>,
+    opts: &SearchOptions,
+) -> anyhow::Result<Vec<ScoredPoint>> {
+    let vec_config = get_vectors_config(client, collection).await?;
+
+    let mut query = QueryPointsBuilder::new(collection)
+        .query(embedding)
+        .limit(opts.limit);
+
+    if let Some(filter) = &opts.filter {
+        query = query.filter(filter.clone());
+    }
+
+    if let Some(payload) = &opts.payload {
+        query = query.with_payload(payload.clone());
+    }
+
+    if let Some(name) = query_vector(&vec_config, opts.using.as_deref())? {
+        query = query.using(name);
+    }
+
+    Ok(client.query(query).await?.result)
+}
+
+/// Embeds the text then finds the nearest points
+pub async fn semantic_search(
+    client: &Qdrant,
+    collection: &str,
+    model: &Mutex<TextEmbedding>,
+    text: &str,
+    opts: &SearchOptions,
+) -> anyhow::Result<Vec<ScoredPoint>> {
+    let embedding = {
+        let mut model = model.lock().map_err(|e| anyhow::anyhow!("{e}"))?;
+        model
+            .embed(vec![text], None)?
+            .pop()
+            .context("No embedding produced")?
+    };
+
+    semantic_query(client, collection, embedding, opts).await
+}
+
+#[cfg(test)]
+mod tests {
+    use qdrant_client::qdrant::{
+        Distance, MultiVectorComparator, MultiVectorConfigBuilder, VectorParamsBuilder,
+        VectorsConfig, VectorsConfigBuilder,
+    };
+
+    use super::*;
+
+    fn named_config(names: &[&str]) -> VecConfig {
+        let mut builder = VectorsConfigBuilder::default();
+        for name in names {
+            let params = VectorParamsBuilder::new(4, Distance::Cosine);
+            let params = if *name == ALIAS_VECTOR {
+                params.multivector_config(MultiVectorConfigBuilder::new(
+                    MultiVectorComparator::MaxSim,
+                ))
+            } else {
+                params
+            };
+            builder.add_named_vector_params(*name, params.build());
+        }
+
+        VectorsConfig::from(builder).config.unwrap()
+    }
+
+    #[test]
+    fn test_unnamed_vector() {
+        let config = VecConfig::Params(VectorParamsBuilder::new(4, Distance::Cosine).build());
+
+        assert_eq!(query_vector(&config, None).unwrap(), None);
+        assert_eq!(vector_size(&config), Some(4));
+    }
+
+    #[test]
+    fn test_prefers_aliases() {
+        let config = named_config(&[DEFAULT_VECTOR, ALIAS_VECTOR]);
+
+        assert_eq!(
+            query_vector(&config, None).unwrap().as_deref(),
+            Some(ALIAS_VECTOR)
+        );
+        assert_eq!(vector_size(&config), Some(4));
+    }
+
+    #[test]
+    fn test_dense_vectors() {
+        let config = named_config(&[DEFAULT_VECTOR, ALIAS_VECTOR, "code"]);
+        let names = dense_vectors(&config).into_keys().collect::<Vec<_>>();
+        assert_eq!(names, vec!["code", DEFAULT_VECTOR]);
+
+        let config = VecConfig::Params(VectorParamsBuilder::new(4, Distance::Cosine).build());
+        assert!(dense_vectors(&config).is_empty());
+    }
+
+    #[test]
+    fn test_falls_back_to_default() {
+        let config = named_config(&[DEFAULT_VECTOR]);
+
+        assert_eq!(
+            query_vector(&config, None).unwrap().as_deref(),
+            Some(DEFAULT_VECTOR)
+        );
+    }
+
+    #[test]
+    fn test_requested_vector() {
+        let config = named_config(&[DEFAULT_VECTOR, ALIAS_VECTOR]);
+
+        assert_eq!(
+            query_vector(&config, Some(DEFAULT_VECTOR))
+                .unwrap()
+                .as_deref(),
+            Some(DEFAULT_VECTOR)
+        );
+        assert!(query_vector(&config, Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_no_suitable_vector() {
+        let config = named_config(&["other"]);
+
+        assert!(query_vector(&config, None).is_err());
+        assert_eq!(vector_size(&config), None);
+    }
+}
@@ -2,10 +2,16 @@ use std::borrow::Cow;
 
 use anyhow::Result;
 use cached::proc_macro::cached;
+use itertools::{Itertools as _, MinMaxResult};
+use log::warn;
+use qdrant_client::qdrant::PointId;
 use rig::Embed;
 use serde_with::{serde_as, skip_serializing_none};
+use tree_sitter::Query;
 use uuid::Uuid;
 
+use crate::parse::Breadcrumb;
+
 #[cached]
 fn make_id_hash(
     path: String,
@@ -23,6 +29,28 @@ fn make_id_hash(
     blake3::hash(data.as_bytes()).as_bytes().to_vec()
 }
 
+/// Smallest snippets worth summarizing. Anything shorter is dropped at extraction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinSize {
+    pub lines: usize,
+    pub chars: usize,
+}
+
+impl Default for MinSize {
+    /// Skips trivial declarations: one-liners, aliases, forward declarations, etc.
+    fn default() -> Self {
+        Self { lines: 5, chars: 0 }
+    }
+}
+
+impl MinSize {
+    // TODO: principled cutoff logic. Ideally exclude code with a single
+    // statement, not counting signature, braces, comments, etc
+    pub fn admits(&self, snippet: &CodeSnippet) -> bool {
+        snippet.body.lines().count() >= self.lines && snippet.body.chars().count() >= self.chars
+    }
+}
+
 #[serde_as]
 #[skip_serializing_none]
 #[derive(Embed, serde::Deserialize, serde::Serialize, Debug, Clone, Default)]
@@ -39,12 +67,27 @@ pub struct CodeSnippet {
 
     pub attributes: Vec<String>,
 
+    /// Kind of definition from the query capture, e.g. "function", "method" or "class"
+    pub kind: Option<String>,
+
     /// The name of this function/method/type/etc
     pub name: String,
 
     /// The contents of the snippet
     pub body: String,
 
+    /// First line of the definition, starting from 1
+    pub start_line: Option<usize>,
+
+    /// Last line of the definition, inclusive
+    pub end_line: Option<usize>,
+
+    /// Byte offset of the definition in the file
+    pub start_byte: Option<usize>,
+
+    /// Byte offset just past the end of the definition
+    pub end_byte: Option<usize>,
+
     /// An LLM generated summary
     #[embed]
     pub summary: String,
@@ -59,6 +102,63 @@ pub struct CodeSnippet {
 }
 
 impl CodeSnippet {
+    /// Builds a snippet from the captures of a query match, without its path.
+    ///
+    /// Capture names are split on dots:
+    /// - `definition.<kind>` spans the body and source range, joined with any other definition captures
+    /// - `name.definition.<kind>` is the name
+    /// - `name.reference.interface` or `interface` is the trait/interface implemented
+    /// - `name.reference.class` is the enclosing class/type
+    /// - `attribute` or `annotation` are collected as attributes
+    ///
+    /// Returns None if the match has no definition capture.
+    pub fn from_match(query: &Query, crumb: &Breadcrumb, src: &[u8]) -> Option<CodeSnippet> {
+        let mut snippet = CodeSnippet::default();
+        let mut name = None;
+        let mut bounds = Vec::new();
+
+        for cap in &crumb.captures {
+            let Some(cap_name) = query.capture_names().get(cap.index as usize) else {
+                continue;
+            };
+
+            let text = || cap.node.utf8_text(src).ok().map(str::to_string);
+            let parts: Vec<&str> = cap_name.split(".").collect();
+            match parts.as_slice() {
+                // What's more common "attribute", "annotation", "decorator"?
+                ["attribute"] | ["annotation"] => snippet.attributes.extend(text()),
+                ["definition", kind] => {
+                    snippet.kind.get_or_insert_with(|| kind.to_string());
+                    let range = cap.node.range();
+                    bounds.push((range.start_byte, range.start_point.row));
+                    bounds.push((range.end_byte, range.end_point.row));
+                }
+                ["name", "definition", _] => name = text().or(name),
+                ["name", "reference", "interface"] | ["interface"] => {
+                    snippet.interface = text().or(snippet.interface)
+                }
+                ["name", "reference", "class"] => snippet.class = text().or(snippet.class),
+                _ => {
+                    warn!("Don't know what to do with this capture: {cap_name}")
+                }
+            }
+        }
+
+        let MinMaxResult::MinMax((a, first_row), (b, last_row)) = bounds.into_iter().minmax()
+        else {
+            return None;
+        };
+
+        snippet.body = str::from_utf8(&src[a..b]).ok()?.to_string();
+        snippet.start_line = Some(first_row + 1);
+        snippet.end_line = Some(last_row + 1);
+        snippet.start_byte = Some(a);
+        snippet.end_byte = Some(b);
+        snippet.name = name.unwrap_or("???".to_string());
+
+        Some(snippet)
+    }
+
     pub fn uuid(&self) -> Result<Uuid> {
         let CodeSnippet {
             path,
@@ -80,6 +180,12 @@ impl CodeSnippet {
         Ok(Uuid::new_v8(hash[..16].try_into()?))
     }
 
+    /// Qdrant point ID derived from the snippet's location rather than its contents,
+    /// so re-indexing a changed snippet upserts over the previous point.
+    pub fn point_id(&self) -> Result<PointId> {
+        Ok(self.uuid()?.to_string().into())
+    }
+
     pub fn body(&self) -> Cow<'_, String> {
         if self.rendered.is_empty() {
             Cow::Borrowed(&self.body)
@@ -88,3 +194,141 @@ impl CodeSnippet {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::anyhow;
+
+    use super::*;
+    use crate::{SourceWalker, parse::process_node, traverse::BUILTIN_LANGUAGES};
+
+    /// Extracts snippets from rust source using the bundled queries
+    async fn extract_rust(source_code: &[u8]) -> Result<Vec<CodeSnippet>> {
+        let mut src_walk = SourceWalker::default();
+        src_walk.load_languages(BUILTIN_LANGUAGES)?;
+
+        let snipper = src_walk.snipper_for_ext("rs").await?;
+        let query = snipper.query.clone();
+        let tree = snipper
+            .parser
+            .parse(source_code, None)
+            .ok_or(anyhow!("Could not parse"))?;
+
+        let snippets = Mutex::new(vec![]);
+        process_node(tree.root_node(), source_code, &query, vec![], &async |it| {
+            if let Some(snippet) = CodeSnippet::from_match(&query, &it.query_match, source_code) {
+                snippets.lock().unwrap().push(snippet);
+            }
+        })
+        .await;
+
+        Ok(snippets.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_min_size() -> Result<()> {
+        let source_code = r#"
+fn alias() -> u32 { 42 }
+
+fn two() {
+}
+        "#
+        .as_bytes();
+
+        let snippets = extract_rust(source_code).await?;
+        assert_eq!(snippets.len(), 2);
+
+        let min_size = MinSize { lines: 2, chars: 5 };
+        assert!(!min_size.admits(&snippets[0]));
+        assert!(min_size.admits(&snippets[1]));
+
+        let min_size = MinSize {
+            lines: 1,
+            chars: 40,
+        };
+        assert!(!min_size.admits(&snippets[0]));
+        assert!(!min_size.admits(&snippets[1]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_from_match() -> Result<()> {
+        let source_code = r#"
+            struct Foobar {
+                foo: String,
+            }
+
+            impl Display for Foobar {
+                fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+                    write!(f, "{}", self.foo)
+                }
+            }
+        "#
+        .as_bytes();
+
+        let snippets = extract_rust(source_code).await?;
+        assert_eq!(snippets.len(), 2);
+
+        let class = &snippets[0];
+        assert_eq!(class.name, "Foobar");
+        assert_eq!((class.start_line, class.end_line), (Some(2), Some(4)));
+        let (start, end) = (class.start_byte.unwrap(), class.end_byte.unwrap());
+        assert_eq!(&source_code[start..end], class.body.as_bytes());
+        assert_eq!(class.kind.as_deref(), Some("class"));
+        assert!(class.body.starts_with("struct Foobar {"));
+        assert_eq!(class.interface, None);
+
+        let method = &snippets[1];
+        assert_eq!(method.name, "fmt");
+        assert_eq!(method.kind.as_deref(), Some("method"));
+        assert_eq!(method.interface.as_deref(), Some("Display"));
+        assert_eq!(method.class.as_deref(), Some("Foobar"));
+        assert!(method.body.starts_with("fn fmt("));
+        assert_eq!((method.start_line, method.end_line), (Some(7), Some(9)));
+
+        Ok(())
+    }
+
+    fn snippet() -> CodeSnippet {
+        CodeSnippet {
+            path: "src/lib.rs".into(),
+            class: Some("Config".into()),
+            name: "load".into(),
+            body: "fn load() {}".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_point_id() -> Result<()> {
+        let first = snippet();
+
+        // Contents and summaries change between runs without moving the point
+        let second = CodeSnippet {
+            body: "fn load() { todo!() }".into(),
+            summary: "Loads the config".into(),
+            hash: vec![1, 2, 3],
+            ..snippet()
+        };
+
+        assert_eq!(first.point_id()?, second.point_id()?);
+        assert_eq!(first.uuid()?.get_version_num(), 8);
+
+        let renamed = CodeSnippet {
+            name: "save".into(),
+            ..snippet()
+        };
+        assert_ne!(first.point_id()?, renamed.point_id()?);
+
+        let moved = CodeSnippet {
+            class: None,
+            ..snippet()
+        };
+        assert_ne!(first.point_id()?, moved.point_id()?);
+
+        Ok(())
+    }
+}
@@ -3,15 +3,15 @@ use cached_path::cached_path;
 use ignore::types::{Types, TypesBuilder};
 use ignore::{Walk, WalkBuilder};
 use itertools::Itertools;
-use log::{debug, warn};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::collections::BTreeMap;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::task;
 use tree_sitter::Tree;
 use tree_sitter::{Language, Parser, Query, WasmStore, wasmtime::Engine};
@@ -20,15 +20,21 @@ use crate::parse::cb::FileMatchArgs;
 
 pub type ParsedFile = (Vec<u8>, Tree, Arc<Query>);
 
+/// Target path that reads source code from stdin instead
+pub const STDIN_PATH: &str = "-";
+
+/// Language specs bundled with the crate
+pub const BUILTIN_LANGUAGES: &str = include_str!("../etc/languages.yml");
+
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LangTemplates {
     pub impl_trait: Option<String>,
     pub class_member: Option<String>,
 }
 
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LanguageSpec {
     pub grammar_path: String,
     pub queries: BTreeMap<String, String>,
@@ -37,7 +43,28 @@ pub struct LanguageSpec {
     pub templates: Option<LangTemplates>,
 }
 
-#[derive(Debug, Default, Deserialize)]
+impl LanguageSpec {
+    /// Languages are enabled unless explicitly turned off
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != Some(false)
+    }
+
+    /// Joins the queries named by a profile, or all of them without one.
+    ///
+    /// A profile is a comma separated list of keys in `queries`, e.g. "functions,methods".
+    /// Keys this language does not define are ignored.
+    pub fn query_source(&self, profile: Option<&str>) -> String {
+        match profile {
+            None => self.queries.values().join("\n"),
+            Some(profile) => profile
+                .split(',')
+                .filter_map(|name| self.queries.get(name.trim()))
+                .join("\n"),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
 #[serde(from = "BTreeMap<String, LanguageSpec>")]
 pub struct LanguageMap {
     languages: BTreeMap<String, LanguageSpec>,
@@ -46,8 +73,10 @@ pub struct LanguageMap {
 
 impl From<BTreeMap<String, LanguageSpec>> for LanguageMap {
     fn from(languages: BTreeMap<String, LanguageSpec>) -> Self {
+        // Extensions of disabled languages are left unsupported
         let ext_to_lang = languages
             .iter()
+            .filter(|(_, v)| v.is_enabled())
             .flat_map(|(k, v)| v.extensions.iter().map(|x| (x.clone(), k.clone())))
             .collect();
 
@@ -59,18 +88,83 @@ impl From<BTreeMap<String, LanguageSpec>> for LanguageMap {
 }
 
 impl LanguageMap {
+    /// Loads the base specs, or the built-ins when no path is given,
+    /// then overlays the user specs at `overlay` if that file exists.
+    pub fn load(base: Option<&Path>, overlay: Option<&Path>) -> Result<Self> {
+        let mut langmap: LanguageMap = match base {
+            Some(path) => serde_yml::from_str(&std::fs::read_to_string(path)?)?,
+            None => serde_yml::from_str(BUILTIN_LANGUAGES)?,
+        };
+
+        if let Some(path) = overlay.filter(|p| p.is_file()) {
+            let user: LanguageMap = serde_yml::from_str(&std::fs::read_to_string(path)?)
+                .map_err(|e| anyhow!("Invalid language spec {path:?}: {e}"))?;
+
+            let names = langmap.merge(user);
+            info!("Languages from {path:?}: {}", names.join(", "));
+        }
+
+        langmap.validate()?;
+        Ok(langmap)
+    }
+
+    /// Adds languages from another map, replacing any with the same name.
+    /// Returns the names taken from the other map.
+    pub fn merge(&mut self, other: LanguageMap) -> Vec<String> {
+        let names = other.languages.keys().cloned().collect();
+
+        let mut languages = std::mem::take(&mut self.languages);
+        languages.extend(other.languages);
+        *self = languages.into();
+
+        names
+    }
+
+    /// Checks that every language is usable and no extension is claimed twice
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+        let mut claimed = BTreeMap::<&str, &str>::new();
+
+        for (name, spec) in &self.languages {
+            if spec.grammar_path.is_empty() {
+                problems.push(format!("{name}: missing grammar_path"));
+            }
+
+            if spec.extensions.is_empty() {
+                problems.push(format!("{name}: no extensions"));
+            }
+
+            if !spec.is_enabled() {
+                continue;
+            }
+
+            for ext in &spec.extensions {
+                if let Some(other) = claimed.insert(ext, name) {
+                    problems.push(format!("{name}: extension '{ext}' also claimed by {other}"));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid language specs:\n{}", problems.join("\n")))
+        }
+    }
+
     pub fn get_by_ext(&self, file_ext: &str) -> Result<(&String, &LanguageSpec)> {
         let lang_name = self
             .ext_to_lang
             .get(file_ext)
             .ok_or(anyhow!("Extension '{file_ext}' is not supported"))?;
 
-        let lang_spec = self
-            .languages
-            .get(lang_name)
-            .ok_or(anyhow!("Language '{lang_name}' is not supported"))?;
+        self.get_by_name(lang_name)
+    }
 
-        Ok((lang_name, lang_spec))
+    pub fn get_by_name(&self, lang_name: &str) -> Result<(&String, &LanguageSpec)> {
+        self.languages
+            .get_key_value(lang_name)
+            .ok_or(anyhow!("Language '{lang_name}' is not supported"))
     }
 
     pub fn get_by_path(&self, path: impl AsRef<Path>) -> Result<(&String, &LanguageSpec)> {
@@ -90,18 +184,65 @@ impl Deref for LanguageMap {
     }
 }
 
+/// Locates a grammar, downloading it into the cache unless it is a local file.
+///
+/// Local grammars are given as a `file://` URL or a path that exists on disk.
+pub fn grammar_file(grammar: &str) -> Result<PathBuf> {
+    if let Some(path) = grammar.strip_prefix("file://") {
+        let path = PathBuf::from(path);
+        if path.is_file() {
+            Ok(path)
+        } else {
+            Err(anyhow!("Grammar file {path:?} does not exist"))
+        }
+    } else if Path::new(grammar).is_file() {
+        Ok(PathBuf::from(grammar))
+    } else {
+        Ok(cached_path(grammar)?)
+    }
+}
+
 pub struct CodeSnipper {
     pub name: String,
+
+    /// Query profile compiled into [Self::query], if not all queries
+    pub profile: Option<String>,
+
     pub blob: Language,
     pub parser: Parser,
     pub query: Arc<Query>,
 }
 
+impl CodeSnipper {
+    /// Reads and parses a file without yielding, e.g. on a blocking thread
+    pub fn parse_path(&mut self, path: impl AsRef<Path>) -> Result<(Vec<u8>, Tree)> {
+        let source_code = std::fs::read(path)?;
+        let tree = self
+            .parser
+            .parse(&source_code, None)
+            .ok_or(anyhow!("Could not parse"))?;
+
+        Ok((source_code, tree))
+    }
+}
+
 #[derive(Default)]
 pub struct SourceWalker {
     pub engine: Engine,
     pub languages: LanguageMap,
-    pub snippers: BTreeMap<String, CodeSnipper>,
+
+    /// Parsers by language and query profile.
+    ///
+    /// More than one accumulates when files are parsed concurrently.
+    pub snippers: BTreeMap<(String, Option<String>), Vec<CodeSnipper>>,
+
+    /// Query profile used when none is given explicitly
+    pub profile: Option<String>,
+
+    /// Languages whose grammar or queries failed to compile, with the reason.
+    ///
+    /// Files of these languages are skipped instead of retrying for each one.
+    pub failed: BTreeMap<(String, Option<String>), String>,
 }
 
 impl TryFrom<&str> for SourceWalker {
@@ -114,15 +255,30 @@ impl TryFrom<&str> for SourceWalker {
     }
 }
 
+impl From<LanguageMap> for SourceWalker {
+    fn from(languages: LanguageMap) -> Self {
+        Self {
+            languages,
+            ..Default::default()
+        }
+    }
+}
+
 impl SourceWalker {
     pub fn new(engine: Engine) -> Self {
         Self {
             engine,
             languages: LanguageMap::default(),
             snippers: BTreeMap::new(),
+            profile: None,
+            failed: BTreeMap::new(),
         }
     }
 
+    pub fn with_profile(self, profile: Option<String>) -> Self {
+        Self { profile, ..self }
+    }
+
     pub fn load_languages(&mut self, langspec: &str) -> anyhow::Result<()> {
         self.languages = serde_yml::from_str(langspec)?;
         Ok(())
@@ -132,26 +288,32 @@ impl SourceWalker {
         engine: &Engine,
         lang_name: String,
         lang_spec: &LanguageSpec,
+        profile: Option<String>,
     ) -> Result<CodeSnipper> {
         let grammar_url = lang_spec.grammar_path.clone();
-        let grammar_path = task::spawn_blocking(move || cached_path(&grammar_url)).await??;
+        let grammar_path = task::spawn_blocking(move || grammar_file(&grammar_url)).await??;
 
         let mut grammar_file = File::open(grammar_path).await?;
         let mut grammar_buf = Vec::new();
         grammar_file.read_to_end(&mut grammar_buf).await?;
 
         let mut store = WasmStore::new(engine)?;
-        let language = store.load_language(&lang_name, &grammar_buf).unwrap();
+        let language = store
+            .load_language(&lang_name, &grammar_buf)
+            .map_err(|err| anyhow!("Could not load the {lang_name} grammar: {err}"))?;
 
         let mut parser = Parser::new();
         parser.set_wasm_store(store)?;
         parser.set_language(&language)?;
 
-        let query = lang_spec.queries.values().join("\n");
-        let query = Arc::new(Query::new(&language, &query)?);
+        let query = lang_spec.query_source(profile.as_deref());
+        let query = Query::new(&language, &query)
+            .map_err(|err| anyhow!("Invalid {lang_name} query: {err}"))?;
+        let query = Arc::new(query);
 
         Ok(CodeSnipper {
             name: lang_name.clone(),
+            profile,
             blob: language,
             parser,
             query,
@@ -159,18 +321,52 @@ impl SourceWalker {
     }
 
     pub async fn snipper_for_ext(&mut self, file_ext: &str) -> Result<&mut CodeSnipper> {
-        let (lang_name, lang_spec) = self.languages.get_by_ext(file_ext)?;
+        let (lang_name, _) = self.languages.get_by_ext(file_ext)?;
+        let lang_name = lang_name.clone();
+        self.snipper_for_lang(&lang_name).await
+    }
+
+    pub async fn snipper_for_lang(&mut self, lang_name: &str) -> Result<&mut CodeSnipper> {
+        let profile = self.profile.clone();
+        self.snipper_for(lang_name, profile.as_deref()).await
+    }
 
-        if !self.snippers.contains_key(lang_name) {
-            self.snippers.insert(
-                lang_name.clone(),
-                Self::make_processor(&self.engine, lang_name.clone(), lang_spec).await?,
-            );
+    /// Parser for a language with only the queries of a profile compiled, or all without one
+    pub async fn snipper_for(
+        &mut self,
+        lang_name: &str,
+        profile: Option<&str>,
+    ) -> Result<&mut CodeSnipper> {
+        let key = (lang_name.to_string(), profile.map(str::to_string));
+
+        if self.snippers.get(&key).is_none_or(Vec::is_empty) {
+            let snipper = self.compile(lang_name, profile).await?;
+            self.snippers.entry(key.clone()).or_default().push(snipper);
         }
 
         self.snippers
-            .get_mut(lang_name)
-            .ok_or(anyhow!("Could not retrieve processor for {file_ext}"))
+            .get_mut(&key)
+            .and_then(|snippers| snippers.last_mut())
+            .ok_or(anyhow!("Could not retrieve processor for {lang_name}"))
+    }
+
+    /// Builds a parser, remembering failures so a broken language is only reported once
+    async fn compile(&mut self, lang_name: &str, profile: Option<&str>) -> Result<CodeSnipper> {
+        let (lang_name, lang_spec) = self.languages.get_by_name(lang_name)?;
+        let key = (lang_name.clone(), profile.map(str::to_string));
+
+        if let Some(reason) = self.failed.get(&key) {
+            return Err(anyhow!("Language '{lang_name}' is disabled: {reason}"));
+        }
+
+        match Self::make_processor(&self.engine, key.0.clone(), lang_spec, key.1.clone()).await {
+            Ok(snipper) => Ok(snipper),
+            Err(err) => {
+                warn!("Skipping files of language '{lang_name}': {err:?}");
+                self.failed.insert(key, err.to_string());
+                Err(err)
+            }
+        }
     }
 
     pub async fn snipper_for_path(&mut self, path: impl AsRef<Path>) -> Result<&mut CodeSnipper> {
@@ -224,9 +420,80 @@ impl SourceWalker {
         Ok(())
     }
 
+    /// Takes a parser for the file's language out of [Self::snippers], or compiles another.
+    ///
+    /// Each parser has its own wasm store, since a `Parser` cannot be shared between tasks.
+    /// Hand it back with [Self::checkin_snipper] once the file is parsed.
+    pub async fn checkout_snipper(&mut self, path: impl AsRef<Path>) -> Result<CodeSnipper> {
+        let (lang_name, _) = self.languages.get_by_path(path)?;
+        let key = (lang_name.clone(), self.profile.clone());
+
+        if let Some(snipper) = self.snippers.get_mut(&key).and_then(Vec::pop) {
+            Ok(snipper)
+        } else {
+            self.compile(&key.0, key.1.as_deref()).await
+        }
+    }
+
+    pub fn checkin_snipper(&mut self, snipper: CodeSnipper) {
+        self.snippers
+            .entry((snipper.name.clone(), snipper.profile.clone()))
+            .or_default()
+            .push(snipper);
+    }
+
+    /// Parses a single file, whether or not its directory would be walked
+    pub async fn process_file<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        cb: &impl AsyncFn(FileMatchArgs),
+    ) -> Result<()> {
+        let (source_code, tree, query) = self.parse_file(path.as_ref()).await?;
+
+        cb(FileMatchArgs {
+            file_path: path.as_ref(),
+            source: source_code.as_slice(),
+            tree: &tree,
+            query: query.as_ref(),
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// Parses everything read from `reader` as the named language, e.g. code piped through stdin.
+    ///
+    /// The callback sees [STDIN_PATH] as the file path.
+    pub async fn process_reader(
+        &mut self,
+        mut reader: impl AsyncRead + Unpin,
+        lang_name: &str,
+        cb: &impl AsyncFn(FileMatchArgs),
+    ) -> Result<()> {
+        let mut source_code = Vec::new();
+        reader.read_to_end(&mut source_code).await?;
+
+        let snipper = self.snipper_for_lang(lang_name).await?;
+        let tree = snipper
+            .parser
+            .parse(&source_code, None)
+            .ok_or(anyhow!("Could not parse"))?;
+        let query = snipper.query.clone();
+
+        cb(FileMatchArgs {
+            file_path: Path::new(STDIN_PATH),
+            source: source_code.as_slice(),
+            tree: &tree,
+            query: query.as_ref(),
+        })
+        .await;
+
+        Ok(())
+    }
+
     pub fn get_types(&self) -> Result<Types> {
         let mut types_builder = TypesBuilder::new();
-        for (lang, spec) in self.languages.deref() {
+        for (lang, spec) in self.languages.iter().filter(|(_, s)| s.is_enabled()) {
             for ext in &spec.extensions {
                 types_builder.add(lang, &format!("*.{ext}"))?;
             }
@@ -319,4 +586,327 @@ mod tests {
         assert_that!(second.node.utf8_text(source_code)?, starts_with("foobar"));
         Ok(())
     }
+
+    #[test]
+    fn test_merge_languages() -> anyhow::Result<()> {
+        let mut langmap: LanguageMap = serde_yml::from_str(BUILTIN_LANGUAGES)?;
+        langmap.validate()?;
+
+        let user: LanguageMap = serde_yml::from_str(&dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+                - rs.in
+            grammar_path: {TREE_SITTER_RUST}
+            queries: {{}}
+          zig:
+            extensions:
+                - zig
+            grammar_path: zig.wasm
+            queries: {{}}
+        "#
+        )))?;
+
+        let builtins = langmap.len();
+        let names = langmap.merge(user);
+
+        assert_that!(names, elements_are![eq("rust"), eq("zig")]);
+        assert_that!(langmap.len(), eq(builtins + 1));
+        assert_that!(langmap["rust"].queries.len(), eq(0));
+        assert_that!(langmap.get_by_ext("rs.in")?.0, eq("rust"));
+        assert_that!(langmap.get_by_ext("zig")?.0, eq("zig"));
+        langmap.validate()?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_reader() -> anyhow::Result<()> {
+        let langspec = dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+            grammar_path: {TREE_SITTER_RUST}
+            queries:
+                functions: |
+                    (function_item
+                        name: (identifier) @name.definition.function) @definition.function
+        "#
+        ));
+
+        let mut src_walk = SourceWalker::try_from(langspec.as_str())?;
+        let names = std::sync::Mutex::new(vec![]);
+        let source_code = b"fn foobar() {}\nfn baz() {}\n";
+
+        src_walk
+            .process_reader(&source_code[..], "rust", &async |args: FileMatchArgs| {
+                assert_that!(args.file_path, eq(Path::new(STDIN_PATH)));
+
+                let mut qc = QueryCursor::new();
+                let mut ms = qc.matches(args.query, args.tree.root_node(), args.source);
+                while let Some(item) = ms.next() {
+                    let name = item.captures[1].node.utf8_text(args.source).unwrap();
+                    names.lock().unwrap().push(name.to_string());
+                }
+            })
+            .await?;
+
+        assert_that!(
+            *names.lock().unwrap(),
+            elements_are![eq("foobar"), eq("baz")]
+        );
+
+        let result = src_walk
+            .process_reader(&source_code[..], "cobol", &async |_| {})
+            .await;
+        assert_that!(result, err(anything()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_process_file() -> anyhow::Result<()> {
+        let langspec = dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+            grammar_path: {TREE_SITTER_RUST}
+            queries:
+                functions: |
+                    (function_item
+                        name: (identifier) @name.definition.function) @definition.function
+        "#
+        ));
+
+        let root = std::env::temp_dir().join(format!("emberlain-file-{}", std::process::id()));
+        std::fs::create_dir_all(&root)?;
+        let file_path = root.join("lib.rs");
+        std::fs::write(&file_path, "fn foobar() {}\nfn baz() {}\n")?;
+
+        let mut src_walk = SourceWalker::try_from(langspec.as_str())?;
+        let names = std::sync::Mutex::new(vec![]);
+
+        let result = src_walk
+            .process_file(&file_path, &async |args: FileMatchArgs| {
+                assert_that!(args.file_path, eq(file_path.as_path()));
+
+                let mut qc = QueryCursor::new();
+                let mut ms = qc.matches(args.query, args.tree.root_node(), args.source);
+                while let Some(item) = ms.next() {
+                    let name = item.captures[1].node.utf8_text(args.source).unwrap();
+                    names.lock().unwrap().push(name.to_string());
+                }
+            })
+            .await;
+        std::fs::remove_dir_all(&root)?;
+        result?;
+
+        assert_that!(
+            *names.lock().unwrap(),
+            elements_are![eq("foobar"), eq("baz")]
+        );
+
+        let result = src_walk
+            .process_file(root.join("missing.rs"), &async |_| {})
+            .await;
+        assert_that!(result, err(anything()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_profiles() -> anyhow::Result<()> {
+        let langspec = dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+            grammar_path: {TREE_SITTER_RUST}
+            queries:
+                functions: |
+                    (function_item
+                        name: (identifier) @name.definition.function) @definition.function
+                types: |
+                    (struct_item
+                        name: (type_identifier) @name.definition.class) @definition.class
+        "#
+        ));
+
+        let mut src_walk = SourceWalker::try_from(langspec.as_str())?;
+        let spec = &src_walk.languages["rust"];
+        assert_that!(spec.query_source(None), contains_substring("struct_item"));
+        assert_that!(
+            spec.query_source(Some("functions")),
+            not(contains_substring("struct_item"))
+        );
+        assert_that!(
+            spec.query_source(Some("functions, types, tests")),
+            eq(&spec.query_source(None))
+        );
+
+        let patterns = |snipper: &mut CodeSnipper| snipper.query.pattern_count();
+        assert_that!(patterns(src_walk.snipper_for("rust", None).await?), eq(2));
+        assert_that!(
+            patterns(src_walk.snipper_for("rust", Some("types")).await?),
+            eq(1)
+        );
+        assert_that!(patterns(src_walk.snipper_for_ext("rs").await?), eq(2));
+
+        // Compiled once for each profile
+        assert_that!(src_walk.snippers.len(), eq(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broken_language() -> anyhow::Result<()> {
+        let langspec = dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+            grammar_path: {TREE_SITTER_RUST}
+            queries:
+                functions: |
+                    (function_item
+                        name: (identifier) @name.definition.function) @definition.function
+                broken: |
+                    (function_item
+                        name: @oops
+          cobol:
+            extensions:
+                - cbl
+            grammar_path: {TREE_SITTER_RUST}
+            queries: {{}}
+        "#
+        ));
+
+        let mut src_walk =
+            SourceWalker::try_from(langspec.as_str())?.with_profile(Some("broken".into()));
+
+        // Malformed queries disable the language instead of aborting
+        let err = src_walk
+            .snipper_for_ext("rs")
+            .await
+            .err()
+            .expect("query should not compile");
+        assert_that!(err.to_string(), contains_substring("Invalid rust query"));
+        let err = src_walk
+            .snipper_for_ext("rs")
+            .await
+            .err()
+            .expect("language should stay disabled");
+        assert_that!(err.to_string(), contains_substring("disabled"));
+
+        // A grammar without the language's symbols fails without a panic
+        assert!(src_walk.snipper_for_ext("cbl").await.is_err());
+
+        // Other profiles of the language keep working
+        assert!(
+            src_walk
+                .snipper_for("rust", Some("functions"))
+                .await
+                .is_ok()
+        );
+        assert_that!(src_walk.failed.len(), eq(2));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_language() -> anyhow::Result<()> {
+        let langmap: LanguageMap = serde_yml::from_str(&dedent(&format!(
+            r#"
+          rust:
+            extensions: [rs]
+            grammar_path: {TREE_SITTER_RUST}
+            queries: {{}}
+          python:
+            extensions: [py, pyi]
+            grammar_path: python.wasm
+            queries: {{}}
+            enabled: false
+          stub:
+            extensions: [pyi]
+            grammar_path: stub.wasm
+            queries: {{}}
+        "#
+        )))?;
+
+        // A disabled language does not conflict with one claiming the same extension
+        langmap.validate()?;
+
+        assert_that!(langmap.get_by_ext("rs")?.0, eq("rust"));
+        assert_that!(langmap.get_by_ext("pyi")?.0, eq("stub"));
+        assert!(langmap.get_by_ext("py").is_err());
+        assert!(langmap.get_by_path("src/main.py").is_err());
+
+        let mut src_walk = SourceWalker::from(langmap);
+        assert!(src_walk.snipper_for_ext("py").await.is_err());
+
+        let types = src_walk.get_types()?;
+        assert!(types.matched("main.py", false).is_ignore());
+        assert!(types.matched("main.rs", false).is_whitelist());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_local_grammar() -> anyhow::Result<()> {
+        let local =
+            std::env::temp_dir().join(format!("emberlain-rust-{}.wasm", std::process::id()));
+        std::fs::copy(cached_path(TREE_SITTER_RUST)?, &local)?;
+
+        for grammar in [
+            local.display().to_string(),
+            format!("file://{}", local.display()),
+        ] {
+            assert_that!(grammar_file(&grammar)?, eq(&local));
+
+            let mut src_walk = SourceWalker::try_from(
+                dedent(&format!(
+                    r#"
+              rust:
+                extensions: [rs]
+                grammar_path: {grammar}
+                queries: {{}}
+            "#
+                ))
+                .as_str(),
+            )?;
+
+            let snipper = src_walk.snipper_for_ext("rs").await?;
+            assert!(snipper.parser.parse("fn main() {}", None).is_some());
+        }
+
+        std::fs::remove_file(&local)?;
+        assert!(grammar_file(&format!("file://{}", local.display())).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_languages() -> anyhow::Result<()> {
+        let langmap: LanguageMap = serde_yml::from_str(&dedent(
+            r#"
+          first:
+            extensions: [x]
+            grammar_path: first.wasm
+            queries: {}
+          second:
+            extensions: [x]
+            grammar_path: ""
+            queries: {}
+        "#,
+        ))?;
+
+        let err = langmap.validate().unwrap_err().to_string();
+        assert_that!(err, contains_substring("missing grammar_path"));
+        assert_that!(err, contains_substring("also claimed by first"));
+
+        Ok(())
+    }
 }
@@ -3,21 +3,50 @@ use flume::{Receiver, Sender};
 use log::{info, warn};
 use qdrant_client::{
     Payload, Qdrant,
-    qdrant::{DeletePayloadPointsBuilder, PointStruct, PointsIdsList, UpsertPointsBuilder, Vector},
+    qdrant::{
+        DeletePayloadPointsBuilder, PointStruct, PointsIdsList, SetPayloadPointsBuilder,
+        UpsertPointsBuilder, Vector,
+    },
 };
+use serde_json::json;
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use typed_builder::TypedBuilder;
 
-use crate::SnippetProgress;
+use crate::{
+    CodeSnippet, SnippetProgress,
+    manifest::ManifestUpdate,
+    search::{ALIAS_VECTOR, DEFAULT_VECTOR},
+};
+
+/// Counts of snippets handled by the embedding worker
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EmbedTally {
+    pub inserted: usize,
+    pub restored: usize,
+    pub failed: usize,
+}
 
 #[derive(TypedBuilder)]
 pub struct EmbeddingWorker {
     embedding: Arc<Mutex<TextEmbedding>>,
     qdrant: Qdrant,
     collection: String,
+
+    /// Notified of files with snippets that could not be inserted
+    #[builder(default)]
+    manifest: Arc<ManifestUpdate>,
+
+    /// Number of new snippets embedded and inserted together
+    #[builder(default = 32)]
+    batch_size: usize,
+
+    /// Flushes a partial batch when no messages arrive for this long
+    #[builder(default = Duration::from_secs(2))]
+    idle_flush: Duration,
 }
 
 impl EmbeddingWorker {
@@ -25,70 +54,190 @@ impl EmbeddingWorker {
         &self,
         receiver: Receiver<SnippetProgress>,
         sender: Sender<SnippetProgress>,
-    ) -> anyhow::Result<()> {
-        while let Ok(msg) = receiver.recv_async().await {
+    ) -> anyhow::Result<EmbedTally> {
+        let mut tally = EmbedTally::default();
+
+        // Messages are held back while a batch fills, so they are forwarded in order
+        let mut pending: Vec<SnippetProgress> = Vec::new();
+        let mut batch_len = 0;
+
+        loop {
+            let msg = if pending.is_empty() {
+                receiver.recv_async().await.ok()
+            } else {
+                match tokio::time::timeout(self.idle_flush, receiver.recv_async()).await {
+                    Ok(msg) => msg.ok(),
+                    Err(_) => {
+                        self.flush(&mut pending, &sender, &mut tally).await?;
+                        batch_len = 0;
+                        continue;
+                    }
+                }
+            };
+
+            let Some(msg) = msg else {
+                break;
+            };
+
             if let SnippetProgress::Snippet { snippet, clean, .. } = &msg {
                 if *clean {
                     // when clean, just unmark __removed
-                    let id = snippet.uuid()?.to_string();
-                    self.qdrant
-                        .delete_payload(
-                            DeletePayloadPointsBuilder::new(
-                                &self.collection,
-                                vec!["__removed".into()],
-                            )
-                            .points_selector(PointsIdsList {
-                                ids: vec![id.into()],
-                            }),
-                        )
-                        .await?;
+                    match self.restore(snippet).await {
+                        Ok(()) => tally.restored += 1,
+                        Err(e) => {
+                            warn!(
+                                "Unable to restore snippet {} in {}: {e:?}",
+                                snippet.name, snippet.path
+                            );
+                            self.manifest.fail(&snippet.path);
+                            tally.failed += 1;
+                        }
+                    }
                 } else {
-                    let result = async {
-                        let options = textwrap::Options::new(100)
-                            .initial_indent(">.< ")
-                            .subsequent_indent("-.- ");
+                    batch_len += 1;
+                }
+            }
 
-                        info!("X.X ID = {:?}", snippet.uuid());
-                        info!("{}", textwrap::fill(&snippet.summary, &options));
+            pending.push(msg);
 
-                        // this could be cleaner
-                        let mut texts = vec![snippet.summary.as_str()];
-                        texts.extend(snippet.queries.iter().map(|s| s.as_str()));
+            if batch_len >= self.batch_size.max(1) {
+                self.flush(&mut pending, &sender, &mut tally).await?;
+                batch_len = 0;
+            }
+        }
 
-                        let embeddings = {
-                            let mut embedder = self.embedding.lock().unwrap();
-                            embedder.embed(texts, None)?
-                        };
+        self.flush(&mut pending, &sender, &mut tally).await?;
 
-                        let embedding = embeddings[0].clone();
+        info!(
+            "Inserted {} snippets, restored {} unchanged, {} failed",
+            tally.inserted, tally.restored, tally.failed
+        );
 
-                        let id = snippet.uuid()?.to_string();
-                        let value = serde_json::to_value(snippet)?;
-                        let payload = Payload::try_from(value)?;
+        Ok(tally)
+    }
 
-                        let vectors = HashMap::from([
-                            ("default".to_string(), Vector::new_dense(embedding)),
-                            ("aliases".to_string(), Vector::new_multi(embeddings)),
-                        ]);
-                        let point = PointStruct::new(id, vectors, payload);
+    async fn restore(&self, snippet: &CodeSnippet) -> anyhow::Result<()> {
+        let id = snippet.point_id()?;
 
-                        let request =
-                            UpsertPointsBuilder::new(self.collection.as_str(), vec![point]).build();
-                        self.qdrant.upsert_points(request).await?;
+        // Unchanged code may still have moved within the file
+        let range = json!({
+            "start_line": snippet.start_line,
+            "end_line": snippet.end_line,
+            "start_byte": snippet.start_byte,
+            "end_byte": snippet.end_byte,
+        });
+        self.qdrant
+            .set_payload(
+                SetPayloadPointsBuilder::new(&self.collection, Payload::try_from(range)?)
+                    .points_selector(PointsIdsList {
+                        ids: vec![id.clone()],
+                    }),
+            )
+            .await?;
 
-                        Ok::<_, anyhow::Error>(())
-                    }
-                    .await;
+        self.qdrant
+            .delete_payload(
+                DeletePayloadPointsBuilder::new(&self.collection, vec!["__removed".into()])
+                    .points_selector(PointsIdsList { ids: vec![id] }),
+            )
+            .await?;
 
-                    if let Err(e) = result {
-                        warn!("Unable to handle snippet: {e:?}");
-                    };
+        Ok(())
+    }
+
+    /// Embeds and inserts the new snippets among the pending messages, then forwards them all
+    async fn flush(
+        &self,
+        pending: &mut Vec<SnippetProgress>,
+        sender: &Sender<SnippetProgress>,
+        tally: &mut EmbedTally,
+    ) -> anyhow::Result<()> {
+        let snippets = pending
+            .iter()
+            .filter_map(|msg| match msg {
+                SnippetProgress::Snippet {
+                    snippet,
+                    clean: false,
+                    ..
+                } => Some(snippet.as_ref()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if !snippets.is_empty() {
+            info!("Inserting a batch of {} snippets", snippets.len());
+
+            if let Err(e) = self.insert_batch(&snippets).await {
+                warn!("Unable to insert batch, retrying one at a time: {e:?}");
+
+                // Isolate the bad snippets so the rest of the batch still makes it in
+                for snippet in &snippets {
+                    match self.insert_batch(&[snippet]).await {
+                        Ok(()) => tally.inserted += 1,
+                        Err(e) => {
+                            warn!(
+                                "Unable to insert snippet {} in {}: {e:?}",
+                                snippet.name, snippet.path
+                            );
+                            self.manifest.fail(&snippet.path);
+                            tally.failed += 1;
+                        }
+                    }
                 }
+            } else {
+                tally.inserted += snippets.len();
             }
+        }
 
+        for msg in pending.drain(..) {
             sender.send_async(msg).await.unwrap();
         }
 
         Ok(())
     }
+
+    async fn insert_batch(&self, snippets: &[&CodeSnippet]) -> anyhow::Result<()> {
+        let options = textwrap::Options::new(100)
+            .initial_indent(">.< ")
+            .subsequent_indent("-.- ");
+
+        // Each snippet embeds its summary followed by its synthetic queries
+        let mut texts = Vec::new();
+        let mut spans = Vec::new();
+        for snippet in snippets {
+            info!("X.X ID = {:?}", snippet.uuid());
+            info!("{}", textwrap::fill(&snippet.summary, &options));
+
+            let start = texts.len();
+            texts.push(snippet.summary.as_str());
+            texts.extend(snippet.queries.iter().map(|s| s.as_str()));
+            spans.push(start..texts.len());
+        }
+
+        let mut embeddings = {
+            let mut embedder = self.embedding.lock().unwrap();
+            embedder.embed(texts, None)?
+        };
+
+        let mut points = Vec::with_capacity(snippets.len());
+        for (snippet, span) in snippets.iter().zip(spans).rev() {
+            let embeddings = embeddings.split_off(span.start);
+            let embedding = embeddings[0].clone();
+
+            let id = snippet.point_id()?;
+            let value = serde_json::to_value(snippet)?;
+            let payload = Payload::try_from(value)?;
+
+            let vectors = HashMap::from([
+                (DEFAULT_VECTOR.to_string(), Vector::new_dense(embedding)),
+                (ALIAS_VECTOR.to_string(), Vector::new_multi(embeddings)),
+            ]);
+            points.push(PointStruct::new(id, vectors, payload));
+        }
+
+        let request = UpsertPointsBuilder::new(self.collection.as_str(), points).build();
+        self.qdrant.upsert_points(request).await?;
+
+        Ok(())
+    }
 }
@@ -1,21 +1,36 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow};
 use flume::Receiver;
 use flume::Sender;
 use indicatif::ProgressBar;
-use itertools::Itertools;
-use itertools::MinMaxResult;
 use log::warn;
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncRead;
+use tokio::task::JoinSet;
 use typed_builder::TypedBuilder;
 
 use crate::{
-    CodeSnippet, SnippetProgress, SourceWalker,
+    CodeSnippet, MinSize, ParsedFile, Progressor, STDIN_PATH, SnippetProgress, SourceWalker,
+    manifest::ManifestUpdate,
     parse::{cb::FileMatchArgs, process_node},
 };
 
 #[derive(TypedBuilder)]
 pub struct ExtractingWorker {
     walker: SourceWalker,
+
+    /// Notified of files that could not be parsed
+    #[builder(default)]
+    manifest: Arc<ManifestUpdate>,
+
+    /// Snippets smaller than this never enter the pipeline
+    #[builder(default)]
+    min_size: MinSize,
+
+    /// Maximum number of files parsed at once, each on a blocking thread
+    #[builder(default = 1)]
+    concurrency: usize,
 }
 
 impl ExtractingWorker {
@@ -25,78 +40,227 @@ impl ExtractingWorker {
         sender: Sender<SnippetProgress>,
         repo_root: impl AsRef<Path>,
     ) -> Result<()> {
-        while let Ok(msg) = receiver.recv_async().await {
-            match msg {
-                SnippetProgress::StartOfFile {
-                    file_path,
-                    progressor,
-                    progress,
-                } => {
-                    sender
-                        .send_async(SnippetProgress::StartOfFile {
-                            file_path: file_path.clone(),
-                            progressor: progressor.clone(),
-                            progress: progress.clone(),
-                        })
-                        .await?;
+        let limit = self.concurrency.max(1);
+        let mut tasks = JoinSet::new();
+        let mut parsing = HashMap::new();
+        let mut open = true;
 
-                    if let Err(err) = extract_file(
-                        &sender,
-                        &mut self.walker,
-                        repo_root.as_ref(),
-                        file_path,
-                        progress.clone(),
-                    )
-                    .await
-                    {
-                        warn!("{err:?}");
-                    }
-                    sender
-                        .send_async(SnippetProgress::EndOfFile {
-                            progressor: progressor.clone(),
-                            progress: progress.clone(),
-                        })
+        loop {
+            tokio::select! {
+                Some(joined) = tasks.join_next_with_id(), if !tasks.is_empty() => {
+                    let (id, parsed) = match joined {
+                        Ok((id, (parsed, snipper))) => {
+                            self.walker.checkin_snipper(snipper);
+                            (id, parsed)
+                        }
+                        // Only this file fails. Its parser is lost, so another gets compiled.
+                        Err(err) => (err.id(), Err(anyhow!("Parsing task failed: {err}"))),
+                    };
+
+                    let (file_path, progressor, progress) = parsing.remove(&id).unwrap();
+                    self.finish_file(&sender, file_path, progressor, progress, parsed)
                         .await?;
                 }
-                msg @ SnippetProgress::MissingFile { .. } => {
-                    sender.send_async(msg).await?;
-                }
-                _ => {
-                    log::warn!("Unexpected message received by ExtractingWorker");
-                    sender.send_async(msg).await?;
+                msg = receiver.recv_async(), if open && tasks.len() < limit => {
+                    let Ok(msg) = msg else {
+                        open = false;
+                        continue;
+                    };
+
+                    match msg {
+                        SnippetProgress::StartOfFile {
+                            file_path,
+                            progressor,
+                            progress,
+                        } => {
+                            // TODO: handle missing files
+                            let abs_path = repo_root.as_ref().join(&file_path);
+
+                            match self.walker.checkout_snipper(&abs_path).await {
+                                Ok(mut snipper) => {
+                                    let handle = tasks.spawn_blocking(move || {
+                                        let query = snipper.query.clone();
+                                        let parsed = snipper
+                                            .parse_path(&abs_path)
+                                            .map(|(source_code, tree)| (source_code, tree, query));
+                                        (parsed, snipper)
+                                    });
+                                    parsing.insert(handle.id(), (file_path, progressor, progress));
+                                }
+                                Err(err) => {
+                                    self.finish_file(
+                                        &sender,
+                                        file_path,
+                                        progressor,
+                                        progress,
+                                        Err(err),
+                                    )
+                                    .await?;
+                                }
+                            }
+                        }
+                        msg @ SnippetProgress::MissingFile { .. } => {
+                            sender.send_async(msg).await?;
+                        }
+                        _ => {
+                            log::warn!("Unexpected message received by ExtractingWorker");
+                            sender.send_async(msg).await?;
+                        }
+                    }
                 }
+                else => break,
             }
         }
 
         Ok::<_, anyhow::Error>(())
     }
-}
 
-async fn extract_file(
-    snippet_tx: &Sender<SnippetProgress>,
-    src_walk: &mut SourceWalker,
-    root_path: impl AsRef<Path>,
-    file_path: impl AsRef<Path>,
-    progress: Option<ProgressBar>,
-) -> Result<()> {
-    let abs_path = root_path.as_ref().join(file_path.as_ref());
+    /// Sends the snippets of a parsed file between its start and end of file markers
+    async fn finish_file(
+        &self,
+        sender: &Sender<SnippetProgress>,
+        file_path: PathBuf,
+        progressor: Arc<Option<Progressor>>,
+        progress: Option<ProgressBar>,
+        parsed: Result<ParsedFile>,
+    ) -> Result<()> {
+        sender
+            .send_async(SnippetProgress::StartOfFile {
+                file_path: file_path.clone(),
+                progressor: progressor.clone(),
+                progress: progress.clone(),
+            })
+            .await?;
 
-    // TODO: handle missing files
+        match parsed.context("Failed to parse file") {
+            Ok((source_code, tree, query)) => {
+                let entry = FileMatchArgs {
+                    file_path: &file_path,
+                    source: source_code.as_slice(),
+                    tree: &tree,
+                    query: query.as_ref(),
+                };
 
-    let (source_code, tree, query) = src_walk
-        .parse_file(abs_path)
-        .await
-        .context("Failed to parse file")?;
+                emit_snippets(
+                    sender,
+                    entry,
+                    self.min_size,
+                    progressor.as_ref().as_ref(),
+                    progress.clone(),
+                )
+                .await;
+            }
+            Err(err) => {
+                warn!("{file_path:?}: {err:?}");
+                self.manifest.fail(&file_path);
+            }
+        }
 
-    // dbg!(tree.root_node().to_sexp());
+        sender
+            .send_async(SnippetProgress::EndOfFile {
+                file_path,
+                progressor,
+                progress,
+            })
+            .await?;
 
-    let entry = FileMatchArgs {
-        file_path: file_path.as_ref(),
-        source: source_code.as_slice(),
-        tree: &tree,
-        query: query.as_ref(),
-    };
+        Ok(())
+    }
+
+    /// Extracts snippets from a single file, whether or not its directory would be walked.
+    ///
+    /// `file_path` is relative to `repo_root`, like the paths sent by the pathfinder.
+    pub async fn run_file(
+        &mut self,
+        repo_root: impl AsRef<Path>,
+        file_path: impl AsRef<Path>,
+        sender: Sender<SnippetProgress>,
+    ) -> Result<()> {
+        let file_path = file_path.as_ref();
+
+        sender
+            .send_async(SnippetProgress::StartOfFile {
+                file_path: file_path.into(),
+                progressor: Default::default(),
+                progress: None,
+            })
+            .await?;
+
+        let min_size = self.min_size;
+        let result = self
+            .walker
+            .process_file(repo_root.as_ref().join(file_path), &async |entry| {
+                let entry = FileMatchArgs { file_path, ..entry };
+                emit_snippets(&sender, entry, min_size, None, None).await
+            })
+            .await;
+
+        if let Err(err) = result {
+            warn!("{err:?}");
+            self.manifest.fail(file_path);
+        }
+
+        sender
+            .send_async(SnippetProgress::EndOfFile {
+                file_path: file_path.into(),
+                progressor: Default::default(),
+                progress: None,
+            })
+            .await?;
 
+        Ok(())
+    }
+
+    /// Extracts snippets from source code read to the end from `reader`, e.g. stdin.
+    ///
+    /// The snippets are framed as a single file at [STDIN_PATH].
+    pub async fn run_reader(
+        &mut self,
+        reader: impl AsyncRead + Unpin,
+        lang_name: &str,
+        sender: Sender<SnippetProgress>,
+    ) -> Result<()> {
+        sender
+            .send_async(SnippetProgress::StartOfFile {
+                file_path: STDIN_PATH.into(),
+                progressor: Default::default(),
+                progress: None,
+            })
+            .await?;
+
+        let min_size = self.min_size;
+        let result = self
+            .walker
+            .process_reader(reader, lang_name, &async |entry| {
+                emit_snippets(&sender, entry, min_size, None, None).await
+            })
+            .await;
+
+        if let Err(err) = result {
+            warn!("{err:?}");
+            self.manifest.fail(STDIN_PATH);
+        }
+
+        sender
+            .send_async(SnippetProgress::EndOfFile {
+                file_path: STDIN_PATH.into(),
+                progressor: Default::default(),
+                progress: None,
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Sends a snippet down the pipeline for each match in a parsed file
+async fn emit_snippets(
+    snippet_tx: &Sender<SnippetProgress>,
+    entry: FileMatchArgs<'_>,
+    min_size: MinSize,
+    progressor: Option<&Progressor>,
+    progress: Option<ProgressBar>,
+) {
     process_node(
         entry.tree.root_node(),
         entry.source,
@@ -108,74 +272,12 @@ async fn extract_file(
             let q = entry.query;
             let src = entry.source;
 
-            // log::debug!("^_- Match {n:?} at {p:?}");
-            let mut attrs: Vec<String> = Vec::new();
-            let mut interface: Option<String> = None;
-            let mut class: Option<String> = None;
-            let mut ident: Option<String> = None;
-            // let mut kind: Option<String> = None;
-            let mut body: Option<String> = None;
-            let mut bounds = Vec::new();
-
-            // Maybe match destructuring should be part of SourceWalker
-            for cap in &n.captures {
-                let index = cap.index as usize;
-                if q.capture_names().len() <= index {
-                    continue;
-                }
-
-                // dbg!(cap.node.to_sexp());
-                let cap_name = q.capture_names()[index];
-                let parts: Vec<&str> = cap_name.split(".").collect();
-                match parts.as_slice() {
-                    // What's more common "attribute", "annotation", "decorator"?
-                    ["attribute"] | ["annotation"] => {
-                        if let Ok(n) = cap.node.utf8_text(src) {
-                            attrs.push(n.to_string());
-                        }
-                    }
-                    ["definition", _] => {
-                        // kind = Some(k.to_string());
-                        bounds.push(cap.node.start_byte());
-                        bounds.push(cap.node.end_byte());
-                    }
-                    ["name", "definition", _] => {
-                        if let Ok(n) = cap.node.utf8_text(src) {
-                            ident = Some(n.to_string());
-                        }
-                    }
-                    ["name", "reference", "interface"] => {
-                        if let Ok(n) = cap.node.utf8_text(src) {
-                            interface = Some(n.to_string());
-                        }
-                    }
-                    ["name", "reference", "class"] => {
-                        if let Ok(n) = cap.node.utf8_text(src) {
-                            class = Some(n.to_string());
-                        }
-                    }
-                    _ => {
-                        warn!("Don't know what to do with this capture: {cap_name}")
-                    }
-                }
-            }
-
-            if let MinMaxResult::MinMax(a, b) = bounds.into_iter().minmax()
-                && let Ok(txt) = str::from_utf8(&src[a..b])
+            if let Some(snippet) = CodeSnippet::from_match(q, &n, src)
+                && min_size.admits(&snippet)
             {
-                body = Some(txt.to_string());
-            }
-
-            // log::debug!("o.O Match results kind: {kind:?} identier: {ident:?} attrs: {attrs:?}");
-            if let Some(body) = &body {
                 let snippet = CodeSnippet {
                     path: p.display().to_string(),
-                    interface,
-                    class,
-                    attributes: attrs,
-                    name: ident.clone().unwrap_or("???".to_string()),
-                    body: body.clone(),
-                    ..Default::default()
+                    ..snippet
                 };
 
                 let msg = SnippetProgress::Snippet {
@@ -185,10 +287,100 @@ async fn extract_file(
                 };
 
                 snippet_tx.send_async(msg).await.unwrap();
+                if let Some(bars) = progressor {
+                    bars.pending_summary.push();
+                }
             }
         },
     )
     .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use textwrap::dedent;
+
+    use super::*;
+
+    const TREE_SITTER_RUST: &str = "https://github.com/tree-sitter/tree-sitter-rust/releases/download/v0.24.0/tree-sitter-rust.wasm";
+
+    #[tokio::test]
+    async fn test_concurrent_parsing() -> anyhow::Result<()> {
+        let langspec = dedent(&format!(
+            r#"
+          rust:
+            extensions:
+                - rs
+            grammar_path: {TREE_SITTER_RUST}
+            queries:
+                functions: |
+                    (function_item
+                        name: (identifier) @name.definition.function) @definition.function
+        "#
+        ));
+
+        let root = std::env::temp_dir().join(format!("emberlain-extract-{}", std::process::id()));
+        std::fs::create_dir_all(&root)?;
+
+        let (path_tx, path_rx) = flume::unbounded();
+        let (snippet_tx, snippet_rx) = flume::unbounded();
+
+        for i in 0..50 {
+            let file_path = PathBuf::from(format!("file{i}.rs"));
+            std::fs::write(root.join(&file_path), format!("fn func{i}() {{}}"))?;
+            path_tx.send(SnippetProgress::StartOfFile {
+                file_path,
+                progressor: Default::default(),
+                progress: None,
+            })?;
+        }
+
+        path_tx.send(SnippetProgress::StartOfFile {
+            file_path: "missing.rs".into(),
+            progressor: Default::default(),
+            progress: None,
+        })?;
+        drop(path_tx);
+
+        let mut worker = ExtractingWorker::builder()
+            .walker(SourceWalker::try_from(langspec.as_str())?)
+            .min_size(MinSize { lines: 0, chars: 0 })
+            .concurrency(4)
+            .build();
 
-    Ok(())
+        let result = worker.run(path_rx, snippet_tx, &root).await;
+        std::fs::remove_dir_all(&root)?;
+        result?;
+
+        // Each file's snippets stay between its own markers, in whatever order files finish
+        let mut open = None;
+        let mut files = 0;
+        let mut snippets = 0;
+        for msg in snippet_rx.drain() {
+            match msg {
+                SnippetProgress::StartOfFile { file_path, .. } => {
+                    assert_eq!(open.replace(file_path), None);
+                }
+                SnippetProgress::Snippet { snippet, .. } => {
+                    assert_eq!(open.as_deref(), Some(Path::new(&snippet.path)));
+                    snippets += 1;
+                }
+                SnippetProgress::EndOfFile { file_path, .. } => {
+                    assert_eq!(open.take(), Some(file_path));
+                    files += 1;
+                }
+                SnippetProgress::MissingFile { .. } => unreachable!(),
+            }
+        }
+
+        // The unreadable file is still framed, without snippets
+        assert_eq!(files, 51);
+        assert_eq!(snippets, 50);
+
+        // Parsers are returned for reuse, up to one per concurrent task
+        let parsers = &worker.walker.snippers[&("rust".to_string(), None)];
+        assert!(!parsers.is_empty() && parsers.len() <= 4);
+
+        Ok(())
+    }
 }
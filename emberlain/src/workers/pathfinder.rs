@@ -14,22 +14,42 @@ use qdrant_client::{
 };
 use typed_builder::TypedBuilder;
 
-use crate::{Progressor, SnippetProgress};
+use crate::{
+    ProgressUnit, Progressor, SnippetProgress,
+    manifest::{FileStamp, Manifest, ManifestUpdate},
+};
 
 #[derive(TypedBuilder)]
 pub struct Pathfinder {
     types: Types,
     qdrant: Qdrant,
     collection: String,
+
+    /// Files indexed by previous runs
+    #[builder(default)]
+    manifest: Arc<Manifest>,
+
+    /// Collects the files sent down the pipeline during this run
+    #[builder(default)]
+    update: Arc<ManifestUpdate>,
+
+    /// Process files even if unchanged since they were last indexed
+    #[builder(default)]
+    force: bool,
 }
 
 impl Pathfinder {
-    pub async fn count_files(&self, target_path: impl AsRef<Path>) -> Result<usize> {
+    /// Sums the measure of every file to crawl, for the length of the overall progress bar
+    pub async fn total_work(
+        &self,
+        target_path: impl AsRef<Path>,
+        unit: ProgressUnit,
+    ) -> Result<u64> {
         let walk = WalkBuilder::new(target_path.as_ref())
             .types(self.types.clone())
             .build();
         let walk = filter_repo(walk);
-        Ok(walk.count())
+        Ok(walk.map(|entry| unit.measure(entry.path())).sum())
     }
 
     pub async fn run(
@@ -87,6 +107,7 @@ impl Pathfinder {
             }
 
             if !file_sizes.contains_key(file_path) {
+                self.update.forget(file_path);
                 sender
                     .send_async(SnippetProgress::MissingFile {
                         file_path: file_path.to_path_buf(),
@@ -94,6 +115,26 @@ impl Pathfinder {
                     .await?;
             } else {
                 let file_size = file_sizes.get(file_path).unwrap();
+                let weight = progressor
+                    .as_ref()
+                    .as_ref()
+                    .map(|bars| bars.unit.measure(repo_root.as_ref().join(file_path)));
+
+                match FileStamp::of(repo_root.as_ref().join(file_path)) {
+                    Ok(stamp) if !self.force && self.manifest.is_unchanged(file_path, &stamp) => {
+                        log::debug!("Skipping unchanged file {file_path:?}");
+                        if let Some(bars) = progressor.as_ref() {
+                            bars.file_progress.inc(weight.unwrap_or(1));
+                        }
+                        continue;
+                    }
+                    Ok(stamp) => self.update.stage(file_path, stamp),
+                    Err(err) => log::warn!("Unable to hash {file_path:?}: {err:?}"),
+                }
+
+                if let Some(bars) = progressor.as_ref() {
+                    bars.start_file(file_path, weight.unwrap_or(1));
+                }
 
                 let progress =
                     make_file_progress(progressor.clone(), file_path.as_path(), *file_size);
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use flume::Receiver;
@@ -8,7 +9,11 @@ use crate::Progressor;
 use crate::SnippetProgress;
 
 #[derive(TypedBuilder)]
-pub struct ProgressWorker {}
+pub struct ProgressWorker {
+    /// Queue depths to update as snippets finish the pipeline
+    #[builder(default)]
+    progressor: Arc<Option<Progressor>>,
+}
 
 impl ProgressWorker {
     pub async fn run(self, receiver: Receiver<SnippetProgress>) -> anyhow::Result<Self> {
@@ -26,18 +31,16 @@ impl ProgressWorker {
                     });
                 }
                 SnippetProgress::EndOfFile {
+                    file_path,
                     progressor,
                     progress,
                 } => {
                     if let Some(bar) = progress.as_ref()
-                        && let Some(Progressor {
-                            multi,
-                            file_progress,
-                        }) = progressor.as_ref()
+                        && let Some(bars @ Progressor { multi, .. }) = progressor.as_ref()
                     {
                         // Emulate detaching a finished bar from the multi by creating a dummy
                         multi.remove(bar);
-                        file_progress.inc(1);
+                        bars.finish_file(&file_path);
 
                         multi.suspend(|| {
                             let tombstone = bar
@@ -57,6 +60,10 @@ impl ProgressWorker {
                 } => {
                     let count = snippet.body.len();
                     progress.as_ref().inspect(|p| p.inc(count as u64));
+
+                    if let Some(bars) = self.progressor.as_ref() {
+                        bars.pending_insert.pop();
+                    }
                 }
                 _ => {}
             }
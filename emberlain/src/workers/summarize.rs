@@ -1,73 +1,241 @@
+use std::{
+    collections::{BTreeSet, VecDeque},
+    sync::Arc,
+};
+
 use flume::{Receiver, Sender};
+use indicatif::ProgressBar;
 use log::{info, warn};
+use tokio::{sync::Semaphore, task::JoinSet};
 use typed_builder::TypedBuilder;
 
 use crate::DynAgent;
-use crate::{CodeSnippet, SnippetProgress};
+use crate::manifest::ManifestUpdate;
+use crate::{CodeSnippet, Progressor, SnippetProgress};
 
 #[derive(TypedBuilder)]
 pub struct SummaryWorker<A: DynAgent> {
-    agent: A,
+    #[builder(setter(transform = |agent: A| Arc::new(agent)))]
+    agent: Arc<A>,
+
+    /// Maximum number of prompts awaiting the LLM at once
+    #[builder(default = 1)]
+    concurrency: usize,
 
     #[builder(default)]
     dry_run: bool,
 
     #[builder(default)]
     reprocess: bool,
+
+    /// Tracks queue depths when progress is displayed
+    #[builder(default)]
+    progressor: Arc<Option<Progressor>>,
+
+    /// Notified of files with snippets that could not be summarized
+    #[builder(default)]
+    manifest: Arc<ManifestUpdate>,
 }
 
-impl<A: DynAgent> SummaryWorker<A> {
+impl<A: DynAgent + 'static> SummaryWorker<A> {
     pub async fn run(
         &self,
         receiver: Receiver<SnippetProgress>,
         sender: Sender<SnippetProgress>,
     ) -> anyhow::Result<()> {
-        while let Ok(msg) = receiver.recv_async().await {
-            match msg {
-                SnippetProgress::Snippet {
-                    progress, snippet, ..
-                } if snippet.summary.is_empty() || self.reprocess => {
-                    // Skip trivial declarations: one-liners, aliases, forward declarations, etc.
-                    if snippet.body.lines().count() <= 4 {
-                        // TODO: principled cutoff logic. Ideally exclude code with a single
-                        // statement, not counting signature, braces, comments, etc
+        let progressor = self.progressor.as_ref();
+        let semaphore = Arc::new(Semaphore::new(self.concurrency.max(1)));
+        let mut tasks = JoinSet::new();
+
+        // Summaries can finish out of order, so end of file markers wait for the
+        // snippets received before them, tracked by sequence number.
+        let mut next_seq = 0usize;
+        let mut in_flight = BTreeSet::new();
+        let mut held = VecDeque::new();
+        let mut open = true;
+
+        loop {
+            tokio::select! {
+                Some(joined) = tasks.join_next(), if !tasks.is_empty() => {
+                    let Summarized { seq, snippet, progress, result } = joined?;
+                    in_flight.remove(&seq);
+
+                    match result {
+                        Ok(resp) => {
+                            let snippet = Box::new(CodeSnippet {
+                                summary: resp,
+                                ..*snippet
+                            });
+                            sender
+                                .send_async(SnippetProgress::Snippet {
+                                    snippet,
+                                    progress,
+                                    clean: false,
+                                })
+                                .await
+                                .unwrap();
+
+                            if let Some(bars) = progressor {
+                                bars.pending_insert.push();
+                            }
+                        }
+                        Err(err) => {
+                            warn!("Could not summarize snippet: {err:?}");
+                            self.manifest.fail(&snippet.path);
+                        }
+                    }
+
+                    while let Some((held_seq, _)) = held.front()
+                        && in_flight.first().is_none_or(|first| first >= held_seq)
+                    {
+                        let (_, msg) = held.pop_front().unwrap();
+                        sender.send_async(msg).await.unwrap();
+                    }
+                }
+                msg = receiver.recv_async(), if open => {
+                    let Ok(msg) = msg else {
+                        open = false;
                         continue;
+                    };
+
+                    let is_snippet = matches!(msg, SnippetProgress::Snippet { .. });
+                    if let Some(bars) = progressor
+                        && is_snippet
+                    {
+                        bars.pending_summary.pop();
                     }
 
-                    let body = snippet.body();
+                    match msg {
+                        SnippetProgress::Snippet {
+                            progress, snippet, ..
+                        } if snippet.summary.is_empty() || self.reprocess => {
+                            let body = snippet.body().into_owned();
 
-                    let options = textwrap::Options::new(100)
-                        .initial_indent(">>> ")
-                        .subsequent_indent("... ");
-                    info!("{}", textwrap::fill(&body, &options));
+                            let options = textwrap::Options::new(100)
+                                .initial_indent(">>> ")
+                                .subsequent_indent("... ");
+                            info!("{}", textwrap::fill(&body, &options));
 
-                    if !self.dry_run {
-                        match self.agent.prompt(&body).await {
-                            Ok(resp) => {
-                                let snippet = Box::new(CodeSnippet {
-                                    summary: resp,
-                                    ..*snippet
-                                });
-                                sender
-                                    .send_async(SnippetProgress::Snippet {
+                            if !self.dry_run {
+                                let permit = semaphore.clone().acquire_owned().await?;
+                                let agent = self.agent.clone();
+                                let seq = next_seq;
+                                next_seq += 1;
+                                in_flight.insert(seq);
+
+                                tasks.spawn(async move {
+                                    let result = agent.prompt(&body).await;
+                                    drop(permit);
+
+                                    Summarized {
+                                        seq,
                                         snippet,
                                         progress,
-                                        clean: false,
-                                    })
-                                    .await
-                                    .unwrap();
+                                        result,
+                                    }
+                                });
+                            }
+                        }
+                        msg @ SnippetProgress::EndOfFile { .. } if !in_flight.is_empty() => {
+                            held.push_back((next_seq, msg));
+                        }
+                        _ => {
+                            sender.send_async(msg).await.unwrap();
+
+                            if let Some(bars) = progressor
+                                && is_snippet
+                            {
+                                bars.pending_insert.push();
                             }
-                            Err(err) => warn!("Could not summarize snippet: {err:?}"),
                         }
                     }
                 }
-                _ => {
-                    sender.send_async(msg).await.unwrap();
-                }
+                else => break,
             }
         }
 
+        for (_, msg) in held {
+            sender.send_async(msg).await.unwrap();
+        }
+
         info!("No more snippets to summarize");
         Ok(())
     }
 }
+
+/// Outcome of a summarization task, returned to the worker loop
+struct Summarized {
+    seq: usize,
+    snippet: Box<CodeSnippet>,
+    progress: Option<ProgressBar>,
+    result: anyhow::Result<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// Answers with the snippet body after a delay, so later snippets finish first
+    struct SlowAgent;
+
+    #[async_trait]
+    impl DynAgent for SlowAgent {
+        async fn prompt(&self, body: &str) -> anyhow::Result<String> {
+            let delay = if body.contains("first") { 50 } else { 5 };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(body.to_string())
+        }
+    }
+
+    fn snippet(name: &str) -> SnippetProgress {
+        SnippetProgress::Snippet {
+            progress: None,
+            snippet: Box::new(CodeSnippet {
+                name: name.into(),
+                body: format!("fn {name}() {{\n    a();\n    b();\n    c();\n}}"),
+                ..Default::default()
+            }),
+            clean: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_end_of_file_waits_for_summaries() -> anyhow::Result<()> {
+        let worker = SummaryWorker::builder()
+            .agent(SlowAgent)
+            .concurrency(4)
+            .build();
+
+        let (in_tx, in_rx) = flume::unbounded();
+        let (out_tx, out_rx) = flume::unbounded();
+
+        in_tx.send(snippet("first"))?;
+        in_tx.send(snippet("second"))?;
+        in_tx.send(SnippetProgress::EndOfFile {
+            file_path: "src/lib.rs".into(),
+            progressor: Default::default(),
+            progress: None,
+        })?;
+        drop(in_tx);
+
+        worker.run(in_rx, out_tx).await?;
+
+        let names = out_rx
+            .drain()
+            .map(|msg| match msg {
+                SnippetProgress::Snippet { snippet, .. } => snippet.name,
+                SnippetProgress::EndOfFile { .. } => "<eof>".into(),
+                _ => "<other>".into(),
+            })
+            .collect::<Vec<_>>();
+
+        // Summaries finish out of order, but the end of the file still comes last
+        assert_eq!(names, ["second", "first", "<eof>"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use emberlain::{
+    init_collection,
+    search::{ALIAS_VECTOR, DEFAULT_VECTOR, SearchOptions, semantic_query},
+};
+use qdrant_client::{
+    Payload, Qdrant,
+    qdrant::{
+        Condition, Filter, PointStruct, UpsertPointsBuilder, Vector, point_id::PointIdOptions,
+        with_payload_selector::SelectorOptions,
+    },
+};
+use serde_json::json;
+
+fn qdrant_url() -> String {
+    std::env::var("QDRANT_URL").unwrap_or("http://localhost:6334".into())
+}
+
+fn point(id: u64, name: &str, embedding: Vec<f32>, aliases: Vec<Vec<f32>>) -> PointStruct {
+    let payload =
+        Payload::try_from(json!({ "name": name, "path": format!("src/{name}.rs") })).unwrap();
+
+    let vectors = HashMap::from([
+        (DEFAULT_VECTOR.to_string(), Vector::new_dense(embedding)),
+        (ALIAS_VECTOR.to_string(), Vector::new_multi(aliases)),
+    ]);
+
+    PointStruct::new(id, vectors, payload)
+}
+
+fn point_num(id: &qdrant_client::qdrant::PointId) -> u64 {
+    match id.point_id_options.as_ref().unwrap() {
+        PointIdOptions::Num(num) => *num,
+        PointIdOptions::Uuid(uuid) => panic!("Unexpected uuid {uuid}"),
+    }
+}
+
+/// Runs against a throwaway collection on a live server
+#[tokio::test]
+#[ignore = "requires a running Qdrant server at QDRANT_URL"]
+async fn test_semantic_query_ephemeral() -> anyhow::Result<()> {
+    let client = Qdrant::from_url(&qdrant_url()).build()?;
+    let collection = format!("emberlain-test-{}", std::process::id());
+
+    init_collection(&client, &collection, 4, &Default::default()).await?;
+
+    let points = vec![
+        point(
+            1,
+            "alpha",
+            vec![1.0, 0.0, 0.0, 0.0],
+            vec![vec![1.0, 0.0, 0.0, 0.0]],
+        ),
+        point(
+            2,
+            "beta",
+            vec![0.0, 1.0, 0.0, 0.0],
+            vec![vec![0.0, 1.0, 0.0, 0.0]],
+        ),
+        point(
+            3,
+            "gamma",
+            vec![0.0, 0.0, 1.0, 0.0],
+            vec![vec![0.0, 0.0, 1.0, 0.0], vec![0.9, 0.1, 0.0, 0.0]],
+        ),
+    ];
+
+    client
+        .upsert_points(UpsertPointsBuilder::new(&collection, points).wait(true))
+        .await?;
+
+    let result = async {
+        // Aliases let gamma match a query near alpha
+        let opts = SearchOptions::builder().limit(2).build();
+        let found = semantic_query(&client, &collection, vec![1.0, 0.0, 0.0, 0.0], &opts).await?;
+        let ids = found.iter().filter_map(|p| p.id.as_ref()).map(point_num);
+        assert_eq!(ids.collect::<Vec<_>>(), vec![1, 3]);
+        assert!(found.iter().all(|p| p.payload.is_empty()));
+
+        // Default vectors only consider the summary embedding
+        let opts = SearchOptions::builder()
+            .limit(2)
+            .using(DEFAULT_VECTOR)
+            .build();
+        let found = semantic_query(&client, &collection, vec![0.9, 0.0, 0.1, 0.0], &opts).await?;
+        let ids = found.iter().filter_map(|p| p.id.as_ref()).map(point_num);
+        assert_eq!(ids.collect::<Vec<_>>(), vec![1, 3]);
+
+        // Filters and payload selection are passed through
+        let opts = SearchOptions::builder()
+            .limit(3)
+            .filter(Filter::must_not([Condition::matches(
+                "name",
+                "alpha".to_string(),
+            )]))
+            .payload(SelectorOptions::Include(vec!["name".to_string()].into()))
+            .build();
+        let found = semantic_query(&client, &collection, vec![1.0, 0.0, 0.0, 0.0], &opts).await?;
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(|p| p.payload.contains_key("name")));
+        assert!(found.iter().all(|p| !p.payload.contains_key("path")));
+
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    client.delete_collection(&collection).await?;
+
+    result
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use emberlain::{
+    CodeSnippet, SnippetProgress, init_collection,
+    search::{ALIAS_VECTOR, DEFAULT_VECTOR},
+    template::Templater,
+    workers::{dedup::DedupWorker, prune::PruningWorker},
+};
+use qdrant_client::{
+    Payload, Qdrant,
+    qdrant::{GetPointsBuilder, PointStruct, UpsertPointsBuilder, Vector},
+};
+
+fn qdrant_url() -> String {
+    std::env::var("QDRANT_URL").unwrap_or("http://localhost:6334".into())
+}
+
+fn snippet(path: &str, name: &str) -> CodeSnippet {
+    CodeSnippet {
+        path: path.into(),
+        name: name.into(),
+        body: format!("fn {name}() {{}}"),
+        ..Default::default()
+    }
+}
+
+fn point(snippet: &CodeSnippet) -> anyhow::Result<PointStruct> {
+    let payload = Payload::try_from(serde_json::to_value(snippet)?)?;
+    let vectors = HashMap::from([
+        (DEFAULT_VECTOR.to_string(), Vector::new_dense(vec![1.0; 4])),
+        (
+            ALIAS_VECTOR.to_string(),
+            Vector::new_multi(vec![vec![1.0; 4]]),
+        ),
+    ]);
+
+    Ok(PointStruct::new(snippet.point_id()?, vectors, payload))
+}
+
+/// Runs against a throwaway collection on a live server
+#[tokio::test]
+#[ignore = "requires a running Qdrant server at QDRANT_URL"]
+async fn test_soft_delete_ephemeral() -> anyhow::Result<()> {
+    let client = Qdrant::from_url(&qdrant_url()).build()?;
+    let collection = format!("emberlain-prune-{}", std::process::id());
+
+    init_collection(&client, &collection, 4, &Default::default()).await?;
+
+    let kept = snippet("src/kept.rs", "alpha");
+    let gone = snippet("src/gone.rs", "beta");
+
+    client
+        .upsert_points(
+            UpsertPointsBuilder::new(&collection, vec![point(&kept)?, point(&gone)?]).wait(true),
+        )
+        .await?;
+
+    let result = async {
+        let deduper = DedupWorker::builder()
+            .templater(Templater::new(Default::default())?)
+            .reprocess(true)
+            .qdrant(client.clone())
+            .collection(collection.clone())
+            .build();
+
+        let (in_tx, in_rx) = flume::unbounded();
+        let (out_tx, out_rx) = flume::unbounded();
+
+        in_tx.send(SnippetProgress::MissingFile {
+            file_path: "src/gone.rs".into(),
+        })?;
+        drop(in_tx);
+        deduper.run(in_rx, out_tx).await?;
+        assert!(out_rx.drain().next().is_none());
+
+        let removed = |snippet: &CodeSnippet| {
+            let client = client.clone();
+            let request = GetPointsBuilder::new(&collection, vec![snippet.point_id().unwrap()])
+                .with_payload(true);
+
+            async move {
+                let resp = client.get_points(request).await?;
+                Ok::<_, anyhow::Error>(
+                    resp.result
+                        .first()
+                        .map(|p| p.payload.contains_key("__removed")),
+                )
+            }
+        };
+
+        // Only points of the missing file are marked
+        assert_eq!(removed(&gone).await?, Some(true));
+        assert_eq!(removed(&kept).await?, Some(false));
+
+        PruningWorker::builder()
+            .cutoff(DateTime::<Utc>::MAX_UTC)
+            .qdrant(client.clone())
+            .collection(collection.clone())
+            .build()
+            .run()
+            .await?;
+
+        // Pruning deletes marked points outright
+        assert_eq!(removed(&gone).await?, None);
+        assert_eq!(removed(&kept).await?, Some(false));
+
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    client.delete_collection(&collection).await?;
+
+    result
+}